@@ -92,6 +92,22 @@ extern "C"
     extern int demi_connect(_Out_ demi_qtoken_t *qt_out, _In_ int sockqd,
                             _In_reads_bytes_(size) const struct sockaddr *addr, _In_ socklen_t size);
 
+    /**
+     * @brief Asynchronously initiates a connection on a socket I/O queue and sends a scatter-gather array on it. With
+     * TCP Fast Open, the data goes in the connection request.
+     *
+     * @param qt_out Store location for I/O queue token.
+     * @param sockqd I/O queue descriptor of the target socket.
+     * @param sga    Scatter-gather array to send.
+     * @param addr   Address of remote host.
+     * @param size   Effective size of the socked address data structure.
+     *
+     * @return On successful completion, zero is returned. On failure, a positive error code is returned instead.
+     */
+    ATTR_NONNULL(1, 3, 4)
+    extern int demi_connect_with_data(_Out_ demi_qtoken_t *qt_out, _In_ int sockqd, _In_ const demi_sgarray_t *sga,
+                                      _In_reads_bytes_(size) const struct sockaddr *addr, _In_ socklen_t size);
+
     /**
      * @brief Closes an I/O queue descriptor.
      *
@@ -101,6 +117,17 @@ extern "C"
      */
     extern int demi_close(_In_ int qd);
 
+    /**
+     * @brief Shuts down all or part of a connection on a socket I/O queue, without closing the I/O queue. Pushes that
+     * have not completed when the sending side is shut down may fail.
+     *
+     * @param sockqd I/O queue descriptor of the target socket.
+     * @param how    Which side of the connection to shut down (SHUT_RD, SHUT_WR or SHUT_RDWR).
+     *
+     * @return On successful completion, zero is returned. On failure, a positive error code is returned instead.
+     */
+    extern int demi_shutdown(_In_ int sockqd, _In_ int how);
+
     /**
      * @brief Asynchronously pushes a scatter-gather array to an I/O queue.
      *
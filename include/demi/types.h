@@ -129,6 +129,31 @@ extern "C"
     } demi_qresult_t;
 #ifdef _WIN32
 #pragma pack(pop)
+#endif
+
+/**
+ * @brief TCP connection statistics, as returned by the TCP_INFO socket option.
+ */
+#ifdef _WIN32
+#pragma pack(push, 1)
+    typedef struct demi_tcp_info
+#endif
+#ifdef __linux__
+        typedef struct __attribute__((__packed__)) demi_tcp_info
+#endif
+    {
+        uint32_t tcpi_rtt;             /**< Smoothed round-trip time in microseconds.  */
+        uint32_t tcpi_rttvar;          /**< Round-trip time variation in microseconds. */
+        uint32_t tcpi_rto;             /**< Retransmission timeout in microseconds.    */
+        uint32_t tcpi_snd_mss;         /**< Maximum segment size for sending.          */
+        uint32_t tcpi_snd_cwnd;        /**< Congestion window in bytes.                */
+        uint32_t tcpi_snd_wnd;         /**< Send window advertised by the peer.        */
+        uint32_t tcpi_rcv_wnd;         /**< Receive window in bytes.                   */
+        uint32_t tcpi_bytes_in_flight; /**< Bytes sent but not yet acknowledged.       */
+        uint32_t tcpi_total_retrans;   /**< Number of retransmitted segments.          */
+    } demi_tcp_info_t;
+#ifdef _WIN32
+#pragma pack(pop)
 #endif
 
     // Callback Function.
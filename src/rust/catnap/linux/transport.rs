@@ -21,7 +21,7 @@ use crate::{
         fail::Fail,
         memory::{DemiBuffer, MemoryRuntime},
         network::{
-            socket::option::{SocketOption, TcpSocketOptions},
+            socket::option::{SocketOption, TcpInfo, TcpSocketOptions},
             transport::NetworkTransport,
         },
         poll_yield, DemiRuntime, SharedDemiRuntime, SharedObject,
@@ -36,6 +36,7 @@ use ::std::{
     net::{Shutdown, SocketAddr, SocketAddrV4},
     ops::{Deref, DerefMut},
     os::fd::{AsRawFd, RawFd},
+    time::Duration,
 };
 
 //======================================================================================================================
@@ -45,6 +46,9 @@ use ::std::{
 // Set to the max number of file descriptors that can be open without increasing the number on Linux.
 const EPOLL_BATCH_SIZE: usize = 1024;
 
+// Maximum length of a congestion control algorithm name on Linux, including the terminating NUL.
+const TCP_CA_NAME_MAX: usize = 16;
+
 //======================================================================================================================
 // Structures
 //======================================================================================================================
@@ -229,6 +233,81 @@ fn get_libc_err(e: io::Error) -> i32 {
     expect_some!(e.raw_os_error(), "should have an os error code")
 }
 
+/// Internal function to set an integer TCP-level option that socket2 does not expose.
+fn set_tcp_int_option(socket: &Socket, name: libc::c_int, value: libc::c_int) -> Result<(), i32> {
+    match unsafe {
+        libc::setsockopt(
+            socket.as_raw_fd(),
+            libc::IPPROTO_TCP,
+            name,
+            &value as *const libc::c_int as *const libc::c_void,
+            std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+        )
+    } {
+        0 => Ok(()),
+        _ => Err(unsafe { *libc::__errno_location() }),
+    }
+}
+
+/// Internal function to get an integer TCP-level option that socket2 does not expose.
+fn get_tcp_int_option(socket: &Socket, name: libc::c_int) -> Result<libc::c_int, i32> {
+    let mut value: libc::c_int = 0;
+    let mut len: libc::socklen_t = std::mem::size_of::<libc::c_int>() as libc::socklen_t;
+    match unsafe {
+        libc::getsockopt(
+            socket.as_raw_fd(),
+            libc::IPPROTO_TCP,
+            name,
+            &mut value as *mut libc::c_int as *mut libc::c_void,
+            &mut len,
+        )
+    } {
+        0 => Ok(value),
+        _ => Err(unsafe { *libc::__errno_location() }),
+    }
+}
+
+/// Internal function to set a string TCP-level option that socket2 does not expose.
+fn set_tcp_str_option(socket: &Socket, name: libc::c_int, value: &str) -> Result<(), i32> {
+    match unsafe {
+        libc::setsockopt(
+            socket.as_raw_fd(),
+            libc::IPPROTO_TCP,
+            name,
+            value.as_ptr() as *const libc::c_void,
+            value.len() as libc::socklen_t,
+        )
+    } {
+        0 => Ok(()),
+        _ => Err(unsafe { *libc::__errno_location() }),
+    }
+}
+
+/// Internal function to get a string TCP-level option that socket2 does not expose, which takes up to `max_len` bytes
+/// including the terminating NUL.
+fn get_tcp_str_option(socket: &Socket, name: libc::c_int, max_len: usize) -> Result<String, i32> {
+    let mut value: Vec<u8> = vec![0; max_len];
+    let mut len: libc::socklen_t = max_len as libc::socklen_t;
+    match unsafe {
+        libc::getsockopt(
+            socket.as_raw_fd(),
+            libc::IPPROTO_TCP,
+            name,
+            value.as_mut_ptr() as *mut libc::c_void,
+            &mut len,
+        )
+    } {
+        0 => {
+            value.truncate(len as usize);
+            if let Some(end) = value.iter().position(|c| *c == 0) {
+                value.truncate(end);
+            }
+            String::from_utf8(value).map_err(|_| libc::EINVAL)
+        },
+        _ => Err(unsafe { *libc::__errno_location() }),
+    }
+}
+
 //======================================================================================================================
 // Trait implementation
 //======================================================================================================================
@@ -354,6 +433,33 @@ impl NetworkTransport for SharedCatnapTransport {
                     Ok(())
                 }
             },
+            SocketOption::KeepAliveIdle(idle) => {
+                if let Err(errno) = set_tcp_int_option(socket, libc::TCP_KEEPIDLE, idle.as_secs() as libc::c_int) {
+                    let cause: String = format!("TCP_KEEPIDLE failed: {:?}", errno);
+                    error!("set_socket_option(): {}", cause);
+                    Err(Fail::new(errno, &cause))
+                } else {
+                    Ok(())
+                }
+            },
+            SocketOption::KeepAliveInterval(interval) => {
+                if let Err(errno) = set_tcp_int_option(socket, libc::TCP_KEEPINTVL, interval.as_secs() as libc::c_int) {
+                    let cause: String = format!("TCP_KEEPINTVL failed: {:?}", errno);
+                    error!("set_socket_option(): {}", cause);
+                    Err(Fail::new(errno, &cause))
+                } else {
+                    Ok(())
+                }
+            },
+            SocketOption::KeepAliveProbes(probes) => {
+                if let Err(errno) = set_tcp_int_option(socket, libc::TCP_KEEPCNT, probes as libc::c_int) {
+                    let cause: String = format!("TCP_KEEPCNT failed: {:?}", errno);
+                    error!("set_socket_option(): {}", cause);
+                    Err(Fail::new(errno, &cause))
+                } else {
+                    Ok(())
+                }
+            },
             SocketOption::NoDelay(nagle_off) => {
                 if let Err(e) = socket.set_nodelay(nagle_off) {
                     let errno: i32 = get_libc_err(e);
@@ -364,6 +470,70 @@ impl NetworkTransport for SharedCatnapTransport {
                     Ok(())
                 }
             },
+            SocketOption::QuickAck(quick_ack) => {
+                if let Err(errno) = set_tcp_int_option(socket, libc::TCP_QUICKACK, quick_ack as libc::c_int) {
+                    let cause: String = format!("TCP_QUICKACK failed: {:?}", errno);
+                    error!("set_socket_option(): {}", cause);
+                    Err(Fail::new(errno, &cause))
+                } else {
+                    Ok(())
+                }
+            },
+            SocketOption::CongestionControl(algorithm) => {
+                if let Err(errno) = set_tcp_str_option(socket, libc::TCP_CONGESTION, algorithm.name()) {
+                    let cause: String = format!("TCP_CONGESTION failed: {:?}", errno);
+                    error!("set_socket_option(): {}", cause);
+                    Err(Fail::new(errno, &cause))
+                } else {
+                    Ok(())
+                }
+            },
+            SocketOption::ReuseAddress(reuse_address) => {
+                if let Err(e) = socket.set_reuse_address(reuse_address) {
+                    let errno: i32 = get_libc_err(e);
+                    let cause: String = format!("SO_REUSEADDR failed: {:?}", errno);
+                    error!("set_socket_option(): {}", cause);
+                    Err(Fail::new(errno, &cause))
+                } else {
+                    Ok(())
+                }
+            },
+            SocketOption::SendBufferSize(size) => {
+                if let Err(e) = socket.set_send_buffer_size(size) {
+                    let errno: i32 = get_libc_err(e);
+                    let cause: String = format!("SO_SNDBUF failed: {:?}", errno);
+                    error!("set_socket_option(): {}", cause);
+                    Err(Fail::new(errno, &cause))
+                } else {
+                    Ok(())
+                }
+            },
+            SocketOption::ReceiveBufferSize(size) => {
+                if let Err(e) = socket.set_recv_buffer_size(size) {
+                    let errno: i32 = get_libc_err(e);
+                    let cause: String = format!("SO_RCVBUF failed: {:?}", errno);
+                    error!("set_socket_option(): {}", cause);
+                    Err(Fail::new(errno, &cause))
+                } else {
+                    Ok(())
+                }
+            },
+            SocketOption::UserTimeout(timeout) => {
+                // Linux takes the timeout in milliseconds, with zero meaning the system default.
+                let millis: libc::c_int = timeout.map_or(0, |timeout| timeout.as_millis() as libc::c_int);
+                if let Err(errno) = set_tcp_int_option(socket, libc::TCP_USER_TIMEOUT, millis) {
+                    let cause: String = format!("TCP_USER_TIMEOUT failed: {:?}", errno);
+                    error!("set_socket_option(): {}", cause);
+                    Err(Fail::new(errno, &cause))
+                } else {
+                    Ok(())
+                }
+            },
+            SocketOption::TcpInfo(_) => {
+                let cause: &str = "TCP_INFO cannot be set";
+                error!("set_socket_option(): {}", cause);
+                Err(Fail::new(libc::ENOPROTOOPT, cause))
+            },
         }
     }
 
@@ -395,6 +565,30 @@ impl NetworkTransport for SharedCatnapTransport {
                     Err(Fail::new(errno, &cause))
                 },
             },
+            SocketOption::KeepAliveIdle(_) => match get_tcp_int_option(socket, libc::TCP_KEEPIDLE) {
+                Ok(idle) => Ok(SocketOption::KeepAliveIdle(Duration::from_secs(idle as u64))),
+                Err(errno) => {
+                    let cause: String = format!("TCP_KEEPIDLE failed: {:?}", errno);
+                    error!("get_socket_option(): {}", cause);
+                    Err(Fail::new(errno, &cause))
+                },
+            },
+            SocketOption::KeepAliveInterval(_) => match get_tcp_int_option(socket, libc::TCP_KEEPINTVL) {
+                Ok(interval) => Ok(SocketOption::KeepAliveInterval(Duration::from_secs(interval as u64))),
+                Err(errno) => {
+                    let cause: String = format!("TCP_KEEPINTVL failed: {:?}", errno);
+                    error!("get_socket_option(): {}", cause);
+                    Err(Fail::new(errno, &cause))
+                },
+            },
+            SocketOption::KeepAliveProbes(_) => match get_tcp_int_option(socket, libc::TCP_KEEPCNT) {
+                Ok(probes) => Ok(SocketOption::KeepAliveProbes(probes as u32)),
+                Err(errno) => {
+                    let cause: String = format!("TCP_KEEPCNT failed: {:?}", errno);
+                    error!("get_socket_option(): {}", cause);
+                    Err(Fail::new(errno, &cause))
+                },
+            },
             SocketOption::NoDelay(_) => match socket.nodelay() {
                 Ok(nagle_off) => Ok(SocketOption::NoDelay(nagle_off)),
                 Err(e) => {
@@ -404,6 +598,94 @@ impl NetworkTransport for SharedCatnapTransport {
                     Err(Fail::new(errno, &cause))
                 },
             },
+            SocketOption::QuickAck(_) => match get_tcp_int_option(socket, libc::TCP_QUICKACK) {
+                Ok(quick_ack) => Ok(SocketOption::QuickAck(quick_ack != 0)),
+                Err(errno) => {
+                    let cause: String = format!("TCP_QUICKACK failed: {:?}", errno);
+                    error!("get_socket_option(): {}", cause);
+                    Err(Fail::new(errno, &cause))
+                },
+            },
+            SocketOption::CongestionControl(_) => {
+                match get_tcp_str_option(socket, libc::TCP_CONGESTION, TCP_CA_NAME_MAX) {
+                    Ok(name) => Ok(SocketOption::CongestionControl(name.parse()?)),
+                    Err(errno) => {
+                        let cause: String = format!("TCP_CONGESTION failed: {:?}", errno);
+                        error!("get_socket_option(): {}", cause);
+                        Err(Fail::new(errno, &cause))
+                    },
+                }
+            },
+            SocketOption::ReuseAddress(_) => match socket.reuse_address() {
+                Ok(reuse_address) => Ok(SocketOption::ReuseAddress(reuse_address)),
+                Err(e) => {
+                    let errno: i32 = get_libc_err(e);
+                    let cause: String = format!("SO_REUSEADDR failed: {:?}", errno);
+                    error!("get_socket_option(): {}", cause);
+                    Err(Fail::new(errno, &cause))
+                },
+            },
+            SocketOption::SendBufferSize(_) => match socket.send_buffer_size() {
+                Ok(size) => Ok(SocketOption::SendBufferSize(size)),
+                Err(e) => {
+                    let errno: i32 = get_libc_err(e);
+                    let cause: String = format!("SO_SNDBUF failed: {:?}", errno);
+                    error!("get_socket_option(): {}", cause);
+                    Err(Fail::new(errno, &cause))
+                },
+            },
+            SocketOption::ReceiveBufferSize(_) => match socket.recv_buffer_size() {
+                Ok(size) => Ok(SocketOption::ReceiveBufferSize(size)),
+                Err(e) => {
+                    let errno: i32 = get_libc_err(e);
+                    let cause: String = format!("SO_RCVBUF failed: {:?}", errno);
+                    error!("get_socket_option(): {}", cause);
+                    Err(Fail::new(errno, &cause))
+                },
+            },
+            SocketOption::UserTimeout(_) => match get_tcp_int_option(socket, libc::TCP_USER_TIMEOUT) {
+                Ok(0) => Ok(SocketOption::UserTimeout(None)),
+                Ok(millis) => Ok(SocketOption::UserTimeout(Some(Duration::from_millis(millis as u64)))),
+                Err(errno) => {
+                    let cause: String = format!("TCP_USER_TIMEOUT failed: {:?}", errno);
+                    error!("get_socket_option(): {}", cause);
+                    Err(Fail::new(errno, &cause))
+                },
+            },
+            SocketOption::TcpInfo(_) => {
+                let mut info: libc::tcp_info = unsafe { std::mem::zeroed() };
+                let mut len: libc::socklen_t = std::mem::size_of::<libc::tcp_info>() as libc::socklen_t;
+                match unsafe {
+                    libc::getsockopt(
+                        socket.as_raw_fd(),
+                        libc::IPPROTO_TCP,
+                        libc::TCP_INFO,
+                        &mut info as *mut libc::tcp_info as *mut libc::c_void,
+                        &mut len,
+                    )
+                } {
+                    // Linux counts the congestion window and the data in flight in segments, and the libc crate does
+                    // not have the fields for the send and receive windows, so we leave those at zero.
+                    0 => Ok(SocketOption::TcpInfo(TcpInfo {
+                        srtt: Duration::from_micros(info.tcpi_rtt as u64),
+                        rttvar: Duration::from_micros(info.tcpi_rttvar as u64),
+                        rto: Duration::from_micros(info.tcpi_rto as u64),
+                        send_mss: info.tcpi_snd_mss,
+                        congestion_window: info.tcpi_snd_cwnd.saturating_mul(info.tcpi_snd_mss),
+                        bytes_in_flight: (info.tcpi_unacked + info.tcpi_retrans)
+                            .saturating_sub(info.tcpi_sacked + info.tcpi_lost)
+                            .saturating_mul(info.tcpi_snd_mss),
+                        total_retransmits: info.tcpi_total_retrans,
+                        ..Default::default()
+                    })),
+                    _ => {
+                        let errno: libc::c_int = unsafe { *libc::__errno_location() };
+                        let cause: String = format!("TCP_INFO failed: {:?}", errno);
+                        error!("get_socket_option(): {}", cause);
+                        Err(Fail::new(errno, &cause))
+                    },
+                }
+            },
         }
     }
 
@@ -480,6 +762,18 @@ impl NetworkTransport for SharedCatnapTransport {
         Ok(())
     }
 
+    /// Shuts down the receiving side, the sending side or both sides of a connected socket on the underlying transport.
+    fn shutdown(&mut self, sd: &mut Self::SocketDescriptor, how: Shutdown) -> Result<(), Fail> {
+        timer!("catnap::linux::transport::shutdown");
+        if let Err(e) = self.socket_from_sd(sd).shutdown(how) {
+            let cause: String = format!("failed to shut down socket: {:?}", e);
+            error!("shutdown(): {}", cause);
+            return Err(Fail::new(get_libc_err(e), &cause));
+        }
+
+        Ok(())
+    }
+
     /// Accept the next incoming connection. This function blocks until a new connection arrives from the underlying
     /// transport.
     async fn accept(&mut self, sd: &mut Self::SocketDescriptor) -> Result<(Self::SocketDescriptor, SocketAddr), Fail> {
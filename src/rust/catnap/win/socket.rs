@@ -16,7 +16,7 @@ use crate::{
 use std::{
     fmt::Debug,
     mem::MaybeUninit,
-    net::{Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6},
+    net::{Ipv4Addr, Ipv6Addr, Shutdown, SocketAddr, SocketAddrV4, SocketAddrV6},
     pin::Pin,
     rc::Rc,
     time::Duration,
@@ -27,10 +27,10 @@ use windows::{
         Foundation::{BOOL, ERROR_NOT_FOUND, FALSE, HANDLE, TRUE},
         Networking::WinSock::{
             bind, closesocket, listen, shutdown, tcp_keepalive, WSAGetLastError, WSARecvFrom, WSASendTo,
-            FROM_PROTOCOL_INFO, INVALID_SOCKET, IPPROTO_TCP, LINGER, SD_BOTH, SIO_KEEPALIVE_VALS, SOCKADDR,
-            SOCKADDR_IN, SOCKADDR_IN6, SOCKADDR_INET, SOCKADDR_STORAGE, SOCKET, SOCKET_ERROR, SOL_SOCKET, SO_KEEPALIVE,
-            SO_LINGER, SO_PROTOCOL_INFOW, SO_UPDATE_ACCEPT_CONTEXT, SO_UPDATE_CONNECT_CONTEXT, TCP_NODELAY, WSABUF,
-            WSAEINVAL, WSAPROTOCOL_INFOW, WSA_FLAG_OVERLAPPED,
+            FROM_PROTOCOL_INFO, INVALID_SOCKET, IPPROTO_TCP, LINGER, SD_BOTH, SD_RECEIVE, SD_SEND, SIO_KEEPALIVE_VALS,
+            SOCKADDR, SOCKADDR_IN, SOCKADDR_IN6, SOCKADDR_INET, SOCKADDR_STORAGE, SOCKET, SOCKET_ERROR, SOL_SOCKET,
+            SO_KEEPALIVE, SO_LINGER, SO_PROTOCOL_INFOW, SO_RCVBUF, SO_REUSEADDR, SO_SNDBUF, SO_UPDATE_ACCEPT_CONTEXT,
+            SO_UPDATE_CONNECT_CONTEXT, TCP_NODELAY, WSABUF, WSAEINVAL, WSAPROTOCOL_INFOW, WSA_FLAG_OVERLAPPED,
         },
         System::IO::{CancelIoEx, OVERLAPPED},
     },
@@ -200,6 +200,48 @@ impl Socket {
         }
     }
 
+    /// Set SO_REUSEADDR socket option.
+    pub fn set_reuse_address(&self, reuse_address: bool) -> Result<(), Fail> {
+        let value: BOOL = if reuse_address { TRUE } else { FALSE };
+        unsafe { WinsockRuntime::do_setsockopt(self.s, SOL_SOCKET, SO_REUSEADDR, Some(&value)) }?;
+        Ok(())
+    }
+
+    /// Get SO_REUSEADDR socket option.
+    pub fn get_reuse_address(&self) -> Result<bool, Fail> {
+        match unsafe { WinsockRuntime::do_getsockopt(self.s, SOL_SOCKET, SO_REUSEADDR) }? {
+            FALSE => Ok(false),
+            _ => Ok(true),
+        }
+    }
+
+    /// Set SO_SNDBUF socket option.
+    pub fn set_send_buffer_size(&self, size: usize) -> Result<(), Fail> {
+        let value: i32 = i32::try_from(size).map_err(|_| Fail::new(libc::EINVAL, "send buffer size is too large"))?;
+        unsafe { WinsockRuntime::do_setsockopt(self.s, SOL_SOCKET, SO_SNDBUF, Some(&value)) }?;
+        Ok(())
+    }
+
+    /// Get SO_SNDBUF socket option.
+    pub fn get_send_buffer_size(&self) -> Result<usize, Fail> {
+        let value: i32 = unsafe { WinsockRuntime::do_getsockopt(self.s, SOL_SOCKET, SO_SNDBUF) }?;
+        Ok(value as usize)
+    }
+
+    /// Set SO_RCVBUF socket option.
+    pub fn set_receive_buffer_size(&self, size: usize) -> Result<(), Fail> {
+        let value: i32 =
+            i32::try_from(size).map_err(|_| Fail::new(libc::EINVAL, "receive buffer size is too large"))?;
+        unsafe { WinsockRuntime::do_setsockopt(self.s, SOL_SOCKET, SO_RCVBUF, Some(&value)) }?;
+        Ok(())
+    }
+
+    /// Get SO_RCVBUF socket option.
+    pub fn get_receive_buffer_size(&self) -> Result<usize, Fail> {
+        let value: i32 = unsafe { WinsockRuntime::do_getsockopt(self.s, SOL_SOCKET, SO_RCVBUF) }?;
+        Ok(value as usize)
+    }
+
     /// Make a new socket like some template socket.
     pub fn new_like(template: &Socket) -> Result<Socket, Fail> {
         // Safety: SO_PROTOCOL_INFOW fills out a WSAPROTOCOL_INFOW structure.
@@ -233,14 +275,20 @@ impl Socket {
     /// Call once the overlapped operation started by `start_disconnect` has completed to finish disconnecting and
     /// shutdown the socket.
     pub fn finish_disconnect(&self, result: OverlappedResult) -> Result<(), Fail> {
-        self.shutdown().and(result.ok())
+        self.shutdown(Shutdown::Both).and(result.ok())
     }
 
-    /// Shutdown communication on the socket. For better asynchronous behavior on connection-oriented sockets,
-    /// `start_disconnect` will start an asynchronous disconnect operation. If the socket is not disconnected prior to
-    /// this call, this call may block for socket teardown, depending on the linger settings.
-    pub fn shutdown(&self) -> Result<(), Fail> {
-        if unsafe { shutdown(self.s, SD_BOTH) } == 0 {
+    /// Shutdown communication on the socket in the direction(s) given by `how`. For better asynchronous behavior on
+    /// connection-oriented sockets, `start_disconnect` will start an asynchronous disconnect operation. If the socket
+    /// is not disconnected prior to this call, this call may block for socket teardown, depending on the linger
+    /// settings.
+    pub fn shutdown(&self, how: Shutdown) -> Result<(), Fail> {
+        let how = match how {
+            Shutdown::Read => SD_RECEIVE,
+            Shutdown::Write => SD_SEND,
+            Shutdown::Both => SD_BOTH,
+        };
+        if unsafe { shutdown(self.s, how) } == 0 {
             Ok(())
         } else {
             Err(expect_last_wsa_error().into())
@@ -30,7 +30,7 @@ use crate::{
 };
 use ::futures::FutureExt;
 use std::{
-    net::{SocketAddr, SocketAddrV4},
+    net::{Shutdown, SocketAddr, SocketAddrV4},
     pin::Pin,
 };
 use windows::Win32::{
@@ -133,7 +133,35 @@ impl NetworkTransport for SharedCatnapTransport {
         match option {
             SocketOption::Linger(linger) => socket.set_linger(linger),
             SocketOption::KeepAlive(tcp_keepalive) => socket.set_tcp_keepalive(&tcp_keepalive),
+            SocketOption::KeepAliveIdle(_) | SocketOption::KeepAliveInterval(_) | SocketOption::KeepAliveProbes(_) => {
+                let cause: &str = "keepalive parameters can only be set through SO_KEEPALIVE on Windows";
+                error!("set_socket_option(): {}", cause);
+                Err(Fail::new(libc::ENOPROTOOPT, cause))
+            },
             SocketOption::NoDelay(nagle_enabled) => socket.set_nagle(nagle_enabled),
+            SocketOption::QuickAck(_) => {
+                let cause: &str = "TCP_QUICKACK is not supported on Windows";
+                error!("set_socket_option(): {}", cause);
+                Err(Fail::new(libc::ENOPROTOOPT, cause))
+            },
+            SocketOption::CongestionControl(_) => {
+                let cause: &str = "TCP_CONGESTION is not supported on Windows";
+                error!("set_socket_option(): {}", cause);
+                Err(Fail::new(libc::ENOPROTOOPT, cause))
+            },
+            SocketOption::ReuseAddress(reuse_address) => socket.set_reuse_address(reuse_address),
+            SocketOption::SendBufferSize(size) => socket.set_send_buffer_size(size),
+            SocketOption::ReceiveBufferSize(size) => socket.set_receive_buffer_size(size),
+            SocketOption::UserTimeout(_) => {
+                let cause: &str = "TCP_USER_TIMEOUT is not supported on Windows";
+                error!("set_socket_option(): {}", cause);
+                Err(Fail::new(libc::ENOPROTOOPT, cause))
+            },
+            SocketOption::TcpInfo(_) => {
+                let cause: &str = "TCP_INFO cannot be set";
+                error!("set_socket_option(): {}", cause);
+                Err(Fail::new(libc::ENOPROTOOPT, cause))
+            },
         }
     }
 
@@ -148,7 +176,37 @@ impl NetworkTransport for SharedCatnapTransport {
         match option {
             SocketOption::Linger(_) => Ok(SocketOption::Linger(socket.get_linger()?)),
             SocketOption::KeepAlive(_) => Ok(SocketOption::KeepAlive(socket.get_tcp_keepalive()?)),
+            SocketOption::KeepAliveIdle(_) | SocketOption::KeepAliveInterval(_) | SocketOption::KeepAliveProbes(_) => {
+                let cause: &str = "keepalive parameters can only be read through SO_KEEPALIVE on Windows";
+                error!("get_socket_option(): {}", cause);
+                Err(Fail::new(libc::ENOPROTOOPT, cause))
+            },
             SocketOption::NoDelay(_) => Ok(SocketOption::NoDelay(socket.get_nagle()?)),
+            SocketOption::QuickAck(_) => {
+                let cause: &str = "TCP_QUICKACK is not supported on Windows";
+                error!("get_socket_option(): {}", cause);
+                Err(Fail::new(libc::ENOPROTOOPT, cause))
+            },
+            SocketOption::CongestionControl(_) => {
+                let cause: &str = "TCP_CONGESTION is not supported on Windows";
+                error!("get_socket_option(): {}", cause);
+                Err(Fail::new(libc::ENOPROTOOPT, cause))
+            },
+            SocketOption::ReuseAddress(_) => Ok(SocketOption::ReuseAddress(socket.get_reuse_address()?)),
+            SocketOption::SendBufferSize(_) => Ok(SocketOption::SendBufferSize(socket.get_send_buffer_size()?)),
+            SocketOption::ReceiveBufferSize(_) => {
+                Ok(SocketOption::ReceiveBufferSize(socket.get_receive_buffer_size()?))
+            },
+            SocketOption::UserTimeout(_) => {
+                let cause: &str = "TCP_USER_TIMEOUT is not supported on Windows";
+                error!("get_socket_option(): {}", cause);
+                Err(Fail::new(libc::ENOPROTOOPT, cause))
+            },
+            SocketOption::TcpInfo(_) => {
+                let cause: &str = "TCP_INFO is not supported on Windows";
+                error!("get_socket_option(): {}", cause);
+                Err(Fail::new(libc::ENOPROTOOPT, cause))
+            },
         }
     }
 
@@ -167,7 +225,12 @@ impl NetworkTransport for SharedCatnapTransport {
 
     /// Synchronously shut down the specified socket.
     fn hard_close(&mut self, socket: &mut Self::SocketDescriptor) -> Result<(), Fail> {
-        socket.shutdown()
+        socket.shutdown(Shutdown::Both)
+    }
+
+    /// Shut down the receiving side, the sending side or both sides of a connected socket.
+    fn shutdown(&mut self, socket: &mut Self::SocketDescriptor, how: Shutdown) -> Result<(), Fail> {
+        socket.shutdown(how)
     }
 
     /// Asynchronously disconnect and shut down a socket.
@@ -181,7 +244,7 @@ impl NetworkTransport for SharedCatnapTransport {
         }
         .await
         {
-            Err(err) if err.errno == libc::ENOTCONN => match socket.shutdown() {
+            Err(err) if err.errno == libc::ENOTCONN => match socket.shutdown(Shutdown::Both) {
                 Err(err) if err.errno == libc::ENOTCONN => Ok(()),
                 r => r,
             },
@@ -61,6 +61,12 @@ impl<T> AsyncQueue<T> {
         self.cond_var.signal();
     }
 
+    /// Insert into an async queue at `index`, shifting everything after it towards the back.
+    pub fn insert(&mut self, index: usize, item: T) {
+        self.queue.insert(index, item);
+        self.cond_var.signal();
+    }
+
     /// Pop from an async queue. If the queue is empty, this function blocks until it finds something in the queue.
     pub async fn pop(&mut self, timeout: Option<Duration>) -> Result<T, Fail> {
         let wait_condition = async {
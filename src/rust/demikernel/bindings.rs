@@ -9,22 +9,26 @@ use crate::{
     demikernel::libos::{name::LibOSName, LibOS},
     pal::{
         socketaddrv4_to_sockaddr, AddressFamily, Linger, SockAddrIn, SockAddrIn6, SockAddrStorage, Socklen, AF_INET,
-        AF_INET6, SOL_SOCKET, SO_LINGER,
+        AF_INET6, IPPROTO_TCP, SHUT_RD, SHUT_RDWR, SHUT_WR, SOL_SOCKET, SO_LINGER, SO_RCVBUF, SO_REUSEADDR, SO_SNDBUF,
+        TCP_CONGESTION, TCP_INFO, TCP_USER_TIMEOUT,
     },
     runtime::{
         fail::Fail,
         logging,
-        types::{demi_args_t, demi_callback_t, demi_qresult_t, demi_qtoken_t, demi_sgarray_t, demi_sgaseg_t},
+        network::socket::option::{CongestionControlAlgorithm, TcpInfo},
+        types::{
+            demi_args_t, demi_callback_t, demi_qresult_t, demi_qtoken_t, demi_sgarray_t, demi_sgaseg_t, demi_tcp_info_t,
+        },
         QToken,
     },
     SocketOption,
 };
-use ::libc::{c_int, c_void};
+use ::libc::{c_int, c_uint, c_void};
 use ::socket2::SockAddr;
 use ::std::{
     cell::RefCell,
     mem::{self, MaybeUninit},
-    net::{SocketAddr, SocketAddrV4},
+    net::{Shutdown, SocketAddr, SocketAddrV4},
     ptr, slice,
     time::Duration,
 };
@@ -234,6 +238,61 @@ pub extern "C" fn demi_connect(
     }
 }
 
+#[no_mangle]
+pub extern "C" fn demi_connect_with_data(
+    qtok_out: *mut demi_qtoken_t,
+    sockqd: c_int,
+    sga: *const demi_sgarray_t,
+    saddr: *const sockaddr,
+    size: Socklen,
+) -> c_int {
+    trace!("demi_connect_with_data()");
+
+    // Check for invalid storage location.
+    if qtok_out.is_null() {
+        warn!("demi_connect_with_data() qtok_out is a null pointer");
+        return libc::EINVAL;
+    }
+
+    // Check if scatter-gather array is invalid.
+    if sga.is_null() {
+        return libc::EINVAL;
+    }
+
+    // Check if socket address is invalid.
+    if saddr.is_null() {
+        return libc::EINVAL;
+    }
+
+    let sga: &demi_sgarray_t = unsafe { &*sga };
+
+    // Get socket address.
+    let endpoint: SocketAddr = match sockaddr_to_socketaddr(saddr, size) {
+        Ok(endpoint) => endpoint,
+        Err(e) => {
+            trace!("demi_connect_with_data() failed: {:?}", e);
+            return e.errno;
+        },
+    };
+
+    // Issue connect operation.
+    let ret: Result<i32, Fail> = do_syscall(|libos| match libos.connect_with_data(sockqd.into(), endpoint, sga) {
+        Ok(qt) => {
+            unsafe { *qtok_out = qt.into() };
+            0
+        },
+        Err(e) => {
+            trace!("demi_connect_with_data() failed: {:?}", e);
+            e.errno
+        },
+    });
+
+    match ret {
+        Ok(ret) => ret,
+        Err(e) => e.errno,
+    }
+}
+
 #[no_mangle]
 pub extern "C" fn demi_close(qd: c_int) -> c_int {
     trace!("demi_close()");
@@ -253,6 +312,33 @@ pub extern "C" fn demi_close(qd: c_int) -> c_int {
     }
 }
 
+#[no_mangle]
+pub extern "C" fn demi_shutdown(sockqd: c_int, how: c_int) -> c_int {
+    trace!("demi_shutdown()");
+
+    // Check if shutdown direction is invalid.
+    let how: Shutdown = match how {
+        SHUT_RD => Shutdown::Read,
+        SHUT_WR => Shutdown::Write,
+        SHUT_RDWR => Shutdown::Both,
+        _ => return libc::EINVAL,
+    };
+
+    // Issue shutdown operation.
+    let ret: Result<i32, Fail> = do_syscall(|libos| match libos.shutdown(sockqd.into(), how) {
+        Ok(..) => 0,
+        Err(e) => {
+            trace!("demi_shutdown() failed: {:?}", e);
+            e.errno
+        },
+    });
+
+    match ret {
+        Ok(ret) => ret,
+        Err(e) => e.errno,
+    }
+}
+
 #[no_mangle]
 pub extern "C" fn demi_pushto(
     qtok_out: *mut demi_qtoken_t,
@@ -604,13 +690,13 @@ pub extern "C" fn demi_setsockopt(
     trace!("demi_setsockopt()");
 
     // Check inputs.
-    if level != SOL_SOCKET {
-        error!("demi_setsockopt(): only options in SOL_SOCKET level are supported");
+    if level != SOL_SOCKET && level != IPPROTO_TCP {
+        error!("demi_setsockopt(): only options in SOL_SOCKET and IPPROTO_TCP levels are supported");
         return libc::ENOTSUP;
     }
 
-    let opt: SocketOption = match optname {
-        SO_LINGER => {
+    let opt: SocketOption = match (level, optname) {
+        (SOL_SOCKET, SO_LINGER) => {
             // Check for invalid storage locations.
             if optval.is_null() {
                 error!("demi_setsockopt(): linger value is a null pointer");
@@ -628,8 +714,101 @@ pub extern "C" fn demi_setsockopt(
                 _ => SocketOption::Linger(Some(Duration::from_secs(linger.l_linger as u64))),
             }
         },
+        (SOL_SOCKET, SO_REUSEADDR) => {
+            // Check for invalid storage locations.
+            if optval.is_null() {
+                error!("demi_setsockopt(): reuse address value is a null pointer");
+                return libc::EINVAL;
+            }
+
+            if (optlen as usize) < mem::size_of::<c_int>() {
+                warn!("demi_setsockopt(): reuse address len is incorrect");
+                return libc::EINVAL;
+            }
+
+            let reuse_address: c_int = unsafe { *(optval as *const c_int) };
+            SocketOption::ReuseAddress(reuse_address != 0)
+        },
+        (SOL_SOCKET, SO_SNDBUF) => {
+            // Check for invalid storage locations.
+            if optval.is_null() {
+                error!("demi_setsockopt(): send buffer size is a null pointer");
+                return libc::EINVAL;
+            }
+
+            if (optlen as usize) < mem::size_of::<c_int>() {
+                warn!("demi_setsockopt(): send buffer size len is incorrect");
+                return libc::EINVAL;
+            }
+
+            let send_buffer_size: c_int = unsafe { *(optval as *const c_int) };
+            if send_buffer_size <= 0 {
+                warn!("demi_setsockopt(): send buffer size must be positive");
+                return libc::EINVAL;
+            }
+            SocketOption::SendBufferSize(send_buffer_size as usize)
+        },
+        (SOL_SOCKET, SO_RCVBUF) => {
+            // Check for invalid storage locations.
+            if optval.is_null() {
+                error!("demi_setsockopt(): receive buffer size is a null pointer");
+                return libc::EINVAL;
+            }
+
+            if (optlen as usize) < mem::size_of::<c_int>() {
+                warn!("demi_setsockopt(): receive buffer size len is incorrect");
+                return libc::EINVAL;
+            }
+
+            let receive_buffer_size: c_int = unsafe { *(optval as *const c_int) };
+            if receive_buffer_size <= 0 {
+                warn!("demi_setsockopt(): receive buffer size must be positive");
+                return libc::EINVAL;
+            }
+            SocketOption::ReceiveBufferSize(receive_buffer_size as usize)
+        },
+        (IPPROTO_TCP, TCP_CONGESTION) => {
+            // Check for invalid storage locations.
+            if optval.is_null() {
+                error!("demi_setsockopt(): congestion control name is a null pointer");
+                return libc::EINVAL;
+            }
+
+            // Like on Linux, the name does not need to be NUL-terminated.
+            let name: &[u8] = unsafe { slice::from_raw_parts(optval as *const u8, optlen as usize) };
+            let end: usize = name.iter().position(|c| *c == 0).unwrap_or(name.len());
+            let name: &str = match std::str::from_utf8(&name[..end]) {
+                Ok(name) => name,
+                Err(_) => {
+                    warn!("demi_setsockopt(): congestion control name is not valid UTF-8");
+                    return libc::EINVAL;
+                },
+            };
+            match name.parse() {
+                Ok(algorithm) => SocketOption::CongestionControl(algorithm),
+                Err(e) => return e.errno,
+            }
+        },
+        (IPPROTO_TCP, TCP_USER_TIMEOUT) => {
+            // Check for invalid storage locations.
+            if optval.is_null() {
+                error!("demi_setsockopt(): user timeout is a null pointer");
+                return libc::EINVAL;
+            }
+
+            if (optlen as usize) < mem::size_of::<c_uint>() {
+                warn!("demi_setsockopt(): user timeout len is incorrect");
+                return libc::EINVAL;
+            }
+
+            // Like on Linux, the timeout is in milliseconds and zero turns it off.
+            let user_timeout: c_uint = unsafe { *(optval as *const c_uint) };
+            SocketOption::UserTimeout(Some(Duration::from_millis(user_timeout as u64)).filter(|t| !t.is_zero()))
+        },
         _ => {
-            error!("demi_setsockopt(): only SO_LINGER is supported right now");
+            error!(
+                "demi_setsockopt(): only SO_LINGER, SO_REUSEADDR, SO_SNDBUF, SO_RCVBUF, TCP_CONGESTION and TCP_USER_TIMEOUT are supported right now"
+            );
             return libc::ENOPROTOOPT;
         },
     };
@@ -663,15 +842,23 @@ pub extern "C" fn demi_getsockopt(
     trace!("demi_getsockopt()");
 
     // Check inputs.
-    if level != SOL_SOCKET {
-        error!("demi_getsockopt(): only options in SOL_SOCKET level are supported");
+    if level != SOL_SOCKET && level != IPPROTO_TCP {
+        error!("demi_getsockopt(): only options in SOL_SOCKET and IPPROTO_TCP levels are supported");
         return libc::ENOTSUP;
     }
 
-    let opt: SocketOption = match optname {
-        SO_LINGER => SocketOption::Linger(None),
+    let opt: SocketOption = match (level, optname) {
+        (SOL_SOCKET, SO_LINGER) => SocketOption::Linger(None),
+        (SOL_SOCKET, SO_REUSEADDR) => SocketOption::ReuseAddress(false),
+        (SOL_SOCKET, SO_SNDBUF) => SocketOption::SendBufferSize(0),
+        (SOL_SOCKET, SO_RCVBUF) => SocketOption::ReceiveBufferSize(0),
+        (IPPROTO_TCP, TCP_CONGESTION) => SocketOption::CongestionControl(CongestionControlAlgorithm::None),
+        (IPPROTO_TCP, TCP_INFO) => SocketOption::TcpInfo(TcpInfo::default()),
+        (IPPROTO_TCP, TCP_USER_TIMEOUT) => SocketOption::UserTimeout(None),
         _ => {
-            error!("demi_getsockopt(): only SO_LINGER is supported right now");
+            error!(
+                "demi_getsockopt(): only SO_LINGER, SO_REUSEADDR, SO_SNDBUF, SO_RCVBUF, TCP_CONGESTION, TCP_INFO and TCP_USER_TIMEOUT are supported right now"
+            );
             return libc::ENOPROTOOPT;
         },
     };
@@ -698,7 +885,7 @@ pub extern "C" fn demi_getsockopt(
 
     match ret {
         Ok(option) => {
-            // Unpack the value based on the option. We only support linger right now.
+            // Unpack the value based on the option.
             match option {
                 SocketOption::Linger(linger) => {
                     let result: Linger = match linger {
@@ -722,8 +909,85 @@ pub extern "C" fn demi_getsockopt(
                         *optlen = result_length as Socklen;
                     }
                 },
+                SocketOption::ReuseAddress(reuse_address) => {
+                    let result_length: usize = mem::size_of::<c_int>();
+                    if (unsafe { *optlen } as usize) < result_length {
+                        warn!("demi_getsockopt(): reuse address len is too small");
+                        return libc::EINVAL;
+                    }
+                    let result: c_int = reuse_address as c_int;
+                    unsafe {
+                        ptr::copy(&result as *const c_int as *const c_void, optval, result_length);
+                        *optlen = result_length as Socklen;
+                    }
+                },
+                SocketOption::SendBufferSize(buffer_size) | SocketOption::ReceiveBufferSize(buffer_size) => {
+                    let result_length: usize = mem::size_of::<c_int>();
+                    if (unsafe { *optlen } as usize) < result_length {
+                        warn!("demi_getsockopt(): buffer size len is too small");
+                        return libc::EINVAL;
+                    }
+                    let result: c_int = buffer_size.min(c_int::MAX as usize) as c_int;
+                    unsafe {
+                        ptr::copy(&result as *const c_int as *const c_void, optval, result_length);
+                        *optlen = result_length as Socklen;
+                    }
+                },
+                SocketOption::CongestionControl(algorithm) => {
+                    // Like on Linux, copy as much of the NUL-terminated name as fits in the caller's buffer.
+                    let name: &[u8] = algorithm.name().as_bytes();
+                    let capacity: usize = unsafe { *optlen } as usize;
+                    let result_length: usize = capacity.min(name.len() + 1);
+                    unsafe {
+                        let dst: &mut [u8] = slice::from_raw_parts_mut(optval as *mut u8, result_length);
+                        let copied: usize = result_length.min(name.len());
+                        dst[..copied].copy_from_slice(&name[..copied]);
+                        if result_length > name.len() {
+                            dst[name.len()] = 0;
+                        }
+                        *optlen = result_length as Socklen;
+                    }
+                },
+                SocketOption::TcpInfo(info) => {
+                    let result: demi_tcp_info_t = demi_tcp_info_t {
+                        tcpi_rtt: info.srtt.as_micros().min(u32::MAX as u128) as u32,
+                        tcpi_rttvar: info.rttvar.as_micros().min(u32::MAX as u128) as u32,
+                        tcpi_rto: info.rto.as_micros().min(u32::MAX as u128) as u32,
+                        tcpi_snd_mss: info.send_mss,
+                        tcpi_snd_cwnd: info.congestion_window,
+                        tcpi_snd_wnd: info.send_window,
+                        tcpi_rcv_wnd: info.receive_window,
+                        tcpi_bytes_in_flight: info.bytes_in_flight,
+                        tcpi_total_retrans: info.total_retransmits,
+                    };
+                    // Like on Linux, copy as much of the statistics as fits in the caller's buffer.
+                    let result_length: usize = (unsafe { *optlen } as usize).min(mem::size_of::<demi_tcp_info_t>());
+                    unsafe {
+                        ptr::copy(
+                            &result as *const demi_tcp_info_t as *const u8,
+                            optval as *mut u8,
+                            result_length,
+                        );
+                        *optlen = result_length as Socklen;
+                    }
+                },
+                SocketOption::UserTimeout(user_timeout) => {
+                    let result_length: usize = mem::size_of::<c_uint>();
+                    if (unsafe { *optlen } as usize) < result_length {
+                        warn!("demi_getsockopt(): user timeout len is too small");
+                        return libc::EINVAL;
+                    }
+                    let result: c_uint =
+                        user_timeout.map_or(0, |timeout| timeout.as_millis().min(c_uint::MAX as u128) as c_uint);
+                    unsafe {
+                        ptr::copy(&result as *const c_uint as *const c_void, optval, result_length);
+                        *optlen = result_length as Socklen;
+                    }
+                },
                 _ => {
-                    let cause: String = format!("Only SO_LINGER is supported right now");
+                    let cause: String = format!(
+                        "Only SO_LINGER, SO_REUSEADDR, SO_SNDBUF, SO_RCVBUF, TCP_CONGESTION, TCP_INFO and TCP_USER_TIMEOUT are supported right now"
+                    );
                     error!("demi_setsockopt(): {}", cause);
                     return libc::EINVAL;
                 },
@@ -858,7 +1122,10 @@ mod test {
     use crate::{
         demikernel::bindings::{demi_getsockopt, demi_init, demi_setsockopt, demi_socket, sockaddr_to_socketaddr},
         ensure_eq, ensure_neq,
-        pal::{AddressFamily, Linger, SockAddrStorage, Socklen, AF_INET, SOL_SOCKET, SO_LINGER},
+        pal::{
+            AddressFamily, Linger, SockAddrStorage, Socklen, AF_INET, IPPROTO_TCP, SOL_SOCKET, SO_LINGER, SO_REUSEADDR,
+            SO_SNDBUF, TCP_CONGESTION,
+        },
     };
 
     #[test]
@@ -1023,4 +1290,173 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    fn test_set_and_get_congestion_control() -> anyhow::Result<()> {
+        // Initialize Demikernel
+
+        use crate::runtime::types::demi_args_t;
+        let args: demi_args_t = demi_args_t::default();
+        let result: c_int = demi_init(&args);
+        ensure_eq!(result, 0);
+
+        let mut qd: c_int = 0;
+        let result: c_int = demi_socket(
+            &mut qd as *mut c_int,
+            Domain::IPV4.into(),
+            Type::STREAM.into(),
+            Protocol::TCP.into(),
+        );
+
+        ensure_eq!(result, 0);
+        ensure_neq!(qd, 0);
+
+        // Unknown algorithms are rejected.
+        let name: &[u8] = b"vegas";
+        let result: c_int = demi_setsockopt(
+            qd,
+            IPPROTO_TCP,
+            TCP_CONGESTION,
+            name.as_ptr() as *const c_void,
+            name.len() as Socklen,
+        );
+        ensure_eq!(result, libc::ENOENT);
+
+        // Switch to Reno.
+        let name: &[u8] = b"reno";
+        let result: c_int = demi_setsockopt(
+            qd,
+            IPPROTO_TCP,
+            TCP_CONGESTION,
+            name.as_ptr() as *const c_void,
+            name.len() as Socklen,
+        );
+        ensure_eq!(result, 0);
+
+        // Check the algorithm's name.
+        let mut name_check: [u8; 16] = [0xff; 16];
+        let mut name_check_len: Socklen = name_check.len() as Socklen;
+        let result: c_int = demi_getsockopt(
+            qd,
+            IPPROTO_TCP,
+            TCP_CONGESTION,
+            name_check.as_mut_ptr() as *mut c_void,
+            &mut name_check_len as *mut Socklen,
+        );
+
+        ensure_eq!(result, 0);
+        ensure_eq!(name_check_len as usize, b"reno\0".len());
+        ensure_eq!(&name_check[..(name_check_len as usize)], b"reno\0");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_and_get_reuse_address() -> anyhow::Result<()> {
+        // Initialize Demikernel
+
+        use crate::runtime::types::demi_args_t;
+        let args: demi_args_t = demi_args_t::default();
+        let result: c_int = demi_init(&args);
+        ensure_eq!(result, 0);
+
+        let mut qd: c_int = 0;
+        let result: c_int = demi_socket(
+            &mut qd as *mut c_int,
+            Domain::IPV4.into(),
+            Type::STREAM.into(),
+            Protocol::TCP.into(),
+        );
+
+        ensure_eq!(result, 0);
+        ensure_neq!(qd, 0);
+
+        // Enable address reuse.
+        let reuse_address: c_int = 1;
+        let result: c_int = demi_setsockopt(
+            qd,
+            SOL_SOCKET,
+            SO_REUSEADDR,
+            &reuse_address as *const c_int as *const c_void,
+            mem::size_of::<c_int>() as Socklen,
+        );
+        ensure_eq!(result, 0);
+
+        // Check that it is enabled.
+        let mut reuse_address_check: c_int = 0;
+        let mut reuse_address_check_len: Socklen = mem::size_of::<c_int>() as Socklen;
+        let result: c_int = demi_getsockopt(
+            qd,
+            SOL_SOCKET,
+            SO_REUSEADDR,
+            &mut reuse_address_check as *mut c_int as *mut c_void,
+            &mut reuse_address_check_len as *mut Socklen,
+        );
+
+        ensure_eq!(result, 0);
+        ensure_eq!(reuse_address_check_len as usize, mem::size_of::<c_int>());
+        ensure_neq!(reuse_address_check, 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_and_get_send_buffer_size() -> anyhow::Result<()> {
+        // Initialize Demikernel
+
+        use crate::runtime::types::demi_args_t;
+        let args: demi_args_t = demi_args_t::default();
+        let result: c_int = demi_init(&args);
+        ensure_eq!(result, 0);
+
+        let mut qd: c_int = 0;
+        let result: c_int = demi_socket(
+            &mut qd as *mut c_int,
+            Domain::IPV4.into(),
+            Type::STREAM.into(),
+            Protocol::TCP.into(),
+        );
+
+        ensure_eq!(result, 0);
+        ensure_neq!(qd, 0);
+
+        // Set the send buffer size.
+        let send_buffer_size: c_int = 65536;
+        let result: c_int = demi_setsockopt(
+            qd,
+            SOL_SOCKET,
+            SO_SNDBUF,
+            &send_buffer_size as *const c_int as *const c_void,
+            mem::size_of::<c_int>() as Socklen,
+        );
+        ensure_eq!(result, 0);
+
+        // Check that it took effect. Linux doubles the value to make room for bookkeeping overhead.
+        let mut send_buffer_size_check: c_int = 0;
+        let mut send_buffer_size_check_len: Socklen = mem::size_of::<c_int>() as Socklen;
+        let result: c_int = demi_getsockopt(
+            qd,
+            SOL_SOCKET,
+            SO_SNDBUF,
+            &mut send_buffer_size_check as *mut c_int as *mut c_void,
+            &mut send_buffer_size_check_len as *mut Socklen,
+        );
+
+        ensure_eq!(result, 0);
+        ensure_eq!(send_buffer_size_check_len as usize, mem::size_of::<c_int>());
+        anyhow::ensure!(send_buffer_size_check >= send_buffer_size);
+
+        // A send buffer must be able to hold some data.
+        let send_buffer_size: c_int = 0;
+        let result: c_int = demi_setsockopt(
+            qd,
+            SOL_SOCKET,
+            SO_SNDBUF,
+            &send_buffer_size as *const c_int as *const c_void,
+            mem::size_of::<c_int>() as Socklen,
+        );
+        ensure_eq!(result, libc::EINVAL);
+
+        Ok(())
+    }
 }
@@ -5,7 +5,11 @@
 // Imports
 //======================================================================================================================
 
-use crate::{pal::KeepAlive, runtime::fail::Fail, MacAddress};
+use crate::{
+    pal::KeepAlive,
+    runtime::{fail::Fail, network::socket::option::CongestionControlAlgorithm},
+    MacAddress,
+};
 #[cfg(any(feature = "catnip-libos"))]
 use ::std::ffi::CString;
 use ::std::{collections::HashMap, fs::File, io::Read, net::Ipv4Addr, ops::Index, str::FromStr, time::Duration};
@@ -31,6 +35,9 @@ mod tcp_socket_options {
     pub const KEEP_ALIVE: &str = "keepalive";
     pub const LINGER: &str = "linger";
     pub const NO_DELAY: &str = "nodelay";
+    pub const CONGESTION_CONTROL: &str = "congestion_control";
+    pub const SEND_BUFFER_SIZE: &str = "send_buffer_size";
+    pub const USER_TIMEOUT: &str = "user_timeout_millis";
 }
 
 // These only apply to the inetstack.
@@ -45,6 +52,27 @@ mod inetstack_config {
     pub const ENABLE_JUMBO_FRAMES: &str = "enable_jumbo_frames";
     pub const UDP_CHECKSUM_OFFLOAD: &str = "udp_checksum_offload";
     pub const TCP_CHECKSUM_OFFLOAD: &str = "tcp_checksum_offload";
+    pub const TCP_RECEIVE_BUFFER_SIZE: &str = "tcp_receive_buffer_size";
+    pub const TCP_RECEIVE_BUFFER_MIN_SIZE: &str = "tcp_receive_buffer_min_size";
+    pub const TCP_RECEIVE_BUFFER_MAX_SIZE: &str = "tcp_receive_buffer_max_size";
+    pub const TCP_SYN_BACKLOG: &str = "tcp_syn_backlog";
+    pub const TCP_SYN_COOKIES: &str = "tcp_syn_cookies";
+    pub const TCP_MTU_PROBING: &str = "tcp_mtu_probing";
+    pub const TCP_ECN: &str = "tcp_ecn";
+    pub const TCP_FASTOPEN: &str = "tcp_fastopen";
+}
+
+// TCP timers and retries of the inetstack, in a subsection of the inetstack options.
+mod tcp_timers {
+    pub const SECTION_NAME: &str = "tcp_timers";
+    pub const RTO_MIN_MICROS: &str = "rto_min_micros";
+    pub const RTO_MAX_MICROS: &str = "rto_max_micros";
+    pub const RTO_INITIAL_MICROS: &str = "rto_initial_micros";
+    pub const HANDSHAKE_TIMEOUT_MICROS: &str = "handshake_timeout_micros";
+    pub const HANDSHAKE_RETRIES: &str = "handshake_retries";
+    pub const DATA_RETRIES: &str = "data_retries";
+    pub const PERSIST_MIN_MICROS: &str = "persist_min_micros";
+    pub const PERSIST_MAX_MICROS: &str = "persist_max_micros";
 }
 
 // DPDK options. These only apply to catnip.
@@ -101,6 +129,10 @@ impl Config {
         Self::get_subsection(&self.0, inetstack_config::SECTION_NAME)
     }
 
+    fn get_tcp_timers(&self) -> Result<&Yaml, Fail> {
+        Self::get_subsection(self.get_inetstack_config()?, tcp_timers::SECTION_NAME)
+    }
+
     #[cfg(feature = "catnip-libos")]
     fn get_dpdk_config(&self) -> Result<&Yaml, Fail> {
         Self::get_subsection(&self.0, dpdk_config::SECTION_NAME)
@@ -175,6 +207,24 @@ impl Config {
         })
     }
 
+    /// Tcp socket option: Reads how long a connection may be idle before the inetstack starts sending keepalive probes,
+    /// from the "time_millis" key of the "keepalive" subsection.
+    pub fn tcp_keepalive_idle(&self) -> Result<Duration, Fail> {
+        Ok(Duration::from_millis(self.get_keepalive_parameter("time_millis")?))
+    }
+
+    /// Tcp socket option: Reads the time between keepalive probes of the inetstack, from the "interval" key of the
+    /// "keepalive" subsection. Like on Windows, this is in milliseconds.
+    pub fn tcp_keepalive_interval(&self) -> Result<Duration, Fail> {
+        Ok(Duration::from_millis(self.get_keepalive_parameter("interval")?))
+    }
+
+    /// Tcp socket option: Reads the number of unanswered keepalive probes after which the inetstack drops a
+    /// connection, from the "probes" key of the "keepalive" subsection.
+    pub fn tcp_keepalive_probes(&self) -> Result<u32, Fail> {
+        self.get_keepalive_parameter("probes")
+    }
+
     /// Tcp socket option: Reads socket linger settings from "linger" subsection. Returned value is Some(_) if enabled;
     /// otherwise, None. The linger duration will be no larger than u16::MAX seconds.
     pub fn linger(&self) -> Result<Option<Duration>, Fail> {
@@ -200,6 +250,50 @@ impl Config {
         }
     }
 
+    /// Tcp socket option: Reads the name of the congestion control algorithm that new sockets use.
+    pub fn congestion_control(&self) -> Result<CongestionControlAlgorithm, Fail> {
+        if let Some(algorithm) = Self::get_typed_env_option(tcp_socket_options::CONGESTION_CONTROL)? {
+            Ok(algorithm)
+        } else {
+            Self::get_typed_str_option(
+                self.get_tcp_socket_options()?,
+                tcp_socket_options::CONGESTION_CONTROL,
+                |val: &str| val.parse().ok(),
+            )
+        }
+    }
+
+    /// Tcp socket option: Reads the number of bytes that new sockets may buffer for sending, like SO_SNDBUF.
+    pub fn send_buffer_size(&self) -> Result<usize, Fail> {
+        let send_buffer_size: usize =
+            if let Some(send_buffer_size) = Self::get_typed_env_option(tcp_socket_options::SEND_BUFFER_SIZE)? {
+                send_buffer_size
+            } else {
+                Self::get_int_option(self.get_tcp_socket_options()?, tcp_socket_options::SEND_BUFFER_SIZE)?
+            };
+        if send_buffer_size == 0 {
+            let cause: String = format!(
+                "parameter \"{}\" must not be zero",
+                tcp_socket_options::SEND_BUFFER_SIZE
+            );
+            error!("send_buffer_size(): {}", cause);
+            return Err(Fail::new(libc::EINVAL, &cause));
+        }
+        Ok(send_buffer_size)
+    }
+
+    /// Tcp socket option: Reads how long data may stay unacknowledged before the connection is dropped, in
+    /// milliseconds. Like Linux's TCP_USER_TIMEOUT, zero disables the timeout.
+    pub fn user_timeout(&self) -> Result<Option<Duration>, Fail> {
+        let user_timeout: u64 =
+            if let Some(user_timeout) = Self::get_typed_env_option(tcp_socket_options::USER_TIMEOUT)? {
+                user_timeout
+            } else {
+                Self::get_int_option(self.get_tcp_socket_options()?, tcp_socket_options::USER_TIMEOUT)?
+            };
+        Ok(Some(Duration::from_millis(user_timeout)).filter(|timeout| !timeout.is_zero()))
+    }
+
     /// Tcp Config: Reads the "ARP table" parameter from the underlying configuration file. If no ARP table is present,
     /// then ARP is disabled. This cannot be passed in as an environment variable.
     pub fn arp_table(&self) -> Result<Option<HashMap<Ipv4Addr, MacAddress>>, Fail> {
@@ -341,6 +435,93 @@ impl Config {
         Self::get_bool_option(self.get_inetstack_config()?, inetstack_config::TCP_CHECKSUM_OFFLOAD)
     }
 
+    pub fn tcp_receive_buffer_size(&self) -> Result<u32, Fail> {
+        Self::get_int_option(self.get_inetstack_config()?, inetstack_config::TCP_RECEIVE_BUFFER_SIZE)
+    }
+
+    pub fn tcp_receive_buffer_min_size(&self) -> Result<u32, Fail> {
+        Self::get_int_option(
+            self.get_inetstack_config()?,
+            inetstack_config::TCP_RECEIVE_BUFFER_MIN_SIZE,
+        )
+    }
+
+    pub fn tcp_receive_buffer_max_size(&self) -> Result<u32, Fail> {
+        Self::get_int_option(
+            self.get_inetstack_config()?,
+            inetstack_config::TCP_RECEIVE_BUFFER_MAX_SIZE,
+        )
+    }
+
+    pub fn tcp_syn_backlog(&self) -> Result<usize, Fail> {
+        Self::get_int_option(self.get_inetstack_config()?, inetstack_config::TCP_SYN_BACKLOG)
+    }
+
+    pub fn tcp_syn_cookies(&self) -> Result<bool, Fail> {
+        Self::get_bool_option(self.get_inetstack_config()?, inetstack_config::TCP_SYN_COOKIES)
+    }
+
+    /// Reads when TCP connections search for the path MTU, like Linux's tcp_mtu_probing: 0 never, 1 after suspecting a
+    /// black hole and 2 always.
+    pub fn tcp_mtu_probing(&self) -> Result<u8, Fail> {
+        Self::get_int_option(self.get_inetstack_config()?, inetstack_config::TCP_MTU_PROBING)
+    }
+
+    /// Reads when TCP connections use ECN, like Linux's tcp_ecn: 0 never, 1 on the connections that we open and those
+    /// whose peers request it, and 2 only when our peers request it.
+    pub fn tcp_ecn(&self) -> Result<u8, Fail> {
+        Self::get_int_option(self.get_inetstack_config()?, inetstack_config::TCP_ECN)
+    }
+
+    /// Reads which side of TCP connections uses Fast Open, like Linux's tcp_fastopen: 0 neither, 1 the connections that
+    /// we open, 2 the connections that our peers open and 3 both.
+    pub fn tcp_fastopen(&self) -> Result<u8, Fail> {
+        Self::get_int_option(self.get_inetstack_config()?, inetstack_config::TCP_FASTOPEN)
+    }
+
+    /// Reads the smallest retransmission timeout of TCP connections, from the "tcp_timers" subsection. Like all TCP
+    /// timers, this is in microseconds, as datacenter deployments may want timeouts below a millisecond.
+    pub fn tcp_rto_min(&self) -> Result<Duration, Fail> {
+        self.get_tcp_timer(tcp_timers::RTO_MIN_MICROS)
+    }
+
+    /// Reads the largest retransmission timeout of TCP connections, which bounds the exponential back-off.
+    pub fn tcp_rto_max(&self) -> Result<Duration, Fail> {
+        self.get_tcp_timer(tcp_timers::RTO_MAX_MICROS)
+    }
+
+    /// Reads the retransmission timeout of TCP connections before we have measured their round-trip time.
+    pub fn tcp_rto_initial(&self) -> Result<Duration, Fail> {
+        self.get_tcp_timer(tcp_timers::RTO_INITIAL_MICROS)
+    }
+
+    /// Reads how long we wait for an answer to a SYN or SYN+ACK before retransmitting it.
+    pub fn tcp_handshake_timeout(&self) -> Result<Duration, Fail> {
+        self.get_tcp_timer(tcp_timers::HANDSHAKE_TIMEOUT_MICROS)
+    }
+
+    /// Reads how many times we retransmit a SYN or SYN+ACK before giving up on the handshake, like Linux's
+    /// tcp_syn_retries.
+    pub fn tcp_handshake_retries(&self) -> Result<usize, Fail> {
+        Self::get_int_option(self.get_tcp_timers()?, tcp_timers::HANDSHAKE_RETRIES)
+    }
+
+    /// Reads how many times in a row we retransmit data without an acknowledgement before dropping the connection,
+    /// like Linux's tcp_retries2.
+    pub fn tcp_data_retries(&self) -> Result<usize, Fail> {
+        Self::get_int_option(self.get_tcp_timers()?, tcp_timers::DATA_RETRIES)
+    }
+
+    /// Reads the shortest interval between window probes while our peer advertises a zero window.
+    pub fn tcp_persist_min(&self) -> Result<Duration, Fail> {
+        self.get_tcp_timer(tcp_timers::PERSIST_MIN_MICROS)
+    }
+
+    /// Reads the longest interval between window probes, which bounds their exponential back-off.
+    pub fn tcp_persist_max(&self) -> Result<Duration, Fail> {
+        self.get_tcp_timer(tcp_timers::PERSIST_MAX_MICROS)
+    }
+
     pub fn udp_checksum_offload(&self) -> Result<bool, Fail> {
         Self::get_bool_option(self.get_inetstack_config()?, inetstack_config::UDP_CHECKSUM_OFFLOAD)
     }
@@ -366,6 +547,25 @@ impl Config {
         }
     }
 
+    /// Reads a parameter of the keepalive mechanism from the "keepalive" subsection, which must not be zero.
+    fn get_keepalive_parameter<T: TryFrom<i64> + Default + PartialEq>(&self, index: &str) -> Result<T, Fail> {
+        let section: &Yaml = Self::get_subsection(self.get_tcp_socket_options()?, tcp_socket_options::KEEP_ALIVE)?;
+        let value: T = Self::get_int_option(section, index)?;
+        if value == T::default() {
+            let message: String = format!("parameter \"{}\" must not be zero", index);
+            return Err(Fail::new(libc::EINVAL, message.as_str()));
+        }
+        Ok(value)
+    }
+
+    /// Reads a timer from the "tcp_timers" subsection, in microseconds.
+    fn get_tcp_timer(&self, index: &str) -> Result<Duration, Fail> {
+        Ok(Duration::from_micros(Self::get_int_option(
+            self.get_tcp_timers()?,
+            index,
+        )?))
+    }
+
     /// Index `yaml` to find the value at `index`, validating that the index exists.
     fn get_option<'a>(yaml: &'a Yaml, index: &str) -> Result<&'a Yaml, Fail> {
         match yaml.index(index) {
@@ -33,7 +33,7 @@ use crate::{
 };
 use ::std::{
     env,
-    net::{SocketAddr, SocketAddrV4},
+    net::{Shutdown, SocketAddr, SocketAddrV4},
     time::Duration,
 };
 
@@ -231,6 +231,25 @@ impl LibOS {
         result
     }
 
+    /// Initiates a connection with a remote peer and sends a scatter-gather array on it, in the SYN with TCP Fast Open.
+    pub fn connect_with_data(
+        &mut self,
+        sockqd: QDesc,
+        remote: SocketAddr,
+        sga: &demi_sgarray_t,
+    ) -> Result<QToken, Fail> {
+        let result: Result<QToken, Fail> = {
+            timer!("demikernel::connect_with_data");
+            match self {
+                LibOS::NetworkLibOS(libos) => libos.connect_with_data(sockqd, remote, sga),
+            }
+        };
+
+        self.poll();
+
+        result
+    }
+
     /// Closes an I/O queue. async_close() + wait() achieves the same effect as this synchronous function.
     pub fn close(&mut self, qd: QDesc) -> Result<(), Fail> {
         let result: Result<(), Fail> = {
@@ -251,6 +270,21 @@ impl LibOS {
         result
     }
 
+    /// Shuts down the receiving side, the sending side or both sides of a connection, without closing the I/O queue.
+    #[allow(unused_variables)]
+    pub fn shutdown(&mut self, sockqd: QDesc, how: Shutdown) -> Result<(), Fail> {
+        let result: Result<(), Fail> = {
+            timer!("demikernel::shutdown");
+            match self {
+                LibOS::NetworkLibOS(libos) => libos.shutdown(sockqd, how),
+            }
+        };
+
+        self.poll();
+
+        result
+    }
+
     pub fn async_close(&mut self, qd: QDesc) -> Result<QToken, Fail> {
         let result: Result<QToken, Fail> = {
             timer!("demikernel::async_close");
@@ -28,7 +28,7 @@ use ::futures::FutureExt;
 use ::socket2::{Domain, Protocol, Type};
 use ::std::{
     mem,
-    net::{Ipv4Addr, SocketAddr, SocketAddrV4},
+    net::{Ipv4Addr, Shutdown, SocketAddr, SocketAddrV4},
     ops::{Deref, DerefMut},
     time::Duration,
 };
@@ -207,7 +207,7 @@ impl<T: NetworkTransport> SharedNetworkLibOS<T> {
         // FIXME: add IPv6 support; https://github.com/microsoft/demikernel/issues/935
         let mut queue: SharedNetworkQueue<T> = self.get_shared_queue(&qd)?;
         let coroutine_constructor = || -> Result<QToken, Fail> {
-            let coroutine = Box::pin(self.clone().connect_coroutine(qd, remote).fuse());
+            let coroutine = Box::pin(self.clone().connect_coroutine(qd, remote, None).fuse());
             self.runtime
                 .clone()
                 .insert_io_coroutine("ioc::network::libos::connect", coroutine)
@@ -216,10 +216,38 @@ impl<T: NetworkTransport> SharedNetworkLibOS<T> {
         queue.connect(coroutine_constructor)
     }
 
+    /// Synchronous code to establish a connection to a remote endpoint and send [sga] on it. With TCP Fast Open, the
+    /// data goes in the SYN and reaches the remote endpoint a round trip sooner than with a push after the connect.
+    pub fn connect_with_data(&mut self, qd: QDesc, remote: SocketAddr, sga: &demi_sgarray_t) -> Result<QToken, Fail> {
+        trace!("connect_with_data() qd={:?}, remote={:?}", qd, remote);
+
+        let buf: DemiBuffer = self.transport.clone_sgarray(sga)?;
+        if buf.len() == 0 {
+            let cause: &str = "zero-length buffer";
+            warn!("connect_with_data(): {}", cause);
+            return Err(Fail::new(libc::EINVAL, cause));
+        };
+
+        let mut queue: SharedNetworkQueue<T> = self.get_shared_queue(&qd)?;
+        let coroutine_constructor = || -> Result<QToken, Fail> {
+            let coroutine = Box::pin(self.clone().connect_coroutine(qd, remote, Some(buf)).fuse());
+            self.runtime
+                .clone()
+                .insert_io_coroutine("ioc::network::libos::connect_with_data", coroutine)
+        };
+
+        queue.connect(coroutine_constructor)
+    }
+
     /// Asynchronous code to establish a connection to a remote endpoint. This function returns a coroutine that runs
     /// asynchronously to connect a queue and performs any necessary multi-queue operations at the LibOS-level after
     /// the connect succeeds or fails.
-    async fn connect_coroutine(self, qd: QDesc, remote: SocketAddr) -> (QDesc, OperationResult) {
+    async fn connect_coroutine(
+        self,
+        qd: QDesc,
+        remote: SocketAddr,
+        data: Option<DemiBuffer>,
+    ) -> (QDesc, OperationResult) {
         // Grab the queue, make sure it hasn't been closed in the meantime.
         // This will bump the Rc refcount so the coroutine can have it's own reference to the shared queue data
         // structure and the SharedNetworkQueue will not be freed until this coroutine finishes.
@@ -228,7 +256,7 @@ impl<T: NetworkTransport> SharedNetworkLibOS<T> {
             Err(e) => return (qd, OperationResult::Failed(e)),
         };
         // Wait for connect operation to complete.
-        match queue.connect_coroutine(remote).await {
+        match queue.connect_coroutine(remote, data).await {
             Ok(()) => {
                 // TODO: Do we need to add this to socket id to queue descriptor table?
                 (qd, OperationResult::Connect)
@@ -240,6 +268,14 @@ impl<T: NetworkTransport> SharedNetworkLibOS<T> {
         }
     }
 
+    /// Shuts down the receiving side, the sending side or both sides of the connection on a SharedNetworkQueue. Unlike
+    /// close, this leaves the queue open, so the application may still read after shutting down the sending side.
+    pub fn shutdown(&mut self, qd: QDesc, how: Shutdown) -> Result<(), Fail> {
+        trace!("shutdown() qd={:?}, how={:?}", qd, how);
+
+        self.get_shared_queue(&qd)?.shutdown(how)
+    }
+
     /// Synchronous code to asynchronously close a queue. This function schedules the coroutine that asynchronously
     /// runs the close and any synchronous multi-queue functionality before the close begins.
     pub fn async_close(&mut self, qd: QDesc) -> Result<QToken, Fail> {
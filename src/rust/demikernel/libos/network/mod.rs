@@ -23,7 +23,7 @@ use crate::{
     },
 };
 use ::std::{
-    net::{SocketAddr, SocketAddrV4},
+    net::{Shutdown, SocketAddr, SocketAddrV4},
     time::Duration,
 };
 
@@ -169,6 +169,35 @@ impl NetworkLibOSWrapper {
         }
     }
 
+    /// Initiates a connection with a remote TCP peer and sends a scatter-gather array on it.
+    pub fn connect_with_data(
+        &mut self,
+        sockqd: QDesc,
+        remote: SocketAddr,
+        sga: &demi_sgarray_t,
+    ) -> Result<QToken, Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOSWrapper::Catpowder(libos) => libos.connect_with_data(sockqd, remote, sga),
+            #[cfg(feature = "catnap-libos")]
+            NetworkLibOSWrapper::Catnap(libos) => libos.connect_with_data(sockqd, remote, sga),
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOSWrapper::Catnip(libos) => libos.connect_with_data(sockqd, remote, sga),
+        }
+    }
+
+    /// Shuts down the receiving side, the sending side or both sides of a TCP connection.
+    pub fn shutdown(&mut self, sockqd: QDesc, how: Shutdown) -> Result<(), Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOSWrapper::Catpowder(libos) => libos.shutdown(sockqd, how),
+            #[cfg(all(feature = "catnap-libos"))]
+            NetworkLibOSWrapper::Catnap(libos) => libos.shutdown(sockqd, how),
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOSWrapper::Catnip(libos) => libos.shutdown(sockqd, how),
+        }
+    }
+
     pub fn async_close(&mut self, sockqd: QDesc) -> Result<QToken, Fail> {
         match self {
             #[cfg(feature = "catpowder-libos")]
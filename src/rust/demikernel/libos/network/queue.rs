@@ -20,7 +20,7 @@ use ::futures::{pin_mut, select_biased, FutureExt};
 use ::socket2::{Domain, Type};
 use ::std::{
     any::Any,
-    net::{SocketAddr, SocketAddrV4},
+    net::{Shutdown, SocketAddr, SocketAddrV4},
     ops::{Deref, DerefMut},
 };
 
@@ -189,9 +189,10 @@ impl<T: NetworkTransport> SharedNetworkQueue<T> {
         self.do_generic_sync_control_path_call(coroutine_constructor)
     }
 
-    /// Asynchronously connects the target queue to a remote address. This function contains all of the single-queue,
-    /// asynchronous code necessary to run a connect and any single-queue functionality after the connect completes.
-    pub async fn connect_coroutine(&mut self, remote: SocketAddr) -> Result<(), Fail> {
+    /// Asynchronously connects the target queue to a remote address, and sends [data] if any. This function contains all
+    /// of the single-queue, asynchronous code necessary to run a connect and any single-queue functionality after the
+    /// connect completes.
+    pub async fn connect_coroutine(&mut self, remote: SocketAddr, mut data: Option<DemiBuffer>) -> Result<(), Fail> {
         // 1. Check whether we can still connect.
         self.state_machine.may_connect()?;
 
@@ -200,7 +201,14 @@ impl<T: NetworkTransport> SharedNetworkQueue<T> {
             let mut state_machine: SocketStateMachine = self.state_machine.clone();
             let mut transport: T = self.transport.clone();
             let state_tracker = state_machine.while_may_connect().fuse();
-            let operation = transport.connect(&mut self.socket, remote).fuse();
+            let socket: &mut T::SocketDescriptor = &mut self.socket;
+            let operation = async {
+                match data.as_mut() {
+                    Some(buf) => transport.connect_with_data(socket, remote, buf).await,
+                    None => transport.connect(socket, remote).await,
+                }
+            }
+            .fuse();
             pin_mut!(state_tracker);
             pin_mut!(operation);
 
@@ -228,6 +236,13 @@ impl<T: NetworkTransport> SharedNetworkQueue<T> {
         }
     }
 
+    /// Shuts down the receiving side, the sending side or both sides of the connection on this queue, without closing
+    /// the queue.
+    pub fn shutdown(&mut self, how: Shutdown) -> Result<(), Fail> {
+        self.state_machine.may_shutdown()?;
+        self.transport.clone().shutdown(&mut self.socket, how)
+    }
+
     /// Start an asynchronous coroutine to close this queue.
     pub fn close<F>(&mut self, coroutine_constructor: F) -> Result<QToken, Fail>
     where
@@ -26,7 +26,7 @@ use protocols::{layer1::PhysicalLayer, layer2::SharedLayer2Endpoint, layer3::Sha
 use ::futures::FutureExt;
 use ::std::{
     fmt::Debug,
-    net::{SocketAddr, SocketAddrV4},
+    net::{Shutdown, SocketAddr, SocketAddrV4},
     ops::{Deref, DerefMut},
 };
 
@@ -250,7 +250,42 @@ impl NetworkTransport for SharedInetStack {
     /// returned instead.
     ///
     async fn connect(&mut self, sd: &mut Self::SocketDescriptor, remote: SocketAddr) -> Result<(), Fail> {
-        self.layer4_endpoint.connect(sd, remote).await
+        self.layer4_endpoint.connect(sd, remote, None).await
+    }
+
+    ///
+    /// **Brief**
+    ///
+    /// Connects the socket referred to by `qd` to the remote endpoint specified by `remote` and sends `buf`, in the
+    /// SYN if we have a TCP Fast Open cookie for `remote`.
+    ///
+    /// **Return Value**
+    ///
+    /// Upon successful completion, `Ok(())` is returned. Upon failure, `Fail` is returned instead.
+    ///
+    async fn connect_with_data(
+        &mut self,
+        sd: &mut Self::SocketDescriptor,
+        remote: SocketAddr,
+        buf: &mut DemiBuffer,
+    ) -> Result<(), Fail> {
+        timer!("inetstack::connect_with_data");
+
+        self.layer4_endpoint.connect(sd, remote, Some(buf.clone())).await?;
+        buf.trim(buf.len())
+    }
+
+    ///
+    /// **Brief**
+    ///
+    /// Shuts down the receiving side, the sending side or both sides of the connection referred to by `sd`.
+    ///
+    /// **Return Value**
+    ///
+    /// Upon successful completion, `Ok(())` is returned. Upon failure, `Fail` is returned instead.
+    ///
+    fn shutdown(&mut self, sd: &mut Self::SocketDescriptor, how: Shutdown) -> Result<(), Fail> {
+        self.layer4_endpoint.shutdown(sd, how)
     }
 
     ///
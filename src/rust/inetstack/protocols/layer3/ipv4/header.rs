@@ -47,6 +47,20 @@ const IPV4_CTRL_FLAG_MF: u8 = 0x1;
 // Structures
 //======================================================================================================================
 
+/// Explicit Congestion Notification codepoints (see RFC 3168 Section 5).
+#[repr(u8)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum EcnCodepoint {
+    /// Not ECN-Capable Transport.
+    NotEct = 0x0,
+    /// ECN-Capable Transport, ECT(1).
+    Ect1 = 0x1,
+    /// ECN-Capable Transport, ECT(0).
+    Ect0 = 0x2,
+    /// Congestion Experienced.
+    Ce = 0x3,
+}
+
 /// IPv4 Datagram Header
 #[derive(Debug, Copy, Clone)]
 pub struct Ipv4Header {
@@ -57,7 +71,7 @@ pub struct Ipv4Header {
     /// Differentiated Services Code Point (6 bits).
     dscp: u8,
     /// Explicit Congestion Notification (2 bits).
-    ecn: u8,
+    ecn: EcnCodepoint,
     /// Total length of the packet including header and data (16 bits).
     #[allow(unused)]
     total_length: u16,
@@ -90,7 +104,7 @@ impl Ipv4Header {
             version: IPV4_VERSION,
             ihl: IPV4_IHL_NO_OPTIONS,
             dscp: 0,
-            ecn: 0,
+            ecn: EcnCodepoint::NotEct,
             total_length: IPV4_HEADER_MIN_SIZE as u16,
             identification: 0,
             flags: IPV4_CTRL_FLAG_DF,
@@ -137,10 +151,7 @@ impl Ipv4Header {
         }
 
         // Explicit congestion notification.
-        let ecn: u8 = hdr_buf[1] & 3;
-        if ecn != 0 {
-            warn!("ignoring ecn field (ecn={:?})", ecn);
-        }
+        let ecn: EcnCodepoint = EcnCodepoint::from(hdr_buf[1]);
 
         let total_length: u16 = u16::from_be_bytes([hdr_buf[2], hdr_buf[3]]);
         if total_length < hdr_size {
@@ -235,7 +246,7 @@ impl Ipv4Header {
         buf[0] = (self.version << 4) | self.ihl;
 
         // DSCP + ECN.
-        buf[1] = (self.dscp << 2) | (self.ecn & 3);
+        buf[1] = (self.dscp << 2) | (self.ecn as u8);
 
         // Total Length.
         buf[2..4].copy_from_slice(&(pkt_size_bytes as u16).to_be_bytes());
@@ -277,6 +288,14 @@ impl Ipv4Header {
         self.protocol
     }
 
+    pub fn get_ecn(&self) -> EcnCodepoint {
+        self.ecn
+    }
+
+    pub fn set_ecn(&mut self, ecn: EcnCodepoint) {
+        self.ecn = ecn;
+    }
+
     pub fn compute_checksum(buf: &[u8]) -> u16 {
         let mut state: u32 = 0xffff;
 
@@ -300,3 +319,20 @@ impl Ipv4Header {
         !state as u16
     }
 }
+
+//======================================================================================================================
+// Trait Implementations
+//======================================================================================================================
+
+/// From trait implementation. Only looks at the two low-order bits of [value], which is where the codepoint sits in the
+/// second byte of the header.
+impl From<u8> for EcnCodepoint {
+    fn from(value: u8) -> Self {
+        match value & 3 {
+            0x0 => EcnCodepoint::NotEct,
+            0x1 => EcnCodepoint::Ect1,
+            0x2 => EcnCodepoint::Ect0,
+            _ => EcnCodepoint::Ce,
+        }
+    }
+}
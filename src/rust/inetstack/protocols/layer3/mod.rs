@@ -12,7 +12,12 @@ pub mod ipv4;
 
 use arrayvec::ArrayVec;
 
-pub use self::{arp::SharedArpPeer, icmpv4::SharedIcmpv4Peer, ip::IpProtocol, ipv4::Ipv4Header};
+pub use self::{
+    arp::SharedArpPeer,
+    icmpv4::SharedIcmpv4Peer,
+    ip::IpProtocol,
+    ipv4::{EcnCodepoint, Ipv4Header},
+};
 
 use crate::{
     demi_sgarray_t,
@@ -68,8 +73,8 @@ impl SharedLayer3Endpoint {
         })))
     }
 
-    pub fn receive(&mut self) -> Result<ArrayVec<(Ipv4Addr, IpProtocol, DemiBuffer), RECEIVE_BATCH_SIZE>, Fail> {
-        let mut batch: ArrayVec<(Ipv4Addr, IpProtocol, DemiBuffer), RECEIVE_BATCH_SIZE> = ArrayVec::new();
+    pub fn receive(&mut self) -> Result<ArrayVec<(Ipv4Header, DemiBuffer), RECEIVE_BATCH_SIZE>, Fail> {
+        let mut batch: ArrayVec<(Ipv4Header, DemiBuffer), RECEIVE_BATCH_SIZE> = ArrayVec::new();
         for (eth2_type, mut packet) in self.layer2_endpoint.receive()? {
             match eth2_type {
                 EtherType2::Arp => {
@@ -110,7 +115,7 @@ impl SharedLayer3Endpoint {
                             self.icmpv4.receive(header, packet);
                             continue;
                         },
-                        _ => batch.push((header.get_src_addr(), protocol, packet)),
+                        _ => batch.push((header, packet)),
                     }
                 },
                 EtherType2::Ipv6 => warn!("Ipv6 not supported yet"), // Ignore for now.
@@ -120,12 +125,22 @@ impl SharedLayer3Endpoint {
     }
 
     pub fn transmit_tcp_packet_nonblocking(&mut self, remote_ipv4_addr: Ipv4Addr, pkt: DemiBuffer) -> Result<(), Fail> {
+        self.transmit_tcp_packet_with_ecn_nonblocking(remote_ipv4_addr, EcnCodepoint::NotEct, pkt)
+    }
+
+    /// Sends a TCP segment in an IP packet that carries the ECN codepoint [ecn].
+    pub fn transmit_tcp_packet_with_ecn_nonblocking(
+        &mut self,
+        remote_ipv4_addr: Ipv4Addr,
+        ecn: EcnCodepoint,
+        pkt: DemiBuffer,
+    ) -> Result<(), Fail> {
         let remote_link_addr: MacAddress = match self.arp.try_query(remote_ipv4_addr) {
             Some(addr) => addr,
             _ => return Err(Fail::new(libc::EAGAIN, "destination not in ARP cache")),
         };
 
-        self.transmit_packet(remote_ipv4_addr, remote_link_addr, IpProtocol::TCP, pkt)
+        self.transmit_packet(remote_ipv4_addr, remote_link_addr, IpProtocol::TCP, ecn, pkt)
     }
 
     pub async fn transmit_tcp_packet_blocking(
@@ -135,7 +150,13 @@ impl SharedLayer3Endpoint {
     ) -> Result<(), Fail> {
         let remote_link_addr: MacAddress = self.arp.query(remote_ipv4_addr).await?;
 
-        self.transmit_packet(remote_ipv4_addr, remote_link_addr, IpProtocol::TCP, pkt)
+        self.transmit_packet(
+            remote_ipv4_addr,
+            remote_link_addr,
+            IpProtocol::TCP,
+            EcnCodepoint::NotEct,
+            pkt,
+        )
     }
 
     pub async fn transmit_udp_packet_blocking(
@@ -145,7 +166,13 @@ impl SharedLayer3Endpoint {
     ) -> Result<(), Fail> {
         let remote_link_addr: MacAddress = self.arp.query(remote_ipv4_addr).await?;
 
-        self.transmit_packet(remote_ipv4_addr, remote_link_addr, IpProtocol::UDP, pkt)
+        self.transmit_packet(
+            remote_ipv4_addr,
+            remote_link_addr,
+            IpProtocol::UDP,
+            EcnCodepoint::NotEct,
+            pkt,
+        )
     }
 
     pub fn transmit_packet(
@@ -153,9 +180,11 @@ impl SharedLayer3Endpoint {
         remote_ipv4_addr: Ipv4Addr,
         remote_link_addr: MacAddress,
         ip_protocol: IpProtocol,
+        ecn: EcnCodepoint,
         mut pkt: DemiBuffer,
     ) -> Result<(), Fail> {
-        let ipv4_header: Ipv4Header = Ipv4Header::new(self.local_ipv4_addr, remote_ipv4_addr, ip_protocol);
+        let mut ipv4_header: Ipv4Header = Ipv4Header::new(self.local_ipv4_addr, remote_ipv4_addr, ip_protocol);
+        ipv4_header.set_ecn(ecn);
         ipv4_header.serialize_and_attach(&mut pkt);
         self.layer2_endpoint.transmit_ipv4_packet(remote_link_addr, pkt)
     }
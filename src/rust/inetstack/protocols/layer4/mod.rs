@@ -20,7 +20,7 @@ use crate::{
     demikernel::config::Config,
     expect_some,
     inetstack::protocols::{
-        layer3::{ip::IpProtocol, SharedLayer3Endpoint},
+        layer3::{ip::IpProtocol, Ipv4Header, SharedLayer3Endpoint},
         layer4::{
             ephemeral::EphemeralPorts,
             tcp::{SharedTcpPeer, SharedTcpSocket},
@@ -36,7 +36,7 @@ use crate::{
     timer, SocketOption,
 };
 use ::socket2::{Domain, Type};
-use ::std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use ::std::net::{Ipv4Addr, Shutdown, SocketAddr, SocketAddrV4};
 #[cfg(test)]
 use ::std::{collections::HashMap, hash::RandomState, time::Duration};
 
@@ -94,13 +94,13 @@ impl Peer {
         }
     }
 
-    fn receive_batch(&mut self, batch: ArrayVec<(Ipv4Addr, IpProtocol, DemiBuffer), RECEIVE_BATCH_SIZE>) {
+    fn receive_batch(&mut self, batch: ArrayVec<(Ipv4Header, DemiBuffer), RECEIVE_BATCH_SIZE>) {
         timer!("inetstack::poll_bg_work::for::for");
         trace!("found packets: {:?}", batch.len());
-        for (src_ipv4_addr, ip_type, payload) in batch {
-            match ip_type {
-                IpProtocol::TCP => self.tcp.receive(src_ipv4_addr, payload),
-                IpProtocol::UDP => self.udp.receive(src_ipv4_addr, payload),
+        for (ipv4_hdr, payload) in batch {
+            match ipv4_hdr.get_protocol() {
+                IpProtocol::TCP => self.tcp.receive(ipv4_hdr, payload),
+                IpProtocol::UDP => self.udp.receive(ipv4_hdr.get_src_addr(), payload),
                 _ => unreachable!("Should have been handled at a lower layer"),
             }
         }
@@ -257,7 +257,8 @@ impl Peer {
     ///
     /// **Brief**
     ///
-    /// Connects the socket referred to by `qd` to the remote endpoint specified by `remote`.
+    /// Connects the socket referred to by `qd` to the remote endpoint specified by `remote`, and sends `data` once
+    /// connected, or in the SYN with TCP Fast Open.
     ///
     /// **Return Value**
     ///
@@ -266,7 +267,7 @@ impl Peer {
     /// remote endpoints. Upon failure, `Fail` is
     /// returned instead.
     ///
-    pub async fn connect(&mut self, sd: &mut Socket, remote: SocketAddr) -> Result<(), Fail> {
+    pub async fn connect(&mut self, sd: &mut Socket, remote: SocketAddr, data: Option<DemiBuffer>) -> Result<(), Fail> {
         trace!("connect(): remote={:?}", remote);
 
         match sd {
@@ -279,12 +280,32 @@ impl Peer {
                     None => SocketAddrV4::new(self.layer3_endpoint.get_local_addr(), self.ephemeral_ports.alloc()?),
                 };
 
-                self.tcp.connect(socket, local, remote).await
+                self.tcp.connect(socket, local, remote, data).await
             },
             _ => Err(Fail::new(libc::EINVAL, "invalid queue type")),
         }
     }
 
+    ///
+    /// **Brief**
+    ///
+    /// Shuts down the receiving side, the sending side or both sides of the connection referred to by `sd`.
+    ///
+    /// **Return Value**
+    ///
+    /// Upon successful completion, `Ok(())` is returned. Upon failure, `Fail` is returned instead.
+    ///
+    pub fn shutdown(&mut self, sd: &mut Socket, how: Shutdown) -> Result<(), Fail> {
+        match sd {
+            Socket::Tcp(socket) => self.tcp.shutdown(socket, how),
+            _ => {
+                let cause: String = format!("opperation not supported");
+                error!("shutdown(): {}", cause);
+                Err(Fail::new(libc::ENOTSUP, &cause))
+            },
+        }
+    }
+
     ///
     /// **Brief**
     ///
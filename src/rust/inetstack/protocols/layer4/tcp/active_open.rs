@@ -7,16 +7,13 @@
 
 use crate::{
     collections::{async_queue::SharedAsyncQueue, async_value::SharedAsyncValue},
-    expect_some,
     inetstack::protocols::{
-        layer3::SharedLayer3Endpoint,
+        layer3::{Ipv4Header, SharedLayer3Endpoint},
         layer4::tcp::{
             constants::{FALLBACK_MSS, MAX_WINDOW_SCALE},
-            established::{
-                congestion_control::{self, CongestionControl},
-                EstablishedSocket,
-            },
-            header::{TcpHeader, TcpOptions2},
+            established::EstablishedSocket,
+            header::{FastOpenCookie, TcpHeader, TcpOptions2},
+            timestamps::{find_timestamps, TcpTimestamps},
             SeqNumber,
         },
         MAX_HEADER_SIZE,
@@ -24,14 +21,19 @@ use crate::{
     runtime::{
         fail::Fail,
         memory::DemiBuffer,
-        network::{config::TcpConfig, socket::option::TcpSocketOptions},
+        network::{
+            config::{Ecn, TcpConfig},
+            socket::option::TcpSocketOptions,
+        },
         QDesc, SharedDemiRuntime, SharedObject,
     },
 };
 use ::futures::{channel::mpsc, select_biased, FutureExt};
 use ::std::{
+    cmp,
     net::{Ipv4Addr, SocketAddrV4},
     ops::{Deref, DerefMut},
+    time::Instant,
 };
 
 //======================================================================================================================
@@ -53,11 +55,19 @@ pub struct ActiveOpenSocket {
     remote: SocketAddrV4,
     runtime: SharedDemiRuntime,
     layer3_endpoint: SharedLayer3Endpoint,
-    recv_queue: SharedAsyncQueue<(Ipv4Addr, TcpHeader, DemiBuffer)>,
+    recv_queue: SharedAsyncQueue<(Ipv4Header, TcpHeader, DemiBuffer)>,
     tcp_config: TcpConfig,
     socket_options: TcpSocketOptions,
     dead_socket_tx: mpsc::UnboundedSender<QDesc>,
     state: SharedAsyncValue<State>,
+    // Instant at which our timestamp clock for this connection reads zero.
+    timestamp_epoch: Instant,
+    // Data that the application wants to send as soon as possible, which we try to send in the SYN with TCP Fast Open
+    // (RFC 7413). The part that goes in the SYN is split off.
+    fast_open_data: Option<DemiBuffer>,
+    fast_open_syn_data: Option<DemiBuffer>,
+    // Fast Open cookie that the server gave us on an earlier connection, or in the SYN+ACK of this one.
+    fast_open_cookie: Option<FastOpenCookie>,
 }
 
 #[derive(Clone)]
@@ -74,10 +84,12 @@ impl SharedActiveOpenSocket {
         remote: SocketAddrV4,
         runtime: SharedDemiRuntime,
         layer3_endpoint: SharedLayer3Endpoint,
-        recv_queue: SharedAsyncQueue<(Ipv4Addr, TcpHeader, DemiBuffer)>,
+        recv_queue: SharedAsyncQueue<(Ipv4Header, TcpHeader, DemiBuffer)>,
         tcp_config: TcpConfig,
         default_socket_options: TcpSocketOptions,
         dead_socket_tx: mpsc::UnboundedSender<QDesc>,
+        fast_open_data: Option<DemiBuffer>,
+        fast_open_cookie: Option<FastOpenCookie>,
     ) -> Result<Self, Fail> {
         // TODO: Add fast path here when remote is already in the ARP cache (and subtract one retry).
 
@@ -92,14 +104,21 @@ impl SharedActiveOpenSocket {
             socket_options: default_socket_options,
             dead_socket_tx,
             state: SharedAsyncValue::new(State::Connecting),
+            timestamp_epoch: runtime.get_now(),
+            fast_open_data,
+            fast_open_syn_data: None,
+            fast_open_cookie,
         })))
     }
 
     fn process_ack(&mut self, header: TcpHeader) -> Result<EstablishedSocket, Fail> {
         let expected_seq: SeqNumber = self.local_isn + SeqNumber::from(1);
+        // The server acknowledges the data in our SYN only if it accepted our Fast Open cookie.
+        let syn_data_len: u32 = self.fast_open_syn_data.as_ref().map_or(0, |data| data.len() as u32);
+        let syn_data_acked: bool = syn_data_len > 0 && header.ack_num == expected_seq + SeqNumber::from(syn_data_len);
 
         // Bail if we didn't receive a ACK packet with the right sequence number.
-        if !(header.ack && header.ack_num == expected_seq) {
+        if !(header.ack && (header.ack_num == expected_seq || syn_data_acked)) {
             let cause: String = format!(
                 "expected ack_num: {}, received ack_num: {}",
                 expected_seq, header.ack_num
@@ -122,30 +141,29 @@ impl SharedActiveOpenSocket {
             return Err(Fail::new(libc::EAGAIN, &cause));
         }
 
+        // Whatever the server did not acknowledge in the SYN, we send once the connection is established.
+        if syn_data_acked {
+            info!("Server accepted {} bytes of Fast Open data", syn_data_len);
+            self.fast_open_syn_data = None;
+        }
+
         debug!("Received SYN+ACK: {:?}", header);
 
         let remote_seq_num = header.seq_num + SeqNumber::from(1);
 
-        let mut tcp_hdr = TcpHeader::new(self.local.port(), self.remote.port());
-        tcp_hdr.ack = true;
-        tcp_hdr.ack_num = remote_seq_num;
-        tcp_hdr.window_size = self.tcp_config.get_receive_window_size();
-        tcp_hdr.seq_num = self.local_isn + SeqNumber::from(1);
-        debug!("Sending ACK: {:?}", tcp_hdr);
+        // Timestamps are only used if our peer echoed the option back.
+        let timestamps: Option<TcpTimestamps> = find_timestamps(header.iter_options())
+            .map(|(tsval, _)| TcpTimestamps::new(self.timestamp_epoch, tsval, remote_seq_num));
 
-        let dst_ipv4_addr: Ipv4Addr = self.remote.ip().clone();
-        let mut pkt: DemiBuffer = DemiBuffer::new_with_headroom(0, MAX_HEADER_SIZE as u16);
-        tcp_hdr.serialize_and_attach(
-            &mut pkt,
-            self.local.ip(),
-            self.remote.ip(),
-            self.tcp_config.get_rx_checksum_offload(),
-        );
-        self.layer3_endpoint
-            .transmit_tcp_packet_nonblocking(dst_ipv4_addr, pkt)?;
+        // Our peer agrees to ECN with ECE, but without CWR, in its SYN+ACK (see RFC 3168 Section 6.1.1).
+        let ecn: bool = self.tcp_config.get_ecn() == Ecn::Enabled && header.ece && !header.cwr;
+        if ecn {
+            info!("Negotiated ECN");
+        }
 
         let mut remote_window_scale = None;
         let mut mss = FALLBACK_MSS;
+        let mut sack_permitted: bool = false;
         for option in header.iter_options() {
             match option {
                 TcpOptions2::WindowScale(w) => {
@@ -156,10 +174,20 @@ impl SharedActiveOpenSocket {
                     info!("Received advertised MSS: {}", m);
                     mss = *m as usize;
                 },
+                TcpOptions2::SelectiveAcknowlegementPermitted => {
+                    info!("Received SACK permitted");
+                    sack_permitted = true;
+                },
+                TcpOptions2::FastOpen(cookie) if !cookie.is_empty() => {
+                    info!("Received Fast Open cookie");
+                    self.fast_open_cookie = Some(*cookie);
+                },
                 _ => continue,
             }
         }
 
+        // Without window scaling, we can only advertise as much of our receive buffer as fits in the window field.
+        let receive_buffer_size: u32 = self.receive_buffer_size();
         let (local_window_scale, remote_window_scale): (u32, u8) = match remote_window_scale {
             Some(remote_window_scale) => {
                 let remote: u8 = if remote_window_scale as usize > MAX_WINDOW_SCALE {
@@ -171,24 +199,38 @@ impl SharedActiveOpenSocket {
                 } else {
                     remote_window_scale
                 };
-                (self.tcp_config.get_window_scale() as u32, remote)
+                (self.receive_window_scale() as u32, remote)
             },
             None => (0, 0),
         };
 
-        // Expect is safe here because the receive window size is a 16-bit unsigned integer and MAX_WINDOW_SCALE is 14,
-        // so it is impossible to overflow the 32-bit unsigned int.
         debug_assert!((local_window_scale as usize) <= MAX_WINDOW_SCALE);
-        let rx_window_size: u32 = expect_some!(
-            (self.tcp_config.get_receive_window_size() as u32).checked_shl(local_window_scale as u32),
-            "Window size overflow"
-        );
-        // Expect is safe here because the window size is a 16-bit unsigned integer and MAX_WINDOW_SCALE is 14, so it is impossible to overflow the 32-bit
+        let rx_window_size: u32 = cmp::min(receive_buffer_size, (u16::MAX as u32) << local_window_scale);
+        // The window in the SYN+ACK is never scaled.
         debug_assert!((remote_window_scale as usize) <= MAX_WINDOW_SCALE);
-        let tx_window_size: u32 = expect_some!(
-            (header.window_size as u32).checked_shl(remote_window_scale as u32),
-            "Window size overflow"
+        let tx_window_size: u32 = header.window_size as u32;
+
+        // Unlike in the SYN, the window in the ACK is scaled.
+        let mut tcp_hdr = TcpHeader::new(self.local.port(), self.remote.port());
+        tcp_hdr.ack = true;
+        tcp_hdr.ack_num = remote_seq_num;
+        tcp_hdr.window_size = (rx_window_size >> local_window_scale) as u16;
+        tcp_hdr.seq_num = header.ack_num;
+        if let Some(timestamps) = timestamps {
+            tcp_hdr.push_option(timestamps.option(self.runtime.get_now()));
+        }
+        debug!("Sending ACK: {:?}", tcp_hdr);
+
+        let dst_ipv4_addr: Ipv4Addr = self.remote.ip().clone();
+        let mut pkt: DemiBuffer = DemiBuffer::new_with_headroom(0, MAX_HEADER_SIZE as u16);
+        tcp_hdr.serialize_and_attach(
+            &mut pkt,
+            self.local.ip(),
+            self.remote.ip(),
+            self.tcp_config.get_rx_checksum_offload(),
         );
+        self.layer3_endpoint
+            .transmit_tcp_packet_nonblocking(dst_ipv4_addr, pkt)?;
 
         info!("Window sizes: local {}, remote {}", rx_window_size, tx_window_size);
         info!(
@@ -207,40 +249,109 @@ impl SharedActiveOpenSocket {
             self.tcp_config.get_ack_delay_timeout(),
             rx_window_size,
             local_window_scale,
-            expected_seq,
+            header.ack_num,
             tx_window_size,
             remote_window_scale,
             mss,
-            congestion_control::None::new,
+            sack_permitted,
+            timestamps,
+            ecn,
             None,
             self.dead_socket_tx.clone(),
             None,
         )?)
     }
 
+    // Our receive buffer size, from SO_RCVBUF or else from the inetstack configuration.
+    fn receive_buffer_size(&self) -> u32 {
+        self.socket_options
+            .get_receive_buffer_size()
+            .map_or(self.tcp_config.get_receive_buffer_size(), |size| size as u32)
+    }
+
+    // The window scale that we advertise has to cover the receive buffer once auto-tuning has grown it.
+    fn receive_window_scale(&self) -> u8 {
+        let max_size: u32 = self
+            .socket_options
+            .get_receive_buffer_size()
+            .map_or(self.tcp_config.get_receive_buffer_max_size(), |size| size as u32);
+        TcpConfig::window_scale(max_size)
+    }
+
+    /// Returns the Fast Open cookie that we should use on the next connection to the same server.
+    pub fn fast_open_cookie(&self) -> Option<FastOpenCookie> {
+        self.fast_open_cookie
+    }
+
     pub async fn connect(mut self) -> Result<EstablishedSocket, Fail> {
         // Start connection handshake.
         let handshake_retries: usize = self.tcp_config.get_handshake_retries();
         let handshake_timeout = self.tcp_config.get_handshake_timeout();
 
+        // With a cookie, we send as much data in the SYN as the smallest MSS allows, because we do not know the MSS of
+        // the server yet (RFC 7413 Section 4.1.3). Without one, we ask the server for a cookie and send the data later.
+        let mut fast_open_option: Option<TcpOptions2> = None;
+        if self.tcp_config.get_fast_open().is_client_enabled() && self.fast_open_data.is_some() {
+            match (self.fast_open_cookie, self.fast_open_data.take()) {
+                (Some(cookie), Some(mut data)) => {
+                    self.fast_open_syn_data = Some(data.split_front(cmp::min(data.len(), FALLBACK_MSS))?);
+                    self.fast_open_data = Some(data);
+                    fast_open_option = Some(TcpOptions2::FastOpen(cookie));
+                },
+                (None, data) => {
+                    self.fast_open_data = data;
+                    fast_open_option = Some(TcpOptions2::FastOpen(FastOpenCookie::default()));
+                },
+                (_, None) => unreachable!("there should be data to send"),
+            }
+        }
+
         // Try to connect.
-        for _ in 0..handshake_retries {
+        for attempt in 0..handshake_retries {
             // Set up SYN packet.
             let mut tcp_hdr = TcpHeader::new(self.local.port(), self.remote.port());
             tcp_hdr.syn = true;
             tcp_hdr.seq_num = self.local_isn;
-            tcp_hdr.window_size = self.tcp_config.get_receive_window_size();
+            // We request ECN with both ECE and CWR set.
+            if self.tcp_config.get_ecn() == Ecn::Enabled {
+                tcp_hdr.ece = true;
+                tcp_hdr.cwr = true;
+            }
+            // The window in a SYN is never scaled.
+            let receive_buffer_size: u32 = self.receive_buffer_size();
+            tcp_hdr.window_size = cmp::min(receive_buffer_size, u16::MAX as u32) as u16;
 
             let mss = self.tcp_config.get_advertised_mss() as u16;
             tcp_hdr.push_option(TcpOptions2::MaximumSegmentSize(mss));
             info!("Advertising MSS: {}", mss);
 
-            tcp_hdr.push_option(TcpOptions2::WindowScale(self.tcp_config.get_window_scale()));
-            info!("Advertising window scale: {}", self.tcp_config.get_window_scale());
+            let window_scale: u8 = self.receive_window_scale();
+            tcp_hdr.push_option(TcpOptions2::WindowScale(window_scale));
+            info!("Advertising window scale: {}", window_scale);
+
+            tcp_hdr.push_option(TcpOptions2::SelectiveAcknowlegementPermitted);
+            info!("Advertising SACK permitted");
+
+            let tsval: u32 = TcpTimestamps::clock(self.timestamp_epoch, self.runtime.get_now());
+            tcp_hdr.push_option(TcpOptions2::Timestamp {
+                sender_timestamp: tsval,
+                echo_timestamp: 0,
+            });
+            info!("Advertising timestamps: {}", tsval);
+
+            if let Some(option) = fast_open_option {
+                tcp_hdr.push_option(option);
+                info!("Sending Fast Open cookie option");
+            }
 
             debug!("Sending SYN {:?}", tcp_hdr);
             let dst_ipv4_addr: Ipv4Addr = self.remote.ip().clone();
-            let mut pkt: DemiBuffer = DemiBuffer::new_with_headroom(0, MAX_HEADER_SIZE as u16);
+            // Only the first SYN carries data, in case a middlebox drops SYNs with data (RFC 7413 Section 4.1.3). The
+            // server may still acknowledge the data if it did receive that SYN.
+            let mut pkt: DemiBuffer = match self.fast_open_syn_data.as_ref().filter(|_| attempt == 0) {
+                Some(syn_data) => syn_data.clone(),
+                None => DemiBuffer::new_with_headroom(0, MAX_HEADER_SIZE as u16),
+            };
             tcp_hdr.serialize_and_attach(
                 &mut pkt,
                 self.local.ip(),
@@ -258,7 +369,7 @@ impl SharedActiveOpenSocket {
             }
 
             // Wait for either a response or timeout.
-            let mut recv_queue: SharedAsyncQueue<(Ipv4Addr, TcpHeader, DemiBuffer)> = self.recv_queue.clone();
+            let mut recv_queue: SharedAsyncQueue<(Ipv4Header, TcpHeader, DemiBuffer)> = self.recv_queue.clone();
             let mut state: SharedAsyncValue<State> = self.state.clone();
             select_biased! {
             r = state.wait_for_change(None).fuse() => if let Ok(r) = r {
@@ -270,7 +381,10 @@ impl SharedActiveOpenSocket {
             },
             r = recv_queue.pop(Some(handshake_timeout)).fuse() => match r {
                 Ok((_, header, _)) => match self.process_ack(header) {
-                        Ok(socket) => return Ok(socket),
+                        Ok(mut socket) => {
+                            self.push_fast_open_data(&mut socket);
+                            return Ok(socket);
+                        },
                         Err(Fail { errno, cause: _ }) if errno == libc::EAGAIN => continue,
                         Err(e) => return Err(e),
                     },
@@ -289,6 +403,19 @@ impl SharedActiveOpenSocket {
         Err(Fail::new(libc::ECONNREFUSED, &cause))
     }
 
+    /// Queues the data that the application gave us to connect with, except for what the server accepted in the SYN.
+    /// The connection is established already, so we don't wait for this data to be acknowledged.
+    fn push_fast_open_data(&mut self, socket: &mut EstablishedSocket) {
+        if let Some(syn_data) = self.fast_open_syn_data.take() {
+            socket.push_connect_data(syn_data);
+        }
+        if let Some(data) = self.fast_open_data.take() {
+            if !data.is_empty() {
+                socket.push_connect_data(data);
+            }
+        }
+    }
+
     pub fn close(&mut self) {
         self.state.set(State::Closed);
     }
@@ -12,11 +12,8 @@ pub async fn acknowledger(mut cb: SharedControlBlock) -> Result<Never, Fail> {
     let mut ack_deadline: SharedAsyncValue<Option<Instant>> = cb.get_receive_ack_deadline();
     let mut deadline: Option<Instant> = ack_deadline.get();
     loop {
-        // TODO: Implement TCP delayed ACKs, subject to restrictions from RFC 1122
-        // - TCP should implement a delayed ACK
-        // - The delay must be less than 500ms
-        // - For a stream of full-sized segments, there should be an ack for every other segment.
-        // TODO: Implement SACKs
+        // The receive path decides when an ACK can be delayed (see RFC 1122 Section 4.2.3.2), sending a segment cancels
+        // the deadline and we only need to send an ACK once it passes.
         match ack_deadline.wait_for_change_until(deadline).await {
             Ok(value) => {
                 deadline = value;
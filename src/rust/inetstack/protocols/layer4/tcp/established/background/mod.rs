@@ -21,10 +21,26 @@ pub async fn background(cb: SharedControlBlock, _dead_socket_tx: mpsc::Unbounded
     let sender = async_timer!("tcp::established::background::sender", cb.clone().background_sender()).fuse();
     pin_mut!(sender);
 
+    let keepalive = async_timer!(
+        "tcp::established::background::keepalive",
+        cb.clone().background_keepalive()
+    )
+    .fuse();
+    pin_mut!(keepalive);
+
     let mut cb2: SharedControlBlock = cb.clone();
     let receiver = async_timer!("tcp::established::background::receiver", cb2.poll()).fuse();
     pin_mut!(receiver);
 
-    let r = futures::join!(receiver, acknowledger, retransmitter, sender);
-    error!("Connection terminated: {:?}", r);
+    // Stop everything once the connection is aborted.
+    let aborted = cb.clone().wait_for_abort().fuse();
+    pin_mut!(aborted);
+
+    let connection = async { futures::join!(receiver, acknowledger, retransmitter, sender, keepalive) }.fuse();
+    pin_mut!(connection);
+
+    futures::select_biased! {
+        e = aborted => debug!("Connection aborted: {:?}", e),
+        r = connection => error!("Connection terminated: {:?}", r),
+    }
 }
@@ -0,0 +1,594 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+// This is version 1 of BBR (Bottleneck Bandwidth and Round-trip propagation time), as described in
+// draft-cardwell-iccrg-bbr-congestion-control-00.  Rather than reacting to loss, BBR builds a model of the path from
+// the delivery rate samples taken on every ACK: the bottleneck bandwidth is the maximum delivery rate seen over the
+// last few round trips and the propagation delay is the minimum RTT seen over the last few seconds.  It then paces
+// data at (a multiple of) the bottleneck bandwidth and caps the data in flight at (a multiple of) the bandwidth-delay
+// product, so that it keeps the pipe full without building up queues in shallow-buffered switches.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::{
+    collections::async_value::SharedAsyncValue,
+    inetstack::protocols::layer4::tcp::{
+        established::congestion_control::{
+            CongestionControl, FastRetransmitRecovery, LimitedTransmit, Options, RateSample,
+            SlowStartCongestionAvoidance,
+        },
+        SeqNumber,
+    },
+};
+use ::std::{
+    cmp::{max, min},
+    collections::VecDeque,
+    fmt::Debug,
+    time::{Duration, Instant},
+};
+
+//======================================================================================================================
+// Structures
+//======================================================================================================================
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Mode {
+    // Ramp up quickly to find the bottleneck bandwidth.
+    Startup,
+    // Drain the queue built up during startup.
+    Drain,
+    // Cruise at the bottleneck bandwidth, periodically probing for more.
+    ProbeBw,
+    // Briefly reduce the data in flight to measure the propagation delay.
+    ProbeRtt,
+}
+
+#[derive(Debug)]
+pub struct Bbr {
+    mss: u32,
+    mode: Mode,
+    // Congestion window: Max number of bytes that may be in flight.
+    cwnd: SharedAsyncValue<u32>,
+    // cwnd before entering ProbeRTT or timing out, restored afterwards.
+    prior_cwnd: u32,
+    // Rate at which we send, in bytes per second.
+    pacing_rate: u64,
+    pacing_gain: f64,
+    cwnd_gain: f64,
+
+    // Windowed max filter of delivery rates (bytes per second) over the last BTLBW_FILTER_ROUNDS round trips, stored as
+    // (round, rate) pairs with decreasing rates.
+    btlbw_filter: VecDeque<(u64, u64)>,
+    // Minimum RTT seen over the last MIN_RTT_FILTER_LEN and when we saw it.
+    min_rtt: Option<Duration>,
+    min_rtt_stamp: Option<Instant>,
+
+    // Number of round trips so far, and the amount delivered at which the next one starts.
+    round_count: u64,
+    next_round_delivered: u64,
+
+    // Bottleneck bandwidth at the last time it grew significantly during startup, and the number of round trips since.
+    full_bw: u64,
+    full_bw_count: u32,
+    filled_pipe: bool,
+
+    // Current phase in the ProbeBW gain cycle and when it started.
+    cycle_index: usize,
+    cycle_stamp: Option<Instant>,
+
+    // When ProbeRTT ends, once we've spent a round trip with a small amount of data in flight.
+    probe_rtt_done_stamp: Option<Instant>,
+    probe_rtt_round_done: bool,
+
+    // Round trip in which we detected a loss, during which we only send as much as gets delivered.
+    packet_conservation_round: Option<u64>,
+
+    // Number of consecutive duplicate ACKs, as counted by the control block.
+    duplicate_ack_count: u32,
+    // Flag to cause the retransmitter to retransmit a segment now.
+    fast_retransmit_now: SharedAsyncValue<bool>,
+
+    // BBR paces instead of using limited transmit, so this is always zero.
+    limited_transmit_cwnd_increase: SharedAsyncValue<u32>,
+}
+
+//======================================================================================================================
+// Constants
+//======================================================================================================================
+
+impl Bbr {
+    // Gain of 2/ln(2), the smallest that doubles the sending rate every round trip during startup.
+    const HIGH_GAIN: f64 = 2.885;
+    // Gains that cycle the pacing rate during ProbeBW: probe for more bandwidth, drain the resulting queue, then cruise.
+    const PACING_GAIN_CYCLE: [f64; 8] = [1.25, 0.75, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0];
+    const PROBE_BW_CWND_GAIN: f64 = 2.0;
+    const BTLBW_FILTER_ROUNDS: u64 = 10;
+    const MIN_RTT_FILTER_LEN: Duration = Duration::from_secs(10);
+    const PROBE_RTT_DURATION: Duration = Duration::from_millis(200);
+    // Startup ends once the bottleneck bandwidth stops growing by at least 25% for three round trips.
+    const FULL_BW_THRESHOLD: f64 = 1.25;
+    const FULL_BW_COUNT: u32 = 3;
+    // Pace slightly below the estimated bandwidth so that we don't build a queue at the bottleneck.
+    const PACING_MARGIN_PERCENT: u64 = 1;
+    const MIN_PIPE_CWND_SEGMENTS: u32 = 4;
+    const DUP_ACK_THRESHOLD: u32 = 3;
+}
+
+//======================================================================================================================
+// Trait Implementations
+//======================================================================================================================
+
+impl CongestionControl for Bbr {
+    fn new(mss: usize, _seq_no: SeqNumber, _options: Option<Options>) -> Box<dyn CongestionControl> {
+        Box::new(Self::with_mss(mss.try_into().unwrap()))
+    }
+}
+
+impl SlowStartCongestionAvoidance for Bbr {
+    fn get_cwnd(&self) -> SharedAsyncValue<u32> {
+        self.cwnd.clone()
+    }
+
+    fn get_pacing_rate(&self) -> Option<u64> {
+        Some(self.pacing_rate)
+    }
+
+    fn on_ack_received(
+        &mut self,
+        _rto: Duration,
+        send_unacked: SeqNumber,
+        send_next: SeqNumber,
+        ack_seq_no: SeqNumber,
+    ) {
+        if ack_seq_no > send_unacked && ack_seq_no <= send_next {
+            self.duplicate_ack_count = 0;
+        }
+    }
+
+    fn on_rate_sample(&mut self, sample: &RateSample) {
+        // Update the model.
+        let round_start: bool = self.update_round(sample);
+        self.update_btlbw(sample);
+        if round_start {
+            self.check_full_pipe(sample);
+        }
+        let min_rtt_expired: bool = self.update_min_rtt(sample);
+
+        // Update the state machine.
+        match self.mode {
+            Mode::Startup if self.filled_pipe => self.enter_drain(),
+            Mode::ProbeBw => self.update_gain_cycle(sample),
+            _ => (),
+        }
+        if self.mode == Mode::Drain && sample.bytes_in_flight <= self.target_cwnd(1.0) {
+            self.enter_probe_bw(sample.now);
+        }
+        if min_rtt_expired && self.mode != Mode::ProbeRtt {
+            self.enter_probe_rtt();
+        }
+        if self.mode == Mode::ProbeRtt {
+            self.update_probe_rtt(sample, round_start);
+        }
+
+        // Update the control parameters.
+        self.set_pacing_rate();
+        self.set_cwnd(sample);
+    }
+
+    fn on_rto(&mut self, _send_unacked: SeqNumber) {
+        // The model is still valid, but we don't know what's in flight anymore: start over from one segment and let the
+        // window grow back up to the target as the retransmissions get acknowledged.
+        self.save_cwnd();
+        self.cwnd.set(self.mss);
+        self.duplicate_ack_count = 0;
+    }
+
+    fn on_ecn_echo(&mut self, _send_unacked: SeqNumber, _send_next: SeqNumber) {
+        // Like BBR v1 in Linux, ignore congestion marks. The draft defines no response to them, and the model already
+        // keeps the data in flight near the bandwidth-delay product, which is what marks at a shallow queue ask for.
+    }
+
+    fn on_loss_detected(&mut self, send_unacked: SeqNumber, send_next: SeqNumber) {
+        // Loss doesn't change the model, but the lost data has left the network. Shrink the window to what is still
+        // in flight, leaving room for the retransmission, and hold it there for a round trip (draft Section 4.2.3.4).
+        self.save_cwnd();
+        let bytes_in_flight: u32 = (send_next - send_unacked).into();
+        self.cwnd.set(bytes_in_flight + self.mss);
+        self.packet_conservation_round = Some(self.round_count);
+    }
+}
+
+impl FastRetransmitRecovery for Bbr {
+    fn get_duplicate_ack_count(&self) -> u32 {
+        self.duplicate_ack_count
+    }
+
+    fn get_retransmit_now_flag(&self) -> SharedAsyncValue<bool> {
+        self.fast_retransmit_now.clone()
+    }
+
+    fn on_duplicate_ack(&mut self, _send_unacked: SeqNumber, _send_next: SeqNumber, duplicate_ack_count: u32) {
+        // Loss doesn't change the model, but we still repair it quickly.
+        self.duplicate_ack_count = duplicate_ack_count;
+        if duplicate_ack_count == Self::DUP_ACK_THRESHOLD {
+            self.fast_retransmit_now.set(true);
+        }
+    }
+
+    fn on_fast_retransmit(&mut self) {
+        self.fast_retransmit_now.set_without_notify(false);
+    }
+}
+
+impl LimitedTransmit for Bbr {
+    fn get_limited_transmit_cwnd_increase(&self) -> SharedAsyncValue<u32> {
+        self.limited_transmit_cwnd_increase.clone()
+    }
+}
+
+//======================================================================================================================
+// Associated Functions
+//======================================================================================================================
+
+impl Bbr {
+    fn with_mss(mss: u32) -> Self {
+        // The initial value of cwnd is set according to RFC5681, section 3.1, page 7.
+        let initial_cwnd: u32 = match mss {
+            0..=1095 => 4 * mss,
+            1096..=2190 => 3 * mss,
+            _ => 2 * mss,
+        };
+
+        Self {
+            mss,
+            mode: Mode::Startup,
+            cwnd: SharedAsyncValue::new(initial_cwnd),
+            prior_cwnd: initial_cwnd,
+            // We don't have an RTT sample yet, so assume 1ms.
+            pacing_rate: (Self::HIGH_GAIN * initial_cwnd as f64 * 1000.0) as u64,
+            pacing_gain: Self::HIGH_GAIN,
+            cwnd_gain: Self::HIGH_GAIN,
+            btlbw_filter: VecDeque::new(),
+            min_rtt: None,
+            min_rtt_stamp: None,
+            round_count: 0,
+            next_round_delivered: 0,
+            full_bw: 0,
+            full_bw_count: 0,
+            filled_pipe: false,
+            cycle_index: 0,
+            cycle_stamp: None,
+            probe_rtt_done_stamp: None,
+            probe_rtt_round_done: false,
+            packet_conservation_round: None,
+            duplicate_ack_count: 0,
+            fast_retransmit_now: SharedAsyncValue::new(false),
+            limited_transmit_cwnd_increase: SharedAsyncValue::new(0),
+        }
+    }
+
+    // Estimated bottleneck bandwidth in bytes per second.
+    fn btlbw(&self) -> u64 {
+        self.btlbw_filter.front().map_or(0, |(_, rate)| *rate)
+    }
+
+    // Returns `gain` times the estimated bandwidth-delay product, plus some headroom for delayed and stretched ACKs.
+    fn target_cwnd(&self, gain: f64) -> u32 {
+        let min_rtt: Duration = match self.min_rtt {
+            Some(min_rtt) => min_rtt,
+            None => return self.prior_cwnd,
+        };
+        let bdp: f64 = self.btlbw() as f64 * min_rtt.as_secs_f64();
+        let target: u64 = (gain * bdp) as u64 + 3 * self.mss as u64;
+        max(
+            min(target, u32::MAX as u64) as u32,
+            Self::MIN_PIPE_CWND_SEGMENTS * self.mss,
+        )
+    }
+
+    // A round trip ends when the segment that was sent at its start gets acknowledged.
+    fn update_round(&mut self, sample: &RateSample) -> bool {
+        if sample.prior_delivered >= self.next_round_delivered {
+            self.next_round_delivered = sample.prior_delivered + sample.delivered;
+            self.round_count += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn update_btlbw(&mut self, sample: &RateSample) {
+        let rate: u64 = sample.delivery_rate();
+        // Application limited samples only tell us the bandwidth is at least this much.
+        if sample.is_app_limited && rate < self.btlbw() {
+            return;
+        }
+        while self.btlbw_filter.back().is_some_and(|(_, r)| *r <= rate) {
+            self.btlbw_filter.pop_back();
+        }
+        self.btlbw_filter.push_back((self.round_count, rate));
+        while self
+            .btlbw_filter
+            .front()
+            .is_some_and(|(round, _)| round + Self::BTLBW_FILTER_ROUNDS <= self.round_count)
+        {
+            self.btlbw_filter.pop_front();
+        }
+    }
+
+    fn check_full_pipe(&mut self, sample: &RateSample) {
+        if self.filled_pipe || sample.is_app_limited {
+            return;
+        }
+        if self.btlbw() as f64 >= self.full_bw as f64 * Self::FULL_BW_THRESHOLD {
+            // Still growing.
+            self.full_bw = self.btlbw();
+            self.full_bw_count = 0;
+            return;
+        }
+        self.full_bw_count += 1;
+        if self.full_bw_count >= Self::FULL_BW_COUNT {
+            debug!(
+                "check_full_pipe(): bottleneck bandwidth estimate {} bytes/s",
+                self.btlbw()
+            );
+            self.filled_pipe = true;
+        }
+    }
+
+    // Returns whether the minimum RTT estimate has expired.
+    fn update_min_rtt(&mut self, sample: &RateSample) -> bool {
+        let expired: bool = self
+            .min_rtt_stamp
+            .is_some_and(|stamp| sample.now > stamp + Self::MIN_RTT_FILTER_LEN);
+        if self.min_rtt.map_or(true, |min_rtt| sample.rtt < min_rtt) || expired {
+            self.min_rtt = Some(sample.rtt);
+            self.min_rtt_stamp = Some(sample.now);
+        }
+        expired
+    }
+
+    fn enter_drain(&mut self) {
+        self.mode = Mode::Drain;
+        self.pacing_gain = 1.0 / Self::HIGH_GAIN;
+        self.cwnd_gain = Self::HIGH_GAIN;
+    }
+
+    fn enter_probe_bw(&mut self, now: Instant) {
+        self.mode = Mode::ProbeBw;
+        self.cwnd_gain = Self::PROBE_BW_CWND_GAIN;
+        // Start cruising rather than probing, so we don't rebuild the queue we just drained.
+        self.cycle_index = 2;
+        self.cycle_stamp = Some(now);
+        self.pacing_gain = Self::PACING_GAIN_CYCLE[self.cycle_index];
+    }
+
+    fn update_gain_cycle(&mut self, sample: &RateSample) {
+        let phase_done: bool = match (self.cycle_stamp, self.min_rtt) {
+            (Some(stamp), Some(min_rtt)) => sample.now - stamp > min_rtt,
+            _ => true,
+        };
+        let should_advance: bool = if self.pacing_gain > 1.0 {
+            // Keep probing until we've actually put the extra data in flight.
+            phase_done && sample.bytes_in_flight >= self.target_cwnd(self.pacing_gain)
+        } else if self.pacing_gain < 1.0 {
+            // Stop draining early if the queue is gone.
+            phase_done || sample.bytes_in_flight <= self.target_cwnd(1.0)
+        } else {
+            phase_done
+        };
+        if should_advance {
+            self.cycle_index = (self.cycle_index + 1) % Self::PACING_GAIN_CYCLE.len();
+            self.cycle_stamp = Some(sample.now);
+            self.pacing_gain = Self::PACING_GAIN_CYCLE[self.cycle_index];
+        }
+    }
+
+    fn enter_probe_rtt(&mut self) {
+        self.mode = Mode::ProbeRtt;
+        self.pacing_gain = 1.0;
+        self.cwnd_gain = 1.0;
+        self.save_cwnd();
+        self.probe_rtt_done_stamp = None;
+    }
+
+    fn update_probe_rtt(&mut self, sample: &RateSample, round_start: bool) {
+        let min_pipe_cwnd: u32 = Self::MIN_PIPE_CWND_SEGMENTS * self.mss;
+        match self.probe_rtt_done_stamp {
+            None if sample.bytes_in_flight <= min_pipe_cwnd => {
+                self.probe_rtt_done_stamp = Some(sample.now + Self::PROBE_RTT_DURATION);
+                self.probe_rtt_round_done = false;
+                self.next_round_delivered = sample.prior_delivered + sample.delivered;
+            },
+            None => (),
+            Some(done_stamp) => {
+                if round_start {
+                    self.probe_rtt_round_done = true;
+                }
+                if self.probe_rtt_round_done && sample.now > done_stamp {
+                    self.min_rtt_stamp = Some(sample.now);
+                    let cwnd: u32 = max(self.cwnd.get(), self.prior_cwnd);
+                    self.cwnd.set(cwnd);
+                    if self.filled_pipe {
+                        self.enter_probe_bw(sample.now);
+                    } else {
+                        self.mode = Mode::Startup;
+                        self.pacing_gain = Self::HIGH_GAIN;
+                        self.cwnd_gain = Self::HIGH_GAIN;
+                    }
+                }
+            },
+        }
+    }
+
+    fn save_cwnd(&mut self) {
+        if self.mode != Mode::ProbeRtt {
+            self.prior_cwnd = self.cwnd.get();
+        } else {
+            self.prior_cwnd = max(self.prior_cwnd, self.cwnd.get());
+        }
+    }
+
+    fn set_pacing_rate(&mut self) {
+        let rate: u64 = (self.pacing_gain * self.btlbw() as f64) as u64 * (100 - Self::PACING_MARGIN_PERCENT) / 100;
+        // Don't slow down during startup until we have a decent estimate.
+        if rate > 0 && (self.filled_pipe || rate > self.pacing_rate) {
+            self.pacing_rate = rate;
+        }
+    }
+
+    fn set_cwnd(&mut self, sample: &RateSample) {
+        let mut cwnd: u32 = self.cwnd.get();
+        match self.packet_conservation_round {
+            // Send one segment for every one that gets delivered.
+            Some(round) if round == self.round_count => {
+                self.cwnd.set(max(cwnd, sample.bytes_in_flight + sample.newly_acked));
+                return;
+            },
+            // The round trip is over, so go back to the window that we had before the loss.
+            Some(_) => {
+                self.packet_conservation_round = None;
+                cwnd = max(cwnd, self.prior_cwnd);
+            },
+            None => (),
+        }
+        let target: u32 = self.target_cwnd(self.cwnd_gain);
+        let mut new_cwnd: u32 = if self.filled_pipe {
+            min(cwnd.saturating_add(sample.newly_acked), target)
+        } else if cwnd < target {
+            cwnd.saturating_add(sample.newly_acked)
+        } else {
+            cwnd
+        };
+        new_cwnd = max(new_cwnd, Self::MIN_PIPE_CWND_SEGMENTS * self.mss);
+        if self.mode == Mode::ProbeRtt {
+            new_cwnd = min(new_cwnd, Self::MIN_PIPE_CWND_SEGMENTS * self.mss);
+        }
+        if new_cwnd != self.cwnd.get() {
+            self.cwnd.set(new_cwnd);
+        }
+    }
+}
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::{Bbr, Mode};
+    use crate::inetstack::protocols::layer4::tcp::{
+        established::congestion_control::{RateSample, SlowStartCongestionAvoidance},
+        SeqNumber,
+    };
+    use ::anyhow::Result;
+    use ::std::time::{Duration, Instant};
+
+    const MSS: u32 = 1000;
+    const RTT: Duration = Duration::from_millis(10);
+
+    // Feeds `bbr` one rate sample per round trip at `rate` bytes per second.
+    fn run_rounds(bbr: &mut Bbr, now: &mut Instant, delivered: &mut u64, rate: u64, rounds: u32, in_flight: u32) {
+        for _ in 0..rounds {
+            let bytes: u64 = rate * RTT.as_millis() as u64 / 1000;
+            *now += RTT;
+            bbr.on_rate_sample(&RateSample {
+                now: *now,
+                delivered: bytes,
+                interval: RTT,
+                prior_delivered: *delivered,
+                rtt: RTT,
+                newly_acked: bytes as u32,
+                bytes_in_flight: in_flight,
+                is_app_limited: false,
+            });
+            *delivered += bytes;
+        }
+    }
+
+    // Test that BBR leaves startup once the bandwidth stops growing, then drains the queue and paces at the bottleneck
+    // bandwidth with a window of about twice the bandwidth-delay product.
+    #[test]
+    fn startup_drain_probe_bw() -> Result<()> {
+        let mut bbr: Bbr = Bbr::with_mss(MSS);
+        let mut now: Instant = Instant::now();
+        let mut delivered: u64 = 0;
+
+        // The bandwidth doubles every round trip until it hits the 1MB/s bottleneck.
+        for rate in [100_000, 200_000, 400_000, 800_000] {
+            run_rounds(&mut bbr, &mut now, &mut delivered, rate, 1, 0);
+            crate::ensure_eq!(bbr.mode, Mode::Startup);
+        }
+        run_rounds(&mut bbr, &mut now, &mut delivered, 1_000_000, 4, 30_000);
+        crate::ensure_eq!(bbr.mode, Mode::Drain);
+        crate::ensure_eq!(bbr.pacing_rate < 1_000_000, true);
+
+        // Once the queue is gone, pace at the bottleneck bandwidth.
+        run_rounds(&mut bbr, &mut now, &mut delivered, 1_000_000, 1, 10_000);
+        crate::ensure_eq!(bbr.mode, Mode::ProbeBw);
+        crate::ensure_eq!(bbr.pacing_rate, 990_000);
+        crate::ensure_eq!(bbr.target_cwnd(bbr.cwnd_gain), 2 * 10_000 + 3 * MSS);
+
+        Ok(())
+    }
+
+    // Test that BBR measures the propagation delay again after not seeing a lower RTT for 10 seconds.
+    #[test]
+    fn probe_rtt() -> Result<()> {
+        let mut bbr: Bbr = Bbr::with_mss(MSS);
+        let mut now: Instant = Instant::now();
+        let mut delivered: u64 = 0;
+        run_rounds(&mut bbr, &mut now, &mut delivered, 1_000_000, 10, 10_000);
+
+        now += Duration::from_secs(10);
+        run_rounds(&mut bbr, &mut now, &mut delivered, 1_000_000, 1, 10_000);
+        crate::ensure_eq!(bbr.mode, Mode::ProbeRtt);
+        crate::ensure_eq!(bbr.cwnd.get(), 4 * MSS);
+
+        // Stay there for at least 200ms and a round trip after the data in flight drops.
+        run_rounds(&mut bbr, &mut now, &mut delivered, 1_000_000, 1, 4 * MSS);
+        crate::ensure_eq!(bbr.mode, Mode::ProbeRtt);
+        run_rounds(&mut bbr, &mut now, &mut delivered, 1_000_000, 25, 4 * MSS);
+        crate::ensure_eq!(bbr.mode == Mode::ProbeRtt, false);
+        crate::ensure_eq!(bbr.cwnd.get() > 4 * MSS, true);
+
+        Ok(())
+    }
+
+    // Test that a loss shrinks the window to the data in flight for a round trip, and that the window that we had
+    // before comes back afterwards.
+    #[test]
+    fn loss_packet_conservation() -> Result<()> {
+        let mut bbr: Bbr = Bbr::with_mss(MSS);
+        let mut now: Instant = Instant::now();
+        let mut delivered: u64 = 0;
+        run_rounds(&mut bbr, &mut now, &mut delivered, 1_000_000, 10, 10_000);
+        let cwnd: u32 = bbr.cwnd.get();
+        crate::ensure_eq!(cwnd > 11 * MSS, true);
+
+        bbr.on_loss_detected(SeqNumber::from(0), SeqNumber::from(10 * MSS));
+        crate::ensure_eq!(bbr.cwnd.get(), 11 * MSS);
+
+        // An ACK in the same round trip only makes room for what it delivered.
+        now += RTT / 2;
+        bbr.on_rate_sample(&RateSample {
+            now,
+            delivered: MSS as u64,
+            interval: RTT,
+            prior_delivered: delivered - MSS as u64,
+            rtt: RTT,
+            newly_acked: MSS,
+            bytes_in_flight: 8 * MSS,
+            is_app_limited: false,
+        });
+        delivered += MSS as u64;
+        crate::ensure_eq!(bbr.cwnd.get(), 11 * MSS);
+
+        run_rounds(&mut bbr, &mut now, &mut delivered, 1_000_000, 1, 10_000);
+        crate::ensure_eq!(bbr.cwnd.get() >= cwnd, true);
+
+        Ok(())
+    }
+}
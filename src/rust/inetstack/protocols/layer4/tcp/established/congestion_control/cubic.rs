@@ -52,6 +52,7 @@ pub struct Cubic {
     pub duplicate_ack_count: Cell<u32>, // The number of consecutive duplicate ACKs we've received.
     pub fast_retransmit_now: SharedAsyncValue<bool>, // Flag to cause the retransmitter to retransmit a segment now.
     pub in_fast_recovery: Cell<bool>,   // Are we currently in the `fast recovery` algorithm.
+    pub in_sack_recovery: Cell<bool>, // Is the sender driving fast recovery with SACKs (RFC 6675) instead of window inflation.
     pub prev_ack_seq_no: Cell<SeqNumber>, // The previous highest ACK sequence number.
     pub recover: Cell<SeqNumber>, // If we receive dup ACKs with sequence numbers greater than this we'll attempt fast recovery.
 
@@ -90,6 +91,7 @@ impl CongestionControl for Cubic {
             last_congestion_was_rto: Cell::new(false),
 
             in_fast_recovery: Cell::new(false),
+            in_sack_recovery: Cell::new(false),
             fast_retransmit_now: SharedAsyncValue::new(false),
             recover: Cell::new(seq_no), // Recover set to initial send sequence number according to RFC6582.
             prev_ack_seq_no: Cell::new(seq_no), // RFC6582 doesn't specify the initial value, but this seems sensible.
@@ -159,7 +161,9 @@ impl Cubic {
             self.fast_retransmit_now.set(true);
             // We don't reset ca_start here even though cwnd has been shrunk because we aren't going
             // straight back into congestion avoidance.
-        } else if duplicate_ack_count > Self::DUP_ACK_THRESHOLD || self.in_fast_recovery.get() {
+        } else if (duplicate_ack_count > Self::DUP_ACK_THRESHOLD || self.in_fast_recovery.get())
+            && !self.in_sack_recovery.get()
+        {
             self.cwnd.modify(|c| c + self.mss);
         }
     }
@@ -178,7 +182,8 @@ impl Cubic {
             // Record that we didn't enter CA from a timeout.
             self.last_congestion_was_rto.set(false);
             self.in_fast_recovery.set(false);
-        } else {
+            self.in_sack_recovery.set(false);
+        } else if !self.in_sack_recovery.get() {
             // Partial acknowledgement
             self.fast_retransmit_now.set(true);
             if bytes_acknowledged >= mss {
@@ -275,6 +280,7 @@ impl Cubic {
         // Exit fast recovery/retransmit
         self.recover.set(send_unacked);
         self.in_fast_recovery.set(false);
+        self.in_sack_recovery.set(false);
     }
 }
 
@@ -332,6 +338,25 @@ impl SlowStartCongestionAvoidance for Cubic {
         self.on_rto_ss_ca();
         self.on_rto_fast_recovery(send_unacked);
     }
+
+    fn on_ecn_echo(&mut self, _send_unacked: SeqNumber, _send_next: SeqNumber) {
+        // Fast recovery already reduced the window for this round trip.
+        if self.in_fast_recovery.get() {
+            return;
+        }
+        // Otherwise, reduce the window as for a fast retransmit and go straight into congestion avoidance.
+        let cwnd: u32 = self.cwnd.get();
+        if self.fast_convergence {
+            self.fast_convergence();
+        } else {
+            self.w_max.set(cwnd);
+        }
+        let reduced_cwnd: u32 = max((cwnd as f32 * Self::BETA_CUBIC) as u32, 2 * self.mss);
+        self.ssthresh.set(reduced_cwnd);
+        self.cwnd.set(reduced_cwnd);
+        self.ca_start.set(Instant::now());
+        self.last_congestion_was_rto.set(false);
+    }
 }
 
 impl FastRetransmitRecovery for Cubic {
@@ -349,6 +374,26 @@ impl FastRetransmitRecovery for Cubic {
         // I should really use some other mechanism here just because it would be nicer...
         self.fast_retransmit_now.set_without_notify(false);
     }
+
+    fn on_sack_recovery(&mut self, _send_unacked: SeqNumber, send_next: SeqNumber) {
+        // The third duplicate ACK may have reduced the window for this loss already.
+        if !self.in_fast_recovery.get() {
+            let cwnd: u32 = self.cwnd.get();
+            if self.fast_convergence {
+                self.fast_convergence();
+            } else {
+                self.w_max.set(cwnd);
+            }
+            self.ssthresh
+                .set(max((cwnd as f32 * Self::BETA_CUBIC) as u32, 2 * self.mss));
+            self.recover.set(send_next);
+            self.in_fast_recovery.set(true);
+        }
+        // RFC 6675 Section 5, step 4.2, with the multiplicative decrease of RFC 9438 instead of halving the window.
+        self.in_sack_recovery.set(true);
+        self.limited_transmit_cwnd_increase.set_without_notify(0);
+        self.cwnd.set(self.ssthresh.get());
+    }
 }
 
 impl LimitedTransmit for Cubic {
@@ -356,3 +401,43 @@ impl LimitedTransmit for Cubic {
         self.limited_transmit_cwnd_increase.clone()
     }
 }
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::Cubic;
+    use crate::inetstack::protocols::layer4::tcp::{established::congestion_control::CongestionControl, SeqNumber};
+    use ::anyhow::Result;
+
+    const MSS: u32 = 1000;
+
+    // Test that an ECN echo reduces the window by the CUBIC multiplicative decrease without retransmitting anything,
+    // and that it leaves the window alone during fast recovery.
+    #[test]
+    fn ecn_echo() -> Result<()> {
+        let isn: SeqNumber = SeqNumber::from(0);
+        let mut cc: Box<dyn CongestionControl> = Cubic::new(MSS as usize, isn, None);
+        let una: SeqNumber = SeqNumber::from(1);
+        let nxt: SeqNumber = SeqNumber::from(4 * MSS + 1);
+        crate::ensure_eq!(cc.get_cwnd().get(), 4 * MSS);
+
+        cc.on_ecn_echo(una, nxt);
+        crate::ensure_eq!(cc.get_cwnd().get(), 2800);
+        crate::ensure_eq!(cc.get_retransmit_now_flag().get(), false);
+
+        // The window never drops below two segments.
+        cc.on_ecn_echo(una, nxt);
+        crate::ensure_eq!(cc.get_cwnd().get(), 2 * MSS);
+
+        // Fast recovery already reduced the window for this round trip.
+        cc.on_sack_recovery(una, nxt);
+        let cwnd: u32 = cc.get_cwnd().get();
+        cc.on_ecn_echo(una, nxt);
+        crate::ensure_eq!(cc.get_cwnd().get(), cwnd);
+
+        Ok(())
+    }
+}
@@ -1,17 +1,26 @@
 // Copyright (c) Microsoft Corporation.
 // Licensed under the MIT license.
 
+mod bbr;
 mod cubic;
+mod newreno;
 mod none;
 mod options;
+mod rate_sample;
 
-use crate::{collections::async_value::SharedAsyncValue, inetstack::protocols::layer4::tcp::SeqNumber};
+use crate::{
+    collections::async_value::SharedAsyncValue, inetstack::protocols::layer4::tcp::SeqNumber,
+    runtime::network::socket::option::CongestionControlAlgorithm,
+};
 use ::std::{fmt::Debug, time::Duration};
 
 pub use self::{
+    bbr::Bbr,
     cubic::Cubic,
+    newreno::NewReno,
     none::None,
     options::{OptionValue, Options},
+    rate_sample::{DeliveryRateEstimator, RateSample, SegmentDeliveryState},
 };
 
 pub trait SlowStartCongestionAvoidance {
@@ -34,6 +43,23 @@ pub trait SlowStartCongestionAvoidance {
 
     // Called immediately before a segment is sent for the 1st time.
     fn on_send(&mut self, _rto: Duration, _num_sent_bytes: u32) {}
+
+    // Called when our peer echoes a congestion mark, at most once per window of data (RFC 3168 Section 6.1.2).
+    fn on_ecn_echo(&mut self, _send_unacked: SeqNumber, _send_next: SeqNumber) {}
+
+    // Called when RACK-TLP detects a loss that duplicate ACKs may not have told us about, at most once per loss recovery
+    // episode (RFC 8985 Section 9.3). Like an ECN echo, this calls for the window reduction of a fast retransmit.
+    fn on_loss_detected(&mut self, send_unacked: SeqNumber, send_next: SeqNumber) {
+        self.on_ecn_echo(send_unacked, send_next)
+    }
+
+    // Called with the delivery rate measured by an ACK that acknowledged new data.
+    fn on_rate_sample(&mut self, _sample: &RateSample) {}
+
+    // Rate (in bytes per second) at which new data should be paced out, or None to send as fast as cwnd allows.
+    fn get_pacing_rate(&self) -> Option<u64> {
+        None
+    }
 }
 
 pub trait FastRetransmitRecovery
@@ -46,7 +72,14 @@ where
 
     fn get_retransmit_now_flag(&self) -> SharedAsyncValue<bool>;
 
+    // Called for every duplicate ACK (RFC 5681 Section 2) with the number of consecutive ones received so far.
+    fn on_duplicate_ack(&mut self, _send_unacked: SeqNumber, _send_next: SeqNumber, _duplicate_ack_count: u32) {}
+
     fn on_fast_retransmit(&mut self) {}
+
+    // Called when the sender enters SACK-based loss recovery (RFC 6675 Section 5, step 4.2). The sender limits what it
+    // sends with its own estimate of the data in flight until recovery ends, so the window must not be inflated.
+    fn on_sack_recovery(&mut self, _send_unacked: SeqNumber, _send_next: SeqNumber) {}
 }
 
 pub trait LimitedTransmit
@@ -63,3 +96,13 @@ pub trait CongestionControl: SlowStartCongestionAvoidance + FastRetransmitRecove
 }
 
 pub type CongestionControlConstructor = fn(usize, SeqNumber, Option<options::Options>) -> Box<dyn CongestionControl>;
+
+/// Returns the constructor for the congestion control algorithm selected through socket options.
+pub fn get_constructor(algorithm: CongestionControlAlgorithm) -> CongestionControlConstructor {
+    match algorithm {
+        CongestionControlAlgorithm::None => None::new,
+        CongestionControlAlgorithm::Cubic => Cubic::new,
+        CongestionControlAlgorithm::NewReno => NewReno::new,
+        CongestionControlAlgorithm::Bbr => Bbr::new,
+    }
+}
@@ -0,0 +1,381 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+// This is the standard congestion control algorithm of RFC 5681 (slow start, congestion avoidance, fast retransmit and
+// fast recovery) with the NewReno modification to fast recovery of RFC 6582, which keeps us in fast recovery until
+// everything outstanding at the time of the loss has been acknowledged.  Duplicate ACKs are counted by the control
+// block, which reports them through `on_duplicate_ack()`.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::{
+    collections::async_value::SharedAsyncValue,
+    inetstack::protocols::layer4::tcp::{
+        established::congestion_control::{
+            CongestionControl, FastRetransmitRecovery, LimitedTransmit, Options, SlowStartCongestionAvoidance,
+        },
+        SeqNumber,
+    },
+};
+use ::std::{
+    cmp::{max, min},
+    fmt::Debug,
+    time::Duration,
+};
+
+//======================================================================================================================
+// Structures
+//======================================================================================================================
+
+#[derive(Debug)]
+pub struct NewReno {
+    mss: u32,
+    // Congestion window: Max number of bytes that may be in flight to prevent congestion.
+    cwnd: SharedAsyncValue<u32>,
+    // The size of cwnd at which we will change from using slow start to congestion avoidance.
+    ssthresh: u32,
+    // Highest sequence number that we have sent, as of the last ACK that we received.
+    high_data: SeqNumber,
+
+    // Number of consecutive duplicate ACKs, as counted by the control block.
+    duplicate_ack_count: u32,
+    // Flag to cause the retransmitter to retransmit a segment now.
+    fast_retransmit_now: SharedAsyncValue<bool>,
+    // Are we currently in fast recovery.
+    in_fast_recovery: bool,
+    // Is the sender driving this fast recovery with SACKs (RFC 6675), rather than with window inflation.
+    in_sack_recovery: bool,
+    // SND.NXT when we last entered fast recovery or timed out.  Duplicate ACKs that don't acknowledge up to it don't
+    // start a new fast recovery.  This is "recover" in RFC 6582, which holds the last byte sent rather than SND.NXT.
+    recover: SeqNumber,
+
+    // The amount by which cwnd should be increased due to the limited transmit algorithm.
+    limited_transmit_cwnd_increase: SharedAsyncValue<u32>,
+}
+
+//======================================================================================================================
+// Constants
+//======================================================================================================================
+
+impl NewReno {
+    const DUP_ACK_THRESHOLD: u32 = 3;
+}
+
+//======================================================================================================================
+// Trait Implementations
+//======================================================================================================================
+
+impl CongestionControl for NewReno {
+    fn new(mss: usize, seq_no: SeqNumber, _options: Option<Options>) -> Box<dyn CongestionControl> {
+        let mss: u32 = mss.try_into().unwrap();
+        // The initial value of cwnd is set according to RFC5681, section 3.1, page 7.
+        let initial_cwnd: u32 = match mss {
+            0..=1095 => 4 * mss,
+            1096..=2190 => 3 * mss,
+            _ => 2 * mss,
+        };
+
+        Box::new(Self {
+            mss,
+            cwnd: SharedAsyncValue::new(initial_cwnd),
+            // According to RFC5681 ssthresh should be initialised 'arbitrarily high'.
+            ssthresh: u32::MAX,
+            high_data: seq_no,
+            duplicate_ack_count: 0,
+            fast_retransmit_now: SharedAsyncValue::new(false),
+            in_fast_recovery: false,
+            in_sack_recovery: false,
+            // Recover set to initial send sequence number according to RFC6582.
+            recover: seq_no,
+            limited_transmit_cwnd_increase: SharedAsyncValue::new(0),
+        })
+    }
+}
+
+impl SlowStartCongestionAvoidance for NewReno {
+    fn get_cwnd(&self) -> SharedAsyncValue<u32> {
+        self.cwnd.clone()
+    }
+
+    fn on_send(&mut self, _rto: Duration, num_bytes_sent: u32) {
+        let new_value: u32 = self.limited_transmit_cwnd_increase.get().saturating_sub(num_bytes_sent);
+        self.limited_transmit_cwnd_increase.set_without_notify(new_value);
+    }
+
+    fn on_ack_received(
+        &mut self,
+        _rto: Duration,
+        send_unacked: SeqNumber,
+        send_next: SeqNumber,
+        ack_seq_no: SeqNumber,
+    ) {
+        self.high_data = send_next;
+        let bytes_acknowledged: u32 = (ack_seq_no - send_unacked).into();
+        if ack_seq_no <= send_unacked || ack_seq_no > send_next {
+            // Duplicate ACKs are reported separately, through on_duplicate_ack().
+            return;
+        }
+        self.duplicate_ack_count = 0;
+        self.limited_transmit_cwnd_increase.set_without_notify(0);
+
+        if self.in_fast_recovery {
+            if ack_seq_no >= self.recover {
+                // Full acknowledgement (RFC 6582 Section 3.2, step 3): Deflate the window and leave fast recovery.
+                let bytes_outstanding: u32 = (send_next - ack_seq_no).into();
+                self.cwnd
+                    .set(min(self.ssthresh, max(bytes_outstanding, self.mss) + self.mss));
+                self.in_fast_recovery = false;
+                self.in_sack_recovery = false;
+            } else if !self.in_sack_recovery {
+                // Partial acknowledgement (RFC 6582 Section 3.2, step 5): Retransmit the next hole right away and
+                // deflate the window by the amount of new data acknowledged.
+                self.fast_retransmit_now.set(true);
+                let deflated_cwnd: u32 = self.cwnd.get().saturating_sub(bytes_acknowledged);
+                if bytes_acknowledged >= self.mss {
+                    self.cwnd.set(deflated_cwnd + self.mss);
+                } else {
+                    self.cwnd.set(deflated_cwnd);
+                }
+            }
+        } else if self.cwnd.get() < self.ssthresh {
+            // Slow start (RFC 5681 Section 3.1, equation 2).
+            self.cwnd.modify(|c| c + min(bytes_acknowledged, self.mss));
+        } else {
+            // Congestion avoidance (RFC 5681 Section 3.1, equation 3).
+            let cwnd_inc: u32 = max(self.mss * self.mss / self.cwnd.get(), 1);
+            self.cwnd.modify(|c| c + cwnd_inc);
+        }
+    }
+
+    fn on_rto(&mut self, send_unacked: SeqNumber) {
+        // RFC 5681 Section 3.1, equation 4.  The window is inflated during fast recovery, so use ssthresh instead.
+        let flight_size: u32 = (self.high_data - send_unacked).into();
+        let window: u32 = if self.in_fast_recovery {
+            self.ssthresh
+        } else {
+            self.cwnd.get()
+        };
+        self.ssthresh = max(min(flight_size, window) / 2, 2 * self.mss);
+        // Loss window.
+        self.cwnd.set(self.mss);
+
+        // RFC 6582 Section 3.2, step 4: Exit fast recovery and don't enter it again because of duplicate ACKs for data
+        // sent before the timeout.
+        self.in_fast_recovery = false;
+        self.in_sack_recovery = false;
+        self.recover = self.high_data;
+        self.duplicate_ack_count = 0;
+        self.limited_transmit_cwnd_increase.set_without_notify(0);
+    }
+
+    fn on_ecn_echo(&mut self, send_unacked: SeqNumber, send_next: SeqNumber) {
+        // RFC 3168 Section 6.1.2: Respond as if a segment was lost, but without retransmitting anything.  Fast recovery
+        // already reduced the window for this round trip.
+        if self.in_fast_recovery {
+            return;
+        }
+        let flight_size: u32 = (send_next - send_unacked).into();
+        self.ssthresh = max(flight_size / 2, 2 * self.mss);
+        self.cwnd.set(self.ssthresh);
+        self.limited_transmit_cwnd_increase.set_without_notify(0);
+    }
+}
+
+impl FastRetransmitRecovery for NewReno {
+    fn get_duplicate_ack_count(&self) -> u32 {
+        self.duplicate_ack_count
+    }
+
+    fn get_retransmit_now_flag(&self) -> SharedAsyncValue<bool> {
+        self.fast_retransmit_now.clone()
+    }
+
+    fn on_duplicate_ack(&mut self, send_unacked: SeqNumber, send_next: SeqNumber, duplicate_ack_count: u32) {
+        self.high_data = send_next;
+        self.duplicate_ack_count = duplicate_ack_count;
+
+        if self.in_fast_recovery {
+            // RFC 6582 Section 3.2, step 3: Every further duplicate ACK means a segment has left the network.  SACKs tell
+            // the sender exactly which ones did.
+            if !self.in_sack_recovery {
+                self.cwnd.modify(|c| c + self.mss);
+            }
+        } else if duplicate_ack_count < Self::DUP_ACK_THRESHOLD {
+            // Limited transmit (RFC 3042): send new data on the first two duplicate ACKs.
+            self.limited_transmit_cwnd_increase.modify(|ltci| ltci + self.mss);
+        } else if duplicate_ack_count == Self::DUP_ACK_THRESHOLD && send_unacked >= self.recover {
+            // RFC 6582 Section 3.2, step 2: Enter fast recovery and retransmit the first unacknowledged segment. The
+            // ACK has to cover more than the last byte sent, which is the one right before `recover`.
+            let flight_size: u32 = (send_next - send_unacked).into();
+            self.ssthresh = max(flight_size / 2, 2 * self.mss);
+            self.recover = send_next;
+            self.in_fast_recovery = true;
+            self.limited_transmit_cwnd_increase.set_without_notify(0);
+            self.cwnd.set(self.ssthresh + Self::DUP_ACK_THRESHOLD * self.mss);
+            self.fast_retransmit_now.set(true);
+        }
+    }
+
+    fn on_fast_retransmit(&mut self) {
+        self.fast_retransmit_now.set_without_notify(false);
+    }
+
+    fn on_sack_recovery(&mut self, send_unacked: SeqNumber, send_next: SeqNumber) {
+        // The third duplicate ACK may have started fast recovery for this loss already.
+        if !self.in_fast_recovery {
+            let flight_size: u32 = (send_next - send_unacked).into();
+            self.ssthresh = max(flight_size / 2, 2 * self.mss);
+            self.recover = send_next;
+            self.in_fast_recovery = true;
+        }
+        // RFC 6675 Section 5, step 4.2: cwnd = ssthresh = FlightSize / 2.
+        self.in_sack_recovery = true;
+        self.limited_transmit_cwnd_increase.set_without_notify(0);
+        self.cwnd.set(self.ssthresh);
+    }
+}
+
+impl LimitedTransmit for NewReno {
+    fn get_limited_transmit_cwnd_increase(&self) -> SharedAsyncValue<u32> {
+        self.limited_transmit_cwnd_increase.clone()
+    }
+}
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::NewReno;
+    use crate::inetstack::protocols::layer4::tcp::{established::congestion_control::CongestionControl, SeqNumber};
+    use ::anyhow::Result;
+    use ::std::time::Duration;
+
+    const MSS: u32 = 1000;
+    const RTO: Duration = Duration::from_secs(1);
+
+    // Test that the third duplicate ACK triggers a fast retransmit and that fast recovery lasts until everything sent
+    // before the loss is acknowledged.
+    #[test]
+    fn fast_recovery() -> Result<()> {
+        let isn: SeqNumber = SeqNumber::from(0);
+        let una: SeqNumber = isn + SeqNumber::from(1);
+        let nxt: SeqNumber = SeqNumber::from(10 * MSS + 1);
+        // Like a new connection, which starts sending right after its SYN.
+        let mut cc: Box<dyn CongestionControl> = NewReno::new(MSS as usize, una, None);
+        crate::ensure_eq!(cc.get_cwnd().get(), 4 * MSS);
+
+        // The first two duplicate ACKs let us send new data.
+        cc.on_duplicate_ack(una, nxt, 1);
+        cc.on_duplicate_ack(una, nxt, 2);
+        crate::ensure_eq!(cc.get_limited_transmit_cwnd_increase().get(), 2 * MSS);
+        crate::ensure_eq!(cc.get_retransmit_now_flag().get(), false);
+
+        // The third one starts fast recovery.
+        cc.on_duplicate_ack(una, nxt, 3);
+        crate::ensure_eq!(cc.get_retransmit_now_flag().get(), true);
+        crate::ensure_eq!(cc.get_cwnd().get(), 5 * MSS + 3 * MSS);
+        cc.on_fast_retransmit();
+
+        // Further duplicate ACKs inflate the window.
+        cc.on_duplicate_ack(una, nxt, 4);
+        crate::ensure_eq!(cc.get_cwnd().get(), 9 * MSS);
+
+        // A partial ACK retransmits the next hole and deflates the window.
+        let partial: SeqNumber = SeqNumber::from(2 * MSS + 1);
+        cc.on_ack_received(RTO, una, nxt, partial);
+        crate::ensure_eq!(cc.get_retransmit_now_flag().get(), true);
+        crate::ensure_eq!(cc.get_cwnd().get(), 9 * MSS - 2 * MSS + MSS);
+        cc.on_fast_retransmit();
+
+        // A full ACK ends fast recovery.
+        cc.on_ack_received(RTO, partial, nxt, nxt);
+        crate::ensure_eq!(cc.get_retransmit_now_flag().get(), false);
+        crate::ensure_eq!(cc.get_cwnd().get(), 2 * MSS);
+
+        // Once everything sent before the loss is acknowledged, another loss starts another fast recovery.
+        cc.on_duplicate_ack(nxt, nxt + SeqNumber::from(4 * MSS), 3);
+        crate::ensure_eq!(cc.get_retransmit_now_flag().get(), true);
+
+        Ok(())
+    }
+
+    // Test that SACK-based loss recovery sets the window to half the flight size and neither inflates nor deflates it
+    // until everything sent before the loss is acknowledged.
+    #[test]
+    fn sack_recovery() -> Result<()> {
+        let isn: SeqNumber = SeqNumber::from(0);
+        let una: SeqNumber = isn + SeqNumber::from(1);
+        let nxt: SeqNumber = SeqNumber::from(10 * MSS + 1);
+        // Like a new connection, which starts sending right after its SYN.
+        let mut cc: Box<dyn CongestionControl> = NewReno::new(MSS as usize, una, None);
+
+        // The third duplicate ACK inflates the window, which entering SACK-based loss recovery undoes.
+        cc.on_duplicate_ack(una, nxt, 1);
+        cc.on_duplicate_ack(una, nxt, 2);
+        cc.on_duplicate_ack(una, nxt, 3);
+        crate::ensure_eq!(cc.get_cwnd().get(), 5 * MSS + 3 * MSS);
+        cc.on_fast_retransmit();
+        cc.on_sack_recovery(una, nxt);
+        crate::ensure_eq!(cc.get_cwnd().get(), 5 * MSS);
+
+        // Duplicate and partial ACKs leave the window alone.
+        cc.on_duplicate_ack(una, nxt, 4);
+        let partial: SeqNumber = SeqNumber::from(2 * MSS + 1);
+        cc.on_ack_received(RTO, una, nxt, partial);
+        crate::ensure_eq!(cc.get_cwnd().get(), 5 * MSS);
+        crate::ensure_eq!(cc.get_retransmit_now_flag().get(), false);
+
+        // A full ACK ends loss recovery.
+        cc.on_ack_received(RTO, partial, nxt, nxt);
+        crate::ensure_eq!(cc.get_cwnd().get(), 2 * MSS);
+
+        // Without duplicate ACKs to start fast recovery, entering SACK-based loss recovery halves the flight size.
+        let una: SeqNumber = nxt;
+        let nxt: SeqNumber = una + SeqNumber::from(8 * MSS);
+        cc.on_sack_recovery(una, nxt);
+        crate::ensure_eq!(cc.get_cwnd().get(), 4 * MSS);
+
+        Ok(())
+    }
+
+    // Test that a retransmission timeout collapses the window.
+    #[test]
+    fn rto() -> Result<()> {
+        let isn: SeqNumber = SeqNumber::from(0);
+        let mut cc: Box<dyn CongestionControl> = NewReno::new(MSS as usize, isn, None);
+        let una: SeqNumber = SeqNumber::from(1);
+        let nxt: SeqNumber = SeqNumber::from(3 * MSS + 1);
+
+        // Slow start grows the window by one MSS per ACK.
+        cc.on_ack_received(RTO, isn, nxt, una);
+        crate::ensure_eq!(cc.get_cwnd().get(), 4 * MSS + 1);
+
+        cc.on_rto(una);
+        crate::ensure_eq!(cc.get_cwnd().get(), MSS);
+
+        Ok(())
+    }
+
+    // Test that an ECN echo halves the window without retransmitting anything.
+    #[test]
+    fn ecn_echo() -> Result<()> {
+        let isn: SeqNumber = SeqNumber::from(0);
+        let mut cc: Box<dyn CongestionControl> = NewReno::new(MSS as usize, isn, None);
+        let una: SeqNumber = SeqNumber::from(1);
+        let nxt: SeqNumber = SeqNumber::from(4 * MSS + 1);
+
+        cc.on_ecn_echo(una, nxt);
+        crate::ensure_eq!(cc.get_cwnd().get(), 2 * MSS);
+        crate::ensure_eq!(cc.get_retransmit_now_flag().get(), false);
+
+        // We are in congestion avoidance from now on.
+        cc.on_ack_received(RTO, una, nxt, una + SeqNumber::from(MSS));
+        crate::ensure_eq!(cc.get_cwnd().get(), 2 * MSS + MSS / 2);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,218 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+// Delivery rate estimation, as described in draft-cheng-iccrg-delivery-rate-estimation.  The sender records a snapshot
+// of the connection's delivery progress in every segment it sends, and when an ACK arrives it compares the snapshot of
+// the most recently sent segment that was acknowledged against the current progress to produce a rate sample.  Model
+// based congestion control algorithms (like BBR) use these samples to estimate the bottleneck bandwidth.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use ::std::{
+    cmp::max,
+    time::{Duration, Instant},
+};
+
+//======================================================================================================================
+// Structures
+//======================================================================================================================
+
+/// Snapshot of the connection's delivery progress, taken when a segment is (re)transmitted.
+#[derive(Clone, Copy, Debug)]
+pub struct SegmentDeliveryState {
+    // Bytes delivered by the time this segment was sent.  This is P.delivered in the draft.
+    delivered: u64,
+    // Time of the last delivery before this segment was sent.  This is P.delivered_time in the draft.
+    delivered_time: Instant,
+    // Send time of the segment most recently acknowledged when this segment was sent.  This is P.first_sent_time in
+    // the draft.
+    first_sent_time: Instant,
+    // Time at which this segment was sent.
+    sent_time: Instant,
+    // Whether the connection was application limited when this segment was sent.
+    is_app_limited: bool,
+}
+
+/// A delivery rate sample, generated for each ACK that acknowledges new data.
+#[derive(Clone, Copy, Debug)]
+pub struct RateSample {
+    /// Time at which the sample was taken.
+    pub now: Instant,
+    /// Bytes delivered over `interval`.
+    pub delivered: u64,
+    /// Length of the sampling interval.
+    pub interval: Duration,
+    /// Total bytes delivered on the connection when the most recently acknowledged segment was sent.
+    pub prior_delivered: u64,
+    /// Round-trip time of the most recently sent segment that this ACK acknowledged.
+    pub rtt: Duration,
+    /// Bytes newly acknowledged by this ACK.
+    pub newly_acked: u32,
+    /// Bytes still in flight after processing this ACK.
+    pub bytes_in_flight: u32,
+    /// Whether the sample was taken while the application did not have enough data to fill the pipe.  Such samples
+    /// may underestimate the available bandwidth.
+    pub is_app_limited: bool,
+}
+
+/// Sender-side state for delivery rate estimation.
+#[derive(Debug)]
+pub struct DeliveryRateEstimator {
+    // Total bytes delivered so far.  This is C.delivered in the draft.
+    delivered: u64,
+    // Time of the last delivery.  This is C.delivered_time in the draft.
+    delivered_time: Instant,
+    // Send time of the most recently acknowledged segment.  This is C.first_sent_time in the draft.
+    first_sent_time: Instant,
+    // If non-zero, the value of `delivered` at which the connection stops being application limited.  This is
+    // C.app_limited in the draft.
+    app_limited: u64,
+
+    // Snapshot of the most recently sent segment acknowledged by the ACK being processed, and the number of bytes
+    // that ACK has acknowledged so far.
+    latest_acked: Option<SegmentDeliveryState>,
+    newly_acked: u32,
+}
+
+//======================================================================================================================
+// Associated Functions
+//======================================================================================================================
+
+impl DeliveryRateEstimator {
+    pub fn new(now: Instant) -> Self {
+        Self {
+            delivered: 0,
+            delivered_time: now,
+            first_sent_time: now,
+            app_limited: 0,
+            latest_acked: None,
+            newly_acked: 0,
+        }
+    }
+
+    /// Takes a snapshot for a segment sent at `now`.  `bytes_in_flight` is the amount of data outstanding before it.
+    pub fn on_send(&mut self, now: Instant, bytes_in_flight: u32) -> SegmentDeliveryState {
+        // Start a new sampling interval when the pipe is empty, so that idle time isn't counted.
+        if bytes_in_flight == 0 {
+            self.first_sent_time = now;
+            self.delivered_time = now;
+        }
+        SegmentDeliveryState {
+            delivered: self.delivered,
+            delivered_time: self.delivered_time,
+            first_sent_time: self.first_sent_time,
+            sent_time: now,
+            is_app_limited: self.app_limited != 0,
+        }
+    }
+
+    /// Records that the sender ran out of data to send with only `bytes_in_flight` outstanding.  Samples are marked as
+    /// application limited until everything sent up to now has been delivered.
+    pub fn on_app_limited(&mut self, bytes_in_flight: u32) {
+        self.app_limited = max(self.delivered + bytes_in_flight as u64, 1);
+    }
+
+    /// Records the delivery of `bytes` bytes of the segment sent with `state`.
+    pub fn on_segment_acked(&mut self, state: &SegmentDeliveryState, bytes: u32, now: Instant) {
+        self.delivered += bytes as u64;
+        self.delivered_time = now;
+        self.newly_acked += bytes;
+
+        // Sample the most recently sent segment.
+        let is_latest: bool = match self.latest_acked {
+            Some(latest) => state.delivered >= latest.delivered && state.sent_time >= latest.sent_time,
+            None => true,
+        };
+        if is_latest {
+            self.latest_acked = Some(*state);
+            self.first_sent_time = state.sent_time;
+        }
+    }
+
+    /// Generates a rate sample for the ACK that was just processed, if it acknowledged anything new.
+    pub fn generate_sample(&mut self, now: Instant, bytes_in_flight: u32) -> Option<RateSample> {
+        let newly_acked: u32 = self.newly_acked;
+        self.newly_acked = 0;
+        let latest: SegmentDeliveryState = self.latest_acked.take()?;
+
+        // Once everything sent while application limited has been delivered, samples are accurate again.
+        if self.app_limited != 0 && self.delivered > self.app_limited {
+            self.app_limited = 0;
+        }
+
+        // Use the longer of the send and ACK intervals, so that ACK compression doesn't inflate the estimate.
+        let send_elapsed: Duration = latest.sent_time - latest.first_sent_time;
+        let ack_elapsed: Duration = self.delivered_time - latest.delivered_time;
+        let interval: Duration = max(send_elapsed, ack_elapsed);
+        if interval.is_zero() {
+            return None;
+        }
+
+        Some(RateSample {
+            now,
+            delivered: self.delivered - latest.delivered,
+            interval,
+            prior_delivered: latest.delivered,
+            rtt: now - latest.sent_time,
+            newly_acked,
+            bytes_in_flight,
+            is_app_limited: latest.is_app_limited,
+        })
+    }
+}
+
+impl RateSample {
+    /// Delivery rate in bytes per second.
+    pub fn delivery_rate(&self) -> u64 {
+        (self.delivered as u128 * 1_000_000_000 / self.interval.as_nanos()) as u64
+    }
+}
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::{DeliveryRateEstimator, RateSample, SegmentDeliveryState};
+    use ::anyhow::Result;
+    use ::std::{
+        collections::VecDeque,
+        time::{Duration, Instant},
+    };
+
+    // Test that a sender that sends one 1000-byte segment per millisecond over a path with a 10ms RTT measures a delivery
+    // rate of 1000 bytes per millisecond.
+    #[test]
+    fn delivery_rate() -> Result<()> {
+        let start: Instant = Instant::now();
+        let mut estimator: DeliveryRateEstimator = DeliveryRateEstimator::new(start);
+        let mut in_flight: VecDeque<SegmentDeliveryState> = VecDeque::new();
+
+        let mut sample: Option<RateSample> = None;
+        for i in 0..30 {
+            let now: Instant = start + Duration::from_millis(i);
+            // Each segment is acknowledged 10ms after it was sent.
+            if i >= 10 {
+                let state: SegmentDeliveryState = in_flight.pop_front().expect("should have a segment in flight");
+                estimator.on_segment_acked(&state, 1000, now);
+                sample = estimator.generate_sample(now, in_flight.len() as u32 * 1000);
+            }
+            in_flight.push_back(estimator.on_send(now, in_flight.len() as u32 * 1000));
+        }
+
+        let sample: RateSample = match sample {
+            Some(sample) => sample,
+            None => anyhow::bail!("should have a rate sample"),
+        };
+        crate::ensure_eq!(sample.delivered, 10000);
+        crate::ensure_eq!(sample.interval, Duration::from_millis(10));
+        crate::ensure_eq!(sample.delivery_rate(), 1_000_000);
+        crate::ensure_eq!(sample.rtt, Duration::from_millis(10));
+        crate::ensure_eq!(sample.is_app_limited, false);
+
+        Ok(())
+    }
+}
@@ -12,14 +12,18 @@ use crate::{
     },
     expect_ok,
     inetstack::protocols::{
-        layer3::SharedLayer3Endpoint,
+        layer3::{ipv4::IPV4_HEADER_MIN_SIZE, EcnCodepoint, Ipv4Header, SharedLayer3Endpoint},
         layer4::tcp::{
             constants::MSL,
             established::{
                 congestion_control::{self, CongestionControlConstructor},
+                plpmtud::PathMtuDiscovery,
+                rcvbuf::ReceiveBufferTuner,
                 sender::Sender,
             },
-            header::TcpHeader,
+            header::{SelectiveAcknowlegement, TcpHeader, TcpOptions2},
+            time_wait::TimeWait,
+            timestamps::{find_timestamps, TcpTimestamps},
             SeqNumber,
         },
         MAX_HEADER_SIZE,
@@ -27,14 +31,18 @@ use crate::{
     runtime::{
         fail::Fail,
         memory::DemiBuffer,
-        network::{config::TcpConfig, socket::option::TcpSocketOptions},
-        yield_with_timeout, SharedDemiRuntime, SharedObject,
+        network::{
+            config::TcpConfig,
+            socket::option::{CongestionControlAlgorithm, TcpInfo, TcpSocketOptions},
+        },
+        SharedDemiRuntime, SharedObject,
     },
 };
-use ::futures::never::Never;
+use ::futures::{never::Never, pin_mut, FutureExt};
 use ::std::{
+    cmp,
     collections::VecDeque,
-    net::{Ipv4Addr, SocketAddrV4},
+    net::{Ipv4Addr, Shutdown, SocketAddrV4},
     ops::{Deref, DerefMut},
     time::{Duration, Instant},
 };
@@ -52,6 +60,15 @@ const MIN_RECV_QUEUE_SIZE_FRAMES: usize = 2048;
 // Ideally, we'd limit out-of-order data to that which (along with the unread data) will fit in the receive window.
 const MAX_OUT_OF_ORDER_SIZE_FRAMES: usize = 16;
 
+// Maximum number of SACK blocks that fit in the option space of a TCP header, with and without the timestamps option.
+// See: https://datatracker.ietf.org/doc/html/rfc2018#section-3 for more details.
+const MAX_SACK_BLOCKS: usize = 4;
+const MAX_SACK_BLOCKS_WITH_TIMESTAMPS: usize = 3;
+
+// Maximum number of challenge ACKs that we send per second on a connection.
+// See: https://datatracker.ietf.org/doc/html/rfc5961#section-7 for more details.
+const CHALLENGE_ACK_LIMIT: u32 = 10;
+
 //======================================================================================================================
 // Structures
 //======================================================================================================================
@@ -100,23 +117,28 @@ struct Receiver {
     // Sequnce number of the last byte of data (FIN).
     fin_seq_no: SharedAsyncValue<Option<SeqNumber>>,
 
-    // Receive queue.  Contains in-order received (and acknowledged) data ready for the application to read.
-    recv_queue: AsyncQueue<DemiBuffer>,
+    // Receive queue.  Contains in-order received (and acknowledged) data ready for the application to read, followed
+    // by an error if the connection was aborted.
+    recv_queue: AsyncQueue<Result<DemiBuffer, Fail>>,
+
+    // Tracks how fast the application reads, to size the receive buffer.
+    buffer_tuner: ReceiveBufferTuner,
 }
 
 impl Receiver {
-    pub fn new(reader_next_seq_no: SeqNumber, receive_next_seq_no: SeqNumber) -> Self {
+    pub fn new(reader_next_seq_no: SeqNumber, receive_next_seq_no: SeqNumber, now: Instant) -> Self {
         Self {
             reader_next_seq_no,
             receive_next_seq_no,
             fin_seq_no: SharedAsyncValue::new(None),
             recv_queue: AsyncQueue::with_capacity(MIN_RECV_QUEUE_SIZE_FRAMES),
+            buffer_tuner: ReceiveBufferTuner::new(reader_next_seq_no, now),
         }
     }
 
     pub async fn pop(&mut self, size: Option<usize>) -> Result<DemiBuffer, Fail> {
         let buf: DemiBuffer = if let Some(size) = size {
-            let mut buf: DemiBuffer = self.recv_queue.pop(None).await?;
+            let mut buf: DemiBuffer = self.pop_queue().await?;
             // Split the buffer if it's too big.
             if buf.len() > size {
                 buf.split_front(size)?
@@ -124,7 +146,7 @@ impl Receiver {
                 buf
             }
         } else {
-            self.recv_queue.pop(None).await?
+            self.pop_queue().await?
         };
 
         match buf.len() {
@@ -139,14 +161,43 @@ impl Receiver {
         Ok(buf)
     }
 
+    async fn pop_queue(&mut self) -> Result<DemiBuffer, Fail> {
+        loop {
+            match self.recv_queue.pop(None).await {
+                Ok(Ok(buf)) => return Ok(buf),
+                Ok(Err(e)) => {
+                    // Leave the error in place, so that every later pop fails as well.
+                    self.recv_queue.push_front(Err(e.clone()));
+                    return Err(e);
+                },
+                // The queue stops waiting after a while, but an idle connection is not an error; keepalive probes
+                // tell us when our peer is gone.
+                Err(Fail { errno, cause: _ }) if errno == libc::ETIMEDOUT => continue,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
     pub fn push(&mut self, buf: DemiBuffer) {
         let buf_len: u32 = buf.len() as u32;
-        self.recv_queue.push(buf);
+        self.recv_queue.push(Ok(buf));
         self.receive_next_seq_no = self.receive_next_seq_no + SeqNumber::from(buf_len as u32);
     }
 
+    // Returns true if there is data in the receive queue that the application has not read yet.
+    pub fn has_unread_data(&self) -> bool {
+        self.recv_queue
+            .get_values()
+            .any(|item| matches!(item, Ok(buf) if buf.len() > 0))
+    }
+
+    // Fails any pending and future pops, once the application has read the data that is already queued.
+    pub fn abort(&mut self, e: Fail) {
+        self.recv_queue.push(Err(e));
+    }
+
     pub fn push_fin(&mut self) {
-        self.recv_queue.push(DemiBuffer::new(0));
+        self.recv_queue.push(Ok(DemiBuffer::new(0)));
         debug_assert_eq!(self.receive_next_seq_no, self.fin_seq_no.get().unwrap());
         // Reset it to wake up any close coroutines waiting for FIN to arrive.
         self.fin_seq_no.set(Some(self.receive_next_seq_no));
@@ -218,10 +269,18 @@ pub struct ControlBlock {
 
     receive_ack_deadline_time_secs: SharedAsyncValue<Option<Instant>>,
 
+    // Acknowledgement number of the last segment that we sent.  Everything between this and RCV.NXT still needs to be
+    // acknowledged.
+    receive_last_ack_sent: SeqNumber,
+
     // This is our receive buffer size, which is also the maximum size of our receive window.
     // Note: The maximum possible advertised window is 1 GiB with window scaling and 64 KiB without.
     receive_buffer_size_frames: u32,
 
+    // Right edge of the receive window that we last advertised (RCV.NXT + RCV.WND).  Used for receiver-side SWS
+    // avoidance.
+    receive_advertised_right_edge: SeqNumber,
+
     // TODO: Review how this is used.  We could have separate window scale factors, so there should be one for the
     // receiver and one for the sender.
     // This is the receive-side window scale factor.
@@ -231,7 +290,7 @@ pub struct ControlBlock {
 
     // Receive queues
     // Incoming packets for this connection.
-    recv_queue: SharedAsyncQueue<(Ipv4Addr, TcpHeader, DemiBuffer)>,
+    recv_queue: SharedAsyncQueue<(Ipv4Header, TcpHeader, DemiBuffer)>,
 
     // Queue of out-of-order segments.  This is where we hold onto data that we've received (because it was within our
     // receive window) but can't yet present to the user because we're missing some other data that comes between this
@@ -239,6 +298,53 @@ pub struct ControlBlock {
     //
     receive_out_of_order_frames: VecDeque<(SeqNumber, DemiBuffer)>,
 
+    // Whether our peer agreed to use selective acknowledgements (RFC 2018) during the handshake.
+    sack_permitted: bool,
+
+    // Starting sequence number of the most recently stored out-of-order segment. RFC 2018 requires the first SACK
+    // block to report the block containing this segment.
+    receive_last_out_of_order_seq_no: Option<SeqNumber>,
+
+    // State of the timestamps option (RFC 7323), if our peer agreed to use it during the handshake.
+    timestamps: Option<TcpTimestamps>,
+
+    // Whether our peer agreed to use ECN (RFC 3168) during the handshake.
+    ecn: bool,
+
+    // Whether a segment that we received was marked CE, and we must set ECE on our ACKs until our peer sets CWR.
+    ecn_echo_pending: bool,
+
+    // Whether we reduced the congestion window in response to ECE, and must set CWR on the next new data segment.
+    ecn_cwr_pending: bool,
+
+    // SND.NXT at the time of our last reduction for ECE. We react to ECE at most once per window of data.
+    ecn_recover_seq_no: SeqNumber,
+
+    // SYN+ACK that accepted the data in our peer's SYN with TCP Fast Open (RFC 7413), until our peer acknowledges it.
+    fast_open_syn_ack: Option<TcpHeader>,
+
+    // Number of challenge ACKs (RFC 5961) sent since the start of the current one-second interval.
+    challenge_ack_count: u32,
+    challenge_ack_interval_start: Instant,
+
+    // Number of consecutive duplicate ACKs (as defined in RFC 5681 Section 2) we've received.
+    duplicate_ack_count: u32,
+
+    // Time at which we last received a segment from our peer.
+    last_receive_time: Instant,
+
+    // Time at which the keepalive timer next expires, if keepalive probes are enabled.
+    keepalive_deadline: SharedAsyncValue<Option<Instant>>,
+
+    // Number of keepalive probes (RFC 9293 Section 3.8.4) sent since we last heard from our peer.
+    keepalive_probes_sent: u32,
+
+    // Error that aborted the connection, if any.  Setting it stops the background coroutines.
+    abort_error: SharedAsyncValue<Option<Fail>>,
+
+    // Whether the application has shut down the receiving side of the connection.  Setting it wakes up pending pops.
+    read_shutdown: SharedAsyncValue<bool>,
+
     // Congestion control trait implementation we're currently using.
     // TODO: Consider switching this to a static implementation to avoid V-table call overhead.
     congestion_control_algorithm: Box<dyn congestion_control::CongestionControl>,
@@ -271,17 +377,37 @@ impl SharedControlBlock {
         send_window_size_frames: u32,
         send_window_scale_shift_bits: u8,
         sender_mss: usize,
-        congestion_control_algorithm_constructor: CongestionControlConstructor,
+        sack_permitted: bool,
+        timestamps: Option<TcpTimestamps>,
+        ecn: bool,
         congestion_control_options: Option<congestion_control::Options>,
-        recv_queue: SharedAsyncQueue<(Ipv4Addr, TcpHeader, DemiBuffer)>,
+        recv_queue: SharedAsyncQueue<(Ipv4Header, TcpHeader, DemiBuffer)>,
         parent_passive_socket_close_queue: Option<SharedAsyncQueue<SocketAddrV4>>,
     ) -> Self {
-        let sender: Sender = Sender::new(
+        // Path MTU discovery never grows segments beyond what our MTU can carry, along with the headers and options that
+        // every segment has.
+        let mut header: TcpHeader = TcpHeader::new(local.port(), remote.port());
+        if let Some(timestamps) = timestamps.as_ref() {
+            header.push_option(timestamps.option(runtime.get_now()));
+        }
+        let header_size: usize = IPV4_HEADER_MIN_SIZE as usize + header.compute_size();
+        let max_mss: usize = (tcp_config.get_mtu() as usize).saturating_sub(header_size);
+        let mut sender: Sender = Sender::new(
             sender_initial_seq_no,
             send_window_size_frames,
             send_window_scale_shift_bits,
-            sender_mss,
+            PathMtuDiscovery::new(tcp_config.get_mtu_probing(), sender_mss, cmp::min(sender_mss, max_mss)),
+            &default_socket_options,
+            &tcp_config,
+            runtime.get_now(),
         );
+        sender.set_send_window_last_update(receive_initial_seq_no);
+        // RACK-TLP needs SACKs to tell which segments our peer has received (RFC 8985 Section 4).
+        if sack_permitted {
+            sender.enable_rack_tlp();
+        }
+        let mss: usize = sender.get_mss();
+        let now: Instant = runtime.get_now();
         Self(SharedObject::<ControlBlock>::new(ControlBlock {
             local,
             remote,
@@ -293,15 +419,35 @@ impl SharedControlBlock {
             state: State::Established,
             receive_ack_delay_timeout_secs,
             receive_ack_deadline_time_secs: SharedAsyncValue::new(None),
+            receive_last_ack_sent: receive_initial_seq_no,
             receive_buffer_size_frames: receive_window_size_frames,
+            receive_advertised_right_edge: receive_initial_seq_no + SeqNumber::from(receive_window_size_frames),
             receive_window_scale_shift_bits,
             receive_out_of_order_frames: VecDeque::new(),
-            receiver: Receiver::new(receive_initial_seq_no, receive_initial_seq_no),
-            congestion_control_algorithm: congestion_control_algorithm_constructor(
-                sender_mss,
-                sender_initial_seq_no,
-                congestion_control_options,
-            ),
+            sack_permitted,
+            receive_last_out_of_order_seq_no: None,
+            timestamps,
+            ecn,
+            ecn_echo_pending: false,
+            ecn_cwr_pending: false,
+            ecn_recover_seq_no: sender_initial_seq_no,
+            fast_open_syn_ack: None,
+            challenge_ack_count: 0,
+            challenge_ack_interval_start: now,
+            duplicate_ack_count: 0,
+            last_receive_time: now,
+            keepalive_deadline: SharedAsyncValue::new(if default_socket_options.is_keepalive_enabled() {
+                Some(now + default_socket_options.get_keepalive_idle())
+            } else {
+                None
+            }),
+            keepalive_probes_sent: 0,
+            abort_error: SharedAsyncValue::new(None),
+            read_shutdown: SharedAsyncValue::new(false),
+            receiver: Receiver::new(receive_initial_seq_no, receive_initial_seq_no, now),
+            congestion_control_algorithm: congestion_control::get_constructor(
+                default_socket_options.get_congestion_control(),
+            )(mss, sender_initial_seq_no, congestion_control_options),
             recv_queue,
             parent_passive_socket_close_queue,
         }))
@@ -325,6 +471,153 @@ impl SharedControlBlock {
         self.sender.background_sender(cb).await
     }
 
+    pub async fn background_keepalive(mut self) -> Result<Never, Fail> {
+        let mut keepalive_deadline: SharedAsyncValue<Option<Instant>> = self.keepalive_deadline.clone();
+        let mut deadline: Option<Instant> = keepalive_deadline.get();
+        loop {
+            match keepalive_deadline.wait_for_change_until(deadline).await {
+                Ok(value) => deadline = value,
+                Err(Fail { errno, cause: _ }) if errno == libc::ETIMEDOUT => {
+                    self.on_keepalive_timeout()?;
+                    deadline = keepalive_deadline.get();
+                },
+                Err(_) => {
+                    unreachable!(
+                        "either the keepalive deadline changed or the deadline passed, no other errors are possible!"
+                    )
+                },
+            }
+        }
+    }
+
+    // Probes an idle connection to find out whether our peer is still there (RFC 1122 Section 4.2.3.6).  Like Linux, we
+    // only probe while we have nothing in flight, as the retransmission timer covers that case.
+    fn on_keepalive_timeout(&mut self) -> Result<(), Fail> {
+        let now: Instant = self.get_now();
+        let idle: Duration = self.socket_options.get_keepalive_idle();
+        let interval: Duration = self.socket_options.get_keepalive_interval();
+
+        let synchronized: bool = self.state == State::Established || self.state == State::CloseWait;
+        if !synchronized || !self.socket_options.is_keepalive_enabled() {
+            self.keepalive_deadline.set(None);
+            return Ok(());
+        }
+
+        if self.sender.get_unacked_seq_no() != self.sender.get_next_seq_no() {
+            self.keepalive_deadline.set(Some(now + idle));
+            return Ok(());
+        }
+
+        // We heard from our peer since we last checked, so the connection has not been idle for long enough yet.
+        let idle_deadline: Instant = self.last_receive_time + idle;
+        if now < idle_deadline {
+            self.keepalive_deadline.set(Some(idle_deadline));
+            return Ok(());
+        }
+
+        if self.keepalive_probes_sent >= self.socket_options.get_keepalive_probes() {
+            let cause: String = format!(
+                "no response to {} keepalive probes (local={:?}, remote={:?})",
+                self.keepalive_probes_sent, self.local, self.remote
+            );
+            warn!("on_keepalive_timeout(): {}", cause);
+            let e: Fail = Fail::new(libc::ETIMEDOUT, &cause);
+            self.abort(e.clone());
+            return Err(e);
+        }
+
+        // A keepalive probe is an ACK with a sequence number that our peer has already seen, which it must answer with
+        // an ACK of its own.
+        let mut header: TcpHeader = self.tcp_header();
+        header.seq_num = self.sender.get_unacked_seq_no() - SeqNumber::from(1);
+        self.emit(header, None);
+        self.keepalive_probes_sent += 1;
+        self.keepalive_deadline.set(Some(now + interval));
+        Ok(())
+    }
+
+    /// Returns once the connection has been aborted, with the error that aborted it.
+    pub async fn wait_for_abort(self) -> Fail {
+        let mut abort_error: SharedAsyncValue<Option<Fail>> = self.abort_error.clone();
+        loop {
+            if let Some(e) = abort_error.get() {
+                return e;
+            }
+            // There is no deadline, so this can only return once the value changes.
+            let _ = abort_error.wait_for_change_until(None).await;
+        }
+    }
+
+    /// Returns once the application has shut down the receiving side of the connection.
+    async fn wait_for_read_shutdown(self) {
+        let mut read_shutdown: SharedAsyncValue<bool> = self.read_shutdown.clone();
+        while !read_shutdown.get() {
+            // There is no deadline, so this can only return once the value changes.
+            let _ = read_shutdown.wait_for_change_until(None).await;
+        }
+    }
+
+    /// Shuts down the receiving side, the sending side or both sides of the connection. Shutting down the sending side
+    /// queues a FIN after any pending data, but we keep receiving data until our peer closes its side as well.
+    pub fn shutdown(&mut self, how: Shutdown) -> Result<(), Fail> {
+        if let Some(e) = self.abort_error.get() {
+            return Err(e);
+        }
+
+        if how == Shutdown::Read || how == Shutdown::Both {
+            self.read_shutdown.set(true);
+        }
+
+        // Shutting down the sending side more than once has no further effect.
+        if (how == Shutdown::Write || how == Shutdown::Both) && !self.sender.has_pushed_fin() {
+            let state: State = match self.state {
+                State::Established => State::FinWait1,
+                State::CloseWait => State::LastAck,
+                state => {
+                    let cause: String = format!("cannot shut down the sending side in state {:?}", state);
+                    error!("shutdown(): {}", cause);
+                    return Err(Fail::new(libc::ENOTCONN, &cause));
+                },
+            };
+            self.sender.push_fin()?;
+            self.state = state;
+        }
+
+        Ok(())
+    }
+
+    /// Resets the connection, as for the ABORT call of RFC 9293 Section 3.10.4: our peer gets a RST and all pending and
+    /// future operations on the connection fail.
+    pub fn reset(&mut self) {
+        if self.abort_error.get().is_some() {
+            return;
+        }
+        // There is nobody left to tell once the close protocol is over.
+        if self.state != State::Closed && self.state != State::TimeWait {
+            let mut header: TcpHeader = self.tcp_header();
+            header.seq_num = self.sender.get_next_seq_no();
+            header.rst = true;
+            self.emit(header, None);
+        }
+        let cause: String = format!(
+            "connection reset locally (local={:?}, remote={:?})",
+            self.local, self.remote
+        );
+        debug!("reset(): {}", cause);
+        self.abort(Fail::new(libc::ECONNABORTED, &cause));
+    }
+
+    // Drops the connection without telling our peer, and fails all pending and future operations on it with [e].
+    pub fn abort(&mut self, e: Fail) {
+        self.state = State::Closed;
+        self.keepalive_deadline.set(None);
+        self.receiver.abort(e.clone());
+        self.abort_error.set(Some(e));
+        if let Some(mut socket_tx) = self.parent_passive_socket_close_queue.take() {
+            socket_tx.push(self.remote);
+        }
+    }
+
     pub fn congestion_control_watch_retransmit_now_flag(&self) -> SharedAsyncValue<bool> {
         self.congestion_control_algorithm.get_retransmit_now_flag()
     }
@@ -337,6 +630,16 @@ impl SharedControlBlock {
         self.congestion_control_algorithm.on_rto(send_unacknowledged)
     }
 
+    pub fn congestion_control_on_sack_recovery(&mut self, send_unacknowledged: SeqNumber, send_next: SeqNumber) {
+        self.congestion_control_algorithm
+            .on_sack_recovery(send_unacknowledged, send_next)
+    }
+
+    pub fn congestion_control_on_loss_detected(&mut self, send_unacknowledged: SeqNumber, send_next: SeqNumber) {
+        self.congestion_control_algorithm
+            .on_loss_detected(send_unacknowledged, send_next)
+    }
+
     pub fn congestion_control_on_send(&mut self, rto: Duration, num_sent_bytes: u32) {
         self.congestion_control_algorithm.on_send(rto, num_sent_bytes)
     }
@@ -349,6 +652,10 @@ impl SharedControlBlock {
         self.congestion_control_algorithm.get_cwnd()
     }
 
+    pub fn congestion_control_get_pacing_rate(&self) -> Option<u64> {
+        self.congestion_control_algorithm.get_pacing_rate()
+    }
+
     pub fn congestion_control_get_limited_transmit_cwnd_increase(&self) -> SharedAsyncValue<u32> {
         self.congestion_control_algorithm.get_limited_transmit_cwnd_increase()
     }
@@ -357,17 +664,62 @@ impl SharedControlBlock {
         self.runtime.get_now()
     }
 
-    pub fn receive(&mut self, remote_ipv4_addr: Ipv4Addr, tcp_hdr: TcpHeader, buf: DemiBuffer) {
-        self.recv_queue.push((remote_ipv4_addr, tcp_hdr, buf));
+    pub fn get_nodelay(&self) -> bool {
+        self.socket_options.get_nodelay()
+    }
+
+    pub fn set_socket_options(&mut self, socket_options: TcpSocketOptions) {
+        let algorithm: CongestionControlAlgorithm = socket_options.get_congestion_control();
+        if algorithm != self.socket_options.get_congestion_control() {
+            // Like Linux, start the new algorithm from scratch.
+            debug!("set_socket_options(): switching congestion control to {}", algorithm);
+            let constructor: CongestionControlConstructor = congestion_control::get_constructor(algorithm);
+            let mut old_cwnd: SharedAsyncValue<u32> = self.congestion_control_algorithm.get_cwnd();
+            self.congestion_control_algorithm = constructor(self.sender.get_mss(), self.sender.get_next_seq_no(), None);
+            // Wake up a sender waiting for the old algorithm to open its window, so it picks up the new one.
+            old_cwnd.set(self.congestion_control_algorithm.get_cwnd().get());
+        }
+        let keepalive_changed: bool = socket_options.is_keepalive_enabled()
+            != self.socket_options.is_keepalive_enabled()
+            || socket_options.get_keepalive_idle() != self.socket_options.get_keepalive_idle();
+        self.sender.set_send_buffer_size(socket_options.get_send_buffer_size());
+        self.sender.set_user_timeout(socket_options.get_user_timeout());
+        if let Some(receive_buffer_size) = socket_options.get_receive_buffer_size() {
+            // The window scale was fixed during the handshake, so it limits how much of the buffer we can advertise.
+            let max_window_size: u32 = (u16::MAX as u32) << self.receive_window_scale_shift_bits;
+            self.receive_buffer_size_frames = cmp::min(receive_buffer_size as u32, max_window_size);
+        }
+        self.socket_options = socket_options;
+        if keepalive_changed && self.abort_error.get().is_none() {
+            // Let the keepalive coroutine reconsider when to probe next.
+            let now: Instant = self.get_now();
+            self.keepalive_deadline.set(Some(now));
+        }
+    }
+
+    pub fn receive(&mut self, ip_hdr: Ipv4Header, tcp_hdr: TcpHeader, buf: DemiBuffer) {
+        self.recv_queue.push((ip_hdr, tcp_hdr, buf));
     }
 
     // This is the main TCP processing routine.
     pub async fn poll(&mut self) -> Result<Never, Fail> {
-        let mut receive_queue: SharedAsyncQueue<(Ipv4Addr, TcpHeader, DemiBuffer)> = self.recv_queue.clone();
+        let mut receive_queue: SharedAsyncQueue<(Ipv4Header, TcpHeader, DemiBuffer)> = self.recv_queue.clone();
 
         // Normal data processing in the Established state.
         loop {
-            let (_, header, data): (Ipv4Addr, TcpHeader, DemiBuffer) = receive_queue.pop(None).await?;
+            let (ip_hdr, header, data): (Ipv4Header, TcpHeader, DemiBuffer) = match receive_queue.pop(None).await {
+                Ok(segment) => segment,
+                // Keep waiting on idle connections.
+                Err(Fail { errno, cause: _ }) if errno == libc::ETIMEDOUT => continue,
+                Err(e) => return Err(e),
+            };
+            // Stop processing segments once we aborted the connection.
+            if let Some(e) = self.abort_error.get() {
+                return Err(e);
+            }
+            // Anything that we hear from our peer answers our keepalive probes.
+            self.last_receive_time = self.get_now();
+            self.keepalive_probes_sent = 0;
 
             debug!(
                 "{:?} Connection Receiving {} bytes + {:?}",
@@ -376,7 +728,7 @@ impl SharedControlBlock {
                 header
             );
 
-            match self.process_packet(header, data) {
+            match self.process_packet(ip_hdr.get_ecn(), header, data) {
                 Ok(()) => (),
                 Err(e) => debug!("Dropped packet: {:?}", e),
             }
@@ -398,23 +750,36 @@ impl SharedControlBlock {
     /// This is the main function for processing an incoming packet during the Established state when the connection is
     /// active. Each step in this function return Ok if there is further processing to be done and EBADMSG if the
     /// packet should be dropped after the step.
-    fn process_packet(&mut self, mut header: TcpHeader, mut data: DemiBuffer) -> Result<(), Fail> {
+    fn process_packet(&mut self, ecn: EcnCodepoint, mut header: TcpHeader, mut data: DemiBuffer) -> Result<(), Fail> {
         let mut seg_start: SeqNumber = header.seq_num;
         let mut seg_end: SeqNumber = seg_start;
         let mut seg_len: u32 = data.len() as u32;
 
-        // Check if the segment is in the receive window and trim off everything else.
+        self.check_fast_open_syn(&header)?;
+        // Reject old duplicates before anything else, then check if the segment is in the receive window and trim off
+        // everything else.
+        self.check_timestamp(&header)?;
         self.check_segment_in_window(&mut header, &mut data, &mut seg_start, &mut seg_end, &mut seg_len)?;
+        self.update_timestamp(&header);
         self.check_rst(&header)?;
         self.check_syn(&header)?;
-        self.process_ack(&header)?;
+        self.process_ecn(ecn, &header);
+        self.process_ack(&header, seg_len)?;
 
         // TODO: Check the URG bit.  If we decide to support this, how should we do it?
         if header.urg {
             warn!("Got packet with URG bit set!");
         }
 
+        // Remember whether this segment may fill a hole in the sequence space, in front of out-of-order data or FIN.
+        let had_out_of_order_data: bool = !self.receive_out_of_order_frames.is_empty()
+            || self
+                .receiver
+                .fin_seq_no
+                .get()
+                .is_some_and(|seq_no| seq_no != self.receiver.receive_next_seq_no);
         if data.len() > 0 {
+            self.sample_receive_rtt(&header);
             self.process_data(data, seg_start, seg_end, seg_len)?;
         }
 
@@ -447,22 +812,31 @@ impl SharedControlBlock {
             trace!("Acking FIN");
             self.send_ack()
         }
-        // We should ACK this segment, preferably via piggybacking on a response.
-        // TODO: Consider replacing the delayed ACK timer with a simple flag.
-        if self.receive_ack_deadline_time_secs.get().is_none() {
+        // We should ACK this segment, preferably via piggybacking on a response.  Segments that don't occupy sequence
+        // space (i.e. pure ACKs) and segments that we have already acknowledged don't need an ACK.
+        if seg_len > 0 && self.receive_last_ack_sent != self.receiver.receive_next_seq_no {
+            self.acknowledge_segment(had_out_of_order_data);
+        }
+
+        Ok(())
+    }
+
+    // Delayed ACKs (RFC 1122 Section 4.2.3.2).  ACK right away if we owe our peer an ACK for at least two full-sized
+    // segments, if this segment filled a hole in the sequence space (RFC 5681 Section 4.2) or if delayed ACKs are
+    // disabled on this socket.  Otherwise, let the delayed ACK timer or an outgoing segment carry the ACK.
+    fn acknowledge_segment(&mut self, had_out_of_order_data: bool) {
+        let bytes_unacknowledged: u32 = (self.receiver.receive_next_seq_no - self.receive_last_ack_sent).into();
+        let full_segments: u32 = bytes_unacknowledged / self.tcp_config.get_advertised_mss() as u32;
+        if full_segments >= 2 || had_out_of_order_data || self.socket_options.get_quickack() {
+            trace!("acknowledge_segment(): sending ack right away");
+            self.send_ack();
+        } else if self.receive_ack_deadline_time_secs.get().is_none() {
             // Start the delayed ACK timer to ensure an ACK gets sent soon even if no piggyback opportunity occurs.
             let timeout: Duration = self.receive_ack_delay_timeout_secs;
             // Getting the current time is extremely cheap as it is just a variable lookup.
             let now: Instant = self.get_now();
             self.receive_ack_deadline_time_secs.set(Some(now + timeout));
-        } else {
-            // We already owe our peer an ACK (the timer was already running), so cancel the timer and ACK now.
-            self.receive_ack_deadline_time_secs.set(None);
-            trace!("process_packet(): sending ack on deadline expiration");
-            self.send_ack();
         }
-
-        Ok(())
     }
 
     // Check to see if the segment is acceptable sequence-wise (i.e. contains some data that fits within the receive
@@ -592,18 +966,70 @@ impl SharedControlBlock {
         Ok(())
     }
 
+    // Protect against wrapped sequence numbers (PAWS).  Segments carrying a timestamp older than the most recent one we
+    // accepted are old duplicates: ACK (if not RST) and drop.  See RFC 7323 Section 5.3.
+    // Returns Ok if further processing is needed and EBADMSG if the packet should be dropped.
+    fn check_timestamp(&mut self, header: &TcpHeader) -> Result<(), Fail> {
+        let timestamps: &TcpTimestamps = match self.timestamps.as_ref() {
+            Some(timestamps) => timestamps,
+            None => return Ok(()),
+        };
+        match find_timestamps(header.iter_options()) {
+            Some((tsval, _)) if timestamps.is_old(tsval) && !header.rst => {
+                trace!("check_timestamp(): send ack on old duplicate segment");
+                self.send_ack();
+                let cause: String = format!("old duplicate segment (tsval={})", tsval);
+                error!("check_timestamp(): {}", cause);
+                Err(Fail::new(libc::EBADMSG, &cause))
+            },
+            Some(_) => Ok(()),
+            // RFC 7323 Section 3.2: Once negotiated, every segment other than a RST must carry the option.
+            None if !header.rst => {
+                let cause: &str = "segment without timestamps option";
+                error!("check_timestamp(): {}", cause);
+                Err(Fail::new(libc::EBADMSG, cause))
+            },
+            None => Ok(()),
+        }
+    }
+
+    // Record the timestamp of an acceptable segment so that we echo it back.  See RFC 7323 Section 4.3.
+    fn update_timestamp(&mut self, header: &TcpHeader) {
+        if let (Some(timestamps), Some((tsval, _))) = (self.timestamps.as_mut(), find_timestamps(header.iter_options()))
+        {
+            timestamps.update_recent(tsval, header.seq_num);
+        }
+    }
+
+    // Receive buffer auto-tuning needs a round-trip time even if we never send data.  With timestamps, every data
+    // segment echoes the time at which we sent the segment that it answers.  See RFC 7323 Section 4.
+    fn sample_receive_rtt(&mut self, header: &TcpHeader) {
+        if let (Some(timestamps), Some((_, tsecr))) = (self.timestamps.as_ref(), find_timestamps(header.iter_options()))
+        {
+            // A sample of zero only means that the round trip took less than a tick of the timestamp clock.
+            let rtt: Duration = cmp::max(timestamps.rtt_sample(tsecr, self.get_now()), Duration::from_millis(1));
+            self.receiver.buffer_tuner.add_rtt_sample(rtt);
+        }
+    }
+
     // Check the RST bit.
     fn check_rst(&mut self, header: &TcpHeader) -> Result<(), Fail> {
         if header.rst {
-            // TODO: RFC 5961 "Blind Reset Attack Using the RST Bit" prevention would have us ACK and drop if the new
-            // segment doesn't start precisely on RCV.NXT.
+            // RFC 5961 "Blind Reset Attack Using the RST Bit" prevention: an in-window RST that doesn't start precisely
+            // on RCV.NXT could come from an attacker, so challenge our peer to reset the connection with the right one.
+            if header.seq_num != self.receiver.receive_next_seq_no {
+                self.send_challenge_ack();
+                let cause: &str = "RST does not start at RCV.NXT";
+                warn!("check_rst(): {}", cause);
+                return Err(Fail::new(libc::EBADMSG, cause));
+            }
 
             // Our peer has given up.  Shut the connection down hard.
-            info!("Received RST");
-            // TODO: Schedule a close coroutine.
             let cause: String = format!("remote reset connection");
             info!("check_rst(): {}", cause);
-            return Err(Fail::new(libc::ECONNRESET, &cause));
+            let e: Fail = Fail::new(libc::ECONNRESET, &cause);
+            self.abort(e.clone());
+            return Err(e);
         }
         Ok(())
     }
@@ -614,23 +1040,68 @@ impl SharedControlBlock {
 
         // Check the SYN bit.
         if header.syn {
-            // TODO: RFC 5961 "Blind Reset Attack Using the SYN Bit" prevention would have us always ACK and drop here.
-
-            // Receiving a SYN here is an error.
+            // RFC 5961 "Blind Reset Attack Using the SYN Bit" prevention: Never reset the connection, instead send a
+            // challenge ACK and drop the segment.  If our peer really restarted, it will answer with an acceptable RST.
+            self.send_challenge_ack();
             let cause: String = format!("Received in-window SYN on established connection.");
-            error!("{}", cause);
-            // TODO: Send Reset.
-            // TODO: Return all outstanding Receive and Send requests with "reset" responses.
-            // TODO: Flush all segment queues.
-
-            // TODO: Start the close coroutine
+            error!("check_syn(): {}", cause);
             return Err(Fail::new(libc::EBADMSG, &cause));
         }
         Ok(())
     }
 
+    // Until our peer acknowledges the SYN+ACK that accepted the data in its SYN, it retransmits the SYN if the SYN+ACK
+    // gets lost. Answer with the same SYN+ACK and drop the SYN, which carries data that we already have.
+    fn check_fast_open_syn(&mut self, header: &TcpHeader) -> Result<(), Fail> {
+        let syn_ack: TcpHeader = match self.fast_open_syn_ack.clone() {
+            Some(syn_ack) => syn_ack,
+            None => return Ok(()),
+        };
+        if header.ack && !header.syn {
+            self.fast_open_syn_ack = None;
+            return Ok(());
+        }
+        if header.syn && !header.ack && !header.rst {
+            debug!("check_fast_open_syn(): resending SYN+ACK: {:?}", syn_ack);
+            let mut pkt: DemiBuffer = DemiBuffer::new_with_headroom(0, MAX_HEADER_SIZE as u16);
+            syn_ack.serialize_and_attach(
+                &mut pkt,
+                self.local.ip(),
+                self.remote.ip(),
+                self.tcp_config.get_tx_checksum_offload(),
+            );
+            let remote_ipv4_addr: Ipv4Addr = *self.remote.ip();
+            if let Err(e) = self
+                .layer3_endpoint
+                .transmit_tcp_packet_nonblocking(remote_ipv4_addr, pkt)
+            {
+                warn!("check_fast_open_syn(): could not send SYN+ACK: {:?}", e);
+            }
+            let cause: &str = "Received retransmitted SYN";
+            debug!("check_fast_open_syn(): {}", cause);
+            return Err(Fail::new(libc::EBADMSG, cause));
+        }
+        Ok(())
+    }
+
+    // Check the ECN codepoint and the CWR bit (RFC 3168 Section 6.1.3). Once we receive a segment marked CE, we set ECE
+    // on every ACK until our peer tells us with CWR that it reduced its congestion window. CWR comes first, so that we
+    // keep echoing a mark that arrives on the same segment.
+    fn process_ecn(&mut self, ecn: EcnCodepoint, header: &TcpHeader) {
+        if !self.ecn {
+            return;
+        }
+        if header.cwr {
+            self.ecn_echo_pending = false;
+        }
+        if ecn == EcnCodepoint::Ce {
+            debug!("process_ecn(): received congestion mark");
+            self.ecn_echo_pending = true;
+        }
+    }
+
     // Check the ACK bit.
-    fn process_ack(&mut self, header: &TcpHeader) -> Result<(), Fail> {
+    fn process_ack(&mut self, header: &TcpHeader, seg_len: u32) -> Result<(), Fail> {
         if !header.ack {
             // All segments on established connections should be ACKs.  Drop this segment.
             let cause: String = format!("Received non-ACK segment on established connection");
@@ -638,9 +1109,6 @@ impl SharedControlBlock {
             return Err(Fail::new(libc::EBADMSG, &cause));
         }
 
-        // TODO: RFC 5961 "Blind Data Injection Attack" prevention would have us perform additional ACK validation
-        // checks here.
-
         // Process the ACK.
         // Start by checking that the ACK acknowledges something new.
         // TODO: Look into removing Watched types.
@@ -648,6 +1116,16 @@ impl SharedControlBlock {
         let send_unacknowledged: SeqNumber = self.sender.get_unacked_seq_no();
         let send_next: SeqNumber = self.sender.get_next_seq_no();
 
+        // RFC 5961 "Blind Data Injection Attack" prevention: only accept ACKs for data that we sent within the largest
+        // window our peer has advertised, i.e. (SND.UNA - MAX.SND.WND) =< SEG.ACK.
+        let oldest_acceptable_ack: SeqNumber = send_unacknowledged - SeqNumber::from(self.sender.get_send_window_max());
+        if header.ack_num < oldest_acceptable_ack {
+            self.send_challenge_ack();
+            let cause: &str = "Received segment acknowledging data that is too old";
+            warn!("process_ack(): {}", cause);
+            return Err(Fail::new(libc::EBADMSG, cause));
+        }
+
         // TODO: Restructure this call into congestion control to either integrate it directly or make it more fine-
         // grained.  It currently duplicates the new/duplicate ack check itself internally, which is inefficient.
         // We should either make separate calls for each case or integrate those cases directly.
@@ -655,18 +1133,54 @@ impl SharedControlBlock {
         self.congestion_control_algorithm
             .on_ack_received(rto, send_unacknowledged, send_next, header.ack_num);
 
+        // RFC 5681 Section 2: A duplicate ACK carries no data, doesn't move SND.UNA or the window, and arrives while
+        // we have data outstanding.
+        if header.ack_num > send_unacknowledged {
+            self.duplicate_ack_count = 0;
+        } else if seg_len == 0
+            && send_unacknowledged < send_next
+            && header.ack_num == send_unacknowledged
+            && !header.syn
+            && !header.fin
+            && !self.sender.is_window_update(header)
+        {
+            self.duplicate_ack_count += 1;
+            let duplicate_ack_count: u32 = self.duplicate_ack_count;
+            self.congestion_control_algorithm
+                .on_duplicate_ack(send_unacknowledged, send_next, duplicate_ack_count);
+        }
+
         // Check whether this is an ack for data that we have sent.
         if header.ack_num <= send_next {
             // Does not matter when we get this since the clock will not move between the beginning of packet
             // processing and now without a call to advance_clock.
             let now: Instant = self.get_now();
-            self.sender.process_ack(header, now);
+            // With timestamps, every ACK for new data gives us an RTT sample, even for retransmitted segments.
+            let rtt_sample: Option<Duration> = match (self.timestamps.as_ref(), find_timestamps(header.iter_options()))
+            {
+                (Some(timestamps), Some((_, tsecr))) if header.ack_num > send_unacknowledged => {
+                    Some(timestamps.rtt_sample(tsecr, now))
+                },
+                _ => None,
+            };
+            if let Some(rate_sample) = self.sender.process_ack(header, now, rtt_sample) {
+                self.congestion_control_algorithm.on_rate_sample(&rate_sample);
+            }
+
+            // Our peer saw congestion on the path. Reduce the congestion window at most once per window of data, and
+            // let our peer know with CWR (RFC 3168 Section 6.1.2).
+            if self.ecn && header.ece && header.ack_num > self.ecn_recover_seq_no {
+                debug!("process_ack(): received ECN echo");
+                self.congestion_control_algorithm.on_ecn_echo(header.ack_num, send_next);
+                self.ecn_recover_seq_no = send_next;
+                self.ecn_cwr_pending = true;
+            }
         } else {
-            // This segment acknowledges data we have yet to send!?  Send an ACK and drop the segment.
-            // TODO: See RFC 5961, this could be a Blind Data Injection Attack.
+            // This segment acknowledges data we have yet to send!?  Send an ACK and drop the segment.  This could be a
+            // Blind Data Injection Attack (RFC 5961 Section 5), so the ACK is rate-limited.
             let cause: String = format!("Received segment acknowledging data we have yet to send!");
             warn!("process_ack(): {}", cause);
-            self.send_ack();
+            self.send_challenge_ack();
             return Err(Fail::new(libc::EBADMSG, &cause));
         }
 
@@ -717,10 +1231,78 @@ impl SharedControlBlock {
         header.ack = true;
         header.ack_num = self.receiver.receive_next_seq_no;
 
+        // Timestamp every segment once negotiated.
+        if let Some(timestamps) = self.timestamps.as_ref() {
+            header.push_option(timestamps.option(self.get_now()));
+        }
+
+        // Echo congestion marks until our peer reacts to them.
+        header.ece = self.ecn_echo_pending;
+
+        // Report any data that we are holding in the out-of-order store.
+        if let Some(option) = self.sack_option() {
+            header.push_option(option);
+        }
+
         // Return this header.
         header
     }
 
+    /// Builds a SACK option describing the contiguous blocks of data held in the out-of-order store, if SACK was
+    /// negotiated on this connection and there is anything to report. As required by RFC 2018, the first block is the
+    /// one containing the most recently received segment, and the remaining blocks follow in sequence number order.
+    fn sack_option(&self) -> Option<TcpOptions2> {
+        if !self.sack_permitted || self.receive_out_of_order_frames.is_empty() {
+            return None;
+        }
+
+        // Coalesce adjacent out-of-order segments into blocks. The out-of-order store is sorted and has no overlaps.
+        let mut blocks: Vec<SelectiveAcknowlegement> = Vec::with_capacity(self.receive_out_of_order_frames.len());
+        for (start, buf) in self.receive_out_of_order_frames.iter() {
+            let end: SeqNumber = *start + SeqNumber::from(buf.len() as u32);
+            match blocks.last_mut() {
+                Some(block) if block.end == *start => block.end = end,
+                _ => blocks.push(SelectiveAcknowlegement { begin: *start, end }),
+            }
+        }
+
+        // Move the block containing the most recently received segment to the front.
+        if let Some(last) = self.receive_last_out_of_order_seq_no {
+            if let Some(index) = blocks.iter().position(|block| block.begin <= last && last < block.end) {
+                let block: SelectiveAcknowlegement = blocks.remove(index);
+                blocks.insert(0, block);
+            }
+        }
+
+        let max_sacks: usize = match self.timestamps {
+            Some(_) => MAX_SACK_BLOCKS_WITH_TIMESTAMPS,
+            None => MAX_SACK_BLOCKS,
+        };
+        let num_sacks: usize = blocks.len().min(max_sacks);
+        let mut sacks: [SelectiveAcknowlegement; MAX_SACK_BLOCKS] = [SelectiveAcknowlegement {
+            begin: SeqNumber::from(0),
+            end: SeqNumber::from(0),
+        }; MAX_SACK_BLOCKS];
+        sacks[..num_sacks].copy_from_slice(&blocks[..num_sacks]);
+        Some(TcpOptions2::SelectiveAcknowlegement { num_sacks, sacks })
+    }
+
+    /// Sends a challenge ACK in response to a segment that might come from a blind attacker (RFC 5961 Sections 3-5).
+    /// Challenge ACKs are rate-limited (RFC 5961 Section 7), so that an attacker can't turn us into an ACK flood.
+    fn send_challenge_ack(&mut self) {
+        let now: Instant = self.get_now();
+        if now >= self.challenge_ack_interval_start + Duration::from_secs(1) {
+            self.challenge_ack_interval_start = now;
+            self.challenge_ack_count = 0;
+        }
+        if self.challenge_ack_count >= CHALLENGE_ACK_LIMIT {
+            warn!("send_challenge_ack(): challenge ACK limit reached, not sending");
+            return;
+        }
+        self.challenge_ack_count += 1;
+        self.send_ack();
+    }
+
     /// Send an ACK to our peer, reflecting our current state.
     pub fn send_ack(&mut self) {
         trace!("sending ack");
@@ -734,6 +1316,24 @@ impl SharedControlBlock {
 
     /// Transmit this message to our connected peer.
     pub fn emit(&mut self, header: TcpHeader, body: Option<DemiBuffer>) {
+        self.emit_with_ecn(header, body, EcnCodepoint::NotEct)
+    }
+
+    /// Transmit a segment of new data to our connected peer. Only these segments are ECN-capable, retransmissions, window
+    /// probes and pure ACKs are not (RFC 3168 Section 6.1.5).
+    pub fn emit_new_data(&mut self, mut header: TcpHeader, body: DemiBuffer) {
+        if !self.ecn {
+            return self.emit(header, Some(body));
+        }
+        // Let our peer know that we reduced the congestion window.
+        if self.ecn_cwr_pending {
+            header.cwr = true;
+            self.ecn_cwr_pending = false;
+        }
+        self.emit_with_ecn(header, Some(body), EcnCodepoint::Ect0)
+    }
+
+    fn emit_with_ecn(&mut self, header: TcpHeader, body: Option<DemiBuffer>, ecn: EcnCodepoint) {
         // Only perform this debug print in debug builds.  debug_assertions is compiler set in non-optimized builds.
         let mut pkt = match body {
             Some(body) => {
@@ -760,7 +1360,7 @@ impl SharedControlBlock {
         // Call lower L3 layer to send the segment.
         if let Err(e) = self
             .layer3_endpoint
-            .transmit_tcp_packet_nonblocking(remote_ipv4_addr, pkt)
+            .transmit_tcp_packet_with_ecn_nonblocking(remote_ipv4_addr, ecn, pkt)
         {
             warn!("could not emit packet: {:?}", e);
             return;
@@ -771,6 +1371,16 @@ impl SharedControlBlock {
 
         // Since we sent an ACK, cancel any outstanding delayed ACK request.
         self.set_receive_ack_deadline(None);
+        self.receive_last_ack_sent = header.ack_num;
+
+        // Remember what we acknowledged, to decide which timestamps to echo.
+        if let Some(timestamps) = self.timestamps.as_mut() {
+            timestamps.on_send(header.ack_num);
+        }
+
+        // Remember the window that we advertised.
+        self.receive_advertised_right_edge =
+            header.ack_num + SeqNumber::from((header.window_size as u32) << self.receive_window_scale_shift_bits);
     }
 
     pub fn get_receive_ack_deadline(&self) -> SharedAsyncValue<Option<Instant>> {
@@ -781,13 +1391,51 @@ impl SharedControlBlock {
         self.receive_ack_deadline_time_secs.set(when);
     }
 
+    pub fn get_receive_buffer_size(&self) -> u32 {
+        self.receive_buffer_size_frames
+    }
+
+    /// Gathers statistics of this connection from the sender, the receiver and congestion control.
+    pub fn tcp_info(&self) -> TcpInfo {
+        TcpInfo {
+            srtt: self.sender.get_srtt().unwrap_or_default(),
+            rttvar: self.sender.get_rttvar(),
+            rto: self.sender.get_rto(),
+            send_mss: self.sender.get_mss() as u32,
+            congestion_window: self.congestion_control_get_cwnd().get(),
+            send_window: self.sender.get_send_window(),
+            receive_window: self.get_receive_window_size(),
+            bytes_in_flight: self.sender.get_bytes_in_flight(),
+            total_retransmits: self.sender.get_total_retransmits(),
+        }
+    }
+
     pub fn get_receive_window_size(&self) -> u32 {
         let bytes_unread: u32 = (self.receiver.receive_next_seq_no - self.receiver.reader_next_seq_no).into();
-        self.receive_buffer_size_frames - bytes_unread
+        // The receive buffer may have shrunk below the data that it already holds.
+        self.receive_buffer_size_frames.saturating_sub(bytes_unread)
     }
 
-    fn hdr_window_size(&self) -> u16 {
+    // Receiver-side SWS avoidance (RFC 1122 Section 4.2.3.3): only move the right edge of the advertised window once it
+    // can advance by at least min(half of our receive buffer, one MSS).
+    fn get_advertised_receive_window_size(&self) -> u32 {
         let window_size: u32 = self.get_receive_window_size();
+        let receive_next: SeqNumber = self.receiver.receive_next_seq_no;
+        let advertised_window_size: u32 = if self.receive_advertised_right_edge > receive_next {
+            (self.receive_advertised_right_edge - receive_next).into()
+        } else {
+            0
+        };
+        let threshold: u32 = cmp::min(self.receive_buffer_size_frames / 2, self.sender.get_mss() as u32);
+        if window_size >= advertised_window_size + threshold {
+            window_size
+        } else {
+            cmp::min(advertised_window_size, window_size)
+        }
+    }
+
+    fn hdr_window_size(&self) -> u16 {
+        let window_size: u32 = self.get_advertised_receive_window_size();
         let hdr_window_size: u16 = expect_ok!(
             (window_size >> self.receive_window_scale_shift_bits).try_into(),
             "Window size overflow"
@@ -801,8 +1449,28 @@ impl SharedControlBlock {
         hdr_window_size
     }
 
+    pub fn push_connect_data(&mut self, buf: DemiBuffer) {
+        self.sender.push_connect_data(buf)
+    }
+
     pub async fn push(&mut self, buf: DemiBuffer) -> Result<(), Fail> {
-        self.sender.push(buf).await
+        if self.sender.has_pushed_fin() {
+            let cause: &str = "cannot send after the sending side was shut down";
+            error!("push(): {}", cause);
+            return Err(Fail::new(libc::EPIPE, cause));
+        }
+
+        let mut cb: Self = self.clone();
+        let push = async move { cb.sender.push(buf).await }.fuse();
+        pin_mut!(push);
+        let aborted = self.clone().wait_for_abort().fuse();
+        pin_mut!(aborted);
+
+        // Data that was not acknowledged before the connection was aborted is lost.
+        futures::select_biased! {
+            e = aborted => Err(e),
+            result = push => result,
+        }
     }
 
     pub async fn pop(&mut self, size: Option<usize>) -> Result<DemiBuffer, Fail> {
@@ -813,7 +1481,54 @@ impl SharedControlBlock {
         //  if self.receiver.reader_next.get() == self.receiver.receive_next.get() {
         // But that will think data is available to be read once we've received a FIN, because FINs consume sequence
         // number space.  Now we call is_empty() on the receive queue instead.
-        self.receiver.pop(size).await
+        //
+        // Once the application has shut down the receiving side, reads return end-of-file right away.
+        if self.read_shutdown.get() {
+            return Ok(DemiBuffer::new(0));
+        }
+
+        let mut cb: Self = self.clone();
+        let pop = async move { cb.receiver.pop(size).await }.fuse();
+        pin_mut!(pop);
+        let read_shutdown = self.clone().wait_for_read_shutdown().fuse();
+        pin_mut!(read_shutdown);
+
+        let result: Result<DemiBuffer, Fail> = futures::select_biased! {
+            _ = read_shutdown => Ok(DemiBuffer::new(0)),
+            result = pop => result,
+        };
+        if result.is_ok() {
+            self.tune_receive_buffer();
+        }
+        result
+    }
+
+    // Receive buffer auto-tuning: grow the receive buffer with the rate at which the application reads, up to the
+    // configured maximum.  Like Linux, setting SO_RCVBUF turns this off.
+    fn tune_receive_buffer(&mut self) {
+        if self.socket_options.get_receive_buffer_size().is_some() {
+            return;
+        }
+        let rtt: Duration = match self.receiver.buffer_tuner.get_rtt_estimate().or(self.sender.get_srtt()) {
+            Some(rtt) => rtt,
+            None => return,
+        };
+        let now: Instant = self.get_now();
+        let reader_next: SeqNumber = self.receiver.reader_next_seq_no;
+        let size: u32 = match self.receiver.buffer_tuner.on_read(reader_next, now, rtt) {
+            Some(size) => size,
+            None => return,
+        };
+        // The window scale was fixed during the handshake, so it limits how much of the buffer we can advertise.
+        let max_size: u32 = cmp::min(
+            self.tcp_config.get_receive_buffer_max_size(),
+            (u16::MAX as u32) << self.receive_window_scale_shift_bits,
+        );
+        let size: u32 = cmp::min(size, max_size);
+        if size > self.receive_buffer_size_frames {
+            debug!("tune_receive_buffer(): growing receive buffer to {} bytes", size);
+            self.receive_buffer_size_frames = size;
+        }
     }
 
     // This routine takes an incoming TCP segment and adds it to the out-of-order receive queue.
@@ -910,6 +1625,7 @@ impl SharedControlBlock {
 
         // Insert the new segment into the correct position.
         self.receive_out_of_order_frames.insert(action_index, (new_start, buf));
+        self.receive_last_out_of_order_seq_no = Some(new_start);
 
         // If the out-of-order store now contains too many entries, delete the later entries.
         // TODO: The out-of-order store is already limited (in size) by our receive window, while the below check
@@ -927,12 +1643,29 @@ impl SharedControlBlock {
     //
     // Returns true if a previously out-of-order segment containing a FIN has now been received.
     //
-    fn receive_data(&mut self, seg_start: SeqNumber, buf: DemiBuffer) {
+    fn receive_data(&mut self, seg_start: SeqNumber, mut buf: DemiBuffer) {
         let recv_next: SeqNumber = self.receiver.receive_next_seq_no;
 
         // This routine should only be called with in-order segment data.
         debug_assert_eq!(seg_start, recv_next);
 
+        // Never hold more data than our receive buffer has room for. Our peer should not have sent more than the window
+        // that we advertised, but the receive buffer may have shrunk since. Our peer retransmits whatever we drop here.
+        let room: usize = self.get_receive_window_size() as usize;
+        if buf.len() > room {
+            warn!(
+                "receive_data(): dropping {} bytes that exceed the receive buffer",
+                buf.len() - room
+            );
+            expect_ok!(
+                buf.trim(buf.len() - room),
+                "'buf' should contain more than 'room' bytes"
+            );
+            if buf.len() == 0 {
+                return;
+            }
+        }
+
         // Push the new segment data onto the end of the receive queue.
         let mut recv_next: SeqNumber = recv_next + SeqNumber::from(buf.len() as u32);
         // This inserts the segment and wakes a waiting pop coroutine.
@@ -942,7 +1675,8 @@ impl SharedControlBlock {
         // the out-of-order queue is now in-order.  If so, we can move it to the receive queue.
         while !self.receive_out_of_order_frames.is_empty() {
             if let Some(stored_entry) = self.receive_out_of_order_frames.front() {
-                if stored_entry.0 == recv_next {
+                // Leave the segment where it is until the receive buffer has room for all of it.
+                if stored_entry.0 == recv_next && stored_entry.1.len() <= self.get_receive_window_size() as usize {
                     // Move this entry's buffer from the out-of-order store to the receive queue.
                     // This data is now considered to be "received" by TCP, and included in our RCV.NXT calculation.
                     debug!("Recovering out-of-order packet at {}", recv_next);
@@ -959,6 +1693,17 @@ impl SharedControlBlock {
         }
     }
 
+    /// Accepts the data that our peer sent in its SYN with a valid TCP Fast Open cookie (RFC 7413 Section 4.2.2), and
+    /// returns `syn_ack` acknowledging it. We keep the SYN+ACK to answer retransmissions of the SYN.
+    pub fn accept_fast_open(&mut self, data: DemiBuffer, mut syn_ack: TcpHeader) -> TcpHeader {
+        let seg_start: SeqNumber = self.receiver.receive_next_seq_no;
+        self.receive_data(seg_start, data);
+        syn_ack.ack_num = self.receiver.receive_next_seq_no;
+        self.receive_last_ack_sent = syn_ack.ack_num;
+        self.fast_open_syn_ack = Some(syn_ack.clone());
+        syn_ack
+    }
+
     fn process_fin(&mut self) {
         let state = match self.state {
             State::Established => State::CloseWait,
@@ -972,24 +1717,54 @@ impl SharedControlBlock {
 
     // This coroutine runs the close protocol.
     pub async fn close(&mut self) -> Result<(), Fail> {
-        // Assert we are in a valid state and move to new state.
-        match self.state {
-            State::Established => self.local_close().await,
-            State::CloseWait => self.remote_already_closed().await,
-            _ => {
-                let cause: String = format!("socket is already closing");
-                error!("close(): {}", cause);
-                Err(Fail::new(libc::EBADF, &cause))
-            },
+        // Close abortively if SO_LINGER is set with a zero timeout, or if the application did not read all the data
+        // that it received while our peer is still sending, to show our peer that this data was lost (RFC 1122 Section
+        // 4.2.2.13). Once our peer has sent its FIN, it only waits for ours.
+        let synchronized: bool = self.state == State::Established || self.state == State::CloseWait;
+        let unread_data: bool = self.state == State::Established && self.receiver.has_unread_data();
+        if synchronized && (self.socket_options.get_linger() == Some(Duration::ZERO) || unread_data) {
+            self.reset();
+            return Ok(());
+        }
+
+        let mut cb: Self = self.clone();
+        let close_protocol = async move {
+            // Assert we are in a valid state and move to new state.
+            match cb.state {
+                State::Established => cb.local_close().await,
+                State::CloseWait => cb.remote_already_closed().await,
+                // The application has already shut down the sending side, so our FIN is on its way.
+                State::FinWait1 | State::Closing => cb.finish_local_close().await,
+                State::LastAck => cb.finish_remote_close().await,
+                _ => {
+                    let cause: String = "socket is already closing".to_string();
+                    error!("close(): {}", cause);
+                    Err(Fail::new(libc::EBADF, &cause))
+                },
+            }
+        }
+        .fuse();
+        pin_mut!(close_protocol);
+        let aborted = self.clone().wait_for_abort().fuse();
+        pin_mut!(aborted);
+
+        // Nothing is left to do if the connection was aborted, even if that happens while we are closing it.
+        futures::select_biased! {
+            _ = aborted => Ok(()),
+            result = close_protocol => result,
         }
     }
 
     async fn local_close(&mut self) -> Result<(), Fail> {
         // 1. Start close protocol by setting state and sending FIN.
         self.state = State::FinWait1;
-        self.sender.push_fin_and_wait_for_ack().await?;
+        self.sender.push_fin()?;
+        self.finish_local_close().await
+    }
 
-        // 2. Got ACK to our FIN. Check if we also received a FIN from remote in the meantime.
+    async fn finish_local_close(&mut self) -> Result<(), Fail> {
+        // 2. Wait for the ACK to our FIN. Check if we also received a FIN from remote in the meantime.
+        self.sender.wait_for_fin_ack().await?;
         let state: State = self.state;
         match state {
             State::FinWait1 => {
@@ -1000,20 +1775,42 @@ impl SharedControlBlock {
             State::Closing => self.state = State::TimeWait,
             state => unreachable!("Cannot be in any other state at this point: {:?}", state),
         };
-        // 3. TIMED_WAIT
+        // 3. TIME_WAIT: The peer takes over the connection from here, see time_wait().
         debug_assert_eq!(self.state, State::TimeWait);
+        Ok(())
+    }
+
+    /// Returns the state that the peer keeps for this connection while it is in the TIME_WAIT state, if it needs to
+    /// keep any.
+    pub fn time_wait(&self) -> Option<TimeWait> {
+        if self.state != State::TimeWait {
+            return None;
+        }
         trace!("socket options: {:?}", self.socket_options.get_linger());
         let timeout: Duration = self.socket_options.get_linger().unwrap_or(MSL * 2);
-        yield_with_timeout(timeout).await;
-        self.state = State::Closed;
-        Ok(())
+        if timeout.is_zero() {
+            return None;
+        }
+        Some(TimeWait::new(
+            self.get_now(),
+            timeout,
+            self.sender.get_next_seq_no(),
+            self.receiver.receive_next_seq_no,
+            self.hdr_window_size(),
+            self.timestamps,
+        ))
     }
 
     async fn remote_already_closed(&mut self) -> Result<(), Fail> {
         // 0. Move state forward
         self.state = State::LastAck;
         // 1. Send FIN and wait for ack before closing.
-        self.sender.push_fin_and_wait_for_ack().await?;
+        self.sender.push_fin()?;
+        self.finish_remote_close().await
+    }
+
+    async fn finish_remote_close(&mut self) -> Result<(), Fail> {
+        self.sender.wait_for_fin_ack().await?;
         self.state = State::Closed;
         Ok(())
     }
@@ -4,36 +4,43 @@
 mod background;
 pub mod congestion_control;
 mod ctrlblk;
+mod plpmtud;
+mod rack;
+mod rcvbuf;
 mod rto;
+mod sack;
 mod sender;
 
 use crate::{
     collections::async_queue::SharedAsyncQueue,
     inetstack::protocols::{
-        layer3::SharedLayer3Endpoint,
+        layer3::{Ipv4Header, SharedLayer3Endpoint},
         layer4::tcp::{
-            congestion_control::CongestionControlConstructor, established::ctrlblk::SharedControlBlock,
-            header::TcpHeader, SeqNumber,
+            established::ctrlblk::SharedControlBlock, header::TcpHeader, time_wait::TimeWait,
+            timestamps::TcpTimestamps, SeqNumber,
         },
     },
     runtime::{
         fail::Fail,
         memory::DemiBuffer,
-        network::{config::TcpConfig, socket::option::TcpSocketOptions},
+        network::{
+            config::TcpConfig,
+            socket::option::{TcpInfo, TcpSocketOptions},
+        },
         QDesc, SharedDemiRuntime,
     },
     QToken,
 };
 use ::futures::{channel::mpsc, FutureExt};
 use ::std::{
-    net::{Ipv4Addr, SocketAddrV4},
+    net::{Shutdown, SocketAddrV4},
     time::Duration,
 };
 
 #[derive(Clone)]
 pub struct EstablishedSocket {
     pub cb: SharedControlBlock,
-    recv_queue: SharedAsyncQueue<(Ipv4Addr, TcpHeader, DemiBuffer)>,
+    recv_queue: SharedAsyncQueue<(Ipv4Header, TcpHeader, DemiBuffer)>,
     // We need this to eventually stop the background task on close.
     #[allow(unused)]
     runtime: SharedDemiRuntime,
@@ -49,7 +56,7 @@ impl EstablishedSocket {
         remote: SocketAddrV4,
         mut runtime: SharedDemiRuntime,
         layer3_endpoint: SharedLayer3Endpoint,
-        recv_queue: SharedAsyncQueue<(Ipv4Addr, TcpHeader, DemiBuffer)>,
+        recv_queue: SharedAsyncQueue<(Ipv4Header, TcpHeader, DemiBuffer)>,
         tcp_config: TcpConfig,
         default_socket_options: TcpSocketOptions,
         receiver_seq_no: SeqNumber,
@@ -60,7 +67,9 @@ impl EstablishedSocket {
         sender_window_size: u32,
         sender_window_scale: u8,
         sender_mss: usize,
-        cc_constructor: CongestionControlConstructor,
+        sack_permitted: bool,
+        timestamps: Option<TcpTimestamps>,
+        ecn: bool,
         congestion_control_options: Option<congestion_control::Options>,
         dead_socket_tx: mpsc::UnboundedSender<QDesc>,
         socket_queue: Option<SharedAsyncQueue<SocketAddrV4>>,
@@ -81,7 +90,9 @@ impl EstablishedSocket {
             sender_window_size,
             sender_window_scale,
             sender_mss,
-            cc_constructor,
+            sack_permitted,
+            timestamps,
+            ecn,
             congestion_control_options,
             recv_queue.clone(),
             socket_queue,
@@ -98,7 +109,11 @@ impl EstablishedSocket {
         })
     }
 
-    pub fn get_recv_queue(&self) -> SharedAsyncQueue<(Ipv4Addr, TcpHeader, DemiBuffer)> {
+    pub fn accept_fast_open(&mut self, data: DemiBuffer, syn_ack: TcpHeader) -> TcpHeader {
+        self.cb.accept_fast_open(data, syn_ack)
+    }
+
+    pub fn get_recv_queue(&self) -> SharedAsyncQueue<(Ipv4Header, TcpHeader, DemiBuffer)> {
         self.recv_queue.clone()
     }
 
@@ -106,6 +121,10 @@ impl EstablishedSocket {
         self.cb.push(buf).await
     }
 
+    pub fn push_connect_data(&mut self, buf: DemiBuffer) {
+        self.cb.push_connect_data(buf)
+    }
+
     pub async fn pop(&mut self, size: Option<usize>) -> Result<DemiBuffer, Fail> {
         self.cb.pop(size).await
     }
@@ -114,7 +133,31 @@ impl EstablishedSocket {
         self.cb.close().await
     }
 
+    pub fn reset(&mut self) {
+        self.cb.reset()
+    }
+
+    pub fn shutdown(&mut self, how: Shutdown) -> Result<(), Fail> {
+        self.cb.shutdown(how)
+    }
+
+    pub fn time_wait(&self) -> Option<TimeWait> {
+        self.cb.time_wait()
+    }
+
     pub fn endpoints(&self) -> (SocketAddrV4, SocketAddrV4) {
         (self.cb.get_local(), self.cb.get_remote())
     }
+
+    pub fn set_socket_options(&mut self, socket_options: TcpSocketOptions) {
+        self.cb.set_socket_options(socket_options)
+    }
+
+    pub fn get_receive_buffer_size(&self) -> u32 {
+        self.cb.get_receive_buffer_size()
+    }
+
+    pub fn tcp_info(&self) -> TcpInfo {
+        self.cb.tcp_info()
+    }
 }
@@ -0,0 +1,284 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+// Packetization layer path MTU discovery (RFC 4821), which does not depend on ICMP messages reaching us.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::{
+    inetstack::protocols::layer4::tcp::{constants::MIN_MSS, SeqNumber},
+    runtime::network::config::MtuProbing,
+};
+use ::std::{
+    cmp,
+    time::{Duration, Instant},
+};
+
+//======================================================================================================================
+// Constants
+//======================================================================================================================
+
+// MSS to start searching from when we always probe.  RFC 4821 Section 7.2 recommends 1024 bytes, like Linux's
+// tcp_base_mss.
+const BASE_MSS: usize = 1024;
+
+// The search is over once the range of MSS values that might work is narrower than this, like Linux's
+// tcp_probe_threshold.
+const SEARCH_THRESHOLD: usize = 8;
+
+// How long after a search we start another one, to find out whether the path got better.  RFC 4821 Section 7.7
+// suggests 10 minutes.
+const REPROBE_INTERVAL: Duration = Duration::from_secs(600);
+
+// Number of consecutive retransmission timeouts after which we suspect a black hole that drops our segments because
+// they are too large for the path.
+const BLACKHOLE_RTOS: u32 = 3;
+
+//======================================================================================================================
+// Structures
+//======================================================================================================================
+
+/// Searches for the largest MSS that the path can carry by sending larger segments (probes) and watching whether they
+/// get acknowledged or lost.
+#[derive(Debug)]
+pub struct PathMtuDiscovery {
+    // Whether we may search at all, and whether we are searching right now.
+    mode: MtuProbing,
+    enabled: bool,
+
+    // MSS currently in use for new segments.
+    mss: usize,
+
+    // Largest MSS known to work and largest MSS that might work.  The current MSS is search_low while searching.
+    search_low: usize,
+    search_high: usize,
+
+    // Largest MSS that we may ever use, and smallest that we may fall back to.
+    max_mss: usize,
+    min_mss: usize,
+
+    // Sequence space and MSS of the probe in flight, if any.
+    probe: Option<(SeqNumber, SeqNumber, usize)>,
+
+    // Number of retransmission timeouts since data was last acknowledged.
+    consecutive_rtos: u32,
+
+    // Time at which to search again after the last search finished.
+    next_search_time: Option<Instant>,
+}
+
+//======================================================================================================================
+// Associated Functions
+//======================================================================================================================
+
+impl PathMtuDiscovery {
+    /// Starts with the `mss` from the handshake, which `max_mss` (derived from our MTU) bounds during the search.
+    pub fn new(mode: MtuProbing, mss: usize, max_mss: usize) -> Self {
+        let min_mss: usize = cmp::min(mss, MIN_MSS);
+        let max_mss: usize = cmp::max(max_mss, min_mss);
+        let (enabled, mss): (bool, usize) = match mode {
+            MtuProbing::Always => (true, cmp::min(mss, cmp::max(BASE_MSS, min_mss))),
+            MtuProbing::Disabled | MtuProbing::OnBlackhole => (false, mss),
+        };
+        Self {
+            mode,
+            enabled,
+            mss,
+            search_low: mss,
+            search_high: max_mss,
+            max_mss,
+            min_mss,
+            probe: None,
+            consecutive_rtos: 0,
+            next_search_time: None,
+        }
+    }
+
+    pub fn get_mss(&self) -> usize {
+        self.mss
+    }
+
+    /// Returns the MSS to probe with if we should send a probe now.
+    pub fn next_probe_size(&mut self, now: Instant) -> Option<usize> {
+        if !self.enabled || self.probe.is_some() {
+            return None;
+        }
+        if self
+            .next_search_time
+            .is_some_and(|next_search_time| now >= next_search_time)
+        {
+            self.next_search_time = None;
+            self.search_high = self.max_mss;
+        }
+        if self.search_high < self.search_low + SEARCH_THRESHOLD {
+            return None;
+        }
+        // Binary search, rounding up so that the probe is always larger than the current MSS.
+        Some((self.search_low + self.search_high + 1) / 2)
+    }
+
+    /// Called after sending a probe of `size` bytes that covers `[start, end)`.
+    pub fn on_probe_sent(&mut self, start: SeqNumber, end: SeqNumber, size: usize) {
+        debug_assert!(size > self.mss);
+        self.probe = Some((start, end, size));
+    }
+
+    /// Called when our peer acknowledges new data up to `ack_num`.
+    pub fn on_ack(&mut self, ack_num: SeqNumber, now: Instant) {
+        self.consecutive_rtos = 0;
+        match self.probe {
+            Some((_, end, size)) if ack_num >= end => {
+                debug!("on_ack(): probe succeeded (mss={})", size);
+                self.probe = None;
+                self.mss = size;
+                self.search_low = size;
+                self.on_search_step(now);
+            },
+            _ => (),
+        }
+    }
+
+    /// Called when we retransmit `[start, end)`.  A probe that we retransmit was lost, most likely because it was too
+    /// large for the path.
+    pub fn on_retransmit(&mut self, start: SeqNumber, end: SeqNumber, now: Instant) {
+        if let Some((probe_start, probe_end, size)) = self.probe {
+            if start < probe_end && probe_start < end {
+                debug!("on_retransmit(): probe failed (mss={})", size);
+                self.probe = None;
+                self.search_high = size - 1;
+                self.on_search_step(now);
+            }
+        }
+    }
+
+    /// Called when the retransmission timer goes off.  Everything in flight, including any probe, is presumed lost.
+    /// Too many timeouts in a row point at a black hole, so we fall back to a smaller MSS and search from there.
+    pub fn on_retransmit_timeout(&mut self, now: Instant) {
+        if let Some((_, _, size)) = self.probe.take() {
+            self.search_high = size - 1;
+        }
+        self.consecutive_rtos += 1;
+        if self.mode == MtuProbing::Disabled || self.consecutive_rtos < BLACKHOLE_RTOS {
+            return;
+        }
+
+        self.consecutive_rtos = 0;
+        let mss: usize = cmp::max(self.mss / 2, self.min_mss);
+        debug!(
+            "on_retransmit_timeout(): suspecting a black hole (mss={} -> {})",
+            self.mss, mss
+        );
+        self.enabled = true;
+        self.search_high = cmp::max(self.mss - 1, mss);
+        self.search_low = mss;
+        self.next_search_time = None;
+        self.mss = mss;
+        self.on_search_step(now);
+    }
+
+    // Schedules the next search once this one is over.
+    fn on_search_step(&mut self, now: Instant) {
+        if self.search_high < self.search_low + SEARCH_THRESHOLD {
+            self.next_search_time = Some(now + REPROBE_INTERVAL);
+        }
+    }
+}
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::{PathMtuDiscovery, BASE_MSS, REPROBE_INTERVAL};
+    use crate::{inetstack::protocols::layer4::tcp::SeqNumber, runtime::network::config::MtuProbing};
+    use ::anyhow::Result;
+    use ::std::time::Instant;
+
+    // Test that we never probe when path MTU discovery is disabled, even after many timeouts.
+    #[test]
+    fn disabled_never_probes() -> Result<()> {
+        let now: Instant = Instant::now();
+        let mut plpmtud: PathMtuDiscovery = PathMtuDiscovery::new(MtuProbing::Disabled, 1460, 8948);
+
+        for _ in 0..10 {
+            plpmtud.on_retransmit_timeout(now);
+        }
+        crate::ensure_eq!(plpmtud.get_mss(), 1460);
+        crate::ensure_eq!(plpmtud.next_probe_size(now), None);
+
+        Ok(())
+    }
+
+    // Test that the search starts from the base MSS and grows the MSS with every successful probe.
+    #[test]
+    fn probes_grow_mss() -> Result<()> {
+        let now: Instant = Instant::now();
+        let mut plpmtud: PathMtuDiscovery = PathMtuDiscovery::new(MtuProbing::Always, 8948, 8948);
+        crate::ensure_eq!(plpmtud.get_mss(), BASE_MSS);
+
+        let size: usize = plpmtud.next_probe_size(now).unwrap();
+        crate::ensure_eq!(size, (BASE_MSS + 8948 + 1) / 2);
+        plpmtud.on_probe_sent(SeqNumber::from(0), SeqNumber::from(size as u32), size);
+        crate::ensure_eq!(plpmtud.next_probe_size(now), None);
+
+        plpmtud.on_ack(SeqNumber::from(1), now);
+        crate::ensure_eq!(plpmtud.get_mss(), BASE_MSS);
+        plpmtud.on_ack(SeqNumber::from(size as u32), now);
+        crate::ensure_eq!(plpmtud.get_mss(), size);
+
+        Ok(())
+    }
+
+    // Test that a lost probe narrows the search without changing the MSS, and that the search ends within the
+    // threshold.
+    #[test]
+    fn lost_probes_narrow_search() -> Result<()> {
+        let now: Instant = Instant::now();
+        let mut plpmtud: PathMtuDiscovery = PathMtuDiscovery::new(MtuProbing::Always, 8948, 8948);
+
+        let mut seq_no: u32 = 0;
+        while let Some(size) = plpmtud.next_probe_size(now) {
+            plpmtud.on_probe_sent(SeqNumber::from(seq_no), SeqNumber::from(seq_no + size as u32), size);
+            if size > 1400 {
+                plpmtud.on_retransmit(SeqNumber::from(seq_no), SeqNumber::from(seq_no + 1), now);
+            } else {
+                seq_no += size as u32;
+                plpmtud.on_ack(SeqNumber::from(seq_no), now);
+            }
+        }
+        crate::ensure_eq!(plpmtud.get_mss() <= 1400, true);
+        crate::ensure_eq!(plpmtud.get_mss() > 1400 - 8, true);
+
+        // We search again after a while.
+        crate::ensure_eq!(plpmtud.next_probe_size(now + REPROBE_INTERVAL).is_some(), true);
+
+        Ok(())
+    }
+
+    // Test that consecutive timeouts make us fall back to a smaller MSS and search from there.
+    #[test]
+    fn blackhole_halves_mss() -> Result<()> {
+        let now: Instant = Instant::now();
+        let mut plpmtud: PathMtuDiscovery = PathMtuDiscovery::new(MtuProbing::OnBlackhole, 1460, 1460);
+        crate::ensure_eq!(plpmtud.next_probe_size(now), None);
+
+        plpmtud.on_retransmit_timeout(now);
+        plpmtud.on_retransmit_timeout(now);
+        crate::ensure_eq!(plpmtud.get_mss(), 1460);
+        plpmtud.on_retransmit_timeout(now);
+        crate::ensure_eq!(plpmtud.get_mss(), 730);
+        crate::ensure_eq!(plpmtud.next_probe_size(now), Some(1095));
+
+        // An acknowledgement resets the count, and we never go below the minimum MSS.
+        plpmtud.on_ack(SeqNumber::from(1), now);
+        for _ in 0..6 {
+            plpmtud.on_retransmit_timeout(now);
+        }
+        crate::ensure_eq!(plpmtud.get_mss(), 536);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,230 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+// RACK: time-based loss detection, and the probe timeout of Tail Loss Probe (TLP).
+// See: https://datatracker.ietf.org/doc/html/rfc8985 for more details.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::inetstack::protocols::layer4::tcp::SeqNumber;
+use ::std::{
+    cmp,
+    time::{Duration, Instant},
+};
+
+//======================================================================================================================
+// Constants
+//======================================================================================================================
+
+/// Probe timeout before we have an RTT sample. This is the initial RTO of RFC 6298.
+const INITIAL_PTO: Duration = Duration::from_secs(1);
+
+/// How long our peer may delay the ACK for a single segment. This is WCDelAckT in RFC 8985.
+const WORST_CASE_DELAYED_ACK: Duration = Duration::from_millis(200);
+
+//======================================================================================================================
+// Structures
+//======================================================================================================================
+
+/// What RACK knows about the most recently sent segment that our peer has received.
+#[derive(Debug)]
+pub struct Rack {
+    // When the most recently sent of the delivered segments was (re)transmitted and where it ends. In RFC 8985 terms,
+    // these are RACK.xmit_ts and RACK.end_seq.
+    xmit_ts: Option<Instant>,
+    end_seq: SeqNumber,
+
+    // Round-trip time of that segment. This is RACK.rtt.
+    rtt: Duration,
+
+    // Smallest round-trip time measured so far. This is RACK.min_RTT.
+    min_rtt: Option<Duration>,
+
+    // Highest sequence number delivered so far, and whether anything was delivered below it without having been
+    // retransmitted. These are RACK.fack and RACK.reordering_seen.
+    fack: SeqNumber,
+    reordering_seen: bool,
+}
+
+//======================================================================================================================
+// Associated Functions
+//======================================================================================================================
+
+impl Rack {
+    /// Creates the RACK state for a connection whose first segment starts at `seq_no`.
+    pub fn new(seq_no: SeqNumber) -> Self {
+        Self {
+            xmit_ts: None,
+            end_seq: seq_no,
+            rtt: Duration::ZERO,
+            min_rtt: None,
+            fack: seq_no,
+            reordering_seen: false,
+        }
+    }
+
+    /// Records that our peer has received, cumulatively or selectively, the segment ending at `end_seq` that we last
+    /// sent at `xmit_ts`. This is RACK_update() and RACK_detect_reordering() in RFC 8985 Section 6.2.
+    pub fn on_delivered(&mut self, xmit_ts: Instant, end_seq: SeqNumber, retransmitted: bool, now: Instant) {
+        let rtt: Duration = now.saturating_duration_since(xmit_ts);
+        // An ACK that arrives sooner than any round trip we've seen is for the original transmission, not the
+        // retransmission, so it does not tell us when the retransmission was delivered.
+        if retransmitted && self.min_rtt.is_some_and(|min_rtt| rtt < min_rtt) {
+            return;
+        }
+        self.min_rtt = Some(self.min_rtt.map_or(rtt, |min_rtt| cmp::min(min_rtt, rtt)));
+        if self
+            .xmit_ts
+            .is_none_or(|rack_xmit_ts| sent_after(xmit_ts, end_seq, rack_xmit_ts, self.end_seq))
+        {
+            self.xmit_ts = Some(xmit_ts);
+            self.end_seq = end_seq;
+            self.rtt = rtt;
+        }
+
+        if end_seq > self.fack {
+            self.fack = end_seq;
+        } else if end_seq < self.fack && !retransmitted {
+            self.reordering_seen = true;
+        }
+    }
+
+    /// Returns how long to wait for a segment that was sent before a delivered one, in case the network reordered it.
+    /// Without any reordering seen so far, we don't wait during loss recovery or once SACKs deem a segment lost. This is
+    /// RACK_update_reo_wnd() in RFC 8985 Section 6.2, without the DSACK-driven growth of the window.
+    pub fn reordering_window(&self, in_recovery: bool, sack_loss: bool, srtt: Option<Duration>) -> Duration {
+        if !self.reordering_seen && (in_recovery || sack_loss) {
+            return Duration::ZERO;
+        }
+        match (self.min_rtt, srtt) {
+            (Some(min_rtt), Some(srtt)) => cmp::min(min_rtt / 4, srtt),
+            (Some(min_rtt), None) => min_rtt / 4,
+            (None, _) => Duration::ZERO,
+        }
+    }
+
+    /// Returns when to deem lost the segment ending at `end_seq` that we last sent at `xmit_ts`, or None if it was not
+    /// sent before the most recently sent segment that has been delivered. See RACK_detect_loss() in RFC 8985 Section
+    /// 6.2.
+    pub fn loss_deadline(&self, xmit_ts: Instant, end_seq: SeqNumber, reo_wnd: Duration) -> Option<Instant> {
+        let rack_xmit_ts: Instant = self.xmit_ts?;
+        if !sent_after(rack_xmit_ts, self.end_seq, xmit_ts, end_seq) {
+            return None;
+        }
+        Some(xmit_ts + self.rtt + reo_wnd)
+    }
+}
+
+//======================================================================================================================
+// Standalone Functions
+//======================================================================================================================
+
+/// Returns how long to wait for an ACK before sending a tail loss probe, which is PTO in RFC 8985 Section 7.2.
+/// `single_segment` is true if we have only one segment in flight, whose ACK our peer may delay.
+pub fn probe_timeout(srtt: Option<Duration>, single_segment: bool) -> Duration {
+    match srtt {
+        Some(srtt) if single_segment => srtt.saturating_mul(2) + WORST_CASE_DELAYED_ACK,
+        Some(srtt) => srtt.saturating_mul(2),
+        None => INITIAL_PTO,
+    }
+}
+
+/// Returns true if the segment ending at `end_seq1` that was sent at `t1` was sent after the one ending at `end_seq2`
+/// that was sent at `t2`.  Segments sent at the same time are ordered by sequence number.
+fn sent_after(t1: Instant, end_seq1: SeqNumber, t2: Instant, end_seq2: SeqNumber) -> bool {
+    t1 > t2 || (t1 == t2 && end_seq1 > end_seq2)
+}
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::{probe_timeout, Rack, INITIAL_PTO, WORST_CASE_DELAYED_ACK};
+    use crate::inetstack::protocols::layer4::tcp::SeqNumber;
+    use ::anyhow::Result;
+    use ::std::time::{Duration, Instant};
+
+    const RTT: Duration = Duration::from_millis(100);
+
+    // Test that a segment sent before a delivered one is deemed lost one RTT plus the reordering window after it was
+    // sent, and that segments sent after the delivered one are not.
+    #[test]
+    fn loss_deadline() -> Result<()> {
+        let start: Instant = Instant::now();
+        let mut rack: Rack = Rack::new(SeqNumber::from(0));
+
+        // Three segments are sent 1 ms apart, and the second one is SACKed.
+        let xmit_ts: [Instant; 3] = [0, 1, 2].map(|i| start + Duration::from_millis(i));
+        rack.on_delivered(xmit_ts[1], SeqNumber::from(200), false, xmit_ts[1] + RTT);
+
+        // Nothing has been reordered yet, so once SACKs deem a segment lost there is no reordering window.
+        crate::ensure_eq!(rack.reordering_window(false, false, Some(RTT)), RTT / 4);
+        crate::ensure_eq!(rack.reordering_window(true, false, Some(RTT)), Duration::ZERO);
+        crate::ensure_eq!(rack.reordering_window(false, true, Some(RTT)), Duration::ZERO);
+
+        let reo_wnd: Duration = rack.reordering_window(false, false, Some(RTT));
+        crate::ensure_eq!(
+            rack.loss_deadline(xmit_ts[0], SeqNumber::from(100), reo_wnd),
+            Some(xmit_ts[0] + RTT + reo_wnd)
+        );
+        crate::ensure_eq!(rack.loss_deadline(xmit_ts[2], SeqNumber::from(300), reo_wnd), None);
+
+        Ok(())
+    }
+
+    // Test that delivering a segment below the highest delivered one opens the reordering window for good.
+    #[test]
+    fn reordering_seen() -> Result<()> {
+        let start: Instant = Instant::now();
+        let mut rack: Rack = Rack::new(SeqNumber::from(0));
+
+        rack.on_delivered(
+            start + Duration::from_millis(1),
+            SeqNumber::from(200),
+            false,
+            start + Duration::from_millis(1) + RTT,
+        );
+        rack.on_delivered(start, SeqNumber::from(100), false, start + RTT);
+        crate::ensure_eq!(rack.reordering_window(true, true, Some(RTT)), RTT / 4);
+
+        // The reordering window never exceeds the smoothed RTT.
+        crate::ensure_eq!(rack.reordering_window(true, true, Some(RTT / 8)), RTT / 8);
+
+        Ok(())
+    }
+
+    // Test that an ACK for the original transmission of a retransmitted segment doesn't move RACK forward.
+    #[test]
+    fn ambiguous_retransmission() -> Result<()> {
+        let start: Instant = Instant::now();
+        let mut rack: Rack = Rack::new(SeqNumber::from(0));
+        rack.on_delivered(start, SeqNumber::from(100), false, start + RTT);
+
+        // The retransmission went out just before the ACK arrived, so the ACK must be for an earlier transmission.
+        let rtx_ts: Instant = start + Duration::from_secs(1);
+        rack.on_delivered(rtx_ts, SeqNumber::from(200), true, rtx_ts + RTT / 2);
+        crate::ensure_eq!(rack.loss_deadline(start, SeqNumber::from(150), Duration::ZERO), None);
+
+        // An ACK that takes at least a round trip is for the retransmission.
+        rack.on_delivered(rtx_ts, SeqNumber::from(200), true, rtx_ts + RTT);
+        crate::ensure_eq!(
+            rack.loss_deadline(start, SeqNumber::from(150), Duration::ZERO),
+            Some(start + RTT)
+        );
+
+        Ok(())
+    }
+
+    // Test the probe timeout with and without an RTT sample.
+    #[test]
+    fn probe_timeouts() -> Result<()> {
+        crate::ensure_eq!(probe_timeout(None, false), INITIAL_PTO);
+        crate::ensure_eq!(probe_timeout(Some(RTT), false), 2 * RTT);
+        crate::ensure_eq!(probe_timeout(Some(RTT), true), 2 * RTT + WORST_CASE_DELAYED_ACK);
+        Ok(())
+    }
+}
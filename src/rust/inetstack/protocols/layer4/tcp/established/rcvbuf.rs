@@ -0,0 +1,128 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+// Receive buffer auto-tuning (dynamic right-sizing), like Linux's tcp_moderate_rcvbuf.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::inetstack::protocols::layer4::tcp::SeqNumber;
+use ::std::time::{Duration, Instant};
+
+//======================================================================================================================
+// Structures
+//======================================================================================================================
+
+/// Measures how much data the application reads per round trip, which is the most that our peer can send per round
+/// trip without the receive window holding it back.
+#[derive(Debug)]
+pub struct ReceiveBufferTuner {
+    // Smoothed round-trip time, measured on incoming data.
+    rtt_estimate: Option<Duration>,
+
+    // Sequence number of the next byte to read and time at which the current measurement started.
+    measurement_start_seq_no: SeqNumber,
+    measurement_start_time: Instant,
+
+    // Most data that the application has read during a single round trip so far.
+    max_bytes_read: u32,
+}
+
+//======================================================================================================================
+// Associated Functions
+//======================================================================================================================
+
+impl ReceiveBufferTuner {
+    pub fn new(reader_next_seq_no: SeqNumber, now: Instant) -> Self {
+        Self {
+            rtt_estimate: None,
+            measurement_start_seq_no: reader_next_seq_no,
+            measurement_start_time: now,
+            max_bytes_read: 0,
+        }
+    }
+
+    /// Smooths round-trip time samples like RFC 6298 does for SRTT.
+    pub fn add_rtt_sample(&mut self, rtt: Duration) {
+        self.rtt_estimate = Some(match self.rtt_estimate {
+            Some(rtt_estimate) => (rtt_estimate * 7 + rtt) / 8,
+            None => rtt,
+        });
+    }
+
+    pub fn get_rtt_estimate(&self) -> Option<Duration> {
+        self.rtt_estimate
+    }
+
+    /// Called after the application has read everything before `reader_next_seq_no`. Once the current measurement has
+    /// lasted for a round trip, returns the receive buffer size that it calls for if the application read more than in
+    /// any previous round trip. That is twice the data read, so that the receive window stays ahead of a peer that
+    /// doubles its sending rate every round trip.
+    pub fn on_read(&mut self, reader_next_seq_no: SeqNumber, now: Instant, rtt: Duration) -> Option<u32> {
+        if now - self.measurement_start_time < rtt {
+            return None;
+        }
+        let bytes_read: u32 = (reader_next_seq_no - self.measurement_start_seq_no).into();
+        self.measurement_start_seq_no = reader_next_seq_no;
+        self.measurement_start_time = now;
+        if bytes_read <= self.max_bytes_read {
+            return None;
+        }
+        self.max_bytes_read = bytes_read;
+        Some(bytes_read.saturating_mul(2))
+    }
+}
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::ReceiveBufferTuner;
+    use crate::inetstack::protocols::layer4::tcp::SeqNumber;
+    use ::anyhow::Result;
+    use ::std::time::{Duration, Instant};
+
+    const RTT: Duration = Duration::from_millis(10);
+
+    // Test that a measurement lasts for a round trip and asks for twice the data read during it.
+    #[test]
+    fn on_read_measures_one_round_trip() -> Result<()> {
+        let start: Instant = Instant::now();
+        let mut tuner: ReceiveBufferTuner = ReceiveBufferTuner::new(SeqNumber::from(0), start);
+
+        crate::ensure_eq!(tuner.on_read(SeqNumber::from(1000), start + RTT / 2, RTT), None);
+        crate::ensure_eq!(tuner.on_read(SeqNumber::from(3000), start + RTT, RTT), Some(6000));
+
+        Ok(())
+    }
+
+    // Test that the buffer only grows when the application reads more than in any previous round trip.
+    #[test]
+    fn on_read_only_grows() -> Result<()> {
+        let start: Instant = Instant::now();
+        let mut tuner: ReceiveBufferTuner = ReceiveBufferTuner::new(SeqNumber::from(0), start);
+
+        crate::ensure_eq!(tuner.on_read(SeqNumber::from(5000), start + RTT, RTT), Some(10000));
+        crate::ensure_eq!(tuner.on_read(SeqNumber::from(6000), start + RTT * 2, RTT), None);
+        crate::ensure_eq!(tuner.on_read(SeqNumber::from(12000), start + RTT * 3, RTT), Some(12000));
+
+        Ok(())
+    }
+
+    // Test that round-trip time samples are smoothed.
+    #[test]
+    fn add_rtt_sample_smooths() -> Result<()> {
+        let mut tuner: ReceiveBufferTuner = ReceiveBufferTuner::new(SeqNumber::from(0), Instant::now());
+
+        crate::ensure_eq!(tuner.get_rtt_estimate(), None);
+        tuner.add_rtt_sample(Duration::from_millis(80));
+        crate::ensure_eq!(tuner.get_rtt_estimate(), Some(Duration::from_millis(80)));
+        tuner.add_rtt_sample(Duration::from_millis(160));
+        crate::ensure_eq!(tuner.get_rtt_estimate(), Some(Duration::from_millis(90)));
+
+        Ok(())
+    }
+}
@@ -1,90 +1,171 @@
 // Copyright (c) Microsoft Corporation.
 // Licensed under the MIT license.
 
-use std::time::Duration;
+use std::{cmp, time::Duration};
 
 // TCP Retransmission Timeout (RTO) Calculator.
 // See RFC 6298 for details.
 
-// TODO: Issue #371 Consider reimplementing this using integer arithmetic instead of floating-point.
+// Clock granularity (G in RFC 6298). A lower bound on the RTO below this takes its place, as we measure round-trip
+// times finely enough for sub-millisecond timeouts.
+const GRANULARITY: Duration = Duration::from_millis(1);
 
 #[derive(Debug)]
 pub struct RtoCalculator {
     // Smoothed round-trip time.
-    srtt: f64,
+    srtt: Duration,
 
     // Round-trip time variation.
-    rttvar: f64,
+    rttvar: Duration,
 
     // Retransmission timeout.
-    rto: f64,
+    rto: Duration,
+
+    // Bounds for the retransmission timeout.
+    min_rto: Duration,
+    max_rto: Duration,
 
     // Whether a RTT (round-trip-time) sample has been received yet.
     received_sample: bool,
 }
 
 impl RtoCalculator {
-    /// Initializes an RTO Calculator.
-    pub fn new() -> Self {
+    /// Initializes an RTO Calculator, which keeps the RTO between `min_rto` and `max_rto`.
+    pub fn new(initial_rto: Duration, min_rto: Duration, max_rto: Duration) -> Self {
         // RFC 6298 recommends an initial value of 1 second for RTO (See also RFC 6298 Appendix A).  The initial values
         // for SRTT and RTTVAR are arbitrary as they aren't used until after the first sample has been received.
         Self {
-            srtt: 1.0,
-            rttvar: 0.0,
-            rto: 1.0,
+            srtt: initial_rto,
+            rttvar: Duration::ZERO,
+            rto: initial_rto.clamp(min_rto, max_rto),
+            min_rto,
+            max_rto,
             received_sample: false,
         }
     }
 
     /// Adds an RTT sample to the calculator.
     pub fn add_sample(&mut self, rtt: Duration) {
-        // RFC 6298's suggested value for alpha is 1/8.
-        const ALPHA: f64 = 0.125;
-        // RFC 6298's suggested value for beta is 1/4.
-        const BETA: f64 = 0.25;
-        // Clock granularity in seconds.
-        const GRANULARITY: f64 = 0.001f64;
-
-        let rtt: f64 = rtt.as_secs_f64();
-
         if !self.received_sample {
             // Initial sample formula from RFC 6298 Section 2.2:
             self.srtt = rtt;
-            self.rttvar = rtt / 2.;
+            self.rttvar = rtt / 2;
             self.received_sample = true;
         } else {
-            // Subsequent sample formula from RFC 6298 Section 2.3:
-            self.rttvar = (1.0 - BETA) * self.rttvar + BETA * (self.srtt - rtt).abs();
-            self.srtt = (1.0 - ALPHA) * self.srtt + ALPHA * rtt;
+            // Subsequent sample formula from RFC 6298 Section 2.3, with the suggested values of 1/4 for beta and 1/8
+            // for alpha:
+            self.rttvar = self.rttvar - self.rttvar / 4 + self.srtt.abs_diff(rtt) / 4;
+            self.srtt = self.srtt - self.srtt / 8 + rtt / 8;
         }
 
         // The new RTO value is the smoothed RTT plus the maximum of the clock granularity and 4 times the RTT variance.
-        let rto: f64 = self.srtt + GRANULARITY.max(4.0 * self.rttvar);
+        let granularity: Duration = cmp::min(GRANULARITY, self.min_rto);
+        let rto: Duration = self
+            .srtt
+            .saturating_add(cmp::max(granularity, self.rttvar.saturating_mul(4)));
 
         // Store the updated RTT value.
         self.update_rto(rto);
     }
 
-    /// Updates the stored RTO value while keeping it within the prescribed bounds (RFC 6298 Section 2.4)
-    fn update_rto(&mut self, new_rto: f64) {
-        // RFC 6298's suggested value for the lower bound is 1 second.  Note this currently uses 1/10 of a second.
-        const LOWER_BOUND_SEC: f64 = 0.100f64;
-        // RFC 6298's suggested value for the upper bound is >= 60 seconds.
-        const UPPER_BOUND_SEC: f64 = 60.0f64;
-
-        // Note: We use clamp() below as it is clearer in intent than a min/max combination.  However, if we were
-        // concerned that new_rto could be NaN here (we're not) we wouldn't want to use clamp() as it would pass NaN
-        // through.  We'd use "self.rto = f64::min(new_rto.max(LOWER_BOUND_SEC), UPPER_BOUND_SEC);" below instead.
-        self.rto = new_rto.clamp(LOWER_BOUND_SEC, UPPER_BOUND_SEC);
+    /// Updates the stored RTO value while keeping it within the configured bounds (RFC 6298 Section 2.4 suggests at
+    /// least 1 second and at most 60 seconds or more, but the lower bound is too high for datacenters).
+    fn update_rto(&mut self, new_rto: Duration) {
+        self.rto = new_rto.clamp(self.min_rto, self.max_rto);
     }
 
     /// Performs an exponential "back off" of the RTO (doubles the current timeout).
     pub fn back_off(&mut self) {
-        self.update_rto(self.rto * 2.0);
+        self.update_rto(self.rto.saturating_mul(2));
     }
 
     /// Gets the current RTO value.
     pub fn rto(&self) -> Duration {
-        Duration::from_secs_f64(self.rto)
+        self.rto
+    }
+
+    /// Gets the smoothed RTT, once we have received a sample.
+    pub fn srtt(&self) -> Option<Duration> {
+        if self.received_sample {
+            Some(self.srtt)
+        } else {
+            None
+        }
+    }
+
+    /// Gets the round-trip time variation.
+    pub fn rttvar(&self) -> Duration {
+        self.rttvar
+    }
+}
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::{RtoCalculator, GRANULARITY};
+    use ::anyhow::Result;
+    use ::std::time::Duration;
+
+    /// Tests that the RTO follows the formulas of RFC 6298 with integer arithmetic, within its bounds.
+    #[test]
+    fn rto_calculator_samples() -> Result<()> {
+        let mut rto: RtoCalculator = RtoCalculator::new(
+            Duration::from_secs(1),
+            Duration::from_micros(200),
+            Duration::from_secs(60),
+        );
+        crate::ensure_eq!(rto.rto(), Duration::from_secs(1));
+        crate::ensure_eq!(rto.srtt(), None);
+
+        // The first sample sets SRTT to the sample and RTTVAR to half of it.
+        rto.add_sample(Duration::from_micros(800));
+        crate::ensure_eq!(rto.srtt(), Some(Duration::from_micros(800)));
+        crate::ensure_eq!(rto.rttvar(), Duration::from_micros(400));
+        crate::ensure_eq!(rto.rto(), Duration::from_micros(2400));
+
+        // Later samples move SRTT by 1/8 and RTTVAR by 1/4 of the difference.
+        rto.add_sample(Duration::from_micros(1600));
+        crate::ensure_eq!(rto.srtt(), Some(Duration::from_micros(900)));
+        crate::ensure_eq!(rto.rttvar(), Duration::from_micros(500));
+        crate::ensure_eq!(rto.rto(), Duration::from_micros(2900));
+
+        // Steady samples let RTTVAR decay. A lower bound below the clock granularity stands in for it, so the RTO stays
+        // that far above SRTT.
+        for _ in 0..200 {
+            rto.add_sample(Duration::from_micros(100));
+        }
+        crate::ensure_eq!(rto.rto(), rto.srtt().unwrap_or_default() + Duration::from_micros(200));
+
+        // With a lower bound above a millisecond, the clock granularity is what keeps the RTO above SRTT.
+        let mut rto: RtoCalculator = RtoCalculator::new(
+            Duration::from_secs(1),
+            Duration::from_millis(2),
+            Duration::from_secs(60),
+        );
+        for _ in 0..200 {
+            rto.add_sample(Duration::from_millis(5));
+        }
+        crate::ensure_eq!(rto.rto(), rto.srtt().unwrap_or_default() + GRANULARITY);
+
+        Ok(())
+    }
+
+    /// Tests that backing off doubles the RTO up to its upper bound.
+    #[test]
+    fn rto_calculator_back_off() -> Result<()> {
+        let mut rto: RtoCalculator = RtoCalculator::new(
+            Duration::from_secs(1),
+            Duration::from_millis(100),
+            Duration::from_secs(3),
+        );
+        rto.back_off();
+        crate::ensure_eq!(rto.rto(), Duration::from_secs(2));
+        rto.back_off();
+        crate::ensure_eq!(rto.rto(), Duration::from_secs(3));
+
+        Ok(())
     }
 }
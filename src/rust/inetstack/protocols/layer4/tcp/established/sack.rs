@@ -0,0 +1,248 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+// SACK scoreboard for conservative loss recovery.
+// See: https://datatracker.ietf.org/doc/html/rfc6675 for more details.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::inetstack::protocols::layer4::tcp::{header::SelectiveAcknowlegement, SeqNumber};
+
+//======================================================================================================================
+// Constants
+//======================================================================================================================
+
+/// Number of duplicate ACKs (or discontiguous SACKed blocks) that indicate a loss. This is DupThresh in RFC 6675.
+pub const DUP_THRESH: u32 = 3;
+
+//======================================================================================================================
+// Structures
+//======================================================================================================================
+
+/// Ranges of sequence space above SND.UNA that our peer has selectively acknowledged.
+#[derive(Debug, Default)]
+pub struct SackScoreboard {
+    // Sorted, non-overlapping and non-adjacent blocks of SACKed sequence space.
+    blocks: Vec<SelectiveAcknowlegement>,
+}
+
+//======================================================================================================================
+// Associated Functions
+//======================================================================================================================
+
+impl SackScoreboard {
+    /// Returns true if nothing has been selectively acknowledged.
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+
+    /// Records the SACK blocks carried by an ACK. Blocks that do not fall between SND.UNA and SND.NXT are ignored.
+    /// Returns true if any sequence space was SACKed for the first time.
+    pub fn update(&mut self, send_unacked: SeqNumber, send_next: SeqNumber, sacks: &[SelectiveAcknowlegement]) -> bool {
+        let sacked_before: u32 = self.sacked_bytes();
+        for sack in sacks {
+            if sack.begin >= sack.end || sack.begin < send_unacked || sack.end > send_next {
+                trace!("update(): ignoring SACK block {:?}", sack);
+                continue;
+            }
+            self.insert(*sack);
+        }
+        self.sacked_bytes() != sacked_before
+    }
+
+    /// Drops everything that has been cumulatively acknowledged.
+    pub fn on_cumulative_ack(&mut self, send_unacked: SeqNumber) {
+        self.blocks.retain(|block| block.end > send_unacked);
+        if let Some(block) = self.blocks.first_mut() {
+            if block.begin < send_unacked {
+                block.begin = send_unacked;
+            }
+        }
+    }
+
+    /// Returns true if our peer has selectively acknowledged all of the sequence space from `begin` up to `end`.
+    pub fn is_sacked(&self, begin: SeqNumber, end: SeqNumber) -> bool {
+        self.blocks.iter().any(|block| block.begin <= begin && end <= block.end)
+    }
+
+    /// Returns the end of the highest SACKed block, if anything has been selectively acknowledged.
+    pub fn highest_sacked(&self) -> Option<SeqNumber> {
+        self.blocks.last().map(|block| block.end)
+    }
+
+    /// Returns true if `seq_no` is deemed lost. This is IsLost() in RFC 6675: either DupThresh discontiguous SACKed
+    /// blocks or more than (DupThresh - 1) * SMSS bytes have been SACKed above it.
+    pub fn is_lost(&self, seq_no: SeqNumber, mss: usize) -> bool {
+        let mut num_blocks: u32 = 0;
+        let mut sacked_bytes: u32 = 0;
+        for block in self.blocks.iter().rev() {
+            if block.end <= seq_no {
+                break;
+            }
+            let begin: SeqNumber = if block.begin > seq_no {
+                block.begin
+            } else {
+                seq_no + SeqNumber::from(1)
+            };
+            num_blocks += 1;
+            sacked_bytes += u32::from(block.end - begin);
+            if num_blocks >= DUP_THRESH || sacked_bytes > (DUP_THRESH - 1) * mss as u32 {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Estimates the number of bytes still in the network. This is SetPipe() in RFC 6675: every octet between SND.UNA
+    /// and HighData that is neither SACKed nor deemed lost counts once, and every octet retransmitted up to HighRxt
+    /// counts once more.
+    pub fn pipe(&self, send_unacked: SeqNumber, high_data: SeqNumber, high_rxt: SeqNumber, mss: usize) -> u32 {
+        let mut pipe: u32 = 0;
+        let mut hole_begin: SeqNumber = send_unacked;
+        for block in self.blocks.iter().chain(std::iter::once(&SelectiveAcknowlegement {
+            begin: high_data,
+            end: high_data,
+        })) {
+            if hole_begin < block.begin {
+                let hole_end: SeqNumber = block.begin;
+                if !self.is_lost(hole_begin, mss) {
+                    pipe += u32::from(hole_end - hole_begin);
+                }
+                if hole_begin < high_rxt {
+                    let rxt_end: SeqNumber = if high_rxt < hole_end { high_rxt } else { hole_end };
+                    pipe += u32::from(rxt_end - hole_begin);
+                }
+            }
+            if block.end > hole_begin {
+                hole_begin = block.end;
+            }
+        }
+        pipe
+    }
+
+    /// Returns the start and length of the next lost range to retransmit, starting no earlier than `high_rxt`. This is
+    /// rule (1) of NextSeg() in RFC 6675. The range is at most `mss` bytes long and never extends into SACKed data.
+    pub fn next_lost_segment(
+        &self,
+        send_unacked: SeqNumber,
+        high_rxt: SeqNumber,
+        mss: usize,
+    ) -> Option<(SeqNumber, u32)> {
+        let mut hole_begin: SeqNumber = send_unacked;
+        for block in self.blocks.iter() {
+            let begin: SeqNumber = if hole_begin < high_rxt { high_rxt } else { hole_begin };
+            if begin < block.begin {
+                if !self.is_lost(begin, mss) {
+                    return None;
+                }
+                let len: u32 = u32::from(block.begin - begin).min(mss as u32);
+                return Some((begin, len));
+            }
+            hole_begin = block.end;
+        }
+        None
+    }
+
+    fn insert(&mut self, mut new: SelectiveAcknowlegement) {
+        // Absorb every block that overlaps or touches the new one, then insert it in order.
+        let mut index: usize = 0;
+        while index < self.blocks.len() {
+            let block: SelectiveAcknowlegement = self.blocks[index];
+            if block.end < new.begin {
+                index += 1;
+            } else if new.end < block.begin {
+                break;
+            } else {
+                if block.begin < new.begin {
+                    new.begin = block.begin;
+                }
+                if block.end > new.end {
+                    new.end = block.end;
+                }
+                self.blocks.remove(index);
+            }
+        }
+        self.blocks.insert(index, new);
+    }
+
+    fn sacked_bytes(&self) -> u32 {
+        self.blocks.iter().map(|block| u32::from(block.end - block.begin)).sum()
+    }
+}
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::SackScoreboard;
+    use crate::inetstack::protocols::layer4::tcp::{header::SelectiveAcknowlegement, SeqNumber};
+    use ::anyhow::Result;
+
+    const MSS: usize = 100;
+
+    fn sack(begin: u32, end: u32) -> SelectiveAcknowlegement {
+        SelectiveAcknowlegement {
+            begin: SeqNumber::from(begin),
+            end: SeqNumber::from(end),
+        }
+    }
+
+    // Test that blocks are merged and that blocks outside of the outstanding window are ignored.
+    #[test]
+    fn update_merges_blocks() -> Result<()> {
+        let mut scoreboard: SackScoreboard = SackScoreboard::default();
+        let una: SeqNumber = SeqNumber::from(0);
+        let nxt: SeqNumber = SeqNumber::from(1000);
+
+        crate::ensure_eq!(scoreboard.update(una, nxt, &[sack(200, 300)]), true);
+        crate::ensure_eq!(scoreboard.update(una, nxt, &[sack(400, 500), sack(300, 400)]), true);
+        crate::ensure_eq!(scoreboard.blocks.len(), 1);
+        crate::ensure_eq!(scoreboard.blocks[0], sack(200, 500));
+        crate::ensure_eq!(scoreboard.is_sacked(SeqNumber::from(300), SeqNumber::from(400)), true);
+        crate::ensure_eq!(scoreboard.is_sacked(SeqNumber::from(100), SeqNumber::from(300)), false);
+
+        // Duplicate and out-of-window blocks do not change the scoreboard.
+        crate::ensure_eq!(scoreboard.update(una, nxt, &[sack(200, 300)]), false);
+        crate::ensure_eq!(scoreboard.update(una, nxt, &[sack(900, 1100)]), false);
+        crate::ensure_eq!(scoreboard.blocks.len(), 1);
+
+        // Cumulative ACKs trim the scoreboard.
+        scoreboard.on_cumulative_ack(SeqNumber::from(300));
+        crate::ensure_eq!(scoreboard.blocks[0], sack(300, 500));
+        scoreboard.on_cumulative_ack(SeqNumber::from(500));
+        crate::ensure_eq!(scoreboard.is_empty(), true);
+
+        Ok(())
+    }
+
+    // Test loss detection, pipe estimation and hole selection for a single lost segment.
+    #[test]
+    fn single_loss() -> Result<()> {
+        let mut scoreboard: SackScoreboard = SackScoreboard::default();
+        let una: SeqNumber = SeqNumber::from(0);
+        let nxt: SeqNumber = SeqNumber::from(1000);
+
+        // Two segments above the hole are not enough to deem it lost.
+        scoreboard.update(una, nxt, &[sack(100, 300)]);
+        crate::ensure_eq!(scoreboard.is_lost(una, MSS), false);
+        crate::ensure_eq!(scoreboard.next_lost_segment(una, una, MSS), None);
+        crate::ensure_eq!(scoreboard.pipe(una, nxt, una, MSS), 800);
+
+        // A third one is.
+        scoreboard.update(una, nxt, &[sack(100, 400)]);
+        crate::ensure_eq!(scoreboard.is_lost(una, MSS), true);
+        crate::ensure_eq!(scoreboard.next_lost_segment(una, una, MSS), Some((una, 100)));
+        crate::ensure_eq!(scoreboard.pipe(una, nxt, una, MSS), 600);
+
+        // Once retransmitted, the hole counts towards the pipe again and is not selected a second time.
+        let high_rxt: SeqNumber = SeqNumber::from(100);
+        crate::ensure_eq!(scoreboard.pipe(una, nxt, high_rxt, MSS), 700);
+        crate::ensure_eq!(scoreboard.next_lost_segment(una, high_rxt, MSS), None);
+
+        Ok(())
+    }
+}
@@ -3,17 +3,33 @@
 
 use crate::{
     collections::{async_queue::SharedAsyncQueue, async_value::SharedAsyncValue},
+    expect_ok,
     inetstack::protocols::layer4::tcp::{
-        established::{rto::RtoCalculator, SharedControlBlock},
-        header::TcpHeader,
+        established::{
+            congestion_control::{DeliveryRateEstimator, RateSample, SegmentDeliveryState},
+            plpmtud::PathMtuDiscovery,
+            rack::{self, Rack},
+            rto::RtoCalculator,
+            sack::{SackScoreboard, DUP_THRESH},
+            SharedControlBlock,
+        },
+        header::{SelectiveAcknowlegement, TcpHeader, TcpOptions2},
         SeqNumber,
     },
-    runtime::{conditional_yield_until, fail::Fail, memory::DemiBuffer},
+    inetstack::protocols::MAX_HEADER_SIZE,
+    runtime::{
+        conditional_yield_until,
+        fail::Fail,
+        memory::DemiBuffer,
+        network::{config::TcpConfig, socket::option::TcpSocketOptions},
+        SharedObject,
+    },
 };
 use ::futures::{pin_mut, select_biased, FutureExt};
-use ::libc::{EBUSY, EINVAL};
+use ::libc::EINVAL;
 use ::std::{
-    fmt,
+    collections::BTreeSet,
+    fmt, mem,
     time::{Duration, Instant},
 };
 use futures::never::Never;
@@ -27,12 +43,30 @@ pub struct UnackedSegment {
     pub bytes: Option<DemiBuffer>,
     // Set to `None` on retransmission to implement Karn's algorithm.
     pub initial_tx: Option<Instant>,
+    // Delivery progress when this segment was last (re)transmitted, for delivery rate estimation.
+    pub delivery_state: SegmentDeliveryState,
+    // When this segment was last (re)transmitted, for RACK loss detection.
+    pub xmit_ts: Instant,
+    // Whether RACK deemed this segment lost since we last sent it, and whether our peer has SACKed all of it.
+    pub lost: bool,
+    pub sacked: bool,
+}
+
+// A push's place in line for room in the send buffer. When it goes away, the turn passes to the next push that is still
+// waiting, so a push that is cancelled while it waits, for example because the connection was aborted, doesn't hold up
+// the pushes behind it.
+struct SendBufferTicket {
+    number: u64,
+    turn: SharedAsyncValue<u64>,
+    abandoned: SharedObject<BTreeSet<u64>>,
 }
 
-// Hard limit for unsent queue.
-// TODO: Remove this.  We should limit the unsent queue by either having a (configurable) send buffer size (in bytes,
-// not segments) and rejecting send requests that exceed that, or by limiting the user's send buffer allocations.
-const UNSENT_QUEUE_CUTOFF: usize = 1024;
+impl UnackedSegment {
+    // Sequence space taken up by this segment. A FIN takes up one sequence number.
+    fn seq_len(&self) -> u32 {
+        self.bytes.as_ref().map_or(1, |bytes| bytes.len() as u32)
+    }
+}
 
 // Minimum size for unacknowledged queue. This number doesn't really matter very much, it just sets the initial size
 // of the unacked queue, below which memory allocation is not required.
@@ -42,6 +76,10 @@ const MIN_UNACKED_QUEUE_SIZE_FRAMES: usize = 64;
 // of the unacked queue, below which memory allocation is not required.
 const MIN_UNSENT_QUEUE_SIZE_FRAMES: usize = 64;
 
+// How long sender-side SWS avoidance may hold back data that the usable window has room for.  RFC 1122 Section 4.2.3.4
+// recommends a value between 0.1 and 1 second.
+const SWS_OVERRIDE_TIMEOUT: Duration = Duration::from_millis(200);
+
 // TODO: Consider moving retransmit timer and congestion control fields out of this structure.
 // TODO: Make all public fields in this structure private.
 pub struct Sender {
@@ -64,6 +102,40 @@ pub struct Sender {
     // Queue of unacknowledged sent data.  RFC 793 calls this the "retransmission queue".
     unacked_queue: SharedAsyncQueue<UnackedSegment>,
 
+    // Parts of the unacknowledged queue that our peer has selectively acknowledged (RFC 6675).
+    sack_scoreboard: SackScoreboard,
+
+    // Number of duplicate ACKs carrying new SACK information since SND.UNA last moved.  In RFC 6675 terms, this is
+    // DupAcks.
+    sack_duplicate_ack_count: u32,
+
+    // SND.NXT at the time we entered SACK-based loss recovery, or None if we are not recovering.  In RFC 6675 terms,
+    // this is RecoveryPoint.
+    sack_recovery_point: Option<SeqNumber>,
+
+    // Highest sequence number retransmitted during the current loss recovery.  In RFC 6675 terms, this is HighRxt.
+    sack_high_rxt: SeqNumber,
+
+    // Flag to wake the retransmitter (and sender) when loss recovery may be able to send something.
+    sack_recovery_now: SharedAsyncValue<bool>,
+
+    // RACK-TLP time-based loss detection (RFC 8985), or None if our peer does not send SACKs.
+    rack: Option<Rack>,
+
+    // When RACK deems lost the segments that it is still giving a chance to arrive out of order.
+    rack_reorder_deadline: SharedAsyncValue<Option<Instant>>,
+
+    // When to send a tail loss probe, and the end of the probe that we are waiting to hear about.  In RFC 8985 terms,
+    // the latter is TLP.end_seq.
+    tlp_deadline: SharedAsyncValue<Option<Instant>>,
+    tlp_end_seq: Option<SeqNumber>,
+
+    // Flag to wake the sender when a tail loss probe should carry new data, which the sender owns.
+    tlp_new_data_now: SharedAsyncValue<bool>,
+
+    // Set when we enter loss recovery or RACK-TLP detects a loss, until congestion control has responded.
+    loss_response_pending: bool,
+
     // Send timers
     // Current retransmission timer expiration time.
     // TODO: Consider storing this directly in the RtoCalculator.
@@ -72,12 +144,26 @@ pub struct Sender {
     // Retransmission Timeout (RTO) calculator.
     rto_calculator: RtoCalculator,
 
+    // Number of retransmission timeouts in a row without new data being acknowledged, and how many we tolerate before
+    // dropping the connection.
+    retransmit_timeouts: usize,
+    data_retries: usize,
+
+    // Bounds of the persist timer, which paces window probes while our peer advertises a zero window.
+    persist_min: Duration,
+    persist_max: Duration,
+
+    // How long data may go unacknowledged before we drop the connection, if at all (RFC 5482), and when our peer last
+    // acknowledged new data or we started sending into an idle connection.
+    user_timeout: SharedAsyncValue<Option<Duration>>,
+    last_progress_time: Instant,
+
     // In RFC 793 terms, this is SND.NXT.
     send_next_seq_no: SharedAsyncValue<SeqNumber>,
 
     // Sequence number of next data to be pushed but not sent. When there is an open window, this is equivalent to
     // send_next_seq_no.
-    unsent_next_seq_no: SeqNumber,
+    unsent_next_seq_no: SharedAsyncValue<SeqNumber>,
 
     // Sequence number of the FIN, after we should never allocate more sequence numbers.
     fin_seq_no: Option<SeqNumber>,
@@ -86,17 +172,51 @@ pub struct Sender {
     // a FIN. This keeps us from having to allocate an empty Demibuffer to indicate FIN.
     unsent_queue: SharedAsyncQueue<Option<DemiBuffer>>,
 
+    // Maximum number of bytes, both unsent and unacknowledged, that we buffer for the user (SO_SNDBUF).
+    send_buffer_size: SharedAsyncValue<usize>,
+
+    // Pushes wait in line for room in the send buffer, so that their data goes out in the order they were made.
+    send_buffer_next_ticket: u64,
+    send_buffer_turn: SharedAsyncValue<u64>,
+    // Tickets of pushes that went away before their turn came.
+    send_buffer_abandoned_tickets: SharedObject<BTreeSet<u64>>,
+
     // Available window to send into, as advertised by our peer.  In RFC 793 terms, this is SND.WND.
     send_window: SharedAsyncValue<u32>,
     send_window_last_update_seq: SeqNumber, // SND.WL1
     send_window_last_update_ack: SeqNumber, // SND.WL2
 
+    // Largest window our peer has advertised so far.  Used for sender-side SWS avoidance (RFC 1122 Section 4.2.3.4).
+    send_window_max: u32,
+
     // RFC 1323: Number of bits to shift advertised window, defaults to zero.
     send_window_scale_shift_bits: u8,
 
-    // Maximum Segment Size currently in use for this connection.
-    // TODO: Revisit this once we support path MTU discovery.
-    mss: usize,
+    // Path MTU discovery, which owns the Maximum Segment Size currently in use for this connection.
+    path_mtu: PathMtuDiscovery,
+
+    // Delivery rate estimation for congestion control.
+    delivery_rate: DeliveryRateEstimator,
+
+    // Earliest time at which we may send the next segment, if congestion control paces our sends.
+    pacing_next_send_time: Option<Instant>,
+
+    // Number of segments that we retransmitted, for connection statistics.
+    total_retransmits: u32,
+}
+
+impl Drop for SendBufferTicket {
+    fn drop(&mut self) {
+        if self.turn.get() != self.number {
+            self.abandoned.insert(self.number);
+            return;
+        }
+        let mut next: u64 = self.number + 1;
+        while self.abandoned.remove(&next) {
+            next += 1;
+        }
+        self.turn.set(next);
+    }
 }
 
 impl fmt::Debug for Sender {
@@ -106,34 +226,69 @@ impl fmt::Debug for Sender {
             .field("send_next", &self.send_next_seq_no)
             .field("send_window", &self.send_window)
             .field("window_scale", &self.send_window_scale_shift_bits)
-            .field("mss", &self.mss)
+            .field("mss", &self.get_mss())
             .finish()
     }
 }
 
 impl Sender {
-    pub fn new(seq_no: SeqNumber, send_window: u32, send_window_scale_shift_bits: u8, mss: usize) -> Self {
+    pub fn new(
+        seq_no: SeqNumber,
+        send_window: u32,
+        send_window_scale_shift_bits: u8,
+        path_mtu: PathMtuDiscovery,
+        socket_options: &TcpSocketOptions,
+        tcp_config: &TcpConfig,
+        now: Instant,
+    ) -> Self {
         Self {
             send_unacked: SharedAsyncValue::new(seq_no),
             unacked_queue: SharedAsyncQueue::with_capacity(MIN_UNACKED_QUEUE_SIZE_FRAMES),
+            sack_scoreboard: SackScoreboard::default(),
+            sack_duplicate_ack_count: 0,
+            sack_recovery_point: None,
+            sack_high_rxt: seq_no,
+            sack_recovery_now: SharedAsyncValue::new(false),
+            rack: None,
+            rack_reorder_deadline: SharedAsyncValue::new(None),
+            tlp_deadline: SharedAsyncValue::new(None),
+            tlp_end_seq: None,
+            tlp_new_data_now: SharedAsyncValue::new(false),
+            loss_response_pending: false,
             retransmit_deadline_time_secs: SharedAsyncValue::new(None),
-            rto_calculator: RtoCalculator::new(),
+            rto_calculator: RtoCalculator::new(
+                tcp_config.get_rto_initial(),
+                tcp_config.get_rto_min(),
+                tcp_config.get_rto_max(),
+            ),
+            retransmit_timeouts: 0,
+            data_retries: tcp_config.get_data_retries(),
+            persist_min: tcp_config.get_persist_min(),
+            persist_max: tcp_config.get_persist_max(),
+            user_timeout: SharedAsyncValue::new(socket_options.get_user_timeout()),
+            last_progress_time: now,
             send_next_seq_no: SharedAsyncValue::new(seq_no),
-            unsent_next_seq_no: seq_no,
+            unsent_next_seq_no: SharedAsyncValue::new(seq_no),
             fin_seq_no: None,
             unsent_queue: SharedAsyncQueue::with_capacity(MIN_UNSENT_QUEUE_SIZE_FRAMES),
+            send_buffer_size: SharedAsyncValue::new(socket_options.get_send_buffer_size()),
+            send_buffer_next_ticket: 0,
+            send_buffer_turn: SharedAsyncValue::new(0),
+            send_buffer_abandoned_tickets: SharedObject::new(BTreeSet::new()),
             send_window: SharedAsyncValue::new(send_window),
             send_window_last_update_seq: seq_no,
             send_window_last_update_ack: seq_no,
+            send_window_max: send_window,
             send_window_scale_shift_bits,
-            mss,
+            path_mtu,
+            delivery_rate: DeliveryRateEstimator::new(now),
+            pacing_next_send_time: None,
+            total_retransmits: 0,
         }
     }
 
     // This function sends a packet and waits for it to be acked.
     pub async fn push(&mut self, buf: DemiBuffer) -> Result<(), Fail> {
-        // If the user is done sending (i.e. has called close on this connection), then they shouldn't be sending.
-        debug_assert!(self.fin_seq_no.is_none());
         // Our API supports send buffers up to usize (variable, depends upon architecture) in size.  While we could
         // allow for larger send buffers, it is simpler and more practical to limit a single send to 1 GiB, which is
         // also the maximum value a TCP can advertise as its receive window (with maximum window scaling).
@@ -146,39 +301,105 @@ impl Sender {
             .try_into()
             .map_err(|_| Fail::new(EINVAL, "buffer too large"))?;
 
-        // TODO: We need to fix this the correct way: limit our send buffer size to the amount we're willing to buffer.
-        if self.unsent_queue.len() > UNSENT_QUEUE_CUTOFF {
-            return Err(Fail::new(EBUSY, "too many packets to send"));
+        // Wait until earlier pushes are in the send buffer and it has room for this one.
+        let ticket: SendBufferTicket = SendBufferTicket {
+            number: self.send_buffer_next_ticket,
+            turn: self.send_buffer_turn.clone(),
+            abandoned: self.send_buffer_abandoned_tickets.clone(),
+        };
+        self.send_buffer_next_ticket += 1;
+        let mut send_unacked_watched: SharedAsyncValue<SeqNumber> = self.send_unacked.clone();
+        let mut send_buffer_size_watched: SharedAsyncValue<usize> = self.send_buffer_size.clone();
+        let mut send_buffer_turn_watched: SharedAsyncValue<u64> = self.send_buffer_turn.clone();
+        while send_buffer_turn_watched.get() != ticket.number || !self.has_send_buffer_space(buf.len()) {
+            select_biased! {
+                _ = send_unacked_watched.wait_for_change_until(None).fuse() => (),
+                _ = send_buffer_size_watched.wait_for_change_until(None).fuse() => (),
+                _ = send_buffer_turn_watched.wait_for_change_until(None).fuse() => (),
+            }
+        }
+        // Let the next push in line have its turn.
+        drop(ticket);
+
+        // The user may have shut down the sending side while we were waiting.
+        if self.fin_seq_no.is_some() {
+            let cause: &str = "cannot send after the sending side was shut down";
+            error!("push(): {}", cause);
+            return Err(Fail::new(libc::EPIPE, cause));
         }
 
         // Place the buffer in the unsent queue.
-        self.unsent_next_seq_no = self.unsent_next_seq_no + (buf.len() as u32).into();
+        let buf_len: u32 = buf.len() as u32;
         self.unsent_queue.push(Some(buf));
+        self.unsent_next_seq_no.modify(|s| s + buf_len.into());
 
         // Wait until the sequnce number of the pushed buffer is acknowledged.
-        let mut send_unacked_watched: SharedAsyncValue<SeqNumber> = self.send_unacked.clone();
-        let ack_seq_no: SeqNumber = self.unsent_next_seq_no;
+        let ack_seq_no: SeqNumber = self.unsent_next_seq_no.get();
         debug_assert!(send_unacked_watched.get() < ack_seq_no);
         while send_unacked_watched.get() < ack_seq_no {
-            send_unacked_watched.wait_for_change(None).await?;
+            // Every ACK wakes all outstanding pushes, so don't arm a timer on each wake-up.
+            send_unacked_watched.wait_for_change_until(None).await?;
         }
         Ok(())
     }
 
+    // Places the data that the application connected with in the outgoing data stream. No push can come before it, so
+    // unlike a push, this doesn't wait for room in the send buffer or for the data to be acknowledged.
+    pub fn push_connect_data(&mut self, buf: DemiBuffer) {
+        debug_assert_eq!(self.send_buffer_next_ticket, 0);
+        let buf_len: u32 = buf.len() as u32;
+        self.unsent_queue.push(Some(buf));
+        self.unsent_next_seq_no.modify(|s| s + buf_len.into());
+    }
+
     // Places a FIN marker in the outgoing data stream. No data can be pushed after this.
-    pub async fn push_fin_and_wait_for_ack(&mut self) -> Result<(), Fail> {
+    pub fn push_fin(&mut self) -> Result<(), Fail> {
         debug_assert!(self.fin_seq_no.is_none());
-        // TODO: We need to fix this the correct way: limit our send buffer size to the amount we're willing to buffer.
-        if self.unsent_queue.len() > UNSENT_QUEUE_CUTOFF {
-            return Err(Fail::new(EBUSY, "too many packets to send"));
+        self.fin_seq_no = Some(self.unsent_next_seq_no.get());
+        self.unsent_queue.push(None);
+        self.unsent_next_seq_no.modify(|s| s + 1.into());
+        Ok(())
+    }
+
+    // Returns true if we can buffer `len` more bytes for sending. A push that is larger than the whole send buffer only
+    // has to wait until everything before it has been acknowledged.
+    fn has_send_buffer_space(&self, len: usize) -> bool {
+        let buffered: usize = u32::from(self.unsent_next_seq_no.get() - self.send_unacked.get()) as usize;
+        buffered == 0 || buffered + len <= self.send_buffer_size.get()
+    }
+
+    pub fn set_send_buffer_size(&mut self, send_buffer_size: usize) {
+        self.send_buffer_size.set(send_buffer_size);
+    }
+
+    pub fn set_user_timeout(&mut self, user_timeout: Option<Duration>) {
+        if user_timeout != self.user_timeout.get() {
+            self.user_timeout.set(user_timeout);
         }
+    }
 
-        self.fin_seq_no = Some(self.unsent_next_seq_no);
-        self.unsent_next_seq_no = self.unsent_next_seq_no + 1.into();
-        self.unsent_queue.push(None);
+    // Records that the send window we start with came from the handshake segment that our peer numbered `seq_no`. These
+    // are SND.WL1 and SND.WL2 in RFC 793, which must track our peer's sequence space and not ours.
+    pub fn set_send_window_last_update(&mut self, seq_no: SeqNumber) {
+        self.send_window_last_update_seq = seq_no;
+        self.send_window_last_update_ack = self.send_unacked.get();
+    }
+
+    // Turns on RACK-TLP loss detection, which relies on our peer sending SACKs.
+    pub fn enable_rack_tlp(&mut self) {
+        self.rack = Some(Rack::new(self.send_unacked.get()));
+    }
+
+    // Returns true if the FIN marker has been placed in the outgoing data stream.
+    pub fn has_pushed_fin(&self) -> bool {
+        self.fin_seq_no.is_some()
+    }
 
+    // Waits until the FIN marker in the outgoing data stream is acked.
+    pub async fn wait_for_fin_ack(&mut self) -> Result<(), Fail> {
+        debug_assert!(self.fin_seq_no.is_some());
         let mut send_unacked_watched: SharedAsyncValue<SeqNumber> = self.send_unacked.clone();
-        let fin_ack_num: SeqNumber = self.unsent_next_seq_no;
+        let fin_ack_num: SeqNumber = self.unsent_next_seq_no.get();
         while self.send_unacked.get() < fin_ack_num {
             send_unacked_watched.wait_for_change(None).await?;
         }
@@ -203,6 +424,7 @@ impl Sender {
         header.seq_num = self.send_next_seq_no.get();
         debug_assert!(self.fin_seq_no.is_some_and(|s| { s == header.seq_num }));
         header.fin = true;
+        self.start_user_timer_if_idle(cb.get_now());
         cb.emit(header, None);
         // Update SND.NXT.
         self.send_next_seq_no.modify(|s| s + 1.into());
@@ -211,6 +433,10 @@ impl Sender {
         let unacked_segment = UnackedSegment {
             bytes: None,
             initial_tx: Some(cb.get_now()),
+            delivery_state: self.take_delivery_snapshot(cb.get_now(), 1),
+            xmit_ts: cb.get_now(),
+            lost: false,
+            sacked: false,
         };
         self.unacked_queue.push(unacked_segment);
         // Set the retransmit timer.
@@ -219,16 +445,21 @@ impl Sender {
             trace!("set retransmit: {:?}", rto);
             self.retransmit_deadline_time_secs.set(Some(cb.get_now() + rto));
         }
+        self.schedule_loss_probe(cb.get_now());
         Ok(())
     }
 
     async fn send_buffer(&mut self, mut buffer: DemiBuffer, cb: &mut SharedControlBlock) -> Result<(), Fail> {
         let mut send_unacked_watched: SharedAsyncValue<SeqNumber> = self.send_unacked.clone();
-        let mut cwnd_watched: SharedAsyncValue<u32> = cb.congestion_control_get_cwnd();
-
-        // The limited transmit algorithm may increase the effective size of cwnd by up to 2 * mss.
-        let mut ltci_watched: SharedAsyncValue<u32> = cb.congestion_control_get_limited_transmit_cwnd_increase();
         let mut win_sz_watched: SharedAsyncValue<u32> = self.send_window.clone();
+        // SACKs received during loss recovery shrink the pipe without moving SND.UNA.
+        let mut sack_recovery_watched: SharedAsyncValue<bool> = self.sack_recovery_now.clone();
+        // More data from the user may let us fill up a segment.
+        let mut unsent_watched: SharedAsyncValue<SeqNumber> = self.unsent_next_seq_no.clone();
+        // A tail loss probe may send new data regardless of the congestion window.
+        let mut tlp_new_data_watched: SharedAsyncValue<bool> = self.tlp_new_data_now.clone();
+        // Deadline after which sender-side SWS avoidance stops holding back data.
+        let mut sws_override_deadline: Option<Instant> = None;
 
         // Try in a loop until we send this segment.
         loop {
@@ -238,29 +469,202 @@ impl Sender {
                 // Send a window probe (this is a one-byte packet designed to elicit a window update from our peer).
                 self.send_window_probe(buffer.split_front(1)?, cb).await?;
             } else {
-                // TODO: Nagle's algorithm - We need to coalese small buffers together to send MSS sized packets.
-                // TODO: Silly window syndrome - See RFC 1122's discussion of the SWS avoidance algorithm.
+                let now: Instant = cb.get_now();
+
+                if tlp_new_data_watched.get() {
+                    self.send_new_data_loss_probe(&mut buffer, cb)?;
+                    if buffer.len() == 0 {
+                        self.check_app_limited(cb);
+                        return Ok(());
+                    }
+                    continue;
+                }
+
+                // Path MTU discovery: send a larger segment than usual if we have enough data to fill it.
+                let probe_size: Option<usize> = match self.get_mtu_probe_size(now, cb) {
+                    Some(probe_size) => {
+                        self.coalesce_unsent(&mut buffer, probe_size)?;
+                        Some(probe_size).filter(|probe_size| buffer.len() >= *probe_size)
+                    },
+                    None => None,
+                };
+                let segment_size: usize = probe_size.unwrap_or(self.get_mss());
+
+                // Nagle's algorithm: coalesce small buffers together to send MSS sized packets.
+                let no_delay: bool = cb.get_nodelay();
+                if !no_delay && buffer.len() < segment_size {
+                    self.coalesce_unsent(&mut buffer, segment_size)?;
+                }
 
                 // We have some window, try to send some or all of the segment.
-                let _: usize = self.send_segment(&mut buffer, cb);
-                // If the buffer is now empty, then we sent all of it.
-                if buffer.len() == 0 {
-                    return Ok(());
+                let usable_window: usize = self.get_open_window_size_bytes(cb, segment_size);
+                let sws_override: bool = sws_override_deadline.is_some_and(|deadline| now >= deadline);
+                let paced: bool = self
+                    .pacing_next_send_time
+                    .is_some_and(|next_send_time| now < next_send_time);
+                if !paced && self.should_send_now(buffer.len(), usable_window, no_delay, sws_override) {
+                    let seq_no: SeqNumber = self.send_next_seq_no.get();
+                    let sent: usize = self.send_segment(&mut buffer, usable_window, cb);
+                    if let Some(probe_size) = probe_size.filter(|probe_size| sent == *probe_size) {
+                        self.path_mtu
+                            .on_probe_sent(seq_no, seq_no + SeqNumber::from(sent as u32), probe_size);
+                    }
+                    sws_override_deadline = None;
+                    // Space out our segments if congestion control asks us to.
+                    self.pacing_next_send_time = cb
+                        .congestion_control_get_pacing_rate()
+                        .filter(|rate| *rate > 0)
+                        .map(|rate| now + Duration::from_nanos(sent as u64 * 1_000_000_000 / rate));
+                    // If the buffer is now empty, then we sent all of it.
+                    if buffer.len() == 0 {
+                        self.check_app_limited(cb);
+                        return Ok(());
+                    }
+                    continue;
                 }
+
+                // Only hold back data that the usable window has room for until the override timer goes off.
+                sws_override_deadline = match sws_override_deadline {
+                    Some(deadline) if usable_window > 0 && buffer.len() > usable_window => Some(deadline),
+                    None if usable_window > 0 && buffer.len() > usable_window => {
+                        Some(cb.get_now() + SWS_OVERRIDE_TIMEOUT)
+                    },
+                    _ => None,
+                };
+
                 // Otherwise, wait until something limiting the window changes and then try again to finish sending
-                // the segment.
-                futures::select_biased! {
-                    _ = send_unacked_watched.wait_for_change(None).fuse() => (),
-                    _ = self.send_next_seq_no.wait_for_change(None).fuse() => (),
-                    _ = win_sz_watched.wait_for_change(None).fuse() => (),
-                    _ = cwnd_watched.wait_for_change(None).fuse() => (),
-                    _ = ltci_watched.wait_for_change(None).fuse() => (),
+                // the segment. Look up congestion control every time around, since the user may switch algorithms.
+                let mut cwnd_watched: SharedAsyncValue<u32> = cb.congestion_control_get_cwnd();
+                // The limited transmit algorithm may increase the effective size of cwnd by up to 2 * mss.
+                let mut ltci_watched: SharedAsyncValue<u32> =
+                    cb.congestion_control_get_limited_transmit_cwnd_increase();
+                let something_changed = async {
+                    select_biased! {
+                        _ = send_unacked_watched.wait_for_change(None).fuse() => (),
+                        _ = self.send_next_seq_no.wait_for_change(None).fuse() => (),
+                        _ = win_sz_watched.wait_for_change(None).fuse() => (),
+                        _ = cwnd_watched.wait_for_change(None).fuse() => (),
+                        _ = ltci_watched.wait_for_change(None).fuse() => (),
+                        _ = sack_recovery_watched.wait_for_change(None).fuse() => (),
+                        _ = unsent_watched.wait_for_change(None).fuse() => (),
+                        _ = tlp_new_data_watched.wait_for_change(None).fuse() => (),
+                    }
+                };
+                pin_mut!(something_changed);
+                let pacing_deadline: Option<Instant> = self.pacing_next_send_time.filter(|_| paced);
+                let deadline: Option<Instant> = match (sws_override_deadline, pacing_deadline) {
+                    (Some(sws_deadline), Some(pacing_deadline)) => Some(cmp::min(sws_deadline, pacing_deadline)),
+                    (sws_deadline, None) => sws_deadline,
+                    (None, pacing_deadline) => pacing_deadline,
                 };
+                match conditional_yield_until(something_changed, deadline).await {
+                    Ok(()) => (),
+                    Err(Fail { errno, cause: _ }) if errno == libc::ETIMEDOUT => trace!("send timer wake"),
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+    }
+
+    // If we ran out of data to send before filling the congestion window, delivery rate samples taken until this data
+    // is acknowledged don't reflect the available bandwidth.
+    fn check_app_limited(&mut self, cb: &mut SharedControlBlock) {
+        let bytes_in_flight: u32 = (self.send_next_seq_no.get() - self.send_unacked.get()).into();
+        if self.unsent_queue.is_empty() && bytes_in_flight < cb.congestion_control_get_cwnd().get() {
+            self.delivery_rate.on_app_limited(bytes_in_flight);
+        }
+    }
+
+    // Starts measuring the user timeout from now if we are about to send into an idle connection.
+    fn start_user_timer_if_idle(&mut self, now: Instant) {
+        if self.send_next_seq_no.get() == self.send_unacked.get() {
+            self.last_progress_time = now;
+        }
+    }
+
+    // Returns when the user timeout drops the connection, if it is set and we have unacknowledged data outstanding.
+    fn get_user_timeout_deadline(&self) -> Option<Instant> {
+        if self.send_next_seq_no.get() == self.send_unacked.get() {
+            return None;
+        }
+        self.user_timeout.get().map(|timeout| self.last_progress_time + timeout)
+    }
+
+    // Aborts the connection if our peer has not acknowledged anything within the user timeout.
+    fn check_user_timeout(&self, cb: &mut SharedControlBlock) -> Result<(), Fail> {
+        match self.get_user_timeout_deadline() {
+            Some(deadline) if cb.get_now() >= deadline => {
+                let cause: String = format!(
+                    "no acknowledgement within the user timeout of {:?} (local={:?}, remote={:?})",
+                    self.user_timeout.get(),
+                    cb.get_local(),
+                    cb.get_remote()
+                );
+                warn!("check_user_timeout(): {}", cause);
+                let e: Fail = Fail::new(libc::ETIMEDOUT, &cause);
+                cb.abort(e.clone());
+                Err(e)
+            },
+            _ => Ok(()),
+        }
+    }
+
+    // Decides whether to send a buffer of `buffer_len` bytes into `usable_window` right away, following the sender-side
+    // SWS avoidance algorithm of RFC 1122 Section 4.2.3.4 and, unless `no_delay` is set, Nagle's algorithm (RFC 896).
+    fn should_send_now(&self, buffer_len: usize, usable_window: usize, no_delay: bool, sws_override: bool) -> bool {
+        if usable_window == 0 {
+            return false;
+        }
+        // We can send a full-sized segment.  Note that the usable window is already capped at one segment.
+        if cmp::min(buffer_len, usable_window) >= self.get_mss() {
+            return true;
+        }
+        // We can send everything that we have.  Nagle's algorithm allows one small segment in flight at a time.
+        if buffer_len <= usable_window {
+            return no_delay || self.send_next_seq_no.get() == self.send_unacked.get();
+        }
+        // We can send at least half of the largest window our peer ever offered, or we have waited long enough.
+        usable_window as u32 >= self.send_window_max / 2 || sws_override
+    }
+
+    // Appends data from the unsent queue to `buffer` until it fills up a segment of `segment_size` bytes or the unsent
+    // queue runs out of data.
+    fn coalesce_unsent(&mut self, buffer: &mut DemiBuffer, segment_size: usize) -> Result<(), Fail> {
+        let mut total_len: usize = buffer.len();
+        let mut parts: Vec<DemiBuffer> = Vec::new();
+        while total_len < segment_size {
+            match self.unsent_queue.try_pop() {
+                Some(Some(mut next)) => {
+                    let wanted: usize = segment_size - total_len;
+                    if next.len() > wanted {
+                        // Leave the rest of this buffer at the front of the unsent queue.
+                        let front: DemiBuffer = next.split_front(wanted)?;
+                        self.unsent_queue.push_front(Some(next));
+                        next = front;
+                    }
+                    total_len += next.len();
+                    parts.push(next);
+                },
+                // Leave the FIN in the unsent queue, it goes out once all data has been sent.
+                Some(None) => {
+                    self.unsent_queue.push_front(None);
+                    break;
+                },
+                None => break,
             }
         }
+        if parts.is_empty() {
+            return Ok(());
+        }
+
+        parts.insert(0, buffer.clone());
+        *buffer = concatenate(parts, total_len);
+        Ok(())
     }
 
     async fn send_window_probe(&mut self, probe: DemiBuffer, cb: &mut SharedControlBlock) -> Result<(), Fail> {
+        self.start_user_timer_if_idle(cb.get_now());
+
         // Update SND.NXT.
         self.send_next_seq_no.modify(|s| s + SeqNumber::from(1));
 
@@ -268,22 +672,41 @@ impl Sender {
         let unacked_segment = UnackedSegment {
             bytes: Some(probe.clone()),
             initial_tx: Some(cb.get_now()),
+            delivery_state: self.take_delivery_snapshot(cb.get_now(), 1),
+            xmit_ts: cb.get_now(),
+            lost: false,
+            sacked: false,
         };
         self.unacked_queue.push(unacked_segment);
 
-        // Note that we loop here *forever*, exponentially backing off.
-        // TODO: Use the correct PERSIST mode timer here.
-        let mut timeout: Duration = Duration::from_secs(1);
+        // Note that we loop here until the window opens, exponentially backing off from the RTO within the bounds of the
+        // persist timer, as RFC 9293 Section 3.8.6.1 allows a receiver to keep its window closed indefinitely.  Only
+        // the user timeout, if set, makes us give up.
+        let mut timeout: Duration = self.rto_calculator.rto().clamp(self.persist_min, self.persist_max);
         let mut win_sz_watched: SharedAsyncValue<u32> = self.send_window.clone();
+        let mut next_probe_time: Instant = cb.get_now();
         loop {
-            // Create packet.
-            let mut header: TcpHeader = cb.tcp_header();
-            header.seq_num = self.send_next_seq_no.get();
-            cb.emit(header, Some(probe.clone()));
+            let now: Instant = cb.get_now();
+            self.check_user_timeout(cb)?;
+            if now >= next_probe_time {
+                // Create packet.
+                let mut header: TcpHeader = cb.tcp_header();
+                header.seq_num = self.send_next_seq_no.get();
+                cb.emit(header, Some(probe.clone()));
+                next_probe_time = now + timeout;
+                timeout = cmp::min(timeout.saturating_mul(2), self.persist_max);
+            }
 
-            match win_sz_watched.wait_for_change(Some(timeout)).await {
+            let deadline: Instant = match self.get_user_timeout_deadline() {
+                Some(user_deadline) => cmp::min(next_probe_time, user_deadline),
+                None => next_probe_time,
+            };
+            match win_sz_watched
+                .wait_for_change(Some(deadline.saturating_duration_since(now)))
+                .await
+            {
                 Ok(_) => return Ok(()),
-                Err(Fail { errno, cause: _ }) if errno == libc::ETIMEDOUT => timeout *= 2,
+                Err(Fail { errno, cause: _ }) if errno == libc::ETIMEDOUT => trace!("persist timer wake"),
                 Err(_) => unreachable!(
                     "either the ack deadline changed or the deadline passed, no other errors are possible!"
                 ),
@@ -291,16 +714,19 @@ impl Sender {
         }
     }
 
-    // Takes a segment and attempts to send it. The buffer must be non-zero length and the function returns the number
-    // of bytes sent.
-    fn send_segment(&mut self, segment: &mut DemiBuffer, cb: &mut SharedControlBlock) -> usize {
+    // Takes a segment and attempts to send up to `max_frame_size_bytes` bytes of it, which the windows must have room
+    // for. The buffer must be non-zero length and the function returns the number of bytes sent.
+    fn send_segment(
+        &mut self,
+        segment: &mut DemiBuffer,
+        max_frame_size_bytes: usize,
+        cb: &mut SharedControlBlock,
+    ) -> usize {
         let buf_len: usize = segment.len();
         debug_assert_ne!(buf_len, 0);
-        // Check window size.
-        let max_frame_size_bytes: usize = match self.get_open_window_size_bytes(cb) {
-            0 => return 0,
-            size => size,
-        };
+        if max_frame_size_bytes == 0 {
+            return 0;
+        }
 
         // Split the packet if necessary.
         // TODO: Use a scatter/gather array to coalesce multiple buffers into a single segment.
@@ -323,12 +749,13 @@ impl Sender {
         cb.congestion_control_on_send(rto, (self.send_next_seq_no.get() - self.send_unacked.get()).into());
 
         // Prepare the segment and send it.
+        self.start_user_timer_if_idle(cb.get_now());
         let mut header: TcpHeader = cb.tcp_header();
         header.seq_num = self.send_next_seq_no.get();
         if do_push {
             header.psh = true;
         }
-        cb.emit(header, Some(segment_data.clone()));
+        cb.emit_new_data(header, segment_data.clone());
 
         // Update SND.NXT.
         self.send_next_seq_no.modify(|s| s + SeqNumber::from(segment_data_len));
@@ -337,6 +764,10 @@ impl Sender {
         let unacked_segment = UnackedSegment {
             bytes: Some(segment_data),
             initial_tx: Some(cb.get_now()),
+            delivery_state: self.take_delivery_snapshot(cb.get_now(), segment_data_len),
+            xmit_ts: cb.get_now(),
+            lost: false,
+            sacked: false,
         };
         self.unacked_queue.push(unacked_segment);
 
@@ -345,10 +776,12 @@ impl Sender {
             let rto: Duration = self.rto_calculator.rto();
             self.retransmit_deadline_time_secs.set(Some(cb.get_now() + rto));
         }
+        self.schedule_loss_probe(cb.get_now());
         segment_data_len as usize
     }
 
-    fn get_open_window_size_bytes(&mut self, cb: &mut SharedControlBlock) -> usize {
+    // Returns the number of bytes that we may send right now, up to `segment_size`.
+    fn get_open_window_size_bytes(&mut self, cb: &mut SharedControlBlock, segment_size: usize) -> usize {
         // Calculate amount of data in flight (SND.NXT - SND.UNA).
         let send_unacknowledged: SeqNumber = self.send_unacked.get();
         let send_next: SeqNumber = self.send_next_seq_no.get();
@@ -363,50 +796,134 @@ impl Sender {
 
         let win_sz: u32 = self.send_window.get();
 
-        if Self::has_open_window(win_sz, sent_data, effective_cwnd) {
-            Self::calculate_open_window_bytes(win_sz, sent_data, self.mss, effective_cwnd)
+        // During SACK-based loss recovery, cwnd limits the pipe rather than everything outstanding (RFC 6675).
+        let in_flight: u32 = match self.sack_recovery_point {
+            Some(_) => self.get_pipe(),
+            None => sent_data,
+        };
+
+        if Self::has_open_window(win_sz, sent_data, in_flight, effective_cwnd) {
+            Self::calculate_open_window_bytes(win_sz, sent_data, in_flight, segment_size, effective_cwnd)
         } else {
             0
         }
     }
 
-    fn has_open_window(win_sz: u32, sent_data: u32, effective_cwnd: u32) -> bool {
-        win_sz > 0 && win_sz >= sent_data && effective_cwnd >= sent_data
+    fn has_open_window(win_sz: u32, sent_data: u32, in_flight: u32, effective_cwnd: u32) -> bool {
+        win_sz > 0 && win_sz >= sent_data && effective_cwnd >= in_flight
     }
 
-    fn calculate_open_window_bytes(win_sz: u32, sent_data: u32, mss: usize, effective_cwnd: u32) -> usize {
+    fn calculate_open_window_bytes(
+        win_sz: u32,
+        sent_data: u32,
+        in_flight: u32,
+        mss: usize,
+        effective_cwnd: u32,
+    ) -> usize {
         cmp::min(
             cmp::min((win_sz - sent_data) as usize, mss),
-            (effective_cwnd - sent_data) as usize,
+            (effective_cwnd - in_flight) as usize,
+        )
+    }
+
+    // Returns the size of the probe to send next for path MTU discovery, if the windows have room for it.  We don't
+    // probe during loss recovery, since a lost probe would then tell us nothing about the path.
+    fn get_mtu_probe_size(&mut self, now: Instant, cb: &mut SharedControlBlock) -> Option<usize> {
+        if self.sack_recovery_point.is_some() {
+            return None;
+        }
+        let probe_size: usize = self.path_mtu.next_probe_size(now)?;
+        if self.get_open_window_size_bytes(cb, probe_size) < probe_size {
+            return None;
+        }
+        Some(probe_size)
+    }
+
+    // Records the delivery progress for a segment (re)transmitted at `now`.  `segment_len` is the sequence space of a new
+    // segment, which has already been added to SND.NXT.
+    fn take_delivery_snapshot(&mut self, now: Instant, segment_len: u32) -> SegmentDeliveryState {
+        let bytes_in_flight: u32 = u32::from(self.send_next_seq_no.get() - self.send_unacked.get()) - segment_len;
+        self.delivery_rate.on_send(now, bytes_in_flight)
+    }
+
+    // Estimate of the number of bytes in the network during loss recovery.
+    fn get_pipe(&self) -> u32 {
+        self.sack_scoreboard.pipe(
+            self.send_unacked.get(),
+            self.send_next_seq_no.get(),
+            self.sack_high_rxt,
+            self.get_mss(),
         )
     }
 
     pub async fn background_retransmitter(&mut self, mut cb: SharedControlBlock) -> Result<Never, Fail> {
         // Watch the retransmission deadline.
         let mut rtx_deadline_watched: SharedAsyncValue<Option<Instant>> = self.retransmit_deadline_time_secs.clone();
-        // Watch the fast retransmit flag.
-        let mut rtx_fast_retransmit_watched: SharedAsyncValue<bool> = cb.congestion_control_watch_retransmit_now_flag();
+        // Watch the SACK-based loss recovery flag.
+        let mut sack_recovery_watched: SharedAsyncValue<bool> = self.sack_recovery_now.clone();
+        // Watch the user timeout, which the application may change at any time.
+        let mut user_timeout_watched: SharedAsyncValue<Option<Duration>> = self.user_timeout.clone();
+        // Watch the RACK reordering timer and the tail loss probe timer.
+        let mut rack_reorder_deadline_watched: SharedAsyncValue<Option<Instant>> = self.rack_reorder_deadline.clone();
+        let mut tlp_deadline_watched: SharedAsyncValue<Option<Instant>> = self.tlp_deadline.clone();
         loop {
+            // Watch the fast retransmit flag.  The congestion control algorithm may change, so get it every time.
+            let mut rtx_fast_retransmit_watched: SharedAsyncValue<bool> =
+                cb.congestion_control_watch_retransmit_now_flag();
             let rtx_deadline: Option<Instant> = rtx_deadline_watched.get();
             let rtx_fast_retransmit: bool = rtx_fast_retransmit_watched.get();
+            // Let congestion control respond to a loss before we retransmit anything.
+            if mem::take(&mut self.loss_response_pending) {
+                let send_unacked: SeqNumber = self.send_unacked.get();
+                let send_next: SeqNumber = self.send_next_seq_no.get();
+                if self.sack_recovery_point.is_some() {
+                    cb.congestion_control_on_sack_recovery(send_unacked, send_next);
+                } else {
+                    // Duplicate ACKs don't always tell congestion control about the losses that RACK-TLP detects.
+                    cb.congestion_control_on_loss_detected(send_unacked, send_next);
+                }
+            }
             if rtx_fast_retransmit {
                 // Notify congestion control about fast retransmit.
                 cb.congestion_control_on_fast_retransmit();
 
-                // Retransmit earliest unacknowledged segment.
-                self.retransmit(&mut cb);
+                // If our peer is telling us what it has, only retransmit the holes.  Otherwise, retransmit earliest
+                // unacknowledged segment.
+                if self.sack_recovery_point.is_some() {
+                    self.retransmit_lost_segments(&mut cb);
+                } else {
+                    self.retransmit(&mut cb);
+                }
+                continue;
+            }
+            if sack_recovery_watched.get() {
+                sack_recovery_watched.set_without_notify(false);
+                self.retransmit_lost_segments(&mut cb);
                 continue;
             }
 
-            // If either changed, wake up.
+            // If any changed, wake up.
             let something_changed = async {
                 select_biased!(
                     _ = rtx_deadline_watched.wait_for_change(None).fuse() => (),
                     _ = rtx_fast_retransmit_watched.wait_for_change(None).fuse() => (),
+                    _ = sack_recovery_watched.wait_for_change(None).fuse() => (),
+                    _ = user_timeout_watched.wait_for_change(None).fuse() => (),
+                    _ = rack_reorder_deadline_watched.wait_for_change(None).fuse() => (),
+                    _ = tlp_deadline_watched.wait_for_change(None).fuse() => (),
                 )
             };
             pin_mut!(something_changed);
-            match conditional_yield_until(something_changed, rtx_deadline).await {
+            let deadline: Option<Instant> = [
+                rtx_deadline,
+                self.get_user_timeout_deadline(),
+                self.rack_reorder_deadline.get(),
+                self.tlp_deadline.get(),
+            ]
+            .into_iter()
+            .flatten()
+            .min();
+            match conditional_yield_until(something_changed, deadline).await {
                 Ok(()) => match self.fin_seq_no {
                     Some(fin_seq_no) if self.send_unacked.get() > fin_seq_no => {
                         return Err(Fail::new(libc::ECONNRESET, "connection closed"));
@@ -414,13 +931,63 @@ impl Sender {
                     _ => continue,
                 },
                 Err(Fail { errno, cause: _ }) if errno == libc::ETIMEDOUT => {
+                    // Give up on our peer if it has not acknowledged anything for too long (RFC 5482).
+                    self.check_user_timeout(&mut cb)?;
+                    let now: Instant = cb.get_now();
+                    // Segments that RACK gave a chance to arrive out of order may be lost by now.
+                    if self.rack_reorder_deadline.get().is_some_and(|deadline| now >= deadline) {
+                        self.detect_lost_segments(now);
+                    }
+                    if self.tlp_deadline.get().is_some_and(|deadline| now >= deadline) {
+                        self.send_loss_probe(&mut cb);
+                    }
+                    if self
+                        .retransmit_deadline_time_secs
+                        .get()
+                        .is_none_or(|rtx_deadline| now < rtx_deadline)
+                    {
+                        continue;
+                    }
+
                     // Retransmit timeout.
                     trace!("retransmit wake");
+
+                    // Like Linux, give up on our peer after too many timeouts in a row (RFC 9293 Section 3.8.3).
+                    if self.retransmit_timeouts >= self.data_retries {
+                        let cause: String = format!(
+                            "no acknowledgement after {} retransmissions (local={:?}, remote={:?})",
+                            self.retransmit_timeouts,
+                            cb.get_local(),
+                            cb.get_remote()
+                        );
+                        warn!("background_retransmitter(): {}", cause);
+                        let e: Fail = Fail::new(libc::ETIMEDOUT, &cause);
+                        cb.abort(e.clone());
+                        return Err(e);
+                    }
+                    self.retransmit_timeouts += 1;
                     // Notify congestion control about RTO.
                     // TODO: Is this the best place for this?
                     // TODO: Why call into ControlBlock to get SND.UNA when congestion_control_on_rto() has access to it?
                     cb.congestion_control_on_rto(self.send_unacked.get());
 
+                    // RFC 4821 Section 7.5: Repeated timeouts may mean that our segments are too large for the path,
+                    // so this may shrink the MSS for the retransmission below.
+                    let mss: usize = self.get_mss();
+                    self.path_mtu.on_retransmit_timeout(cb.get_now());
+                    if self.get_mss() < mss {
+                        self.mark_oversized_segments_lost();
+                    }
+
+                    // RFC 6675 Section 5.1: Leave loss recovery, we start over from SND.UNA.
+                    self.sack_recovery_point = None;
+                    self.sack_high_rxt = self.send_unacked.get();
+
+                    // RFC 8985 Section 7.3: Forget about the last tail loss probe, it didn't get an ACK either.
+                    self.tlp_end_seq = None;
+                    self.tlp_deadline.set(None);
+                    self.tlp_new_data_now.set(false);
+
                     // RFC 6298 Section 5.4: Retransmit earliest unacknowledged segment.
                     self.retransmit(&mut cb);
 
@@ -440,37 +1007,269 @@ impl Sender {
         }
     }
 
-    /// Retransmits the earliest segment that has not (yet) been acknowledged by our peer.
+    // Like Linux's tcp_simple_retransmit(), deems lost the segments that no longer fit into the MSS, since the path has
+    // just dropped them. Once an ACK for the first retransmission arrives, the rest goes out without waiting for another
+    // timeout.
+    fn mark_oversized_segments_lost(&mut self) {
+        let mss: usize = self.get_mss();
+        for segment in self.unacked_queue.get_mut_values() {
+            if !segment.sacked && segment.bytes.as_ref().is_some_and(|bytes| bytes.len() > mss) {
+                segment.lost = true;
+            }
+        }
+    }
+
+    /// Retransmits the earliest data that has not (yet) been acknowledged by our peer, as a full-sized segment if we
+    /// have enough unacknowledged data.
     pub fn retransmit(&mut self, cb: &mut SharedControlBlock) {
-        match self.unacked_queue.get_front_mut() {
-            Some(segment) => {
-                // We're retransmitting this, so we can no longer use an ACK for it as an RTT measurement (as we can't
-                // tell if the ACK is for the original or the retransmission).  Remove the transmission timestamp from
-                // the entry.
-                segment.initial_tx.take();
+        let send_unacked: SeqNumber = self.send_unacked.get();
+        self.retransmit_range(send_unacked, u32::MAX, cb);
+    }
 
-                // Clone the segment data for retransmission.
-                let data: Option<DemiBuffer> = segment.bytes.as_ref().map(|b| b.clone());
+    /// Retransmits the segments that RACK deemed lost and the holes that our peer's SACKs have marked as lost, for as
+    /// long as the congestion window allows. See RFC 6675 Section 5 and RFC 8985 Section 6.2 for more details.
+    fn retransmit_lost_segments(&mut self, cb: &mut SharedControlBlock) {
+        let send_unacked: SeqNumber = self.send_unacked.get();
+        // RFC 6675 Section 5: Estimate the data in flight once per ACK, and add every retransmission to it (step C.4).
+        let mut pipe: u32 = self.get_pipe();
+        loop {
+            // The first retransmission on entering loss recovery is not subject to the congestion window.
+            if self.sack_high_rxt > send_unacked {
+                let cwnd: u32 = cb.congestion_control_get_cwnd().get();
+                if cwnd.saturating_sub(pipe) < self.get_mss() as u32 {
+                    return;
+                }
+            }
+            let (seq_no, len): (SeqNumber, u32) = match self.next_rack_lost_segment().or_else(|| {
+                self.sack_scoreboard
+                    .next_lost_segment(send_unacked, self.sack_high_rxt, self.get_mss())
+            }) {
+                Some(segment) => segment,
+                None => return,
+            };
+            let sent: u32 = match self.retransmit_range(seq_no, len, cb) {
+                0 => return,
+                sent => sent,
+            };
+            pipe += sent;
+            if self.sack_high_rxt < seq_no + SeqNumber::from(sent) {
+                self.sack_high_rxt = seq_no + SeqNumber::from(sent);
+            }
+        }
+    }
 
-                // TODO: Issue #198 Repacketization - we should send a full MSS (and set the FIN flag if applicable).
+    // Returns the start and length of the first run of segments that RACK deemed lost, up to one MSS long.
+    fn next_rack_lost_segment(&self) -> Option<(SeqNumber, u32)> {
+        let mss: u32 = self.get_mss() as u32;
+        let mut lost: Option<(SeqNumber, u32)> = None;
+        let mut segment_start: SeqNumber = self.send_unacked.get();
+        for segment in self.unacked_queue.get_values() {
+            lost = match lost {
+                None if segment.lost => Some((segment_start, segment.seq_len())),
+                Some((seq_no, len)) if segment.lost && len < mss => Some((seq_no, len + segment.seq_len())),
+                Some(_) => break,
+                None => None,
+            };
+            segment_start = segment_start + SeqNumber::from(segment.seq_len());
+        }
+        lost.map(|(seq_no, len)| (seq_no, cmp::min(len, mss)))
+    }
 
-                // Prepare and send the segment.
-                let mut header: TcpHeader = cb.tcp_header();
-                header.seq_num = self.send_unacked.get();
-                // If data exists, then this is a regular packet, otherwise, its a FIN.
-                if data.is_some() {
-                    header.psh = true;
-                } else {
-                    header.fin = true;
-                }
-                cb.emit(header, data);
+    // Sends a tail loss probe, to elicit an ACK that lets RACK detect losses at the end of a flight without waiting for
+    // the RTO (RFC 8985 Section 7.3). The probe is new data if we have some and our peer's window has room for a whole
+    // segment of it, which the background sender sends for us, and otherwise a retransmission of the last segment that
+    // we sent.
+    fn send_loss_probe(&mut self, cb: &mut SharedControlBlock) {
+        self.tlp_deadline.set(None);
+        if self.sack_recovery_point.is_some() || self.tlp_end_seq.is_some() || self.tlp_new_data_now.get() {
+            return;
+        }
+        let send_next: SeqNumber = self.send_next_seq_no.get();
+        let unsent_data_end: SeqNumber = self.fin_seq_no.unwrap_or(self.unsent_next_seq_no.get());
+        if unsent_data_end > send_next
+            && self.get_probe_window() >= cmp::min(u32::from(unsent_data_end - send_next) as usize, self.get_mss())
+        {
+            trace!("send_loss_probe(): probing with new data");
+            self.tlp_new_data_now.set(true);
+            return;
+        }
+        let len: u32 = match self.unacked_queue.get_values().next_back() {
+            Some(segment) => cmp::min(segment.seq_len(), self.get_mss() as u32),
+            None => return,
+        };
+        trace!("send_loss_probe(): probing with the last {} bytes", len);
+        if self.retransmit_range(send_next - SeqNumber::from(len), len, cb) == 0 {
+            return;
+        }
+        self.on_loss_probe_sent(cb);
+    }
+
+    // Sends up to one MSS of `buffer` as a tail loss probe, which the congestion window doesn't hold back. Like any other
+    // segment, the probe only carries less than an MSS if that is all the data we have (RFC 1122 Section 4.2.3.4), so
+    // if our peer's window shrank in the meantime, we probe with a retransmission instead.
+    fn send_new_data_loss_probe(&mut self, buffer: &mut DemiBuffer, cb: &mut SharedControlBlock) -> Result<(), Fail> {
+        self.tlp_new_data_now.set(false);
+        let mss: usize = self.get_mss();
+        self.coalesce_unsent(buffer, mss)?;
+        let probe_size: usize = cmp::min(buffer.len(), mss);
+        if self.get_probe_window() < probe_size {
+            self.send_loss_probe(cb);
+            return Ok(());
+        }
+        self.send_segment(buffer, probe_size, cb);
+        self.on_loss_probe_sent(cb);
+        Ok(())
+    }
+
+    // Returns the number of bytes of new data that our peer's window has room for, regardless of the congestion window.
+    fn get_probe_window(&self) -> usize {
+        let sent_data: u32 = (self.send_next_seq_no.get() - self.send_unacked.get()).into();
+        self.send_window.get().saturating_sub(sent_data) as usize
+    }
+
+    // Waits to hear about the tail loss probe that we just sent, which ends at SND.NXT, and restarts the retransmission
+    // timer.
+    fn on_loss_probe_sent(&mut self, cb: &mut SharedControlBlock) {
+        self.tlp_end_seq = Some(self.send_next_seq_no.get());
+        self.tlp_deadline.set(None);
+        let deadline: Instant = cb.get_now() + self.rto_calculator.rto();
+        self.retransmit_deadline_time_secs.set(Some(deadline));
+    }
+
+    // Arms the tail loss probe timer if we have data in flight and are neither recovering from a loss nor waiting to
+    // hear about an earlier probe (RFC 8985 Section 7.2). The probe goes out no later than the RTO would have.
+    fn schedule_loss_probe(&mut self, now: Instant) {
+        let bytes_in_flight: u32 = (self.send_next_seq_no.get() - self.send_unacked.get()).into();
+        let deadline: Option<Instant> = match self.retransmit_deadline_time_secs.get() {
+            Some(rto_deadline)
+                if self.rack.is_some()
+                    && self.sack_recovery_point.is_none()
+                    && self.tlp_end_seq.is_none()
+                    && bytes_in_flight > 0 =>
+            {
+                let single_segment: bool = bytes_in_flight <= self.get_mss() as u32;
+                let pto: Duration = rack::probe_timeout(self.rto_calculator.srtt(), single_segment);
+                Some(cmp::min(now + pto, rto_deadline))
             },
-            None => (),
+            _ => None,
+        };
+        if deadline != self.tlp_deadline.get() {
+            self.tlp_deadline.set(deadline);
         }
     }
 
-    // Process an ack.
-    pub fn process_ack(&mut self, header: &TcpHeader, now: Instant) {
+    /// Retransmits up to `len` bytes of sequence space starting at `seq_no` in a single segment. Whole unacknowledged
+    /// segments that follow are merged in for as long as the segment stays within one MSS, and the FIN flag is set if
+    /// the segment reaches a FIN that we deem lost. Returns the number of bytes of sequence space retransmitted.
+    fn retransmit_range(&mut self, seq_no: SeqNumber, len: u32, cb: &mut SharedControlBlock) -> u32 {
+        let now: Instant = cb.get_now();
+        let delivery_state: SegmentDeliveryState = self.take_delivery_snapshot(now, 0);
+        let max_data_len: usize = cmp::min(len as usize, self.get_mss());
+        let first_index: usize = match self.split_unacked_segments(seq_no, max_data_len) {
+            Some(index) => index,
+            None => return 0,
+        };
+        let mut parts: Vec<DemiBuffer> = Vec::new();
+        let mut data_len: usize = 0;
+        let mut fin: bool = false;
+        for segment in self.unacked_queue.get_mut_values().skip(first_index) {
+            match segment.bytes.as_ref() {
+                // Like Linux, never split a following segment to fill up this one.
+                Some(bytes) if data_len + bytes.len() > max_data_len => break,
+                Some(bytes) => {
+                    data_len += bytes.len();
+                    parts.push(bytes.clone());
+                },
+                // The FIN takes up sequence space too, so only send it if it fits. Unless we already deem it lost, it waits
+                // for its own retransmission rather than riding along with data.
+                None if (data_len as u32) < len && (parts.is_empty() || segment.lost) => fin = true,
+                None => break,
+            }
+
+            // Karn's algorithm: no RTT samples from retransmitted segments.
+            segment.initial_tx.take();
+            segment.delivery_state = delivery_state;
+            segment.xmit_ts = now;
+            segment.lost = false;
+        }
+
+        let mut header: TcpHeader = cb.tcp_header();
+        header.seq_num = seq_no;
+        header.fin = fin;
+        let data: Option<DemiBuffer> = match parts.len() {
+            0 if !fin => return 0,
+            0 => None,
+            1 => parts.pop(),
+            _ => Some(concatenate(parts, data_len)),
+        };
+        header.psh = data.is_some();
+        cb.emit(header, data);
+        self.total_retransmits = self.total_retransmits.wrapping_add(1);
+        let sent: u32 = data_len as u32 + fin as u32;
+        self.path_mtu.on_retransmit(seq_no, seq_no + SeqNumber::from(sent), now);
+        sent
+    }
+
+    // Splits the unacknowledged segments so that one of them starts at `seq_no` and carries at most `max_data_len`
+    // bytes, and returns its position in the unacknowledged queue. The bytes split off keep their transmission history,
+    // so that retransmitting only part of a segment doesn't cost the rest of it its RTT sample.
+    fn split_unacked_segments(&mut self, seq_no: SeqNumber, max_data_len: usize) -> Option<usize> {
+        let mut segment_start: SeqNumber = self.send_unacked.get();
+        let mut index: usize = 0;
+        for segment in self.unacked_queue.get_values() {
+            let segment_end: SeqNumber = segment_start + SeqNumber::from(segment.seq_len());
+            if seq_no < segment_end {
+                break;
+            }
+            segment_start = segment_end;
+            index += 1;
+        }
+        if index == self.unacked_queue.len() {
+            return None;
+        }
+
+        let offset: usize = u32::from(seq_no - segment_start) as usize;
+        if offset > 0 {
+            self.split_unacked_segment(index, offset);
+            index += 1;
+        }
+        self.split_unacked_segment(index, max_data_len);
+        Some(index)
+    }
+
+    // Splits the unacknowledged segment at `index` after its first `len` bytes, unless it is no longer than that.
+    fn split_unacked_segment(&mut self, index: usize, len: usize) {
+        let segment: &mut UnackedSegment = self
+            .unacked_queue
+            .get_mut_values()
+            .nth(index)
+            .expect("'index' should be within the unacknowledged queue");
+        let back: DemiBuffer = match segment.bytes.as_mut() {
+            Some(bytes) if bytes.len() > len => expect_ok!(
+                bytes.split_back(len),
+                "should be able to split back because we just checked the length"
+            ),
+            _ => return,
+        };
+        let unacked_segment: UnackedSegment = UnackedSegment {
+            bytes: Some(back),
+            initial_tx: segment.initial_tx,
+            delivery_state: segment.delivery_state,
+            xmit_ts: segment.xmit_ts,
+            lost: segment.lost,
+            sacked: segment.sacked,
+        };
+        self.unacked_queue.insert(index + 1, unacked_segment);
+    }
+
+    // Process an ack. If timestamps are in use, `rtt_sample` is the round-trip time measured from the echoed timestamp.
+    // Returns the delivery rate measured by this ACK, if it acknowledged new data.
+    pub fn process_ack(
+        &mut self,
+        header: &TcpHeader,
+        now: Instant,
+        rtt_sample: Option<Duration>,
+    ) -> Option<RateSample> {
         // Start by checking that the ACK acknowledges something new.
         // TODO: Look into removing Watched types.
         let send_unacknowledged: SeqNumber = self.send_unacked.get();
@@ -483,11 +1282,23 @@ impl Sender {
             let bytes_acknowledged: u32 = (header.ack_num - self.send_unacked.get()).into();
             // Convert that into a usize for counting bytes to remove from the unacked queue.
             let mut bytes_remaining: usize = bytes_acknowledged as usize;
+            // Prefer the RTT sample from the echoed timestamp.  Otherwise, time the non-retransmitted segments that
+            // this ACK covers (Karn's algorithm).
+            if let Some(rtt) = rtt_sample {
+                self.rto_calculator.add_sample(rtt);
+            }
             // Remove bytes from the unacked queue.
+            let mut segment_start: SeqNumber = send_unacknowledged;
             while bytes_remaining != 0 {
-                bytes_remaining = match self.unacked_queue.try_pop() {
+                let segment: Option<UnackedSegment> = self.unacked_queue.try_pop();
+                if let Some(segment) = segment.as_ref() {
+                    let segment_end: SeqNumber = segment_start + SeqNumber::from(segment.seq_len());
+                    self.rack_on_acked(segment, segment_end, header.ack_num, now);
+                    segment_start = segment_end;
+                }
+                bytes_remaining = match segment {
                     Some(segment) if segment.bytes.is_none() => self.process_acked_fin(bytes_remaining, header.ack_num),
-                    Some(segment) => self.process_acked_segment(bytes_remaining, segment, now),
+                    Some(segment) => self.process_acked_segment(bytes_remaining, segment, now, rtt_sample.is_none()),
                     None => {
                         unreachable!("There should be enough data in the unacked_queue for the number of bytes acked")
                     }, // Shouldn't have bytes_remaining with no segments remaining in unacked_queue.
@@ -496,6 +1307,25 @@ impl Sender {
 
             // Update SND.UNA to SEG.ACK.
             self.send_unacked.set(header.ack_num);
+            self.retransmit_timeouts = 0;
+            self.last_progress_time = now;
+
+            // A probe that got through lets us use larger segments.
+            self.path_mtu.on_ack(header.ack_num, now);
+
+            // Forget about SACKed data that has now been cumulatively acknowledged.
+            self.sack_scoreboard.on_cumulative_ack(header.ack_num);
+            self.sack_duplicate_ack_count = 0;
+            if self.sack_high_rxt < header.ack_num {
+                self.sack_high_rxt = header.ack_num;
+            }
+            if self
+                .sack_recovery_point
+                .is_some_and(|recovery_point| header.ack_num >= recovery_point)
+            {
+                // RFC 6675 Section 5: Everything outstanding when we detected the loss has been acknowledged.
+                self.sack_recovery_point = None;
+            }
 
             // Check and update send window if necessary.
             self.update_send_window(header);
@@ -508,10 +1338,187 @@ impl Sender {
                 debug_assert_eq!(self.send_next_seq_no.get(), header.ack_num);
             }
             self.retransmit_deadline_time_secs.set(retransmit_deadline_time_secs);
+
+            // Retransmit what is already deemed lost right away.
+            if self.unacked_queue.get_front().is_some_and(|segment| segment.lost) {
+                self.sack_recovery_now.set(true);
+            }
         } else {
-            // Duplicate ACK (doesn't acknowledge anything new).  We can mostly ignore this, except for fast-retransmit.
-            // TODO: Implement fast-retransmit.  In which case, we'd increment our dup-ack counter here.
+            // Duplicate ACK (doesn't acknowledge anything new).  The ControlBlock counts these for fast retransmit.
             warn!("process_ack(): received duplicate ack ({:?})", header.ack_num);
+
+            // It may still carry a window update (RFC 793 Section 3.9), which is how our peer reopens its window.
+            if send_unacknowledged == header.ack_num {
+                self.update_send_window(header);
+            }
+        }
+
+        let acked_new_data: bool = send_unacknowledged < header.ack_num;
+        let sacked_new_data: bool = self.process_sacks(header, acked_new_data);
+        if self.rack.is_some() {
+            if sacked_new_data {
+                self.rack_on_sacked(now);
+            }
+            self.process_loss_probe_ack(header, acked_new_data || sacked_new_data);
+            self.detect_lost_segments(now);
+            if acked_new_data {
+                self.schedule_loss_probe(now);
+            }
+        }
+
+        let bytes_in_flight: u32 = (self.send_next_seq_no.get() - self.send_unacked.get()).into();
+        self.delivery_rate.generate_sample(now, bytes_in_flight)
+    }
+
+    // Updates the SACK scoreboard and enters or advances SACK-based loss recovery (RFC 6675 Section 5). Returns true if
+    // our peer SACKed anything new.
+    fn process_sacks(&mut self, header: &TcpHeader, acked_new_data: bool) -> bool {
+        let send_unacked: SeqNumber = self.send_unacked.get();
+        let send_next: SeqNumber = self.send_next_seq_no.get();
+        let mut sacked_new_data: bool = false;
+        for option in header.iter_options() {
+            if let TcpOptions2::SelectiveAcknowlegement { num_sacks, sacks } = option {
+                sacked_new_data |= self
+                    .sack_scoreboard
+                    .update(send_unacked, send_next, &sacks[..*num_sacks]);
+            }
+        }
+        if self.sack_scoreboard.is_empty() {
+            return sacked_new_data;
+        }
+
+        if !acked_new_data && sacked_new_data {
+            self.sack_duplicate_ack_count += 1;
+        }
+
+        match self.sack_recovery_point {
+            Some(_) => {
+                // Any new information might let us send more during loss recovery.
+                if acked_new_data || sacked_new_data {
+                    self.sack_recovery_now.set(true);
+                }
+            },
+            None => {
+                if self.sack_duplicate_ack_count >= DUP_THRESH
+                    || self.sack_scoreboard.is_lost(send_unacked, self.get_mss())
+                {
+                    debug!(
+                        "process_sacks(): entering loss recovery (recovery_point={:?})",
+                        send_next
+                    );
+                    self.sack_recovery_point = Some(send_next);
+                    self.sack_high_rxt = send_unacked;
+                    // RFC 6675 Section 5, step 4.2: Congestion control sets ssthresh and cwnd.
+                    self.loss_response_pending = true;
+                    self.sack_recovery_now.set(true);
+                }
+            },
+        }
+        sacked_new_data
+    }
+
+    // Tells RACK about a segment that an ACK for `ack_num` removed from the unacknowledged queue, unless it was only
+    // partially acknowledged or RACK already heard about it through a SACK.
+    fn rack_on_acked(&mut self, segment: &UnackedSegment, segment_end: SeqNumber, ack_num: SeqNumber, now: Instant) {
+        if let Some(rack) = self.rack.as_mut() {
+            if !segment.sacked && segment_end <= ack_num {
+                rack.on_delivered(segment.xmit_ts, segment_end, segment.initial_tx.is_none(), now);
+            }
+        }
+    }
+
+    // Tells RACK about the segments that our peer SACKed for the first time. Nothing above the highest SACKed block can
+    // have been SACKed, so we stop there.
+    fn rack_on_sacked(&mut self, now: Instant) {
+        let (rack, highest_sacked): (&mut Rack, SeqNumber) =
+            match (self.rack.as_mut(), self.sack_scoreboard.highest_sacked()) {
+                (Some(rack), Some(highest_sacked)) => (rack, highest_sacked),
+                _ => return,
+            };
+        let mut segment_start: SeqNumber = self.send_unacked.get();
+        for segment in self.unacked_queue.get_mut_values() {
+            if segment_start >= highest_sacked {
+                break;
+            }
+            let segment_end: SeqNumber = segment_start + SeqNumber::from(segment.seq_len());
+            if !segment.sacked && self.sack_scoreboard.is_sacked(segment_start, segment_end) {
+                segment.sacked = true;
+                segment.lost = false;
+                rack.on_delivered(segment.xmit_ts, segment_end, segment.initial_tx.is_none(), now);
+            }
+            segment_start = segment_end;
+        }
+    }
+
+    // Marks as lost the segments that RACK expected our peer to have received by now, and enters loss recovery to
+    // retransmit them (RFC 8985 Section 6.2). Segments that may just have been reordered get until the reordering timer
+    // goes off. We first sent the segments in sequence order, so the scan stops at the first one that we have not
+    // retransmitted and that was sent after RACK.xmit_ts: we sent everything behind it later still.
+    fn detect_lost_segments(&mut self, now: Instant) {
+        let send_unacked: SeqNumber = self.send_unacked.get();
+        let in_recovery: bool = self.sack_recovery_point.is_some();
+        let sack_loss: bool = self.sack_scoreboard.is_lost(send_unacked, self.get_mss());
+        let rack: &Rack = match self.rack.as_ref() {
+            Some(rack) => rack,
+            None => return,
+        };
+        let reo_wnd: Duration = rack.reordering_window(in_recovery, sack_loss, self.rto_calculator.srtt());
+        let mut reorder_deadline: Option<Instant> = None;
+        let mut any_lost: bool = false;
+        let mut segment_start: SeqNumber = send_unacked;
+        for segment in self.unacked_queue.get_mut_values() {
+            let segment_end: SeqNumber = segment_start + SeqNumber::from(segment.seq_len());
+            if !segment.sacked && !segment.lost {
+                match rack.loss_deadline(segment.xmit_ts, segment_end, reo_wnd) {
+                    Some(deadline) if deadline <= now => segment.lost = true,
+                    Some(deadline) => reorder_deadline = cmp::max(reorder_deadline, Some(deadline)),
+                    None if segment.initial_tx.is_some() => break,
+                    None => (),
+                }
+            }
+            any_lost |= segment.lost;
+            segment_start = segment_end;
+        }
+        if reorder_deadline != self.rack_reorder_deadline.get() {
+            self.rack_reorder_deadline.set(reorder_deadline);
+        }
+
+        if !any_lost {
+            return;
+        }
+        if !in_recovery {
+            let send_next: SeqNumber = self.send_next_seq_no.get();
+            debug!(
+                "detect_lost_segments(): entering loss recovery (recovery_point={:?})",
+                send_next
+            );
+            self.sack_recovery_point = Some(send_next);
+            self.sack_high_rxt = send_unacked;
+            self.loss_response_pending = true;
+        }
+        self.sack_recovery_now.set(true);
+    }
+
+    // Checks whether an ACK tells us that our last tail loss probe repaired a loss, which congestion control has to
+    // respond to like any other (RFC 8985 Section 7.4). `new_information` is true if the ACK acknowledged or SACKed
+    // anything new.
+    fn process_loss_probe_ack(&mut self, header: &TcpHeader, new_information: bool) {
+        let tlp_end_seq: SeqNumber = match self.tlp_end_seq {
+            Some(tlp_end_seq) if header.ack_num >= tlp_end_seq => tlp_end_seq,
+            _ => return,
+        };
+        if first_sack_block(header).is_some_and(|block| block.end <= header.ack_num && block.end == tlp_end_seq) {
+            // A DSACK for the probe: our peer already had the segment that we probed with.
+            self.tlp_end_seq = None;
+        } else if header.ack_num > tlp_end_seq {
+            // Our peer never told us that it received the segment twice, so the probe must have repaired a loss.
+            debug!("process_loss_probe_ack(): tail loss probe repaired a loss");
+            self.tlp_end_seq = None;
+            self.loss_response_pending = true;
+            self.sack_recovery_now.set(true);
+        } else if !new_information {
+            // A duplicate ACK: both the original segment and the probe arrived.
+            self.tlp_end_seq = None;
         }
     }
 
@@ -530,11 +1537,16 @@ impl Sender {
         0
     }
 
-    fn process_acked_segment(&mut self, bytes_remaining: usize, mut segment: UnackedSegment, now: Instant) -> usize {
+    fn process_acked_segment(
+        &mut self,
+        bytes_remaining: usize,
+        mut segment: UnackedSegment,
+        now: Instant,
+        take_rtt_sample: bool,
+    ) -> usize {
         // Add sample for RTO if we have an initial transmit time.
         // Note that in the case of repacketization, an ack for the first byte is enough for the time sample because it still represents the RTO for that single byte.
-        // TODO: TCP timestamp support.
-        if let Some(initial_tx) = segment.initial_tx {
+        if let Some(initial_tx) = segment.initial_tx.filter(|_| take_rtt_sample) {
             self.rto_calculator.add_sample(now - initial_tx);
         }
 
@@ -542,6 +1554,9 @@ impl Sender {
             .bytes
             .take()
             .expect("there should be data because this is not a FIN.");
+        let bytes_acked: usize = cmp::min(data.len(), bytes_remaining);
+        self.delivery_rate
+            .on_segment_acked(&segment.delivery_state, bytes_acked as u32, now);
         if data.len() > bytes_remaining {
             // Put this segment on the unacknowledged list.
             let unacked_segment = UnackedSegment {
@@ -550,6 +1565,10 @@ impl Sender {
                         .expect("Should be able to split back because we just checked the length"),
                 ),
                 initial_tx: None,
+                delivery_state: segment.delivery_state,
+                xmit_ts: segment.xmit_ts,
+                lost: segment.lost,
+                sacked: false,
             };
             // Leave this segment on the unacknowledged queue.
             self.unacked_queue.push_front(unacked_segment);
@@ -562,13 +1581,10 @@ impl Sender {
     fn update_retransmit_deadline(&self, now: Instant) -> Option<Instant> {
         match self.unacked_queue.get_front() {
             Some(UnackedSegment {
-                bytes: _,
                 initial_tx: Some(initial_tx),
+                ..
             }) => Some(*initial_tx + self.rto_calculator.rto()),
-            Some(UnackedSegment {
-                bytes: _,
-                initial_tx: None,
-            }) => Some(now + self.rto_calculator.rto()),
+            Some(UnackedSegment { initial_tx: None, .. }) => Some(now + self.rto_calculator.rto()),
             None => None,
         }
     }
@@ -581,6 +1597,7 @@ impl Sender {
         {
             self.send_window
                 .set((header.window_size as u32) << self.send_window_scale_shift_bits);
+            self.send_window_max = cmp::max(self.send_window_max, self.send_window.get());
             self.send_window_last_update_seq = header.seq_num;
             self.send_window_last_update_ack = header.ack_num;
 
@@ -593,6 +1610,11 @@ impl Sender {
         }
     }
 
+    // Check whether this segment advertises a different window than the one we're currently using.
+    pub fn is_window_update(&self, header: &TcpHeader) -> bool {
+        (header.window_size as u32) << self.send_window_scale_shift_bits != self.send_window.get()
+    }
+
     // Get SD.UNA.
     pub fn get_unacked_seq_no(&self) -> SeqNumber {
         self.send_unacked.get()
@@ -607,4 +1629,133 @@ impl Sender {
     pub fn get_rto(&self) -> Duration {
         self.rto_calculator.rto()
     }
+
+    // Get the smoothed RTT, if we have measured one yet.
+    pub fn get_srtt(&self) -> Option<Duration> {
+        self.rto_calculator.srtt()
+    }
+
+    // Get the round-trip time variation.
+    pub fn get_rttvar(&self) -> Duration {
+        self.rto_calculator.rttvar()
+    }
+
+    // Get the window that our peer advertised.  In RFC 793 terms, this is SND.WND.
+    pub fn get_send_window(&self) -> u32 {
+        self.send_window.get()
+    }
+
+    // Get the number of bytes that we estimate to be in the network.  This is the pipe of RFC 6675, which is just
+    // SND.NXT - SND.UNA until our peer SACKs some data.
+    pub fn get_bytes_in_flight(&self) -> u32 {
+        self.get_pipe()
+    }
+
+    // Get the number of segments that we retransmitted.
+    pub fn get_total_retransmits(&self) -> u32 {
+        self.total_retransmits
+    }
+
+    // Get the largest window that our peer has advertised.  In RFC 5961 terms, this is MAX.SND.WND.
+    pub fn get_send_window_max(&self) -> u32 {
+        self.send_window_max
+    }
+
+    // Get the MSS currently in use for sending.
+    pub fn get_mss(&self) -> usize {
+        self.path_mtu.get_mss()
+    }
+}
+
+// Returns the first SACK block that `header` carries, which is where our peer reports duplicate segments (RFC 2883).
+fn first_sack_block(header: &TcpHeader) -> Option<SelectiveAcknowlegement> {
+    header.iter_options().find_map(|option| match option {
+        TcpOptions2::SelectiveAcknowlegement { num_sacks, sacks } if *num_sacks > 0 => Some(sacks[0]),
+        _ => None,
+    })
+}
+
+// Copies `parts`, which hold `total_len` bytes altogether, into a single buffer with room for the headers.
+fn concatenate(parts: Vec<DemiBuffer>, total_len: usize) -> DemiBuffer {
+    let mut buffer: DemiBuffer = DemiBuffer::new_with_headroom(total_len as u16, MAX_HEADER_SIZE as u16);
+    let mut offset: usize = 0;
+    for part in parts {
+        buffer[offset..(offset + part.len())].copy_from_slice(&part[..]);
+        offset += part.len();
+    }
+    debug_assert_eq!(offset, total_len);
+    buffer
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Sender;
+    use crate::{
+        inetstack::protocols::layer4::tcp::{established::plpmtud::PathMtuDiscovery, SeqNumber},
+        runtime::{
+            fail::Fail,
+            memory::DemiBuffer,
+            network::{
+                config::{MtuProbing, TcpConfig},
+                socket::option::TcpSocketOptions,
+            },
+            SharedObject,
+        },
+    };
+    use ::anyhow::Result;
+    use ::futures::task::noop_waker_ref;
+    use ::std::{future::Future, pin::Pin, task::Context, time::Instant};
+
+    type Push = Pin<Box<dyn Future<Output = Result<(), Fail>>>>;
+
+    // Test that pushes waiting for room in the send buffer go in order, and that a push that is cancelled while it
+    // waits gives up its turn, whether or not its turn has come.
+    #[test]
+    fn cancelled_push_gives_up_its_turn() -> Result<()> {
+        let mut ctx: Context = Context::from_waker(noop_waker_ref());
+        let mut sender: SharedObject<Sender> = SharedObject::new(Sender::new(
+            SeqNumber::from(0),
+            65535,
+            0,
+            PathMtuDiscovery::new(MtuProbing::Disabled, 1460, 1460),
+            &TcpSocketOptions::default(),
+            &TcpConfig::default(),
+            Instant::now(),
+        ));
+        sender.set_send_buffer_size(32);
+
+        // The first push fills the send buffer and the others wait in line.
+        let mut pushes: Vec<Push> = (0..5).map(|_| push(&sender, 32)).collect();
+        for push in pushes.iter_mut() {
+            crate::ensure_eq!(push.as_mut().poll(&mut ctx).is_pending(), true);
+        }
+        crate::ensure_eq!(u32::from(sender.unsent_next_seq_no.get()), 32);
+
+        // Cancel the second push, whose turn it is, and the fourth one, whose turn hasn't come yet.
+        drop(pushes.remove(3));
+        drop(pushes.remove(1));
+
+        // Room for another push goes to the third one, and then to the fifth one.
+        sender.set_send_buffer_size(64);
+        poll_all(&mut pushes, &mut ctx)?;
+        crate::ensure_eq!(u32::from(sender.unsent_next_seq_no.get()), 64);
+        sender.set_send_buffer_size(96);
+        poll_all(&mut pushes, &mut ctx)?;
+        crate::ensure_eq!(u32::from(sender.unsent_next_seq_no.get()), 96);
+
+        Ok(())
+    }
+
+    fn push(sender: &SharedObject<Sender>, len: usize) -> Push {
+        let mut sender: SharedObject<Sender> = sender.clone();
+        Box::pin(async move { sender.push(DemiBuffer::new(len as u16)).await })
+    }
+
+    fn poll_all(pushes: &mut [Push], ctx: &mut Context) -> Result<()> {
+        for push in pushes.iter_mut() {
+            // None of the pushes get acknowledged.
+            crate::ensure_eq!(push.as_mut().poll(ctx).is_pending(), true);
+        }
+        Ok(())
+    }
 }
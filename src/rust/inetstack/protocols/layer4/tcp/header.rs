@@ -15,6 +15,7 @@ use ::std::{
 pub const MIN_TCP_HEADER_SIZE: usize = 20;
 pub const MAX_TCP_HEADER_SIZE: usize = 60;
 pub const MAX_TCP_OPTIONS: usize = 5;
+pub const MAX_FAST_OPEN_COOKIE_SIZE: usize = 16;
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct SelectiveAcknowlegement {
@@ -22,6 +23,33 @@ pub struct SelectiveAcknowlegement {
     pub end: SeqNumber,
 }
 
+/// A TCP Fast Open cookie (RFC 7413 Section 4.1.1). An empty cookie asks the server for one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct FastOpenCookie {
+    len: usize,
+    bytes: [u8; MAX_FAST_OPEN_COOKIE_SIZE],
+}
+
+impl FastOpenCookie {
+    pub fn new(cookie: &[u8]) -> Self {
+        debug_assert!(cookie.len() <= MAX_FAST_OPEN_COOKIE_SIZE);
+        let mut bytes: [u8; MAX_FAST_OPEN_COOKIE_SIZE] = [0; MAX_FAST_OPEN_COOKIE_SIZE];
+        bytes[..cookie.len()].copy_from_slice(cookie);
+        Self {
+            len: cookie.len(),
+            bytes,
+        }
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum TcpOptions2 {
     EndOfOptionsList,
@@ -37,6 +65,7 @@ pub enum TcpOptions2 {
         sender_timestamp: u32,
         echo_timestamp: u32,
     },
+    FastOpen(FastOpenCookie),
 }
 
 impl TcpOptions2 {
@@ -50,6 +79,7 @@ impl TcpOptions2 {
             SelectiveAcknowlegementPermitted => 2,
             SelectiveAcknowlegement { num_sacks, .. } => 2 + 8 * num_sacks,
             Timestamp { .. } => 10,
+            FastOpen(cookie) => 2 + cookie.as_slice().len(),
         }
     }
 
@@ -97,11 +127,18 @@ impl TcpOptions2 {
                 buf[6..10].copy_from_slice(&echo_timestamp.to_be_bytes());
                 10
             },
+            FastOpen(cookie) => {
+                let len: usize = 2 + cookie.as_slice().len();
+                buf[0] = 34;
+                buf[1] = len as u8;
+                buf[2..len].copy_from_slice(cookie.as_slice());
+                len
+            },
         }
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct TcpHeader {
     pub src_port: u16,
     pub dst_port: u16,
@@ -291,6 +328,20 @@ impl TcpHeader {
                             echo_timestamp,
                         }
                     },
+                    34 => {
+                        let mut temp: [u8; 1] = [0; 1];
+                        option_rdr.read_exact(&mut temp)?;
+                        let option_length: u8 = temp[0];
+                        // The cookie is either empty, or between 4 and 16 bytes long, in steps of two.
+                        let cookie_length: usize = match (option_length as usize).checked_sub(2) {
+                            Some(0) => 0,
+                            Some(len) if (4..=MAX_FAST_OPEN_COOKIE_SIZE).contains(&len) && len % 2 == 0 => len,
+                            _ => return Err(Fail::new(EBADMSG, "invalid TCP Fast Open cookie size")),
+                        };
+                        let mut cookie: [u8; MAX_FAST_OPEN_COOKIE_SIZE] = [0; MAX_FAST_OPEN_COOKIE_SIZE];
+                        option_rdr.read_exact(&mut cookie[..cookie_length])?;
+                        TcpOptions2::FastOpen(FastOpenCookie::new(&cookie[..cookie_length]))
+                    },
                     _ => return Err(Fail::new(EBADMSG, "invalid TCP option")),
                 };
                 if num_options >= option_list.len() {
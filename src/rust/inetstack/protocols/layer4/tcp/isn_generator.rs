@@ -1,20 +1,30 @@
-// Copyright (c) Microsoft Corporation.
-// Licensed under the MIT license.
-
-use crate::inetstack::protocols::layer4::tcp::SeqNumber;
-#[allow(unused_imports)]
-use std::{hash::Hasher, net::SocketAddrV4, num::Wrapping};
+use crate::inetstack::protocols::layer4::tcp::{header::FastOpenCookie, SeqNumber};
+#[allow(deprecated)]
+use std::hash::SipHasher;
+use std::{
+    hash::Hasher,
+    net::{Ipv4Addr, SocketAddrV4},
+    num::Wrapping,
+};
 
 #[allow(dead_code)]
 pub struct IsnGenerator {
-    nonce: u32,
+    // Key of the pseudo-random function that our ISNs and cookies are built on, which must stay secret for the cookies
+    // to be unforgeable.
+    secret: u128,
     counter: Wrapping<u16>,
 }
 
+// The standard library deprecates SipHasher in favor of hashers for hash tables, which don't let us pick the key.
+#[allow(deprecated)]
 impl IsnGenerator {
-    pub fn new(nonce: u32) -> Self {
+    const COOKIE_COUNTER_MASK: u32 = 0x1f;
+    const COOKIE_DATA_MASK: u8 = 0x7;
+    const COOKIE_HASH_MASK: u32 = 0x00ff_ffff;
+
+    pub fn new(secret: u128) -> Self {
         Self {
-            nonce,
+            secret,
             counter: Wrapping(0),
         }
     }
@@ -24,20 +34,214 @@ impl IsnGenerator {
         SeqNumber::from(0)
     }
 
+    /// Generates an ISN from a keyed hash of the connection's addresses and a counter (RFC 6528 Section 3).
     #[cfg(not(test))]
     pub fn generate(&mut self, local: &SocketAddrV4, remote: &SocketAddrV4) -> SeqNumber {
-        let crc: crc::Crc<u32> = crc::Crc::<u32>::new(&crc::CRC_32_CKSUM);
-        let mut digest = crc.digest();
-        digest.update(&remote.ip().octets());
-        let remote_port: u16 = remote.port().into();
-        digest.update(&remote_port.to_be_bytes());
-        digest.update(&local.ip().octets());
-        let local_port: u16 = local.port().into();
-        digest.update(&local_port.to_be_bytes());
-        digest.update(&self.nonce.to_be_bytes());
-        let digest = digest.finalize();
-        let isn = SeqNumber::from(digest + self.counter.0 as u32);
+        let mut hasher: SipHasher = self.hasher();
+        hasher.write(&remote.ip().octets());
+        hasher.write(&remote.port().to_be_bytes());
+        hasher.write(&local.ip().octets());
+        hasher.write(&local.port().to_be_bytes());
+        let isn = SeqNumber::from((hasher.finish() as u32).wrapping_add(self.counter.0 as u32));
         self.counter += Wrapping(1);
         isn
     }
+
+    /// Generates a SYN cookie (RFC 4987 Section 3.6) to use as our ISN for a connection that we keep no state for.
+    /// The cookie carries `data` (3 bits) and the low bits of `counter`, a clock that should tick about once a minute.
+    /// The layout is the traditional one: 5 bits of the counter, then the 3 bits of data, and then 24 bits of a hash of
+    /// the connection's addresses, the peer's ISN and the counter.
+    pub fn generate_cookie(
+        &self,
+        local: &SocketAddrV4,
+        remote: &SocketAddrV4,
+        remote_isn: SeqNumber,
+        data: u8,
+        counter: u32,
+    ) -> SeqNumber {
+        debug_assert!(data <= Self::COOKIE_DATA_MASK);
+        let hash: u32 = self.cookie_hash(local, remote, remote_isn, counter);
+        SeqNumber::from(((counter & Self::COOKIE_COUNTER_MASK) << 27) | ((data as u32) << 24) | hash)
+    }
+
+    /// Checks a SYN cookie that we generated no more than one `counter` tick ago, and returns the data that it carries.
+    pub fn check_cookie(
+        &self,
+        local: &SocketAddrV4,
+        remote: &SocketAddrV4,
+        remote_isn: SeqNumber,
+        cookie: SeqNumber,
+        counter: u32,
+    ) -> Option<u8> {
+        let cookie: u32 = cookie.into();
+        let cookie_counter: u32 = cookie >> 27;
+        let data: u8 = ((cookie >> 24) as u8) & Self::COOKIE_DATA_MASK;
+        for counter in [counter, counter.wrapping_sub(1)] {
+            if counter & Self::COOKIE_COUNTER_MASK == cookie_counter
+                && self.cookie_hash(local, remote, remote_isn, counter) == cookie & Self::COOKIE_HASH_MASK
+            {
+                return Some(data);
+            }
+        }
+        None
+    }
+
+    /// Generates the TCP Fast Open cookie (RFC 7413 Section 4.1.2) that we give to a client, so that it can send data
+    /// in the SYN of later connections. The cookie is a 64-bit keyed hash of the client's and our own addresses.
+    pub fn generate_fast_open_cookie(&self, local: &Ipv4Addr, remote: &Ipv4Addr) -> FastOpenCookie {
+        FastOpenCookie::new(&self.fast_open_cookie_hash(local, remote).to_be_bytes())
+    }
+
+    /// Checks that a client presents the TCP Fast Open cookie that we gave to it.
+    pub fn check_fast_open_cookie(&self, local: &Ipv4Addr, remote: &Ipv4Addr, cookie: &FastOpenCookie) -> bool {
+        self.generate_fast_open_cookie(local, remote) == *cookie
+    }
+
+    fn fast_open_cookie_hash(&self, local: &Ipv4Addr, remote: &Ipv4Addr) -> u64 {
+        let mut hasher: SipHasher = self.hasher();
+        hasher.write(&remote.octets());
+        hasher.write(&local.octets());
+        hasher.finish()
+    }
+
+    fn cookie_hash(&self, local: &SocketAddrV4, remote: &SocketAddrV4, remote_isn: SeqNumber, counter: u32) -> u32 {
+        let mut hasher: SipHasher = self.hasher();
+        hasher.write(&remote.ip().octets());
+        hasher.write(&remote.port().to_be_bytes());
+        hasher.write(&local.ip().octets());
+        hasher.write(&local.port().to_be_bytes());
+        hasher.write(&u32::from(remote_isn).to_be_bytes());
+        hasher.write(&counter.to_be_bytes());
+        (hasher.finish() as u32) & Self::COOKIE_HASH_MASK
+    }
+
+    /// Returns a hasher for SipHash-2-4 keyed with our secret. Unlike a CRC, this is a pseudo-random function, so seeing
+    /// the cookies that we give out doesn't let an attacker work out the ones that we would give to others.
+    fn hasher(&self) -> SipHasher {
+        SipHasher::new_with_keys(self.secret as u64, (self.secret >> 64) as u64)
+    }
+}
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::IsnGenerator;
+    use crate::inetstack::protocols::layer4::tcp::{header::FastOpenCookie, SeqNumber};
+    use ::anyhow::Result;
+    use ::std::net::{Ipv4Addr, SocketAddrV4};
+
+    // Test that a SYN cookie is accepted during its own and the following tick, but not for another connection or later.
+    #[test]
+    fn syn_cookie() -> Result<()> {
+        let generator: IsnGenerator = IsnGenerator::new(0x0123_4567_89ab_cdef_0123_4567_89ab_cdef);
+        let local: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::new(192, 168, 1, 1), 80);
+        let remote: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::new(192, 168, 1, 2), 50000);
+        let remote_isn: SeqNumber = SeqNumber::from(1000);
+        let counter: u32 = 31;
+
+        let cookie: SeqNumber = generator.generate_cookie(&local, &remote, remote_isn, 5, counter);
+        crate::ensure_eq!(
+            generator.check_cookie(&local, &remote, remote_isn, cookie, counter),
+            Some(5)
+        );
+        crate::ensure_eq!(
+            generator.check_cookie(&local, &remote, remote_isn, cookie, counter + 1),
+            Some(5)
+        );
+        crate::ensure_eq!(
+            generator.check_cookie(&local, &remote, remote_isn, cookie, counter + 2),
+            None
+        );
+
+        // The cookie is bound to the connection.
+        let other: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::new(192, 168, 1, 2), 50001);
+        crate::ensure_eq!(
+            generator.check_cookie(&local, &other, remote_isn, cookie, counter),
+            None
+        );
+        let other_isn: SeqNumber = SeqNumber::from(2000);
+        crate::ensure_eq!(
+            generator.check_cookie(&local, &remote, other_isn, cookie, counter),
+            None
+        );
+
+        // And to our secret, even in how the cookies of two connections differ, which would let an attacker who sees
+        // one cookie work out the other if the hash was linear.
+        let other_generator: IsnGenerator = IsnGenerator::new(0xfedc_ba98_7654_3210_fedc_ba98_7654_3210);
+        crate::ensure_eq!(
+            other_generator.check_cookie(&local, &remote, remote_isn, cookie, counter),
+            None
+        );
+        let difference = |generator: &IsnGenerator| -> u32 {
+            u32::from(generator.generate_cookie(&local, &remote, remote_isn, 5, counter))
+                ^ u32::from(generator.generate_cookie(&local, &other, remote_isn, 5, counter))
+        };
+        crate::ensure_eq!(difference(&generator) == difference(&other_generator), false);
+
+        Ok(())
+    }
+
+    // Test that a Fast Open cookie is only accepted from the client that we gave it to.
+    #[test]
+    fn fast_open_cookie() -> Result<()> {
+        let generator: IsnGenerator = IsnGenerator::new(0x0123_4567_89ab_cdef_0123_4567_89ab_cdef);
+        let local: Ipv4Addr = Ipv4Addr::new(192, 168, 1, 1);
+        let remote: Ipv4Addr = Ipv4Addr::new(192, 168, 1, 2);
+
+        let cookie: FastOpenCookie = generator.generate_fast_open_cookie(&local, &remote);
+        crate::ensure_eq!(cookie.as_slice().len(), 8);
+        crate::ensure_eq!(generator.check_fast_open_cookie(&local, &remote, &cookie), true);
+
+        let other: Ipv4Addr = Ipv4Addr::new(192, 168, 1, 3);
+        crate::ensure_eq!(generator.check_fast_open_cookie(&local, &other, &cookie), false);
+        crate::ensure_eq!(
+            generator.check_fast_open_cookie(&local, &remote, &FastOpenCookie::new(&[])),
+            false
+        );
+
+        let other_generator: IsnGenerator = IsnGenerator::new(0xfedc_ba98_7654_3210_fedc_ba98_7654_3210);
+        crate::ensure_eq!(other_generator.check_fast_open_cookie(&local, &remote, &cookie), false);
+
+        Ok(())
+    }
+
+    // Test that a client can't turn its own Fast Open cookie into one for a neighboring address by flipping bits, as it
+    // could if the cookie was a CRC.
+    #[test]
+    fn fast_open_cookie_unforgeable() -> Result<()> {
+        let generator: IsnGenerator = IsnGenerator::new(0x0123_4567_89ab_cdef_0123_4567_89ab_cdef);
+        let local: Ipv4Addr = Ipv4Addr::new(192, 168, 1, 1);
+        let remote: Ipv4Addr = Ipv4Addr::new(192, 168, 1, 2);
+        let other: Ipv4Addr = Ipv4Addr::new(192, 168, 1, 3);
+
+        let cookie: u64 = u64::from_be_bytes(
+            generator
+                .generate_fast_open_cookie(&local, &remote)
+                .as_slice()
+                .try_into()?,
+        );
+        for bit in 0..u64::BITS {
+            let forged: FastOpenCookie = FastOpenCookie::new(&(cookie ^ (1 << bit)).to_be_bytes());
+            crate::ensure_eq!(generator.check_fast_open_cookie(&local, &other, &forged), false);
+        }
+
+        // Nor do the cookies of two clients differ in a way that doesn't depend on our secret.
+        let other_generator: IsnGenerator = IsnGenerator::new(0xfedc_ba98_7654_3210_fedc_ba98_7654_3210);
+        let difference = |generator: &IsnGenerator| -> Vec<u8> {
+            let cookie: FastOpenCookie = generator.generate_fast_open_cookie(&local, &remote);
+            let other_cookie: FastOpenCookie = generator.generate_fast_open_cookie(&local, &other);
+            cookie
+                .as_slice()
+                .iter()
+                .zip(other_cookie.as_slice())
+                .map(|(a, b)| a ^ b)
+                .collect()
+        };
+        crate::ensure_eq!(difference(&generator) == difference(&other_generator), false);
+
+        Ok(())
+    }
 }
@@ -10,6 +10,8 @@ mod passive_open;
 pub mod peer;
 mod sequence_number;
 pub mod socket;
+mod time_wait;
+mod timestamps;
 
 #[cfg(test)]
 mod tests;
@@ -12,15 +12,13 @@ use crate::{
     },
     expect_some,
     inetstack::protocols::{
-        layer3::SharedLayer3Endpoint,
+        layer3::{Ipv4Header, SharedLayer3Endpoint},
         layer4::tcp::{
             constants::FALLBACK_MSS,
-            established::{
-                congestion_control::{self, CongestionControl},
-                EstablishedSocket,
-            },
-            header::{TcpHeader, TcpOptions2},
+            established::EstablishedSocket,
+            header::{FastOpenCookie, TcpHeader, TcpOptions2},
             isn_generator::IsnGenerator,
+            timestamps::{find_timestamps, TcpTimestamps},
             SeqNumber,
         },
         MAX_HEADER_SIZE,
@@ -29,7 +27,11 @@ use crate::{
         conditional_yield_with_timeout,
         fail::Fail,
         memory::DemiBuffer,
-        network::{config::TcpConfig, consts::MAX_WINDOW_SCALE, socket::option::TcpSocketOptions},
+        network::{
+            config::{Ecn, TcpConfig},
+            consts::MAX_WINDOW_SCALE,
+            socket::option::TcpSocketOptions,
+        },
         QDesc, SharedDemiRuntime, SharedObject,
     },
     QToken,
@@ -37,12 +39,23 @@ use crate::{
 use ::futures::{channel::mpsc, FutureExt};
 use ::libc::{EBADMSG, ETIMEDOUT};
 use ::std::{
+    cmp,
     collections::HashMap,
     net::{Ipv4Addr, SocketAddrV4},
     ops::{Deref, DerefMut},
-    time::Duration,
+    time::{Duration, Instant},
 };
 
+//======================================================================================================================
+// Constants
+//======================================================================================================================
+
+// MSS values that a SYN cookie can encode. We use the largest one that does not exceed the MSS that our peer advertised.
+const SYN_COOKIE_MSS: [u16; 8] = [536, 1024, 1220, 1300, 1400, 1440, 1460, 8960];
+
+// How often the SYN cookie counter ticks. A cookie stays valid until the counter has ticked twice.
+const SYN_COOKIE_PERIOD: Duration = Duration::from_secs(64);
+
 //======================================================================================================================
 // Structures
 //======================================================================================================================
@@ -56,13 +69,28 @@ enum State {
     Closed,
 }
 
+/// What our peer offered in its SYN and we agreed to.
+#[derive(Clone, Copy)]
+struct SynOptions {
+    remote_window_scale: Option<u8>,
+    mss: usize,
+    sack_permitted: bool,
+    timestamps: Option<TcpTimestamps>,
+    ecn: bool,
+}
+
 pub struct PassiveSocket {
     // TCP Connection State.
     state: SharedAsyncValue<State>,
-    connections: HashMap<SocketAddrV4, SharedAsyncQueue<(Ipv4Addr, TcpHeader, DemiBuffer)>>,
-    recv_queue: SharedAsyncQueue<(Ipv4Addr, TcpHeader, DemiBuffer)>,
+    connections: HashMap<SocketAddrV4, SharedAsyncQueue<(Ipv4Header, TcpHeader, DemiBuffer)>>,
+    recv_queue: SharedAsyncQueue<(Ipv4Header, TcpHeader, DemiBuffer)>,
     ready: AsyncQueue<Result<EstablishedSocket, Fail>>,
     max_backlog: usize,
+    // Number of connections in the middle of the handshake.
+    syn_queue_len: usize,
+    // Start of the SYN cookie counter, and the last time that we sent a SYN cookie.
+    syn_cookie_epoch: Instant,
+    last_syn_cookie: Option<Instant>,
     isn_generator: IsnGenerator,
     local: SocketAddrV4,
     runtime: SharedDemiRuntime,
@@ -88,21 +116,24 @@ impl SharedPassiveSocket {
         local: SocketAddrV4,
         max_backlog: usize,
         mut runtime: SharedDemiRuntime,
-        recv_queue: SharedAsyncQueue<(Ipv4Addr, TcpHeader, DemiBuffer)>,
+        recv_queue: SharedAsyncQueue<(Ipv4Header, TcpHeader, DemiBuffer)>,
         layer3_endpoint: SharedLayer3Endpoint,
         tcp_config: TcpConfig,
         default_socket_options: TcpSocketOptions,
         dead_socket_tx: mpsc::UnboundedSender<QDesc>,
-        nonce: u32,
+        secret: u128,
     ) -> Result<Self, Fail> {
         let socket_queue: SharedAsyncQueue<SocketAddrV4> = SharedAsyncQueue::<SocketAddrV4>::default();
         let mut me: Self = Self(SharedObject::<PassiveSocket>::new(PassiveSocket {
             state: SharedAsyncValue::new(State::Listening),
-            connections: HashMap::<SocketAddrV4, SharedAsyncQueue<(Ipv4Addr, TcpHeader, DemiBuffer)>>::new(),
+            connections: HashMap::<SocketAddrV4, SharedAsyncQueue<(Ipv4Header, TcpHeader, DemiBuffer)>>::new(),
             recv_queue,
             ready: AsyncQueue::<Result<EstablishedSocket, Fail>>::default(),
             max_backlog,
-            isn_generator: IsnGenerator::new(nonce),
+            syn_queue_len: 0,
+            syn_cookie_epoch: runtime.get_now(),
+            last_syn_cookie: None,
+            isn_generator: IsnGenerator::new(secret),
             local,
             runtime: runtime.clone(),
             layer3_endpoint,
@@ -123,6 +154,11 @@ impl SharedPassiveSocket {
         self.local
     }
 
+    /// Sets the socket options that connections accepted from now on start with.
+    pub fn set_socket_options(&mut self, socket_options: TcpSocketOptions) {
+        self.socket_options = socket_options;
+    }
+
     /// Accept a new connection by fetching one from the queue of requests, blocking if there are no new requests.
     pub async fn do_accept(&mut self) -> Result<EstablishedSocket, Fail> {
         self.ready.pop(None).await?
@@ -137,7 +173,7 @@ impl SharedPassiveSocket {
     async fn poll(mut self) {
         loop {
             let mut socket_queue: SharedAsyncQueue<SocketAddrV4> = self.socket_queue.clone();
-            let mut recv_queue: SharedAsyncQueue<(Ipv4Addr, TcpHeader, DemiBuffer)> = self.recv_queue.clone();
+            let mut recv_queue: SharedAsyncQueue<(Ipv4Header, TcpHeader, DemiBuffer)> = self.recv_queue.clone();
             let mut state: SharedAsyncValue<State> = self.state.clone();
             // Remove sockets that have been closed.
             futures::select! {
@@ -150,16 +186,22 @@ impl SharedPassiveSocket {
                 },
                 result = recv_queue.pop(None).fuse() => {
                     match result {
-                        Ok((ipv4_addr, tcp_hdr, buf)) =>  {
-                                    let remote: SocketAddrV4 = SocketAddrV4::new(ipv4_addr, tcp_hdr.src_port);
+                        Ok((ipv4_hdr, tcp_hdr, buf)) =>  {
+                                    let remote: SocketAddrV4 = SocketAddrV4::new(ipv4_hdr.get_src_addr(), tcp_hdr.src_port);
                                     if let Some(recv_queue) = self.connections.get_mut(&remote) {
                                         // Packet is either for an inflight request or established connection.
-                                        recv_queue.push((ipv4_addr, tcp_hdr, buf));
+                                        recv_queue.push((ipv4_hdr, tcp_hdr, buf));
                                         continue;
                                     }
 
-                                    // If not a SYN, then this packet is not for a new connection and we throw it away.
+                                    // If not a SYN, then this packet is not for a new connection and we throw it away, unless
+                                    // it completes a handshake that we answered with a SYN cookie.
                                     if !tcp_hdr.syn || tcp_hdr.ack || tcp_hdr.rst {
+                                        if let Some(mss) = self.check_syn_cookie(&remote, &tcp_hdr) {
+                                            self.complete_syn_cookie_handshake(ipv4_hdr, remote, tcp_hdr, buf, mss);
+                                            continue;
+                                        }
+
                                         let cause: String = format!(
                                             "invalid TCP flags (syn={}, ack={}, rst={})",
                                             tcp_hdr.syn, tcp_hdr.ack, tcp_hdr.rst
@@ -169,18 +211,8 @@ impl SharedPassiveSocket {
                                         continue;
                                     }
 
-                                    // Check if this SYN segment carries any data.
-                                    if !buf.is_empty() {
-                                        // RFC 793 allows connections to be established with data-carrying segments, but we do not support this.
-                                        // We simply drop the data and and proceed with the three-way handshake protocol, on the hope that the
-                                        // remote will retransmit the data after the connection is established.
-                                        // See: https://datatracker.ietf.org/doc/html/rfc793#section-3.4 fo more details.
-                                        warn!("Received SYN with data (len={})", buf.len());
-                                        // TODO: https://github.com/microsoft/demikernel/issues/1115
-                                    }
-
                                     // Start a new connection.
-                                    self.handle_new_syn(remote, tcp_hdr);
+                                    self.handle_new_syn(remote, tcp_hdr, buf);
                         }
                         Err(_) => continue,
                     }
@@ -194,15 +226,13 @@ impl SharedPassiveSocket {
         }
     }
 
-    fn handle_new_syn(&mut self, remote: SocketAddrV4, tcp_hdr: TcpHeader) {
+    fn handle_new_syn(&mut self, remote: SocketAddrV4, tcp_hdr: TcpHeader, buf: DemiBuffer) {
         debug!("Received SYN: {:?}", tcp_hdr);
-        let inflight_len: usize = self.connections.len();
-        // Check backlog. Since we might receive data even on connections that have completed their handshake, all
-        // ready sockets are also in the inflight table.
-        if inflight_len >= self.max_backlog {
+        // Check backlog of connections waiting to be accepted.
+        if self.ready.len() >= self.max_backlog {
             let cause: String = format!(
                 "backlog full (inflight={}, ready={}, backlog={})",
-                inflight_len,
+                self.syn_queue_len,
                 self.ready.len(),
                 self.max_backlog
             );
@@ -211,17 +241,33 @@ impl SharedPassiveSocket {
             return;
         }
 
+        // Check backlog of connections in the middle of the handshake. Once it is full, we stop keeping state for new
+        // connection requests and answer them with SYN cookies instead, so that a SYN flood can't exhaust our memory.
+        if self.syn_queue_len >= self.tcp_config.get_syn_backlog() {
+            if self.tcp_config.get_syn_cookies() {
+                self.send_syn_cookie(remote, tcp_hdr);
+            } else {
+                let cause: String = format!(
+                    "SYN backlog full (inflight={}, syn_backlog={})",
+                    self.syn_queue_len,
+                    self.tcp_config.get_syn_backlog()
+                );
+                warn!("handle_new_syn(): {}", cause);
+            }
+            return;
+        }
+
         // Send SYN+ACK.
         let local: SocketAddrV4 = self.local.clone();
         let local_isn = self.isn_generator.generate(&local, &remote);
         let remote_isn = tcp_hdr.seq_num;
 
         // Allocate a new coroutine to send the SYN+ACK and retry if necessary.
-        let recv_queue: SharedAsyncQueue<(Ipv4Addr, TcpHeader, DemiBuffer)> =
-            SharedAsyncQueue::<(Ipv4Addr, TcpHeader, DemiBuffer)>::default();
+        let recv_queue: SharedAsyncQueue<(Ipv4Header, TcpHeader, DemiBuffer)> =
+            SharedAsyncQueue::<(Ipv4Header, TcpHeader, DemiBuffer)>::default();
         let future = self
             .clone()
-            .send_syn_ack_and_wait_for_ack(remote, remote_isn, local_isn, tcp_hdr, recv_queue.clone())
+            .send_syn_ack_and_wait_for_ack(remote, remote_isn, local_isn, tcp_hdr, buf, recv_queue.clone())
             .fuse();
         match self
             .runtime
@@ -236,6 +282,131 @@ impl SharedPassiveSocket {
         };
         // TODO: Clean up the connections table once we have merged all of the routing tables into one.
         self.connections.insert(remote, recv_queue);
+        self.syn_queue_len += 1;
+    }
+
+    /// Answers a SYN with a SYN cookie, without keeping any state for the connection.
+    fn send_syn_cookie(&mut self, remote: SocketAddrV4, tcp_hdr: TcpHeader) {
+        // The cookie only has room for the MSS, so the connection goes without window scaling, SACK, timestamps or
+        // ECN.
+        let mss: usize = tcp_hdr
+            .iter_options()
+            .find_map(|option| match option {
+                TcpOptions2::MaximumSegmentSize(mss) => Some(*mss as usize),
+                _ => None,
+            })
+            .unwrap_or(FALLBACK_MSS);
+        let mss_index: usize = SYN_COOKIE_MSS
+            .iter()
+            .rposition(|cookie_mss| *cookie_mss as usize <= mss)
+            .unwrap_or(0);
+
+        let now: Instant = self.runtime.get_now();
+        let local_isn: SeqNumber = self.isn_generator.generate_cookie(
+            &self.local,
+            &remote,
+            tcp_hdr.seq_num,
+            mss_index as u8,
+            self.syn_cookie_counter(now),
+        );
+        self.last_syn_cookie = Some(now);
+
+        let syn_ack: TcpHeader = self.syn_ack_header(local_isn, tcp_hdr.seq_num, remote);
+        debug!("Sending SYN+ACK with SYN cookie: {:?}", syn_ack);
+        let mut pkt: DemiBuffer = DemiBuffer::new_with_headroom(0, MAX_HEADER_SIZE as u16);
+        syn_ack.serialize_and_attach(
+            &mut pkt,
+            self.local.ip(),
+            remote.ip(),
+            self.tcp_config.get_tx_checksum_offload(),
+        );
+        if let Err(e) = self.layer3_endpoint.transmit_tcp_packet_nonblocking(*remote.ip(), pkt) {
+            warn!("Could not send SYN+ACK: {:?}", e);
+        }
+    }
+
+    /// Checks if [tcp_hdr] completes a handshake that we answered with a SYN cookie, and returns the MSS that the cookie
+    /// carries if so.
+    fn check_syn_cookie(&self, remote: &SocketAddrV4, tcp_hdr: &TcpHeader) -> Option<usize> {
+        if !self.tcp_config.get_syn_cookies() || !tcp_hdr.ack || tcp_hdr.syn || tcp_hdr.rst {
+            return None;
+        }
+
+        // Only accept cookies while we may have sent some, so that guessing them is pointless the rest of the time.
+        let now: Instant = self.runtime.get_now();
+        match self.last_syn_cookie {
+            Some(last_syn_cookie) if now - last_syn_cookie < 2 * SYN_COOKIE_PERIOD => (),
+            _ => return None,
+        }
+
+        let remote_isn: SeqNumber = tcp_hdr.seq_num - SeqNumber::from(1);
+        let local_isn: SeqNumber = tcp_hdr.ack_num - SeqNumber::from(1);
+        let mss_index: u8 = self.isn_generator.check_cookie(
+            &self.local,
+            remote,
+            remote_isn,
+            local_isn,
+            self.syn_cookie_counter(now),
+        )?;
+        Some(SYN_COOKIE_MSS[mss_index as usize] as usize)
+    }
+
+    /// Rebuilds the connection for a handshake that we answered with a SYN cookie.
+    fn complete_syn_cookie_handshake(
+        &mut self,
+        ipv4_hdr: Ipv4Header,
+        remote: SocketAddrV4,
+        tcp_hdr: TcpHeader,
+        buf: DemiBuffer,
+        mss: usize,
+    ) {
+        debug!("Received ACK for SYN cookie: {:?}", tcp_hdr);
+        // We can't queue this connection, so drop the ACK. Our peer will retransmit it, or its first data segment.
+        if self.ready.len() >= self.max_backlog {
+            let cause: String = format!(
+                "backlog full (ready={}, backlog={})",
+                self.ready.len(),
+                self.max_backlog
+            );
+            warn!("complete_syn_cookie_handshake(): {}", cause);
+            return;
+        }
+
+        let mut recv_queue: SharedAsyncQueue<(Ipv4Header, TcpHeader, DemiBuffer)> =
+            SharedAsyncQueue::<(Ipv4Header, TcpHeader, DemiBuffer)>::default();
+        let syn_options: SynOptions = SynOptions {
+            remote_window_scale: None,
+            mss,
+            sack_permitted: false,
+            timestamps: None,
+            ecn: false,
+        };
+        let new_socket: EstablishedSocket = match self.new_established_socket(
+            recv_queue.clone(),
+            remote,
+            tcp_hdr.ack_num - SeqNumber::from(1),
+            tcp_hdr.seq_num - SeqNumber::from(1),
+            tcp_hdr.window_size,
+            syn_options,
+        ) {
+            Ok(new_socket) => new_socket,
+            Err(e) => {
+                warn!("complete_syn_cookie_handshake(): {:?}", e);
+                return;
+            },
+        };
+        self.connections.insert(remote, recv_queue.clone());
+
+        // If there is data or a FIN with the ACK, deliver it.
+        if !buf.is_empty() || tcp_hdr.fin {
+            recv_queue.push((ipv4_hdr, tcp_hdr, buf));
+        }
+        self.ready.push(Ok(new_socket));
+    }
+
+    /// Reads the SYN cookie counter.
+    fn syn_cookie_counter(&self, now: Instant) -> u32 {
+        ((now - self.syn_cookie_epoch).as_secs() / SYN_COOKIE_PERIOD.as_secs()) as u32
     }
 
     /// Sends a RST segment to `remote`.
@@ -289,11 +460,36 @@ impl SharedPassiveSocket {
         remote_isn: SeqNumber,
         local_isn: SeqNumber,
         tcp_hdr: TcpHeader,
-        recv_queue: SharedAsyncQueue<(Ipv4Addr, TcpHeader, DemiBuffer)>,
+        buf: DemiBuffer,
+        recv_queue: SharedAsyncQueue<(Ipv4Header, TcpHeader, DemiBuffer)>,
     ) {
+        let result: Result<EstablishedSocket, Fail> = self
+            .clone()
+            .handshake(remote, remote_isn, local_isn, tcp_hdr, buf, recv_queue)
+            .await;
+        // The handshake is over, one way or the other.
+        self.syn_queue_len -= 1;
+        if result.is_err() {
+            self.connections.remove(&remote);
+        }
+        self.ready.push(result);
+    }
+
+    async fn handshake(
+        mut self,
+        remote: SocketAddrV4,
+        remote_isn: SeqNumber,
+        local_isn: SeqNumber,
+        tcp_hdr: TcpHeader,
+        buf: DemiBuffer,
+        recv_queue: SharedAsyncQueue<(Ipv4Header, TcpHeader, DemiBuffer)>,
+    ) -> Result<EstablishedSocket, Fail> {
         // Set up new inflight accept connection.
         let mut remote_window_scale = None;
         let mut mss = FALLBACK_MSS;
+        let mut sack_permitted: bool = false;
+        let mut syn_timestamp: Option<u32> = None;
+        let mut fast_open_cookie: Option<FastOpenCookie> = None;
         for option in tcp_hdr.iter_options() {
             match option {
                 TcpOptions2::WindowScale(w) => {
@@ -304,77 +500,151 @@ impl SharedPassiveSocket {
                     info!("Received advertised MSS: {}", m);
                     mss = *m as usize;
                 },
+                TcpOptions2::SelectiveAcknowlegementPermitted => {
+                    info!("Received SACK permitted");
+                    sack_permitted = true;
+                },
+                TcpOptions2::Timestamp { sender_timestamp, .. } => {
+                    info!("Received timestamp: {}", sender_timestamp);
+                    syn_timestamp = Some(*sender_timestamp);
+                },
+                TcpOptions2::FastOpen(cookie) => {
+                    info!("Received Fast Open cookie: {:?}", cookie.as_slice());
+                    fast_open_cookie = Some(*cookie);
+                },
                 _ => continue,
             }
         }
 
+        // Our peer requests ECN with both ECE and CWR in its SYN (see RFC 3168 Section 6.1.1).
+        let ecn: bool = self.tcp_config.get_ecn() != Ecn::Disabled && tcp_hdr.ece && tcp_hdr.cwr;
+        if ecn {
+            info!("Negotiated ECN");
+        }
+
+        // Only agree to timestamps if our peer offered them in its SYN.
+        let timestamp_epoch: Instant = self.runtime.get_now();
+        let timestamps: Option<TcpTimestamps> =
+            syn_timestamp.map(|tsval| TcpTimestamps::new(timestamp_epoch, tsval, remote_isn + SeqNumber::from(1)));
+        let syn_options: SynOptions = SynOptions {
+            remote_window_scale,
+            mss,
+            sack_permitted,
+            timestamps,
+            ecn,
+        };
+
+        // TCP Fast Open (RFC 7413 Section 4.2.2). If our peer presents the cookie that we gave it, accept the data in
+        // its SYN and hand the connection to the application right away. Otherwise, give it a cookie for its next
+        // connections and drop the data, which it retransmits once the handshake completes.
+        let mut new_fast_open_cookie: Option<FastOpenCookie> = None;
+        if let Some(cookie) = fast_open_cookie.filter(|_| self.tcp_config.get_fast_open().is_server_enabled()) {
+            if !self
+                .isn_generator
+                .check_fast_open_cookie(self.local.ip(), remote.ip(), &cookie)
+            {
+                new_fast_open_cookie = Some(
+                    self.isn_generator
+                        .generate_fast_open_cookie(self.local.ip(), remote.ip()),
+                );
+            } else if !buf.is_empty() {
+                // Unlike the window in the ACK, the window in the SYN is not scaled.
+                let header_window_size: u16 = match remote_window_scale {
+                    Some(scale) => tcp_hdr
+                        .window_size
+                        .checked_shr(cmp::min(scale as usize, MAX_WINDOW_SCALE) as u32)
+                        .unwrap_or(0),
+                    None => tcp_hdr.window_size,
+                };
+                let mut socket: EstablishedSocket = self.new_established_socket(
+                    recv_queue,
+                    remote,
+                    local_isn,
+                    remote_isn,
+                    header_window_size,
+                    syn_options,
+                )?;
+                info!("Accepting {} bytes of Fast Open data", buf.len());
+                let syn_ack: TcpHeader = self.syn_ack_with_options(local_isn, remote_isn, remote, &syn_options, None);
+                let syn_ack: TcpHeader = socket.accept_fast_open(buf, syn_ack);
+                self.transmit_syn_ack(remote, syn_ack).await?;
+                return Ok(socket);
+            }
+        }
+        if !buf.is_empty() && fast_open_cookie.is_none() {
+            // RFC 793 allows connections to be established with data-carrying segments, but we only accept data in the
+            // SYN with a Fast Open cookie. Our peer retransmits the data after the connection is established.
+            warn!("Received SYN with data (len={})", buf.len());
+        }
+
         let mut handshake_retries: usize = self.tcp_config.get_handshake_retries();
         let handshake_timeout: Duration = self.tcp_config.get_handshake_timeout();
 
         loop {
             // Send the SYN + ACK.
-            if let Err(e) = self.send_syn_ack(local_isn, remote_isn, remote).await {
-                self.ready.push(Err(e));
-                return;
-            }
+            let syn_ack: TcpHeader =
+                self.syn_ack_with_options(local_isn, remote_isn, remote, &syn_options, new_fast_open_cookie);
+            self.transmit_syn_ack(remote, syn_ack).await?;
 
             // Start ack timer.
 
             // Wait for ACK in response.
-            let ack = self.clone().wait_for_ack(
-                recv_queue.clone(),
-                remote,
-                local_isn,
-                remote_isn,
-                tcp_hdr.window_size,
-                remote_window_scale,
-                mss,
-            );
+            let ack = self
+                .clone()
+                .wait_for_ack(recv_queue.clone(), remote, local_isn, remote_isn, syn_options);
 
             // Either we get an ack or a timeout.
             match conditional_yield_with_timeout(ack, handshake_timeout).await {
                 // Got an ack
-                Ok(result) => {
-                    self.ready.push(result);
-                    return;
-                },
+                Ok(result) => return result,
                 Err(Fail { errno, cause: _ }) if errno == ETIMEDOUT => {
                     if handshake_retries > 0 {
                         handshake_retries = handshake_retries - 1;
                         continue;
                     } else {
-                        self.ready.push(Err(Fail::new(ETIMEDOUT, "handshake timeout")));
-                        return;
+                        return Err(Fail::new(ETIMEDOUT, "handshake timeout"));
                     }
                 },
-                Err(e) => {
-                    self.ready.push(Err(e));
-                    return;
-                },
+                Err(e) => return Err(e),
             }
         }
     }
 
-    async fn send_syn_ack(
-        &mut self,
+    /// Builds a SYN+ACK with the options that we agree to.
+    fn syn_ack_with_options(
+        &self,
         local_isn: SeqNumber,
         remote_isn: SeqNumber,
         remote: SocketAddrV4,
-    ) -> Result<(), Fail> {
-        let mut tcp_hdr = TcpHeader::new(self.local.port(), remote.port());
-        tcp_hdr.syn = true;
-        tcp_hdr.seq_num = local_isn;
-        tcp_hdr.ack = true;
-        tcp_hdr.ack_num = remote_isn + SeqNumber::from(1);
-        tcp_hdr.window_size = self.tcp_config.get_receive_window_size();
+        syn_options: &SynOptions,
+        fast_open_cookie: Option<FastOpenCookie>,
+    ) -> TcpHeader {
+        let mut tcp_hdr: TcpHeader = self.syn_ack_header(local_isn, remote_isn, remote);
+        // We agree to ECN with ECE alone.
+        tcp_hdr.ece = syn_options.ecn;
+        let window_scale: u8 = self.receive_window_scale();
+        tcp_hdr.push_option(TcpOptions2::WindowScale(window_scale));
+        info!("Advertising window scale: {}", window_scale);
+
+        // Only agree to SACK if our peer offered it in its SYN.
+        if syn_options.sack_permitted {
+            tcp_hdr.push_option(TcpOptions2::SelectiveAcknowlegementPermitted);
+            info!("Advertising SACK permitted");
+        }
 
-        let mss = self.tcp_config.get_advertised_mss() as u16;
-        tcp_hdr.push_option(TcpOptions2::MaximumSegmentSize(mss));
-        info!("Advertising MSS: {}", mss);
+        // Likewise for timestamps.
+        if let Some(timestamps) = syn_options.timestamps {
+            tcp_hdr.push_option(timestamps.option(self.runtime.get_now()));
+        }
 
-        tcp_hdr.push_option(TcpOptions2::WindowScale(self.tcp_config.get_window_scale()));
-        info!("Advertising window scale: {}", self.tcp_config.get_window_scale());
+        if let Some(cookie) = fast_open_cookie {
+            tcp_hdr.push_option(TcpOptions2::FastOpen(cookie));
+            info!("Sending Fast Open cookie");
+        }
+        tcp_hdr
+    }
 
+    async fn transmit_syn_ack(&mut self, remote: SocketAddrV4, tcp_hdr: TcpHeader) -> Result<(), Fail> {
         debug!("Sending SYN+ACK: {:?}", tcp_hdr);
         let dst_ipv4_addr: Ipv4Addr = remote.ip().clone();
         let mut pkt: DemiBuffer = DemiBuffer::new_with_headroom(0, MAX_HEADER_SIZE as u16);
@@ -389,15 +659,45 @@ impl SharedPassiveSocket {
             .await
     }
 
+    // Our receive buffer size, from SO_RCVBUF or else from the inetstack configuration.
+    fn receive_buffer_size(&self) -> u32 {
+        self.socket_options
+            .get_receive_buffer_size()
+            .map_or(self.tcp_config.get_receive_buffer_size(), |size| size as u32)
+    }
+
+    // The window scale that we advertise has to cover the receive buffer once auto-tuning has grown it.
+    fn receive_window_scale(&self) -> u8 {
+        let max_size: u32 = self
+            .socket_options
+            .get_receive_buffer_size()
+            .map_or(self.tcp_config.get_receive_buffer_max_size(), |size| size as u32);
+        TcpConfig::window_scale(max_size)
+    }
+
+    /// Builds a SYN+ACK that only carries the MSS option.
+    fn syn_ack_header(&self, local_isn: SeqNumber, remote_isn: SeqNumber, remote: SocketAddrV4) -> TcpHeader {
+        let mut tcp_hdr = TcpHeader::new(self.local.port(), remote.port());
+        tcp_hdr.syn = true;
+        tcp_hdr.seq_num = local_isn;
+        tcp_hdr.ack = true;
+        tcp_hdr.ack_num = remote_isn + SeqNumber::from(1);
+        // The window in a SYN is never scaled.
+        tcp_hdr.window_size = cmp::min(self.receive_buffer_size(), u16::MAX as u32) as u16;
+
+        let mss = self.tcp_config.get_advertised_mss() as u16;
+        tcp_hdr.push_option(TcpOptions2::MaximumSegmentSize(mss));
+        info!("Advertising MSS: {}", mss);
+        tcp_hdr
+    }
+
     async fn wait_for_ack(
         self,
-        mut recv_queue: SharedAsyncQueue<(Ipv4Addr, TcpHeader, DemiBuffer)>,
+        mut recv_queue: SharedAsyncQueue<(Ipv4Header, TcpHeader, DemiBuffer)>,
         remote: SocketAddrV4,
         local_isn: SeqNumber,
         remote_isn: SeqNumber,
-        header_window_size: u16,
-        remote_window_scale: Option<u8>,
-        mss: usize,
+        mut syn_options: SynOptions,
     ) -> Result<EstablishedSocket, Fail> {
         let (ipv4_hdr, tcp_hdr, buf) = recv_queue.pop(None).await?;
         debug!("Received ACK: {:?}", tcp_hdr);
@@ -407,17 +707,54 @@ impl SharedPassiveSocket {
             return Err(Fail::new(EBADMSG, "invalid SYN+ACK seq num"));
         }
 
-        // Calculate the window.
-        let (local_window_scale, remote_window_scale): (u32, u8) = match remote_window_scale {
+        // Pick up the timestamp of the ACK, this is what we echo from now on.
+        if let (Some(timestamps), Some((tsval, _))) =
+            (syn_options.timestamps.as_mut(), find_timestamps(tcp_hdr.iter_options()))
+        {
+            timestamps.update_recent(tsval, tcp_hdr.seq_num);
+        }
+
+        // Unlike the window in the SYN, the window in the ACK is scaled, so that is where we pick up the send window.
+        let header_window_size: u16 = tcp_hdr.window_size;
+
+        // If there is data with the SYN+ACK, deliver it.
+        if !buf.is_empty() {
+            recv_queue.push((ipv4_hdr, tcp_hdr, buf));
+        }
+
+        self.new_established_socket(
+            recv_queue,
+            remote,
+            local_isn,
+            remote_isn,
+            header_window_size,
+            syn_options,
+        )
+    }
+
+    /// Creates the socket for a connection that completed the handshake.
+    fn new_established_socket(
+        &self,
+        recv_queue: SharedAsyncQueue<(Ipv4Header, TcpHeader, DemiBuffer)>,
+        remote: SocketAddrV4,
+        local_isn: SeqNumber,
+        remote_isn: SeqNumber,
+        header_window_size: u16,
+        syn_options: SynOptions,
+    ) -> Result<EstablishedSocket, Fail> {
+        // Calculate the window. Without window scaling, we can only advertise as much of our receive buffer as fits in
+        // the window field.
+        let receive_buffer_size: u32 = self.receive_buffer_size();
+        let (local_window_scale, remote_window_scale): (u32, u8) = match syn_options.remote_window_scale {
             Some(remote_window_scale) => {
                 if (remote_window_scale as usize) < MAX_WINDOW_SCALE {
-                    (self.tcp_config.get_window_scale() as u32, remote_window_scale)
+                    (self.receive_window_scale() as u32, remote_window_scale)
                 } else {
                     warn!(
                         "remote windows scale larger than {:?} is incorrect, so setting to {:?}. See RFC 1323.",
                         MAX_WINDOW_SCALE, MAX_WINDOW_SCALE
                     );
-                    (self.tcp_config.get_window_scale() as u32, MAX_WINDOW_SCALE as u8)
+                    (self.receive_window_scale() as u32, MAX_WINDOW_SCALE as u8)
                 }
             },
             None => (0, 0),
@@ -429,13 +766,8 @@ impl SharedPassiveSocket {
             (header_window_size as u32).checked_shl(remote_window_scale as u32),
             "Window size overflow"
         );
-        // Expect is safe here because the receive window size is a 16-bit unsigned integer and MAX_WINDOW_SCALE is 14,
-        // so it is impossible to overflow the 32-bit unsigned int.
         debug_assert!((local_window_scale as usize) <= MAX_WINDOW_SCALE);
-        let local_window_size: u32 = expect_some!(
-            (self.tcp_config.get_receive_window_size() as u32).checked_shl(local_window_scale),
-            "Window size overflow"
-        );
+        let local_window_size: u32 = cmp::min(receive_buffer_size, (u16::MAX as u32) << local_window_scale);
         info!(
             "Window sizes: local {}, remote {}",
             local_window_size, remote_window_size
@@ -445,11 +777,6 @@ impl SharedPassiveSocket {
             local_window_scale, remote_window_scale
         );
 
-        // If there is data with the SYN+ACK, deliver it.
-        if !buf.is_empty() {
-            recv_queue.push((ipv4_hdr, tcp_hdr, buf));
-        }
-
         let new_socket: EstablishedSocket = EstablishedSocket::new(
             self.local,
             remote,
@@ -465,8 +792,10 @@ impl SharedPassiveSocket {
             local_isn + SeqNumber::from(1),
             remote_window_size,
             remote_window_scale,
-            mss,
-            congestion_control::None::new,
+            syn_options.mss,
+            syn_options.sack_permitted,
+            syn_options.timestamps,
+            syn_options.ecn,
             None,
             self.dead_socket_tx.clone(),
             Some(self.socket_queue.clone()),
@@ -7,9 +7,17 @@
 
 use crate::{
     demikernel::config::Config,
+    expect_some,
     inetstack::protocols::{
-        layer3::SharedLayer3Endpoint,
-        layer4::tcp::{header::TcpHeader, isn_generator::IsnGenerator, socket::SharedTcpSocket, SeqNumber},
+        layer3::{Ipv4Header, SharedLayer3Endpoint},
+        layer4::tcp::{
+            header::{FastOpenCookie, TcpHeader},
+            isn_generator::IsnGenerator,
+            socket::SharedTcpSocket,
+            time_wait::TimeWait,
+            SeqNumber,
+        },
+        MAX_HEADER_SIZE,
     },
     runtime::{
         fail::Fail,
@@ -29,8 +37,9 @@ use ::rand::{prelude::SmallRng, Rng, SeedableRng};
 
 use ::std::{
     collections::HashMap,
-    net::{Ipv4Addr, SocketAddr, SocketAddrV4},
+    net::{Ipv4Addr, Shutdown, SocketAddr, SocketAddrV4},
     ops::{Deref, DerefMut},
+    time::Instant,
 };
 
 //======================================================================================================================
@@ -40,13 +49,18 @@ use ::std::{
 pub struct TcpPeer {
     runtime: SharedDemiRuntime,
     isn_generator: IsnGenerator,
+    // Secret key of the ISNs and cookies of this stack, which our listening sockets share.
+    secret: u128,
     layer3_endpoint: SharedLayer3Endpoint,
     local_ipv4_addr: Ipv4Addr,
     tcp_config: TcpConfig,
     default_socket_options: TcpSocketOptions,
-    rng: SmallRng,
     dead_socket_tx: mpsc::UnboundedSender<QDesc>,
     addresses: HashMap<SocketId, SharedTcpSocket>,
+    // Connections that we actively closed and that are now in the TIME_WAIT state, by local and remote address.
+    time_wait: HashMap<(SocketAddrV4, SocketAddrV4), TimeWait>,
+    // TCP Fast Open cookies that servers gave us, by server address.
+    fast_open_cookies: HashMap<Ipv4Addr, FastOpenCookie>,
 }
 
 #[derive(Clone)]
@@ -64,18 +78,20 @@ impl SharedTcpPeer {
         rng_seed: [u8; 32],
     ) -> Result<Self, Fail> {
         let mut rng: SmallRng = SmallRng::from_seed(rng_seed);
-        let nonce: u32 = rng.gen();
+        let secret: u128 = rng.gen();
         let (tx, _) = mpsc::unbounded();
         Ok(Self(SharedObject::<TcpPeer>::new(TcpPeer {
-            isn_generator: IsnGenerator::new(nonce),
+            isn_generator: IsnGenerator::new(secret),
+            secret,
             runtime,
             layer3_endpoint,
             local_ipv4_addr: config.local_ipv4_addr()?,
             tcp_config: TcpConfig::new(config)?,
             default_socket_options: TcpSocketOptions::new(config)?,
-            rng,
             dead_socket_tx: tx,
             addresses: HashMap::<SocketId, SharedTcpSocket>::new(),
+            time_wait: HashMap::<(SocketAddrV4, SocketAddrV4), TimeWait>::new(),
+            fast_open_cookies: HashMap::<Ipv4Addr, FastOpenCookie>::new(),
         })))
     }
 
@@ -126,8 +142,7 @@ impl SharedTcpPeer {
     pub fn listen(&mut self, socket: &mut SharedTcpSocket, backlog: usize) -> Result<(), Fail> {
         // Most checks should have been performed already
         debug_assert!(socket.local().is_some());
-        let nonce: u32 = self.rng.gen();
-        socket.listen(backlog, nonce)
+        socket.listen(backlog, self.secret)
     }
 
     /// Runs until a new connection is accepted.
@@ -145,13 +160,29 @@ impl SharedTcpPeer {
         }
     }
 
-    /// Runs until the connect to remote is made or times out.
+    /// Runs until the connect to remote is made or times out. Once connected, sends `data`, which goes in the SYN if we
+    /// have a Fast Open cookie for `remote`.
     pub async fn connect(
         &mut self,
         socket: &mut SharedTcpSocket,
         local: SocketAddrV4,
         remote: SocketAddrV4,
+        data: Option<DemiBuffer>,
     ) -> Result<(), Fail> {
+        // Don't reuse the address pair of a connection in the TIME_WAIT state, unless the socket allows it. Otherwise,
+        // late segments from the old connection could be taken for segments of the new one.
+        if let Some(entry) = self.time_wait.get(&(local, remote)) {
+            if !entry.is_expired(self.runtime.get_now()) && !socket.get_reuse_address() {
+                let cause: String = format!(
+                    "connection is in TIME_WAIT state (local={:?}, remote={:?})",
+                    local, remote
+                );
+                error!("connect(): {}", cause);
+                return Err(Fail::new(libc::EADDRINUSE, &cause));
+            }
+            self.time_wait.remove(&(local, remote));
+        }
+
         // If socket is already bound to a local address, use it but remove the old binding.
         self.addresses.remove(&SocketId::Passive(local));
         // Insert the connection to receive incoming packets for this address pair.
@@ -169,12 +200,19 @@ impl SharedTcpPeer {
             );
         }
         let local_isn: SeqNumber = self.isn_generator.generate(&local, &remote);
+        let fast_open_cookie: Option<FastOpenCookie> = self.fast_open_cookies.get(remote.ip()).copied();
         // Wait for connect to complete.
-        if let Err(e) = socket.connect(local, remote, local_isn).await {
-            self.addresses.remove(&SocketId::Active(local, remote.clone()));
-            Err(e)
-        } else {
-            Ok(())
+        match socket.connect(local, remote, local_isn, data, fast_open_cookie).await {
+            Ok(fast_open_cookie) => {
+                if let Some(fast_open_cookie) = fast_open_cookie {
+                    self.fast_open_cookies.insert(*remote.ip(), fast_open_cookie);
+                }
+                Ok(())
+            },
+            Err(e) => {
+                self.addresses.remove(&SocketId::Active(local, remote));
+                Err(e)
+            },
         }
     }
 
@@ -199,12 +237,23 @@ impl SharedTcpPeer {
         Ok((None, incoming))
     }
 
+    /// Shuts down the receiving side, the sending side, or both sides of a TCP connection.
+    pub fn shutdown(&mut self, socket: &mut SharedTcpSocket, how: Shutdown) -> Result<(), Fail> {
+        socket.shutdown(how)
+    }
+
     /// Closes a TCP socket.
     pub async fn close(&mut self, socket: &mut SharedTcpSocket) -> Result<(), Fail> {
         // Wait for close to complete.
         // Handle result: If unsuccessful, free the new queue descriptor.
         if let Some(socket_id) = socket.close().await? {
             self.addresses.remove(&socket_id);
+            // If we closed the connection first, keep it around in the TIME_WAIT state.
+            if let (SocketId::Active(local, remote), Some(entry)) = (socket_id, socket.time_wait()) {
+                let now: Instant = self.runtime.get_now();
+                self.time_wait.retain(|_, entry| !entry.is_expired(now));
+                self.time_wait.insert((local, remote), entry);
+            }
         }
         Ok(())
     }
@@ -217,9 +266,10 @@ impl SharedTcpPeer {
     }
 
     /// Processes an incoming TCP segment.
-    pub fn receive(&mut self, src_ipv4_addr: Ipv4Addr, mut buf: DemiBuffer) {
+    pub fn receive(&mut self, ipv4_hdr: Ipv4Header, mut buf: DemiBuffer) {
         // We can assume that the destination is our local IPv4 address; otherwise, the IP layer would have discarded
         // the packet already.
+        let src_ipv4_addr: Ipv4Addr = ipv4_hdr.get_src_addr();
         let tcp_hdr: TcpHeader = match TcpHeader::parse_and_strip(
             &src_ipv4_addr,
             &self.local_ipv4_addr,
@@ -237,21 +287,82 @@ impl SharedTcpPeer {
         let local: SocketAddrV4 = SocketAddrV4::new(self.local_ipv4_addr, tcp_hdr.dst_port);
         let remote: SocketAddrV4 = SocketAddrV4::new(src_ipv4_addr, tcp_hdr.src_port);
 
+        // Segments for a connection in the TIME_WAIT state are absorbed here. Only a new connection request may take
+        // over the address pair, and only if the listening socket allows it.
+        if let Some(mut entry) = self.time_wait.remove(&(local, remote)) {
+            let now: Instant = self.runtime.get_now();
+            let reopen: bool = tcp_hdr.syn
+                && !tcp_hdr.ack
+                && match self.addresses.get(&SocketId::Passive(local)) {
+                    Some(socket) => socket.get_reuse_address(),
+                    None => false,
+                };
+            if !entry.is_expired(now) && !reopen {
+                if let Some(ack) = entry.receive(&tcp_hdr, buf.len(), now) {
+                    self.send_control_segment(remote, ack);
+                }
+                self.time_wait.insert((local, remote), entry);
+                return;
+            }
+        }
+
         // Retrieve the queue descriptor based on the incoming segment.
-        let socket: &mut SharedTcpSocket = match self.addresses.get_mut(&SocketId::Active(local, remote)) {
-            Some(socket) => socket,
-            None => match self.addresses.get_mut(&SocketId::Passive(local)) {
-                Some(socket) => socket,
-                None => {
-                    let cause: String = format!("no queue descriptor for remote address (remote={})", remote.ip());
-                    error!("receive(): {}", &cause);
-                    return;
-                },
-            },
+        let socket_id: SocketId = if self.addresses.contains_key(&SocketId::Active(local, remote)) {
+            SocketId::Active(local, remote)
+        } else if self
+            .addresses
+            .get(&SocketId::Passive(local))
+            .is_some_and(|socket| socket.is_receiving())
+        {
+            SocketId::Passive(local)
+        } else {
+            debug!(
+                "receive(): no socket for segment (local={:?}, remote={:?})",
+                local, remote
+            );
+            self.send_rst_for_closed_port(remote, &tcp_hdr, buf.len());
+            return;
         };
+        let socket: &mut SharedTcpSocket = expect_some!(self.addresses.get_mut(&socket_id), "socket should exist");
 
         // Dispatch to further processing depending on the socket state.
-        socket.receive(src_ipv4_addr, tcp_hdr, buf)
+        socket.receive(ipv4_hdr, tcp_hdr, buf)
+    }
+
+    /// Answers a segment that does not belong to any connection with a RST, so that our peer fails fast instead of
+    /// timing out (RFC 9293 Section 3.10.7.1).
+    fn send_rst_for_closed_port(&mut self, remote: SocketAddrV4, segment: &TcpHeader, data_len: usize) {
+        // Never answer a RST, or we could end up in a RST war with our peer.
+        if segment.rst {
+            return;
+        }
+        let mut header: TcpHeader = TcpHeader::new(segment.dst_port, segment.src_port);
+        header.rst = true;
+        if segment.ack {
+            // Make the RST acceptable by taking the sequence number from the ACK field.
+            header.seq_num = segment.ack_num;
+        } else {
+            // Otherwise, acknowledge everything in the segment, including the SYN and FIN flags.
+            let seg_len: u32 = data_len as u32 + segment.syn as u32 + segment.fin as u32;
+            header.ack = true;
+            header.ack_num = segment.seq_num + SeqNumber::from(seg_len);
+        }
+        self.send_control_segment(remote, header);
+    }
+
+    /// Sends a segment that does not belong to any connection that we keep a control block for.
+    fn send_control_segment(&mut self, remote: SocketAddrV4, header: TcpHeader) {
+        debug!("send_control_segment(): sending {:?} to {:?}", header, remote);
+        let mut pkt: DemiBuffer = DemiBuffer::new_with_headroom(0, MAX_HEADER_SIZE as u16);
+        header.serialize_and_attach(
+            &mut pkt,
+            &self.local_ipv4_addr,
+            remote.ip(),
+            self.tcp_config.get_tx_checksum_offload(),
+        );
+        if let Err(e) = self.layer3_endpoint.transmit_tcp_packet_nonblocking(*remote.ip(), pkt) {
+            warn!("Could not send segment: {:?}", e);
+        }
     }
 }
 
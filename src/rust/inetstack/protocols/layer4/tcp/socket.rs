@@ -9,10 +9,14 @@ use crate::{
     collections::async_queue::SharedAsyncQueue,
     expect_some,
     inetstack::protocols::{
-        layer3::SharedLayer3Endpoint,
+        layer3::{Ipv4Header, SharedLayer3Endpoint},
         layer4::tcp::{
-            active_open::SharedActiveOpenSocket, established::EstablishedSocket, header::TcpHeader,
-            passive_open::SharedPassiveSocket, SeqNumber,
+            active_open::SharedActiveOpenSocket,
+            established::EstablishedSocket,
+            header::{FastOpenCookie, TcpHeader},
+            passive_open::SharedPassiveSocket,
+            time_wait::TimeWait,
+            SeqNumber,
         },
     },
     runtime::{
@@ -20,6 +24,7 @@ use crate::{
         memory::DemiBuffer,
         network::{
             config::TcpConfig,
+            consts::MAX_RECEIVE_WINDOW_SIZE,
             socket::{
                 option::{SocketOption, TcpSocketOptions},
                 SocketId,
@@ -30,8 +35,9 @@ use crate::{
 };
 use ::futures::channel::mpsc;
 use ::std::{
+    cmp,
     fmt::Debug,
-    net::{Ipv4Addr, SocketAddrV4},
+    net::{Shutdown, SocketAddrV4},
     ops::{Deref, DerefMut},
 };
 
@@ -55,7 +61,7 @@ pub enum SocketState {
 /// Per-queue metadata for the TCP socket.
 pub struct TcpSocket {
     state: SocketState,
-    recv_queue: Option<SharedAsyncQueue<(Ipv4Addr, TcpHeader, DemiBuffer)>>,
+    recv_queue: Option<SharedAsyncQueue<(Ipv4Header, TcpHeader, DemiBuffer)>>,
     runtime: SharedDemiRuntime,
     layer3_endpoint: SharedLayer3Endpoint,
     tcp_config: TcpConfig,
@@ -97,7 +103,7 @@ impl SharedTcpSocket {
         default_socket_options: TcpSocketOptions,
         dead_socket_tx: mpsc::UnboundedSender<QDesc>,
     ) -> Self {
-        let recv_queue: SharedAsyncQueue<(Ipv4Addr, TcpHeader, DemiBuffer)> = socket.get_recv_queue();
+        let recv_queue: SharedAsyncQueue<(Ipv4Header, TcpHeader, DemiBuffer)> = socket.get_recv_queue();
         Self(SharedObject::<TcpSocket>::new(TcpSocket {
             state: SocketState::Established(socket),
             recv_queue: Some(recv_queue),
@@ -111,10 +117,53 @@ impl SharedTcpSocket {
 
     /// Set an SO_* option on the socket.
     pub fn set_socket_option(&mut self, option: SocketOption) -> Result<(), Fail> {
+        match option {
+            SocketOption::KeepAliveIdle(duration) | SocketOption::KeepAliveInterval(duration) if duration.is_zero() => {
+                let cause: &str = "keepalive times must not be zero";
+                error!("set_socket_option(): {}", cause);
+                return Err(Fail::new(libc::EINVAL, cause));
+            },
+            SocketOption::KeepAliveProbes(0) => {
+                let cause: &str = "number of keepalive probes must not be zero";
+                error!("set_socket_option(): {}", cause);
+                return Err(Fail::new(libc::EINVAL, cause));
+            },
+            SocketOption::SendBufferSize(0) | SocketOption::ReceiveBufferSize(0) => {
+                let cause: &str = "buffer sizes must not be zero";
+                error!("set_socket_option(): {}", cause);
+                return Err(Fail::new(libc::EINVAL, cause));
+            },
+            _ => (),
+        }
         match option {
             SocketOption::Linger(linger) => self.socket_options.set_linger(linger),
             SocketOption::KeepAlive(keep_alive) => self.socket_options.set_keepalive(keep_alive),
+            SocketOption::KeepAliveIdle(idle) => self.socket_options.set_keepalive_idle(idle),
+            SocketOption::KeepAliveInterval(interval) => self.socket_options.set_keepalive_interval(interval),
+            SocketOption::KeepAliveProbes(probes) => self.socket_options.set_keepalive_probes(probes),
             SocketOption::NoDelay(no_delay) => self.socket_options.set_nodelay(no_delay),
+            SocketOption::QuickAck(quick_ack) => self.socket_options.set_quickack(quick_ack),
+            SocketOption::CongestionControl(algorithm) => self.socket_options.set_congestion_control(algorithm),
+            SocketOption::ReuseAddress(reuse_address) => self.socket_options.set_reuse_address(reuse_address),
+            SocketOption::SendBufferSize(size) => self.socket_options.set_send_buffer_size(size),
+            // Like Linux, silently cap the receive buffer at the largest window that we can advertise.
+            SocketOption::ReceiveBufferSize(size) => self
+                .socket_options
+                .set_receive_buffer_size(Some(cmp::min(size, MAX_RECEIVE_WINDOW_SIZE as usize))),
+            SocketOption::UserTimeout(user_timeout) => self.socket_options.set_user_timeout(user_timeout),
+            SocketOption::TcpInfo(_) => {
+                let cause: &str = "connection statistics cannot be set";
+                error!("set_socket_option(): {}", cause);
+                return Err(Fail::new(libc::ENOPROTOOPT, cause));
+            },
+        }
+        // Options also apply to a connection that is already established, and to connections that a listening socket
+        // accepts from now on.
+        let socket_options: TcpSocketOptions = self.socket_options;
+        match self.state {
+            SocketState::Established(ref mut socket) => socket.set_socket_options(socket_options),
+            SocketState::Listening(ref mut socket) => socket.set_socket_options(socket_options),
+            _ => (),
         }
         Ok(())
     }
@@ -125,7 +174,39 @@ impl SharedTcpSocket {
         match option {
             SocketOption::Linger(_) => Ok(SocketOption::Linger(self.socket_options.get_linger())),
             SocketOption::KeepAlive(_) => Ok(SocketOption::KeepAlive(self.socket_options.get_keepalive())),
+            SocketOption::KeepAliveIdle(_) => Ok(SocketOption::KeepAliveIdle(self.socket_options.get_keepalive_idle())),
+            SocketOption::KeepAliveInterval(_) => Ok(SocketOption::KeepAliveInterval(
+                self.socket_options.get_keepalive_interval(),
+            )),
+            SocketOption::KeepAliveProbes(_) => Ok(SocketOption::KeepAliveProbes(
+                self.socket_options.get_keepalive_probes(),
+            )),
             SocketOption::NoDelay(_) => Ok(SocketOption::NoDelay(self.socket_options.get_nodelay())),
+            SocketOption::QuickAck(_) => Ok(SocketOption::QuickAck(self.socket_options.get_quickack())),
+            SocketOption::CongestionControl(_) => Ok(SocketOption::CongestionControl(
+                self.socket_options.get_congestion_control(),
+            )),
+            SocketOption::ReuseAddress(_) => Ok(SocketOption::ReuseAddress(self.socket_options.get_reuse_address())),
+            SocketOption::SendBufferSize(_) => {
+                Ok(SocketOption::SendBufferSize(self.socket_options.get_send_buffer_size()))
+            },
+            // Like Linux, report the size that auto-tuning has grown the receive buffer of a connection to.
+            SocketOption::ReceiveBufferSize(_) => Ok(SocketOption::ReceiveBufferSize(match self.state {
+                SocketState::Established(ref socket) => socket.get_receive_buffer_size() as usize,
+                _ => self
+                    .socket_options
+                    .get_receive_buffer_size()
+                    .unwrap_or(self.tcp_config.get_receive_buffer_size() as usize),
+            })),
+            SocketOption::UserTimeout(_) => Ok(SocketOption::UserTimeout(self.socket_options.get_user_timeout())),
+            SocketOption::TcpInfo(_) => match self.state {
+                SocketState::Established(ref socket) => Ok(SocketOption::TcpInfo(socket.tcp_info())),
+                _ => {
+                    let cause: &str = "socket is not connected";
+                    error!("get_socket_option(): {}", cause);
+                    Err(Fail::new(libc::ENOTCONN, cause))
+                },
+            },
         }
     }
 
@@ -151,9 +232,9 @@ impl SharedTcpSocket {
     }
 
     /// Sets the target queue to listen for incoming connections.
-    pub fn listen(&mut self, backlog: usize, nonce: u32) -> Result<(), Fail> {
-        let recv_queue: SharedAsyncQueue<(Ipv4Addr, TcpHeader, DemiBuffer)> =
-            SharedAsyncQueue::<(Ipv4Addr, TcpHeader, DemiBuffer)>::default();
+    pub fn listen(&mut self, backlog: usize, secret: u128) -> Result<(), Fail> {
+        let recv_queue: SharedAsyncQueue<(Ipv4Header, TcpHeader, DemiBuffer)> =
+            SharedAsyncQueue::<(Ipv4Header, TcpHeader, DemiBuffer)>::default();
         self.state = SocketState::Listening(SharedPassiveSocket::new(
             expect_some!(
                 self.local(),
@@ -166,7 +247,7 @@ impl SharedTcpSocket {
             self.tcp_config.clone(),
             self.socket_options.clone(),
             self.dead_socket_tx.clone(),
-            nonce,
+            secret,
         )?);
         self.recv_queue = Some(recv_queue);
         Ok(())
@@ -191,14 +272,18 @@ impl SharedTcpSocket {
         Ok(new_queue)
     }
 
+    /// Connects to `remote` and sends `data` once connected, in the SYN if we have a Fast Open cookie for the server.
+    /// Returns the Fast Open cookie to use on the next connection to the same server.
     pub async fn connect(
         &mut self,
         local: SocketAddrV4,
         remote: SocketAddrV4,
         local_isn: SeqNumber,
-    ) -> Result<(), Fail> {
-        let recv_queue: SharedAsyncQueue<(Ipv4Addr, TcpHeader, DemiBuffer)> =
-            SharedAsyncQueue::<(Ipv4Addr, TcpHeader, DemiBuffer)>::default();
+        data: Option<DemiBuffer>,
+        fast_open_cookie: Option<FastOpenCookie>,
+    ) -> Result<Option<FastOpenCookie>, Fail> {
+        let recv_queue: SharedAsyncQueue<(Ipv4Header, TcpHeader, DemiBuffer)> =
+            SharedAsyncQueue::<(Ipv4Header, TcpHeader, DemiBuffer)>::default();
         // Create active socket.
         let socket: SharedActiveOpenSocket = SharedActiveOpenSocket::new(
             local_isn,
@@ -210,12 +295,14 @@ impl SharedTcpSocket {
             self.tcp_config.clone(),
             self.socket_options.clone(),
             self.dead_socket_tx.clone(),
+            data,
+            fast_open_cookie,
         )?;
         self.state = SocketState::Connecting(socket.clone());
         self.recv_queue = Some(recv_queue);
-        let new_socket = socket.connect().await?;
+        let new_socket = socket.clone().connect().await?;
         self.state = SocketState::Established(new_socket);
-        Ok(())
+        Ok(socket.fast_open_cookie())
     }
 
     pub async fn push(&mut self, buf: DemiBuffer) -> Result<(), Fail> {
@@ -236,6 +323,17 @@ impl SharedTcpSocket {
         }
     }
 
+    pub fn shutdown(&mut self, how: Shutdown) -> Result<(), Fail> {
+        match self.state {
+            SocketState::Established(ref mut socket) => socket.shutdown(how),
+            _ => {
+                let cause: &str = "socket is not connected";
+                error!("shutdown(): {}", cause);
+                Err(Fail::new(libc::ENOTCONN, cause))
+            },
+        }
+    }
+
     pub async fn close(&mut self) -> Result<Option<SocketId>, Fail> {
         match self.state {
             // Closing an active socket.
@@ -268,7 +366,7 @@ impl SharedTcpSocket {
         match self.state {
             // Closing an active socket.
             SocketState::Established(ref mut socket) => {
-                // TODO: Send a RST or something?
+                socket.reset();
                 Ok(Some(SocketId::Active(socket.endpoints().0, socket.endpoints().1)))
             },
             // Closing a listening socket.
@@ -292,6 +390,19 @@ impl SharedTcpSocket {
         }
     }
 
+    /// Returns true if the SO_REUSEADDR-like option is set on this socket.
+    pub fn get_reuse_address(&self) -> bool {
+        self.socket_options.get_reuse_address()
+    }
+
+    /// Returns the state to keep for a connection that has been actively closed and is now in the TIME_WAIT state.
+    pub fn time_wait(&self) -> Option<TimeWait> {
+        match self.state {
+            SocketState::Established(ref socket) => socket.time_wait(),
+            _ => None,
+        }
+    }
+
     pub fn endpoints(&self) -> Result<(SocketAddrV4, SocketAddrV4), Fail> {
         match self.state {
             SocketState::Established(ref socket) => Ok(socket.endpoints()),
@@ -300,7 +411,7 @@ impl SharedTcpSocket {
         }
     }
 
-    pub fn receive(&mut self, ip_hdr: Ipv4Addr, tcp_hdr: TcpHeader, buf: DemiBuffer) {
+    pub fn receive(&mut self, ip_hdr: Ipv4Header, tcp_hdr: TcpHeader, buf: DemiBuffer) {
         // If this queue has an allocated receive queue, then direct the packet there.
         if let Some(recv_queue) = self.recv_queue.as_mut() {
             recv_queue.push((ip_hdr, tcp_hdr, buf));
@@ -308,6 +419,11 @@ impl SharedTcpSocket {
         }
     }
 
+    /// Returns true if this socket takes incoming segments, that is, if it is listening or connected.
+    pub fn is_receiving(&self) -> bool {
+        self.recv_queue.is_some()
+    }
+
     /// Returns the local address to which the target queue is bound.
     pub fn local(&self) -> Option<SocketAddrV4> {
         match self.state {
@@ -0,0 +1,311 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+// Tests for Explicit Congestion Notification (RFC 3168), where the test plays the other side of the connection so that
+// it can look at the ECN flags and codepoints of our segments and mark its own.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use super::{pop_segments, receive_at, Segment, LOCAL_PORT, REMOTE_PORT, WINDOW_SIZE};
+use crate::{
+    ensure_eq,
+    inetstack::{
+        protocols::{
+            layer3::ipv4::EcnCodepoint,
+            layer4::tcp::{
+                header::{TcpHeader, TcpOptions2},
+                SeqNumber,
+            },
+            MAX_HEADER_SIZE,
+        },
+        test_helpers::{self, engine::SharedEngine, physical_layer::SharedTestPhysicalLayer},
+    },
+    runtime::{
+        memory::DemiBuffer,
+        network::{
+            socket::option::{CongestionControlAlgorithm, SocketOption, TcpInfo},
+            types::MacAddress,
+        },
+        OperationResult,
+    },
+    QDesc, QToken,
+};
+use ::anyhow::Result;
+use ::std::{
+    net::{Ipv4Addr, SocketAddrV4},
+    time::{Duration, Instant},
+};
+
+//======================================================================================================================
+// Constants
+//======================================================================================================================
+
+const MSS: u16 = 500;
+
+//======================================================================================================================
+// Tests
+//======================================================================================================================
+
+// Test that we request ECN with ECE and CWR in our SYN, and that only new data is ECN-capable once our peer agrees to
+// it with ECE alone in its SYN+ACK.
+#[test]
+fn connect_negotiates_ecn() -> Result<()> {
+    let (mut connection, syn): (Connection, Segment) = Connection::connect(true)?;
+    ensure_eq!((syn.header.ece, syn.header.cwr), (true, true));
+    ensure_eq!(syn.ecn, EcnCodepoint::NotEct);
+    connection.push(100)?;
+    let segments: Vec<Segment> = connection.pop_segments()?;
+    ensure_eq!(segments.len(), 1);
+    ensure_eq!((segments[0].len, segments[0].ecn), (100, EcnCodepoint::Ect0));
+
+    // Without ECE in the SYN+ACK, our data is not ECN-capable.
+    let (mut connection, _): (Connection, Segment) = Connection::connect(false)?;
+    connection.push(100)?;
+    let segments: Vec<Segment> = connection.pop_segments()?;
+    ensure_eq!(segments.len(), 1);
+    ensure_eq!((segments[0].len, segments[0].ecn), (100, EcnCodepoint::NotEct));
+
+    Ok(())
+}
+
+// Test that we agree to ECN with ECE alone in our SYN+ACK when our peer requests it, and not otherwise.
+#[test]
+fn accept_negotiates_ecn() -> Result<()> {
+    let mut connection: Connection = Connection::listen()?;
+    let syn_ack: Segment = connection.receive_syn(REMOTE_PORT, true)?;
+    ensure_eq!((syn_ack.header.ece, syn_ack.header.cwr), (true, false));
+    ensure_eq!(syn_ack.ecn, EcnCodepoint::NotEct);
+
+    let syn_ack: Segment = connection.receive_syn(REMOTE_PORT + 1, false)?;
+    ensure_eq!((syn_ack.header.ece, syn_ack.header.cwr), (false, false));
+
+    Ok(())
+}
+
+// Test that once our peer's data arrives with a congestion mark, we set ECE on our ACKs until our peer answers with
+// CWR.
+#[test]
+fn congestion_mark_echoed_until_cwr() -> Result<()> {
+    let (mut connection, _): (Connection, Segment) = Connection::connect(true)?;
+    connection.set_socket_option(SocketOption::QuickAck(true))?;
+
+    connection.receive_data(100, EcnCodepoint::Ect0, false)?;
+    ensure_eq!(connection.pop_ack()?.ece, false);
+    connection.receive_data(100, EcnCodepoint::Ce, false)?;
+    ensure_eq!(connection.pop_ack()?.ece, true);
+    connection.receive_data(100, EcnCodepoint::Ect0, false)?;
+    ensure_eq!(connection.pop_ack()?.ece, true);
+    connection.receive_data(100, EcnCodepoint::Ect0, true)?;
+    ensure_eq!(connection.pop_ack()?.ece, false);
+
+    Ok(())
+}
+
+// Test that an ECN echo reduces the congestion window once per window of data, and that the next new data that we send
+// carries CWR.
+#[test]
+fn cwr_after_window_reduction() -> Result<()> {
+    let (mut connection, _): (Connection, Segment) = Connection::connect(true)?;
+    connection.set_socket_option(SocketOption::CongestionControl(CongestionControlAlgorithm::NewReno))?;
+
+    // Slow start grows the window from four segments to eight.
+    for _ in 0..4 {
+        connection.push(MSS as usize)?;
+    }
+    ensure_eq!(connection.pop_segments()?.len(), 4);
+    for ack_num in [501, 1001, 1501, 2001] {
+        connection.receive_ack(ack_num, false)?;
+    }
+    ensure_eq!(connection.tcp_info()?.congestion_window, 8 * MSS as u32);
+    for _ in 0..8 {
+        connection.push(MSS as usize)?;
+    }
+    ensure_eq!(connection.pop_segments()?.len(), 8);
+
+    // The window drops to half of the data in flight.
+    connection.receive_ack(2501, true)?;
+    ensure_eq!(connection.tcp_info()?.congestion_window, 7 * MSS as u32 / 2);
+    // Another echo for the same window of data doesn't reduce the window again.
+    connection.receive_ack(3001, true)?;
+    ensure_eq!(connection.tcp_info()?.congestion_window >= 7 * MSS as u32 / 2, true);
+
+    // Once there is room in the window, the next new data carries CWR, and only that.
+    connection.receive_ack(5501, false)?;
+    for _ in 0..2 {
+        connection.push(MSS as usize)?;
+    }
+    let cwr: Vec<bool> = connection
+        .pop_segments()?
+        .iter()
+        .map(|segment| segment.header.cwr)
+        .collect();
+    ensure_eq!(cwr, vec![true, false]);
+
+    Ok(())
+}
+
+//======================================================================================================================
+// Standalone Functions
+//======================================================================================================================
+
+/// Our side of a connection, where the test plays our peer. Sequence numbers are relative to the initial sequence
+/// numbers of either side.
+struct Connection {
+    engine: SharedEngine,
+    local_ipv4: Ipv4Addr,
+    local_mac: MacAddress,
+    local_port: u16,
+    qd: QDesc,
+    local_isn: SeqNumber,
+    remote_seq_no: SeqNumber,
+}
+
+impl Connection {
+    /// Opens a connection with a configuration that requests ECN, and returns it along with our SYN. Our peer agrees to
+    /// ECN in its SYN+ACK if `ece` is set.
+    fn connect(ece: bool) -> Result<(Self, Segment)> {
+        let mut connection: Self = Self::new(
+            test_helpers::CARRIE_CONFIG_PATH,
+            test_helpers::CARRIE_IPV4,
+            test_helpers::CARRIE_MAC,
+        )?;
+        connection.qd = connection.engine.tcp_socket()?;
+        let remote: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, REMOTE_PORT);
+        let connect_qt: QToken = connection.engine.tcp_connect(connection.qd, remote)?;
+        let mut segments: Vec<Segment> = connection.pop_segments()?;
+        ensure_eq!(segments.len(), 1);
+        let syn: Segment = segments.remove(0);
+        ensure_eq!(syn.header.syn, true);
+        connection.local_isn = syn.header.seq_num;
+        connection.local_port = syn.header.src_port;
+
+        let mut syn_ack: TcpHeader = connection.tcp_header(REMOTE_PORT);
+        syn_ack.syn = true;
+        syn_ack.ack = true;
+        syn_ack.ack_num = connection.local_isn + SeqNumber::from(1);
+        syn_ack.ece = ece;
+        syn_ack.push_option(TcpOptions2::MaximumSegmentSize(MSS));
+        connection.receive(syn_ack, 0, EcnCodepoint::NotEct)?;
+        connection.remote_seq_no = SeqNumber::from(1);
+        match connection.engine.wait(connect_qt, Duration::from_secs(1))? {
+            (_, OperationResult::Connect) => (),
+            (_, result) => anyhow::bail!("connect should succeed (result={:?})", result),
+        }
+        // Throw away the ACK for the SYN+ACK.
+        connection.pop_segments()?;
+        Ok((connection, syn))
+    }
+
+    /// Listens for connections with a configuration that only agrees to ECN when our peer requests it.
+    fn listen() -> Result<Self> {
+        let mut connection: Self = Self::new(
+            test_helpers::ALICE_CONFIG_PATH,
+            test_helpers::ALICE_IPV4,
+            test_helpers::ALICE_MAC,
+        )?;
+        connection.qd = connection.engine.tcp_socket()?;
+        connection
+            .engine
+            .tcp_bind(connection.qd, SocketAddrV4::new(test_helpers::ALICE_IPV4, LOCAL_PORT))?;
+        connection.engine.tcp_listen(connection.qd, 16)?;
+        Ok(connection)
+    }
+
+    fn new(config_path: &str, local_ipv4: Ipv4Addr, local_mac: MacAddress) -> Result<Self> {
+        let now: Instant = Instant::now();
+        let test_rig: SharedTestPhysicalLayer = SharedTestPhysicalLayer::new_test(now);
+        let engine: SharedEngine = SharedEngine::new(config_path, test_rig, now)?;
+        Ok(Self {
+            engine,
+            local_ipv4,
+            local_mac,
+            local_port: LOCAL_PORT,
+            qd: QDesc::from(0),
+            local_isn: SeqNumber::from(0),
+            remote_seq_no: SeqNumber::from(0),
+        })
+    }
+
+    /// Receives a SYN from `remote_port` that requests ECN if `ece_cwr` is set, and returns our SYN+ACK.
+    fn receive_syn(&mut self, remote_port: u16, ece_cwr: bool) -> Result<Segment> {
+        let mut syn: TcpHeader = self.tcp_header(remote_port);
+        syn.syn = true;
+        syn.ece = ece_cwr;
+        syn.cwr = ece_cwr;
+        syn.push_option(TcpOptions2::MaximumSegmentSize(MSS));
+        self.receive(syn, 0, EcnCodepoint::NotEct)?;
+        let mut segments: Vec<Segment> = self.pop_segments()?;
+        ensure_eq!(segments.len(), 1);
+        let syn_ack: Segment = segments.remove(0);
+        ensure_eq!(syn_ack.header.syn && syn_ack.header.ack, true);
+        Ok(syn_ack)
+    }
+
+    fn push(&mut self, len: usize) -> Result<()> {
+        let buf: DemiBuffer = DemiBuffer::new_with_headroom(len as u16, MAX_HEADER_SIZE as u16);
+        self.engine.tcp_push(self.qd, buf)?;
+        self.engine.poll();
+        Ok(())
+    }
+
+    fn set_socket_option(&mut self, option: SocketOption) -> Result<()> {
+        self.engine.tcp_set_socket_option(self.qd, option)?;
+        Ok(())
+    }
+
+    fn tcp_info(&mut self) -> Result<TcpInfo> {
+        match self
+            .engine
+            .tcp_get_socket_option(self.qd, SocketOption::TcpInfo(TcpInfo::default()))?
+        {
+            SocketOption::TcpInfo(tcp_info) => Ok(tcp_info),
+            option => anyhow::bail!("should get TCP_INFO (option={:?})", option),
+        }
+    }
+
+    /// Sends `len` bytes of data with the `ecn` codepoint, and with CWR if `cwr` is set.
+    fn receive_data(&mut self, len: usize, ecn: EcnCodepoint, cwr: bool) -> Result<()> {
+        let mut header: TcpHeader = self.tcp_header(REMOTE_PORT);
+        header.ack = true;
+        header.ack_num = self.local_isn + SeqNumber::from(1);
+        header.cwr = cwr;
+        self.receive(header, len, ecn)?;
+        self.remote_seq_no = self.remote_seq_no + SeqNumber::from(len as u32);
+        Ok(())
+    }
+
+    /// Sends an ACK for `ack_num`, with ECE if `ece` is set.
+    fn receive_ack(&mut self, ack_num: u32, ece: bool) -> Result<()> {
+        let mut header: TcpHeader = self.tcp_header(REMOTE_PORT);
+        header.ack = true;
+        header.ack_num = self.local_isn + SeqNumber::from(ack_num);
+        header.ece = ece;
+        self.receive(header, 0, EcnCodepoint::NotEct)
+    }
+
+    /// Returns the pure ACK that we sent.
+    fn pop_ack(&mut self) -> Result<TcpHeader> {
+        let mut segments: Vec<Segment> = self.pop_segments()?;
+        ensure_eq!(segments.len(), 1);
+        let ack: Segment = segments.remove(0);
+        ensure_eq!((ack.len, ack.ecn), (0, EcnCodepoint::NotEct));
+        Ok(ack.header)
+    }
+
+    fn pop_segments(&mut self) -> Result<Vec<Segment>> {
+        pop_segments(&mut self.engine)
+    }
+
+    fn tcp_header(&self, remote_port: u16) -> TcpHeader {
+        let mut header: TcpHeader = TcpHeader::new(remote_port, self.local_port);
+        header.seq_num = self.remote_seq_no;
+        header.window_size = WINDOW_SIZE;
+        header
+    }
+
+    fn receive(&mut self, header: TcpHeader, len: usize, ecn: EcnCodepoint) -> Result<()> {
+        receive_at(&mut self.engine, self.local_ipv4, self.local_mac, header, len, ecn)
+    }
+}
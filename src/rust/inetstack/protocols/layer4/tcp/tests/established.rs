@@ -0,0 +1,622 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+// Tests for the established state that need finer control over our peer's segments than the packet simulator gives
+// us, such as SACK blocks.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use super::{advance_clock, pop_segments, receive, Segment, LOCAL_PORT, MSS, REMOTE_PORT, WINDOW_SIZE};
+use crate::{
+    ensure_eq,
+    inetstack::{
+        protocols::{
+            layer4::tcp::{
+                header::{SelectiveAcknowlegement, TcpHeader, TcpOptions2},
+                SeqNumber,
+            },
+            MAX_HEADER_SIZE,
+        },
+        test_helpers::{self, engine::SharedEngine, physical_layer::SharedTestPhysicalLayer},
+    },
+    runtime::{
+        memory::DemiBuffer,
+        network::socket::option::{CongestionControlAlgorithm, SocketOption, TcpInfo},
+        OperationResult, SharedDemiRuntime,
+    },
+    QDesc, QToken,
+};
+use ::anyhow::Result;
+use ::std::{
+    collections::VecDeque,
+    net::SocketAddrV4,
+    time::{Duration, Instant},
+};
+
+//======================================================================================================================
+// Constants
+//======================================================================================================================
+
+const KEEPALIVE_IDLE: Duration = Duration::from_secs(60);
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(5);
+// Matches the retransmission limits in our configuration.
+const DATA_RETRIES: usize = 15;
+const RTO_MAX: Duration = Duration::from_secs(60);
+
+//======================================================================================================================
+// Tests
+//======================================================================================================================
+
+// Test that a retransmission merges whole segments up to the MSS, and that a segment it didn't carry keeps its
+// original transmission time, so a partial ACK doesn't push its retransmission back by another RTO.
+#[test]
+fn retransmission_merges_whole_segments() -> Result<()> {
+    let mut connection: Connection = Connection::accept(MSS, &[])?;
+    for _ in 0..4 {
+        connection.push(400)?;
+    }
+    ensure_eq!(
+        connection.pop_segments()?,
+        vec![
+            (1, 400, false),
+            (401, 400, false),
+            (801, 400, false),
+            (1201, 400, false)
+        ]
+    );
+
+    // The first three segments fit into one MSS, the fourth one doesn't.
+    connection.advance_clock(Duration::from_secs(1));
+    ensure_eq!(connection.pop_segments()?, vec![(1, 1200, false)]);
+
+    // A partial ACK doesn't make us resend what we just retransmitted.
+    connection.advance_clock(Duration::from_millis(100));
+    connection.receive_ack(801, &[])?;
+    ensure_eq!(connection.pop_segments()?, vec![]);
+
+    // Once everything that we retransmitted is acknowledged, the fourth segment times out on its own schedule, which
+    // started when we first sent it.
+    connection.advance_clock(Duration::from_millis(100));
+    connection.receive_ack(1201, &[])?;
+    ensure_eq!(connection.pop_segments()?, vec![]);
+    connection.advance_clock(Duration::from_millis(800));
+    ensure_eq!(connection.pop_segments()?, vec![(1201, 400, false)]);
+    connection.receive_ack(1601, &[])?;
+    connection.wait_for_pushes()?;
+
+    Ok(())
+}
+
+// Test that retransmitting only the start of a segment leaves the rest of it behind as a segment of its own. When the
+// MSS shrinks, that rest is deemed lost and goes out as soon as the first part is acknowledged.
+#[test]
+fn retransmission_splits_partially_resent_segment() -> Result<()> {
+    let mut connection: Connection = Connection::accept(MSS, &[])?;
+    connection.push(1450)?;
+    connection.push(400)?;
+    ensure_eq!(connection.pop_segments()?, vec![(1, 1450, false), (1451, 400, false)]);
+
+    // The full-sized segment is lost twice, with the retransmission timer backing off in between.
+    connection.advance_clock(Duration::from_secs(1));
+    ensure_eq!(connection.pop_segments()?, vec![(1, 1450, false)]);
+    connection.advance_clock(Duration::from_secs(2));
+    ensure_eq!(connection.pop_segments()?, vec![(1, 1450, false)]);
+
+    // Suspect a black hole and resend the first half of the segment.
+    connection.advance_clock(Duration::from_secs(4));
+    ensure_eq!(connection.pop_segments()?, vec![(1, 725, false)]);
+
+    // The second half goes out as soon as the first half gets through, on its own since the next segment doesn't fit.
+    connection.advance_clock(Duration::from_millis(100));
+    connection.receive_ack(726, &[])?;
+    ensure_eq!(connection.pop_segments()?, vec![(726, 725, false)]);
+
+    // The next segment was never retransmitted, so it times out one (backed off) RTO after we first sent it.
+    connection.advance_clock(Duration::from_millis(100));
+    connection.receive_ack(1451, &[])?;
+    ensure_eq!(connection.pop_segments()?, vec![]);
+    connection.advance_clock(Duration::from_millis(800));
+    ensure_eq!(connection.pop_segments()?, vec![(1451, 400, false)]);
+    connection.receive_ack(1851, &[])?;
+    connection.wait_for_pushes()?;
+
+    Ok(())
+}
+
+// Test that SACK-based loss recovery halves the window, retransmits only the holes as the window allows and ends once
+// everything that was outstanding is acknowledged.
+#[test]
+fn sack_loss_recovery() -> Result<()> {
+    let mut connection: Connection = Connection::accept(500, &[TcpOptions2::SelectiveAcknowlegementPermitted])?;
+    connection.set_socket_option(SocketOption::CongestionControl(CongestionControlAlgorithm::NewReno))?;
+
+    // Slow start takes the window from four to eight segments.
+    for _ in 0..4 {
+        connection.push(500)?;
+    }
+    ensure_eq!(connection.pop_segments()?.len(), 4);
+    connection.advance_clock(Duration::from_millis(10));
+    for ack_num in [501, 1001, 1501, 2001] {
+        connection.receive_ack(ack_num, &[])?;
+    }
+    connection.wait_for_pushes()?;
+    ensure_eq!(connection.tcp_info()?.congestion_window, 4000);
+
+    // The first and the fourth of the next eight segments get lost.
+    for _ in 0..8 {
+        connection.push(500)?;
+    }
+    ensure_eq!(connection.pop_segments()?.len(), 8);
+    connection.advance_clock(Duration::from_millis(10));
+    connection.receive_ack(2001, &[(2501, 3001)])?;
+    connection.receive_ack(2001, &[(2501, 3501)])?;
+    ensure_eq!(connection.pop_segments()?, vec![]);
+
+    // The third duplicate ACK starts loss recovery, with room for the first hole only.
+    connection.receive_ack(2001, &[(2501, 3501), (4001, 4501)])?;
+    ensure_eq!(connection.pop_segments()?, vec![(2001, 500, false)]);
+    ensure_eq!(connection.tcp_info()?.congestion_window, 2000);
+
+    // The second hole has to wait until enough segments have left the network, without the window growing.
+    connection.receive_ack(2001, &[(2501, 3501), (4001, 5001)])?;
+    ensure_eq!(connection.pop_segments()?, vec![]);
+    connection.receive_ack(2001, &[(2501, 3501), (4001, 5501)])?;
+    ensure_eq!(connection.pop_segments()?, vec![(3501, 500, false)]);
+    ensure_eq!(connection.tcp_info()?.congestion_window, 2000);
+
+    // A partial ACK has nothing left to retransmit, and the full ACK ends loss recovery.
+    connection.receive_ack(3501, &[(4001, 5501)])?;
+    ensure_eq!(connection.pop_segments()?, vec![]);
+    connection.receive_ack(6001, &[])?;
+    connection.wait_for_pushes()?;
+    ensure_eq!(connection.tcp_info()?.total_retransmits, 2);
+
+    // New data flows again, limited by the deflated window.
+    connection.push(500)?;
+    connection.push(500)?;
+    connection.push(500)?;
+    ensure_eq!(connection.pop_segments()?, vec![(6001, 500, false), (6501, 500, false)]);
+
+    Ok(())
+}
+
+// Test that RACK deems a segment lost once our peer SACKs one that we sent after it, long enough after it that
+// reordering can't explain the hole, even though a single SACK block is not enough for RFC 6675 to deem it lost.
+#[test]
+fn rack_sack_deems_segment_lost() -> Result<()> {
+    let mut connection: Connection = Connection::accept(500, &[TcpOptions2::SelectiveAcknowlegementPermitted])?;
+    connection.push(500)?;
+    ensure_eq!(connection.pop_segments()?, vec![(1, 500, false)]);
+    connection.advance_clock(Duration::from_millis(20));
+    connection.push(500)?;
+    ensure_eq!(connection.pop_segments()?, vec![(501, 500, false)]);
+
+    connection.advance_clock(Duration::from_millis(20));
+    connection.receive_ack(1, &[(501, 1001)])?;
+    ensure_eq!(connection.pop_segments()?, vec![(1, 500, false)]);
+    connection.receive_ack(1001, &[])?;
+    connection.wait_for_pushes()?;
+    ensure_eq!(connection.tcp_info()?.total_retransmits, 1);
+
+    Ok(())
+}
+
+// Test that a segment sent along with one that our peer SACKs gets a quarter of the minimum RTT to arrive out of
+// order before the reordering timer deems it lost.
+#[test]
+fn rack_reordering_timer() -> Result<()> {
+    let mut connection: Connection = Connection::accept(500, &[TcpOptions2::SelectiveAcknowlegementPermitted])?;
+    connection.push(500)?;
+    connection.push(500)?;
+    ensure_eq!(connection.pop_segments()?, vec![(1, 500, false), (501, 500, false)]);
+
+    connection.advance_clock(Duration::from_millis(20));
+    connection.receive_ack(1, &[(501, 1001)])?;
+    ensure_eq!(connection.pop_segments()?, vec![]);
+    connection.advance_clock(Duration::from_millis(4));
+    ensure_eq!(connection.pop_segments()?, vec![]);
+    connection.advance_clock(Duration::from_millis(1));
+    ensure_eq!(connection.pop_segments()?, vec![(1, 500, false)]);
+    connection.receive_ack(1001, &[])?;
+    connection.wait_for_pushes()?;
+
+    Ok(())
+}
+
+// Test that a tail loss probe sends new data when the congestion window holds it back but our peer's window has room
+// for it, rather than retransmitting the last segment that we sent.
+#[test]
+fn tail_loss_probe_sends_new_data() -> Result<()> {
+    let mut connection: Connection = Connection::accept(500, &[TcpOptions2::SelectiveAcknowlegementPermitted])?;
+    connection.set_socket_option(SocketOption::CongestionControl(CongestionControlAlgorithm::NewReno))?;
+
+    // An RTT sample of 10 ms sets the probe timeout to 20 ms while more than one segment is in flight, and slow start
+    // opens the window to five segments.
+    connection.push(500)?;
+    ensure_eq!(connection.pop_segments()?, vec![(1, 500, false)]);
+    connection.advance_clock(Duration::from_millis(10));
+    connection.receive_ack(501, &[])?;
+    connection.wait_for_pushes()?;
+
+    for _ in 0..6 {
+        connection.push(500)?;
+    }
+    ensure_eq!(connection.pop_segments()?.len(), 5);
+    connection.advance_clock(Duration::from_millis(19));
+    ensure_eq!(connection.pop_segments()?, vec![]);
+    connection.advance_clock(Duration::from_millis(1));
+    ensure_eq!(connection.pop_segments()?, vec![(3001, 500, false)]);
+    ensure_eq!(connection.tcp_info()?.total_retransmits, 0);
+
+    connection.receive_ack(3501, &[])?;
+    connection.wait_for_pushes()?;
+
+    Ok(())
+}
+
+// Test that switching congestion control algorithms mid-transfer lets data held back by the old algorithm's window go
+// out under the new one.
+#[test]
+fn congestion_control_switch_mid_transfer() -> Result<()> {
+    let mut connection: Connection = Connection::accept(500, &[])?;
+    connection.set_socket_option(SocketOption::CongestionControl(CongestionControlAlgorithm::NewReno))?;
+
+    // NewReno's initial window holds four segments.
+    for _ in 0..6 {
+        connection.push(500)?;
+    }
+    ensure_eq!(connection.pop_segments()?.len(), 4);
+    ensure_eq!(connection.pop_segments()?, vec![]);
+
+    // Without congestion control, only the peer's window limits us.
+    connection.set_socket_option(SocketOption::CongestionControl(CongestionControlAlgorithm::None))?;
+    ensure_eq!(connection.pop_segments()?, vec![(2001, 500, false), (2501, 500, false)]);
+    connection.receive_ack(3001, &[])?;
+    connection.wait_for_pushes()?;
+
+    // Switching back starts NewReno from scratch, with its initial window.
+    connection.set_socket_option(SocketOption::CongestionControl(CongestionControlAlgorithm::NewReno))?;
+    for _ in 0..6 {
+        connection.push(500)?;
+    }
+    ensure_eq!(connection.pop_segments()?.len(), 4);
+    connection.receive_ack(5001, &[])?;
+    ensure_eq!(connection.pop_segments()?, vec![(5001, 500, false), (5501, 500, false)]);
+
+    Ok(())
+}
+
+// Test that a push that doesn't fit into the send buffer is held back until there is room for it, and that pushes
+// complete in the order they were made.
+#[test]
+fn push_with_small_send_buffer() -> Result<()> {
+    let mut connection: Connection = Connection::accept(500, &[])?;
+    connection.set_socket_option(SocketOption::SendBufferSize(1000))?;
+
+    // The third push waits for room in the send buffer.
+    for _ in 0..3 {
+        connection.push(500)?;
+    }
+    ensure_eq!(connection.pop_segments()?, vec![(1, 500, false), (501, 500, false)]);
+    ensure_eq!(connection.completed_pushes()?, 0);
+
+    // Acknowledging the first push makes room for the third one.
+    connection.receive_ack(501, &[])?;
+    ensure_eq!(connection.pop_segments()?, vec![(1001, 500, false)]);
+    ensure_eq!(connection.completed_pushes()?, 1);
+    connection.receive_ack(1501, &[])?;
+    ensure_eq!(connection.completed_pushes()?, 2);
+
+    Ok(())
+}
+
+// Test that we drop the connection once our peer leaves as many retransmissions in a row unacknowledged as our
+// configuration allows, failing the push that is waiting for the acknowledgement.
+#[test]
+fn retransmissions_exhausted() -> Result<()> {
+    let mut connection: Connection = Connection::accept(MSS, &[])?;
+    connection.push(500)?;
+    ensure_eq!(connection.pop_segments()?, vec![(1, 500, false)]);
+
+    // The retransmission timeout backs off, but never beyond its maximum.
+    for _ in 0..DATA_RETRIES {
+        connection.advance_clock(RTO_MAX);
+        ensure_eq!(connection.pop_segments()?, vec![(1, 500, false)]);
+    }
+
+    connection.advance_clock(RTO_MAX);
+    ensure_eq!(connection.pop_segments()?, vec![]);
+    let push_qt: QToken = connection.pushes.pop_front().expect("there should be a pending push");
+    match connection.engine.wait(push_qt, Duration::from_secs(1))? {
+        (_, OperationResult::Failed(e)) if e.errno == libc::ETIMEDOUT => (),
+        (_, result) => anyhow::bail!("push should fail with ETIMEDOUT (result={:?})", result),
+    }
+
+    Ok(())
+}
+
+// Test that keepalive probes go out once the connection has been idle for long enough, and that the connection is
+// dropped once our peer leaves all of them unanswered.
+#[test]
+fn keepalive_probes_exhausted() -> Result<()> {
+    let mut connection: Connection = Connection::accept(MSS, &[])?;
+    connection.enable_keepalive(3)?;
+    let pop_qt: QToken = connection.engine.tcp_pop(connection.qd)?;
+
+    // A probe carries the sequence number right before the next one that our peer expects.
+    connection.advance_clock(KEEPALIVE_IDLE);
+    ensure_eq!(connection.pop_keepalive_probes()?, 1);
+    for _ in 0..2 {
+        connection.advance_clock(KEEPALIVE_INTERVAL);
+        ensure_eq!(connection.pop_keepalive_probes()?, 1);
+    }
+
+    connection.advance_clock(KEEPALIVE_INTERVAL);
+    match connection.engine.wait(pop_qt, Duration::from_secs(1))? {
+        (_, OperationResult::Failed(e)) if e.errno == libc::ETIMEDOUT => (),
+        (_, result) => anyhow::bail!("pop should fail with ETIMEDOUT (result={:?})", result),
+    }
+
+    Ok(())
+}
+
+// Test that hearing back from our peer resets the keepalive timer, so that the next probe waits for the connection to
+// be idle again and our peer gets the full number of probes to answer.
+#[test]
+fn keepalive_probe_ack_resets_timer() -> Result<()> {
+    let mut connection: Connection = Connection::accept(MSS, &[])?;
+    connection.enable_keepalive(2)?;
+    let pop_qt: QToken = connection.engine.tcp_pop(connection.qd)?;
+
+    connection.advance_clock(KEEPALIVE_IDLE);
+    ensure_eq!(connection.pop_keepalive_probes()?, 1);
+    connection.advance_clock(KEEPALIVE_INTERVAL);
+    ensure_eq!(connection.pop_keepalive_probes()?, 1);
+    connection.receive_ack(1, &[])?;
+
+    // Nothing goes out until the connection has been idle again.
+    connection.advance_clock(KEEPALIVE_INTERVAL);
+    ensure_eq!(connection.pop_keepalive_probes()?, 0);
+    connection.advance_clock(KEEPALIVE_IDLE - KEEPALIVE_INTERVAL);
+    ensure_eq!(connection.pop_keepalive_probes()?, 1);
+    connection.advance_clock(KEEPALIVE_INTERVAL);
+    ensure_eq!(connection.pop_keepalive_probes()?, 1);
+    ensure_eq!(
+        connection.engine.get_runtime().get_completed_task(&pop_qt).is_none(),
+        true
+    );
+
+    Ok(())
+}
+
+// Test that a RST from our peer fails the pushes and pops that are still pending with ECONNRESET.
+#[test]
+fn reset_fails_pending_operations() -> Result<()> {
+    let mut connection: Connection = Connection::accept(MSS, &[])?;
+    connection.push(500)?;
+    ensure_eq!(connection.pop_segments()?, vec![(1, 500, false)]);
+    let pop_qt: QToken = connection.engine.tcp_pop(connection.qd)?;
+    connection.engine.poll();
+
+    let mut rst: TcpHeader = connection.tcp_header();
+    rst.rst = true;
+    connection.receive(rst)?;
+
+    let push_qt: QToken = connection.pushes.pop_front().expect("there should be a push");
+    for qt in [push_qt, pop_qt] {
+        match connection.engine.wait(qt, Duration::from_secs(1))? {
+            (_, OperationResult::Failed(e)) if e.errno == libc::ECONNRESET => (),
+            (_, result) => anyhow::bail!("operation should fail with ECONNRESET (result={:?})", result),
+        }
+    }
+
+    Ok(())
+}
+
+// Test that closing with SO_LINGER set to a zero timeout resets the connection instead of sending a FIN.
+#[test]
+fn close_with_zero_linger() -> Result<()> {
+    let mut connection: Connection = Connection::accept(MSS, &[])?;
+    connection.set_socket_option(SocketOption::Linger(Some(Duration::ZERO)))?;
+
+    let close_qt: QToken = connection.engine.tcp_async_close(connection.qd)?;
+    match connection.engine.wait(close_qt, Duration::from_secs(1))? {
+        (_, OperationResult::Close) => (),
+        (_, result) => anyhow::bail!("close should succeed (result={:?})", result),
+    }
+    let (header, len): (TcpHeader, usize) = connection.pop_segment()?;
+    ensure_eq!((header.rst, header.fin, len), (true, false, 0));
+    ensure_eq!(header.seq_num, connection.local_isn + SeqNumber::from(1));
+
+    Ok(())
+}
+
+//======================================================================================================================
+// Standalone Functions
+//======================================================================================================================
+
+/// An established connection that we accepted, where the test plays our peer. Sequence numbers are relative to the
+/// initial sequence numbers of either side.
+struct Connection {
+    engine: SharedEngine,
+    now: Instant,
+    qd: QDesc,
+    local_isn: SeqNumber,
+    remote_seq_no: SeqNumber,
+    pushes: VecDeque<QToken>,
+}
+
+impl Connection {
+    /// Accepts a connection from a peer that announces `mss` and whose SYN carries `options`.
+    fn accept(mss: u16, options: &[TcpOptions2]) -> Result<Self> {
+        let now: Instant = Instant::now();
+        let test_rig: SharedTestPhysicalLayer = SharedTestPhysicalLayer::new_test(now);
+        let mut engine: SharedEngine = SharedEngine::new(test_helpers::ALICE_CONFIG_PATH, test_rig, now)?;
+        let listen_qd: QDesc = engine.tcp_socket()?;
+        engine.tcp_bind(listen_qd, SocketAddrV4::new(test_helpers::ALICE_IPV4, LOCAL_PORT))?;
+        engine.tcp_listen(listen_qd, 1)?;
+        let accept_qt: QToken = engine.tcp_accept(listen_qd)?;
+
+        let mut connection: Self = Self {
+            engine,
+            now,
+            qd: listen_qd,
+            local_isn: SeqNumber::from(0),
+            remote_seq_no: SeqNumber::from(0),
+            pushes: VecDeque::new(),
+        };
+
+        let mut syn: TcpHeader = connection.tcp_header();
+        syn.syn = true;
+        syn.push_option(TcpOptions2::MaximumSegmentSize(mss));
+        for option in options {
+            syn.push_option(*option);
+        }
+        connection.receive(syn)?;
+        let (syn_ack, _): (TcpHeader, usize) = connection.pop_segment()?;
+        ensure_eq!(syn_ack.syn, true);
+        connection.local_isn = syn_ack.seq_num;
+        connection.remote_seq_no = SeqNumber::from(1);
+        connection.receive_ack(1, &[])?;
+
+        connection.qd = match connection.engine.wait(accept_qt, Duration::from_secs(1))? {
+            (_, OperationResult::Accept((qd, _))) => qd,
+            (_, result) => anyhow::bail!("accept should succeed (result={:?})", result),
+        };
+        Ok(connection)
+    }
+
+    /// Pushes `len` bytes of data.
+    fn push(&mut self, len: usize) -> Result<()> {
+        let buf: DemiBuffer = DemiBuffer::new_with_headroom(len as u16, MAX_HEADER_SIZE as u16);
+        let push_qt: QToken = self.engine.tcp_push(self.qd, buf)?;
+        self.pushes.push_back(push_qt);
+        self.engine.poll();
+        Ok(())
+    }
+
+    /// Waits for all pushes to complete.
+    fn wait_for_pushes(&mut self) -> Result<()> {
+        while let Some(push_qt) = self.pushes.pop_front() {
+            match self.engine.wait(push_qt, Duration::from_secs(1))? {
+                (_, OperationResult::Push) => (),
+                (_, result) => anyhow::bail!("push should succeed (result={:?})", result),
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns how many pushes have completed since we last checked, making sure that they completed in order.
+    fn completed_pushes(&mut self) -> Result<usize> {
+        // The scheduler hands out one completed task at a time, so collect all of them before we look.
+        let mut runtime: SharedDemiRuntime = self.engine.get_runtime();
+        for _ in 0..self.pushes.len() {
+            runtime.run_any(&[], Duration::ZERO);
+        }
+        let mut completed: Vec<bool> = Vec::new();
+        for push_qt in self.pushes.iter() {
+            match runtime.get_completed_task(push_qt) {
+                Some((_, OperationResult::Push)) => completed.push(true),
+                Some((_, result)) => anyhow::bail!("push should succeed (result={:?})", result),
+                None => completed.push(false),
+            }
+        }
+        let num_completed: usize = completed.iter().take_while(|completed| **completed).count();
+        ensure_eq!(completed.iter().filter(|completed| **completed).count(), num_completed);
+        self.pushes.drain(..num_completed);
+        Ok(num_completed)
+    }
+
+    fn set_socket_option(&mut self, option: SocketOption) -> Result<()> {
+        self.engine.tcp_set_socket_option(self.qd, option)?;
+        Ok(())
+    }
+
+    /// Turns on keepalive probes, giving up on our peer after `probes` of them go unanswered.
+    fn enable_keepalive(&mut self, probes: u32) -> Result<()> {
+        self.set_socket_option(SocketOption::KeepAliveIdle(KEEPALIVE_IDLE))?;
+        self.set_socket_option(SocketOption::KeepAliveInterval(KEEPALIVE_INTERVAL))?;
+        self.set_socket_option(SocketOption::KeepAliveProbes(probes))?;
+        self.set_socket_option(SocketOption::KeepAlive(true))
+    }
+
+    /// Returns how many keepalive probes we sent, failing on any other segment.
+    fn pop_keepalive_probes(&mut self) -> Result<usize> {
+        let segments: Vec<(u32, usize, bool)> = self.pop_segments()?;
+        for segment in segments.iter() {
+            ensure_eq!(*segment, (0, 0, false));
+        }
+        Ok(segments.len())
+    }
+
+    fn tcp_info(&mut self) -> Result<TcpInfo> {
+        match self
+            .engine
+            .tcp_get_socket_option(self.qd, SocketOption::TcpInfo(TcpInfo::default()))?
+        {
+            SocketOption::TcpInfo(tcp_info) => Ok(tcp_info),
+            option => anyhow::bail!("should get TCP_INFO (option={:?})", option),
+        }
+    }
+
+    fn advance_clock(&mut self, duration: Duration) {
+        advance_clock(&mut self.engine, &mut self.now, duration);
+    }
+
+    /// Sends an ACK for `ack_num` that SACKs the `sacks` ranges.
+    fn receive_ack(&mut self, ack_num: u32, sacks: &[(u32, u32)]) -> Result<()> {
+        let mut header: TcpHeader = self.tcp_header();
+        header.ack = true;
+        header.ack_num = self.local_isn + SeqNumber::from(ack_num);
+        if !sacks.is_empty() {
+            let mut blocks: [SelectiveAcknowlegement; 4] = [SelectiveAcknowlegement {
+                begin: SeqNumber::from(0),
+                end: SeqNumber::from(0),
+            }; 4];
+            for (block, (begin, end)) in blocks.iter_mut().zip(sacks) {
+                block.begin = self.local_isn + SeqNumber::from(*begin);
+                block.end = self.local_isn + SeqNumber::from(*end);
+            }
+            header.push_option(TcpOptions2::SelectiveAcknowlegement {
+                num_sacks: sacks.len(),
+                sacks: blocks,
+            });
+        }
+        self.receive(header)
+    }
+
+    /// Returns the relative sequence number, the length and the FIN flag of every segment that we sent.
+    fn pop_segments(&mut self) -> Result<Vec<(u32, usize, bool)>> {
+        let segments: Vec<Segment> = pop_segments(&mut self.engine)?;
+        Ok(segments
+            .iter()
+            .map(|segment| {
+                (
+                    u32::from(segment.header.seq_num - self.local_isn),
+                    segment.len,
+                    segment.header.fin,
+                )
+            })
+            .collect())
+    }
+
+    fn pop_segment(&mut self) -> Result<(TcpHeader, usize)> {
+        let mut segments: Vec<Segment> = pop_segments(&mut self.engine)?;
+        ensure_eq!(segments.len(), 1);
+        let segment: Segment = segments.remove(0);
+        Ok((segment.header, segment.len))
+    }
+
+    fn tcp_header(&self) -> TcpHeader {
+        let mut header: TcpHeader = TcpHeader::new(REMOTE_PORT, LOCAL_PORT);
+        header.seq_num = self.remote_seq_no;
+        header.window_size = WINDOW_SIZE;
+        header
+    }
+
+    fn receive(&mut self, header: TcpHeader) -> Result<()> {
+        receive(&mut self.engine, header, 0)
+    }
+}
@@ -0,0 +1,261 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+// Tests for TCP Fast Open (RFC 7413), where the test plays the other side of the connection so that it can look at
+// the data and the cookies in the SYNs.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use super::{advance_clock, pop_segments, receive, Segment, LOCAL_PORT, MSS, REMOTE_PORT, WINDOW_SIZE};
+use crate::{
+    ensure_eq,
+    inetstack::{
+        protocols::{
+            layer4::tcp::{
+                header::{FastOpenCookie, TcpHeader, TcpOptions2},
+                SeqNumber,
+            },
+            MAX_HEADER_SIZE,
+        },
+        test_helpers::{self, engine::SharedEngine, physical_layer::SharedTestPhysicalLayer},
+    },
+    runtime::{memory::DemiBuffer, OperationResult},
+    QDesc, QToken,
+};
+use ::anyhow::Result;
+use ::std::{
+    net::SocketAddrV4,
+    time::{Duration, Instant},
+};
+
+//======================================================================================================================
+// Constants
+//======================================================================================================================
+
+const DATA_SIZE: usize = 32;
+// Matches the handshake timeout in our configuration.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(3);
+
+//======================================================================================================================
+// Tests
+//======================================================================================================================
+
+// Test that a client asks for a cookie on its first connection, sends data in the SYN with that cookie on the next
+// ones, leaves the data out of a retransmitted SYN, and completes the connect without waiting for the data to be
+// acknowledged.
+#[test]
+fn connect_with_data() -> Result<()> {
+    let mut peer: Peer = Peer::new()?;
+    let cookie: FastOpenCookie = FastOpenCookie::new(&[1, 2, 3, 4, 5, 6, 7, 8]);
+
+    // Without a cookie, the SYN asks for one and the data goes out once the connection is established.
+    let (qt, syn, len): (QToken, TcpHeader, usize) = peer.connect_with_data()?;
+    ensure_eq!(len, 0);
+    ensure_eq!(fast_open_cookie(&syn), Some(FastOpenCookie::default()));
+    peer.receive_syn_ack(&syn, 0, Some(cookie))?;
+    peer.wait(qt)?;
+    let segments: Vec<Segment> = peer.pop_segments()?;
+    ensure_eq!(segments.len(), 2);
+    ensure_eq!(segments[1].header.seq_num, syn.seq_num + SeqNumber::from(1));
+    ensure_eq!(segments[1].len, DATA_SIZE);
+    peer.receive_data_ack(&syn, DATA_SIZE)?;
+
+    // With the cookie, the data goes in the SYN, and the server acknowledges it in its SYN+ACK.
+    let (qt, syn, len): (QToken, TcpHeader, usize) = peer.connect_with_data()?;
+    ensure_eq!(len, DATA_SIZE);
+    ensure_eq!(fast_open_cookie(&syn), Some(cookie));
+    peer.receive_syn_ack(&syn, DATA_SIZE, None)?;
+    peer.wait(qt)?;
+    let segments: Vec<Segment> = peer.pop_segments()?;
+    ensure_eq!(segments.len(), 1);
+    ensure_eq!(segments[0].len, 0);
+
+    // A retransmitted SYN leaves out the data, which goes out once the connection is established.
+    let (qt, syn, len): (QToken, TcpHeader, usize) = peer.connect_with_data()?;
+    ensure_eq!(len, DATA_SIZE);
+    peer.advance_clock(HANDSHAKE_TIMEOUT);
+    let segments: Vec<Segment> = peer.pop_segments()?;
+    ensure_eq!(segments.len(), 1);
+    ensure_eq!(segments[0].header.syn, true);
+    ensure_eq!(segments[0].len, 0);
+    ensure_eq!(fast_open_cookie(&segments[0].header), Some(cookie));
+    peer.receive_syn_ack(&syn, 0, None)?;
+    peer.wait(qt)?;
+    let segments: Vec<Segment> = peer.pop_segments()?;
+    ensure_eq!(segments.len(), 2);
+    ensure_eq!(segments[1].header.seq_num, syn.seq_num + SeqNumber::from(1));
+    ensure_eq!(segments[1].len, DATA_SIZE);
+
+    Ok(())
+}
+
+// Test that a server gives out a cookie, accepts the data in a SYN with that cookie before the handshake completes,
+// and drops the data in a SYN with a forged cookie.
+#[test]
+fn accept_with_data() -> Result<()> {
+    let mut peer: Peer = Peer::new()?;
+    let listen_qd: QDesc = peer.engine.tcp_socket()?;
+    peer.engine
+        .tcp_bind(listen_qd, SocketAddrV4::new(test_helpers::ALICE_IPV4, LOCAL_PORT))?;
+    peer.engine.tcp_listen(listen_qd, 1)?;
+
+    // A SYN that asks for a cookie gets one.
+    let accept_qt: QToken = peer.engine.tcp_accept(listen_qd)?;
+    let (syn_ack, _): (TcpHeader, usize) = peer.receive_syn(REMOTE_PORT, Some(FastOpenCookie::default()), 0)?;
+    let cookie: FastOpenCookie = match fast_open_cookie(&syn_ack) {
+        Some(cookie) if !cookie.is_empty() => cookie,
+        cookie => anyhow::bail!("SYN+ACK should carry a cookie (cookie={:?})", cookie),
+    };
+    peer.receive_ack(REMOTE_PORT, &syn_ack)?;
+    peer.accept(accept_qt)?;
+
+    // With that cookie, the application gets the data right away, before our peer completes the handshake.
+    let accept_qt: QToken = peer.engine.tcp_accept(listen_qd)?;
+    let (syn_ack, _): (TcpHeader, usize) = peer.receive_syn(REMOTE_PORT + 1, Some(cookie), DATA_SIZE)?;
+    ensure_eq!(syn_ack.ack_num, SeqNumber::from(1 + DATA_SIZE as u32));
+    ensure_eq!(fast_open_cookie(&syn_ack), None);
+    let qd: QDesc = peer.accept(accept_qt)?;
+    let pop_qt: QToken = peer.engine.tcp_pop(qd)?;
+    match peer.engine.wait(pop_qt, Duration::from_secs(1))? {
+        (_, OperationResult::Pop(_, buf)) => ensure_eq!(buf.len(), DATA_SIZE),
+        (_, result) => anyhow::bail!("pop should succeed (result={:?})", result),
+    }
+
+    // With a forged cookie, the data is dropped and our peer gets a valid cookie.
+    let accept_qt: QToken = peer.engine.tcp_accept(listen_qd)?;
+    let forged_cookie: FastOpenCookie = FastOpenCookie::new(&[0; 8]);
+    let (syn_ack, _): (TcpHeader, usize) = peer.receive_syn(REMOTE_PORT + 2, Some(forged_cookie), DATA_SIZE)?;
+    ensure_eq!(syn_ack.ack_num, SeqNumber::from(1));
+    ensure_eq!(fast_open_cookie(&syn_ack), Some(cookie));
+    peer.receive_ack(REMOTE_PORT + 2, &syn_ack)?;
+    peer.accept(accept_qt)?;
+
+    Ok(())
+}
+
+//======================================================================================================================
+// Standalone Functions
+//======================================================================================================================
+
+/// Our side of the connections, along with the clock. The test plays the other side.
+struct Peer {
+    engine: SharedEngine,
+    now: Instant,
+}
+
+impl Peer {
+    fn new() -> Result<Self> {
+        let now: Instant = Instant::now();
+        let test_rig: SharedTestPhysicalLayer = SharedTestPhysicalLayer::new_test(now);
+        let engine: SharedEngine = SharedEngine::new(test_helpers::ALICE_CONFIG_PATH, test_rig, now)?;
+        Ok(Self { engine, now })
+    }
+
+    /// Opens a connection with data, and returns the SYN that we sent along with the length of the data in it.
+    fn connect_with_data(&mut self) -> Result<(QToken, TcpHeader, usize)> {
+        let qd: QDesc = self.engine.tcp_socket()?;
+        let remote: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, REMOTE_PORT);
+        let buf: DemiBuffer = DemiBuffer::new_with_headroom(DATA_SIZE as u16, MAX_HEADER_SIZE as u16);
+        let qt: QToken = self.engine.tcp_connect_with_data(qd, remote, buf)?;
+        let mut segments: Vec<Segment> = self.pop_segments()?;
+        ensure_eq!(segments.len(), 1);
+        let syn: Segment = segments.remove(0);
+        ensure_eq!(syn.header.syn, true);
+        Ok((qt, syn.header, syn.len))
+    }
+
+    /// Answers `syn` with a SYN+ACK that acknowledges `acked_len` bytes of data and carries `cookie`.
+    fn receive_syn_ack(&mut self, syn: &TcpHeader, acked_len: usize, cookie: Option<FastOpenCookie>) -> Result<()> {
+        let mut syn_ack: TcpHeader = TcpHeader::new(syn.dst_port, syn.src_port);
+        syn_ack.syn = true;
+        syn_ack.ack = true;
+        syn_ack.seq_num = SeqNumber::from(0);
+        syn_ack.ack_num = syn.seq_num + SeqNumber::from(1 + acked_len as u32);
+        syn_ack.window_size = WINDOW_SIZE;
+        syn_ack.push_option(TcpOptions2::MaximumSegmentSize(MSS));
+        if let Some(cookie) = cookie {
+            syn_ack.push_option(TcpOptions2::FastOpen(cookie));
+        }
+        self.receive(syn_ack, 0)
+    }
+
+    /// Acknowledges `len` bytes of the data that we sent on the connection that `syn` opened.
+    fn receive_data_ack(&mut self, syn: &TcpHeader, len: usize) -> Result<()> {
+        let mut ack: TcpHeader = TcpHeader::new(syn.dst_port, syn.src_port);
+        ack.ack = true;
+        ack.seq_num = SeqNumber::from(1);
+        ack.ack_num = syn.seq_num + SeqNumber::from(1 + len as u32);
+        ack.window_size = WINDOW_SIZE;
+        self.receive(ack, 0)
+    }
+
+    /// Opens a connection from `remote_port` with a SYN that carries `cookie` and `len` bytes of data, and returns our
+    /// SYN+ACK.
+    fn receive_syn(
+        &mut self,
+        remote_port: u16,
+        cookie: Option<FastOpenCookie>,
+        len: usize,
+    ) -> Result<(TcpHeader, usize)> {
+        let mut syn: TcpHeader = TcpHeader::new(remote_port, LOCAL_PORT);
+        syn.syn = true;
+        syn.seq_num = SeqNumber::from(0);
+        syn.window_size = WINDOW_SIZE;
+        syn.push_option(TcpOptions2::MaximumSegmentSize(MSS));
+        if let Some(cookie) = cookie {
+            syn.push_option(TcpOptions2::FastOpen(cookie));
+        }
+        self.receive(syn, len)?;
+        let mut segments: Vec<Segment> = self.pop_segments()?;
+        ensure_eq!(segments.len(), 1);
+        let syn_ack: Segment = segments.remove(0);
+        ensure_eq!(syn_ack.header.syn && syn_ack.header.ack, true);
+        Ok((syn_ack.header, syn_ack.len))
+    }
+
+    /// Completes the handshake that `syn_ack` answered.
+    fn receive_ack(&mut self, remote_port: u16, syn_ack: &TcpHeader) -> Result<()> {
+        let mut ack: TcpHeader = TcpHeader::new(remote_port, LOCAL_PORT);
+        ack.ack = true;
+        ack.seq_num = syn_ack.ack_num;
+        ack.ack_num = syn_ack.seq_num + SeqNumber::from(1);
+        ack.window_size = WINDOW_SIZE;
+        self.receive(ack, 0)
+    }
+
+    fn wait(&mut self, connect_qt: QToken) -> Result<()> {
+        match self.engine.wait(connect_qt, Duration::from_secs(1))? {
+            (_, OperationResult::Connect) => Ok(()),
+            (_, result) => anyhow::bail!("connect should succeed (result={:?})", result),
+        }
+    }
+
+    fn accept(&mut self, accept_qt: QToken) -> Result<QDesc> {
+        match self.engine.wait(accept_qt, Duration::from_secs(1))? {
+            (_, OperationResult::Accept((qd, _))) => Ok(qd),
+            (_, result) => anyhow::bail!("accept should succeed (result={:?})", result),
+        }
+    }
+
+    fn advance_clock(&mut self, duration: Duration) {
+        advance_clock(&mut self.engine, &mut self.now, duration);
+    }
+
+    fn pop_segments(&mut self) -> Result<Vec<Segment>> {
+        pop_segments(&mut self.engine)
+    }
+
+    fn receive(&mut self, header: TcpHeader, len: usize) -> Result<()> {
+        receive(&mut self.engine, header, len)
+    }
+}
+
+/// Returns the Fast Open cookie in `header`, which is empty in a request for a cookie.
+fn fast_open_cookie(header: &TcpHeader) -> Option<FastOpenCookie> {
+    header.iter_options().find_map(|option| match option {
+        TcpOptions2::FastOpen(cookie) => Some(*cookie),
+        _ => None,
+    })
+}
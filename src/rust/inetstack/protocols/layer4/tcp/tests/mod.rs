@@ -1,9 +1,151 @@
 // Copyright (c) Microsoft Corporation.
 // Licensed under the MIT license.
 
+// Helpers that the frame-level tests share, where the test plays the other side of the connection and builds and parses
+// raw frames.
+
 //======================================================================================================================
 // Exports
 //======================================================================================================================
 
+#[cfg(debug_assertions)]
+mod ecn;
+#[cfg(debug_assertions)]
+mod established;
+#[cfg(debug_assertions)]
+mod fast_open;
 #[cfg(debug_assertions)]
 mod simulator;
+#[cfg(debug_assertions)]
+mod syn_cookies;
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+#[cfg(debug_assertions)]
+use crate::{
+    ensure_eq,
+    inetstack::{
+        protocols::{
+            layer2::{EtherType2, Ethernet2Header},
+            layer3::{
+                ip::IpProtocol,
+                ipv4::{EcnCodepoint, Ipv4Header},
+            },
+            layer4::tcp::header::TcpHeader,
+            MAX_HEADER_SIZE,
+        },
+        test_helpers::{self, engine::SharedEngine},
+    },
+    runtime::{memory::DemiBuffer, network::types::MacAddress},
+};
+#[cfg(debug_assertions)]
+use ::anyhow::Result;
+#[cfg(debug_assertions)]
+use ::std::{
+    net::Ipv4Addr,
+    time::{Duration, Instant},
+};
+
+//======================================================================================================================
+// Constants
+//======================================================================================================================
+
+#[cfg(debug_assertions)]
+const LOCAL_PORT: u16 = 12345;
+#[cfg(debug_assertions)]
+const REMOTE_PORT: u16 = 23456;
+#[cfg(debug_assertions)]
+const WINDOW_SIZE: u16 = 65535;
+#[cfg(debug_assertions)]
+const MAX_POLLS: usize = 4;
+#[cfg(debug_assertions)]
+const MSS: u16 = 1450;
+
+//======================================================================================================================
+// Structures
+//======================================================================================================================
+
+/// A segment that we sent, along with the length of its data and its ECN codepoint.
+#[cfg(debug_assertions)]
+struct Segment {
+    header: TcpHeader,
+    len: usize,
+    ecn: EcnCodepoint,
+}
+
+//======================================================================================================================
+// Standalone Functions
+//======================================================================================================================
+
+/// Delivers `header` with `len` bytes of data from our peer to ALICE.
+#[cfg(debug_assertions)]
+fn receive(engine: &mut SharedEngine, header: TcpHeader, len: usize) -> Result<()> {
+    receive_at(
+        engine,
+        test_helpers::ALICE_IPV4,
+        test_helpers::ALICE_MAC,
+        header,
+        len,
+        EcnCodepoint::NotEct,
+    )
+}
+
+/// Delivers `header` with `len` bytes of data from our peer to the host at `local_ipv4` and `local_mac`, marking it with
+/// the `ecn` codepoint.
+#[cfg(debug_assertions)]
+fn receive_at(
+    engine: &mut SharedEngine,
+    local_ipv4: Ipv4Addr,
+    local_mac: MacAddress,
+    header: TcpHeader,
+    len: usize,
+    ecn: EcnCodepoint,
+) -> Result<()> {
+    let remote_ipv4: Ipv4Addr = test_helpers::BOB_IPV4;
+    let mut pkt: DemiBuffer = DemiBuffer::new_with_headroom(len as u16, MAX_HEADER_SIZE as u16);
+    header.serialize_and_attach(&mut pkt, &remote_ipv4, &local_ipv4, false);
+    let mut ipv4_header: Ipv4Header = Ipv4Header::new(remote_ipv4, local_ipv4, IpProtocol::TCP);
+    ipv4_header.set_ecn(ecn);
+    ipv4_header.serialize_and_attach(&mut pkt);
+    Ethernet2Header::new(local_mac, test_helpers::BOB_MAC, EtherType2::Ipv4).serialize_and_attach(&mut pkt);
+    engine.push_frame(pkt);
+    engine.poll();
+    Ok(())
+}
+
+/// Returns every segment that we sent.
+#[cfg(debug_assertions)]
+fn pop_segments(engine: &mut SharedEngine) -> Result<Vec<Segment>> {
+    // The scheduler only runs a few coroutines per poll.
+    for _ in 0..MAX_POLLS {
+        engine.poll();
+    }
+    let mut segments: Vec<Segment> = Vec::new();
+    for mut pkt in engine.pop_all_frames() {
+        Ethernet2Header::parse_and_strip(&mut pkt)?;
+        let ipv4_header: Ipv4Header = Ipv4Header::parse_and_strip(&mut pkt)?;
+        ensure_eq!(ipv4_header.get_protocol(), IpProtocol::TCP);
+        let header: TcpHeader = TcpHeader::parse_and_strip(
+            &ipv4_header.get_src_addr(),
+            &ipv4_header.get_dest_addr(),
+            &mut pkt,
+            true,
+        )?;
+        segments.push(Segment {
+            header,
+            len: pkt.len(),
+            ecn: ipv4_header.get_ecn(),
+        });
+    }
+    Ok(segments)
+}
+
+/// Moves the clock of `engine` forward by `duration`, and lets it run whatever timers went off.
+#[cfg(debug_assertions)]
+fn advance_clock(engine: &mut SharedEngine, now: &mut Instant, duration: Duration) {
+    *now += duration;
+    engine.advance_clock(*now);
+    engine.poll();
+}
@@ -0,0 +1,198 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+// Tests for SYN cookies (RFC 4987 Section 3.6), where the test plays the other side of the connections so that it can
+// fill the SYN queue and echo the cookies in our SYN+ACKs, which the packet simulator can't predict.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use super::{advance_clock, pop_segments, receive, Segment, LOCAL_PORT, MAX_POLLS, MSS, REMOTE_PORT, WINDOW_SIZE};
+use crate::{
+    ensure_eq,
+    inetstack::{
+        protocols::layer4::tcp::{
+            header::{TcpHeader, TcpOptions2},
+            SeqNumber,
+        },
+        test_helpers::{self, engine::SharedEngine, physical_layer::SharedTestPhysicalLayer},
+    },
+    runtime::{
+        network::socket::option::{SocketOption, TcpInfo},
+        OperationResult,
+    },
+    QDesc, QToken,
+};
+use ::anyhow::Result;
+use ::std::{
+    net::SocketAddrV4,
+    time::{Duration, Instant},
+};
+
+//======================================================================================================================
+// Constants
+//======================================================================================================================
+
+// Matches the SYN backlog and the handshake timeout in our configuration.
+const SYN_BACKLOG: u16 = 128;
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(3);
+// Long enough for a cookie to expire.
+const SYN_COOKIE_LIFETIME: Duration = Duration::from_secs(128);
+
+//======================================================================================================================
+// Tests
+//======================================================================================================================
+
+// Test that once the SYN queue is full, a SYN gets a SYN+ACK with a cookie that carries nothing but the MSS, and that
+// echoing the cookie in the ACK establishes the connection.
+#[test]
+fn syn_queue_overflow() -> Result<()> {
+    let mut listener: Listener = Listener::new()?;
+    listener.fill_syn_queue(1000)?;
+
+    let accept_qt: QToken = listener.engine.tcp_accept(listener.qd)?;
+    let syn_ack: TcpHeader = listener.receive_syn(REMOTE_PORT)?;
+    let options: Vec<TcpOptions2> = syn_ack.iter_options().copied().collect();
+    ensure_eq!(options, vec![TcpOptions2::MaximumSegmentSize(MSS)]);
+
+    listener.receive_ack(REMOTE_PORT, syn_ack.seq_num + SeqNumber::from(1))?;
+    let qd: QDesc = match listener.engine.wait(accept_qt, Duration::from_secs(1))? {
+        (_, OperationResult::Accept((qd, remote))) if remote.port() == REMOTE_PORT => qd,
+        (_, result) => anyhow::bail!("accept should succeed (result={:?})", result),
+    };
+
+    // The cookie rounds the MSS down to one that it can encode.
+    match listener
+        .engine
+        .tcp_get_socket_option(qd, SocketOption::TcpInfo(TcpInfo::default()))?
+    {
+        SocketOption::TcpInfo(tcp_info) => ensure_eq!(tcp_info.send_mss, 1440),
+        option => anyhow::bail!("should get TCP_INFO (option={:?})", option),
+    }
+
+    Ok(())
+}
+
+// Test that an ACK with a forged or an expired cookie gets a RST instead of establishing a connection.
+#[test]
+fn syn_cookie_rejected() -> Result<()> {
+    let mut listener: Listener = Listener::new()?;
+    listener.fill_syn_queue(1000)?;
+    let syn_ack: TcpHeader = listener.receive_syn(REMOTE_PORT)?;
+    let cookie: SeqNumber = syn_ack.seq_num;
+
+    // A cookie that we did not give out.
+    listener.receive_ack(REMOTE_PORT, cookie + SeqNumber::from(2))?;
+    ensure_eq!(listener.pop_rsts()?, 1);
+    // A cookie that we gave to another connection.
+    listener.receive_ack(REMOTE_PORT + 1, cookie + SeqNumber::from(1))?;
+    ensure_eq!(listener.pop_rsts()?, 1);
+
+    // Once the cookie expires, it is rejected even while we are still giving out other cookies. By then, the handshakes
+    // in the SYN queue have timed out, so fill it up again.
+    let mut elapsed: Duration = Duration::ZERO;
+    while elapsed < SYN_COOKIE_LIFETIME {
+        listener.advance_clock(HANDSHAKE_TIMEOUT);
+        elapsed += HANDSHAKE_TIMEOUT;
+    }
+    for _ in 0..SYN_BACKLOG {
+        let accept_qt: QToken = listener.engine.tcp_accept(listener.qd)?;
+        match listener.engine.wait(accept_qt, Duration::from_secs(1))? {
+            (_, OperationResult::Failed(e)) if e.errno == libc::ETIMEDOUT => (),
+            (_, result) => anyhow::bail!("accept should fail with ETIMEDOUT (result={:?})", result),
+        }
+    }
+    listener.fill_syn_queue(2000)?;
+    listener.receive_syn(REMOTE_PORT + 2)?;
+    listener.receive_ack(REMOTE_PORT, cookie + SeqNumber::from(1))?;
+    ensure_eq!(listener.pop_rsts()?, 1);
+
+    // None of these ACKs established a connection.
+    let accept_qt: QToken = listener.engine.tcp_accept(listener.qd)?;
+    listener.engine.poll();
+    ensure_eq!(
+        listener.engine.get_runtime().get_completed_task(&accept_qt).is_none(),
+        true
+    );
+
+    Ok(())
+}
+
+//======================================================================================================================
+// Standalone Functions
+//======================================================================================================================
+
+/// A listening socket, along with the clock. The test plays the peers that connect to it.
+struct Listener {
+    engine: SharedEngine,
+    now: Instant,
+    qd: QDesc,
+}
+
+impl Listener {
+    fn new() -> Result<Self> {
+        let now: Instant = Instant::now();
+        let test_rig: SharedTestPhysicalLayer = SharedTestPhysicalLayer::new_test(now);
+        let mut engine: SharedEngine = SharedEngine::new(test_helpers::ALICE_CONFIG_PATH, test_rig, now)?;
+        let qd: QDesc = engine.tcp_socket()?;
+        engine.tcp_bind(qd, SocketAddrV4::new(test_helpers::ALICE_IPV4, LOCAL_PORT))?;
+        engine.tcp_listen(qd, 16)?;
+        Ok(Self { engine, now, qd })
+    }
+
+    /// Fills the SYN queue with handshakes from ports starting at `first_port`, which we never complete.
+    fn fill_syn_queue(&mut self, first_port: u16) -> Result<()> {
+        for remote_port in first_port..first_port + SYN_BACKLOG {
+            let syn_ack: TcpHeader = self.receive_syn(remote_port)?;
+            // Only the SYN+ACKs of handshakes in the SYN queue offer more than the MSS.
+            ensure_eq!(syn_ack.iter_options().count() > 1, true);
+        }
+        Ok(())
+    }
+
+    /// Receives a SYN from `remote_port` that offers window scaling and SACK, and returns our SYN+ACK.
+    fn receive_syn(&mut self, remote_port: u16) -> Result<TcpHeader> {
+        let mut syn: TcpHeader = TcpHeader::new(remote_port, LOCAL_PORT);
+        syn.syn = true;
+        syn.seq_num = SeqNumber::from(0);
+        syn.window_size = WINDOW_SIZE;
+        syn.push_option(TcpOptions2::MaximumSegmentSize(MSS));
+        syn.push_option(TcpOptions2::WindowScale(0));
+        syn.push_option(TcpOptions2::SelectiveAcknowlegementPermitted);
+        receive(&mut self.engine, syn, 0)?;
+        let mut segments: Vec<Segment> = pop_segments(&mut self.engine)?;
+        ensure_eq!(segments.len(), 1);
+        let syn_ack: TcpHeader = segments.remove(0).header;
+        ensure_eq!(syn_ack.syn && syn_ack.ack, true);
+        Ok(syn_ack)
+    }
+
+    /// Receives the ACK that completes the handshake from `remote_port`, acknowledging `ack_num`.
+    fn receive_ack(&mut self, remote_port: u16, ack_num: SeqNumber) -> Result<()> {
+        let mut ack: TcpHeader = TcpHeader::new(remote_port, LOCAL_PORT);
+        ack.ack = true;
+        ack.seq_num = SeqNumber::from(1);
+        ack.ack_num = ack_num;
+        ack.window_size = WINDOW_SIZE;
+        receive(&mut self.engine, ack, 0)
+    }
+
+    /// Returns how many RSTs we sent, failing on any other segment.
+    fn pop_rsts(&mut self) -> Result<usize> {
+        let segments: Vec<Segment> = pop_segments(&mut self.engine)?;
+        for segment in segments.iter() {
+            ensure_eq!(segment.header.rst, true);
+        }
+        Ok(segments.len())
+    }
+
+    fn advance_clock(&mut self, duration: Duration) {
+        advance_clock(&mut self.engine, &mut self.now, duration);
+        // Throw away the retransmitted SYN+ACKs.
+        for _ in 0..MAX_POLLS {
+            self.engine.poll();
+        }
+        self.engine.pop_all_frames();
+    }
+}
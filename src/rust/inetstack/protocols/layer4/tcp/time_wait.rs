@@ -0,0 +1,167 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+// After an active close, the connection stays in the TIME_WAIT state for twice the maximum segment lifetime (RFC 9293
+// Section 3.6.1).  We don't keep the whole control block around for this; instead, the peer keeps this lightweight
+// record of the connection, which is enough to acknowledge a retransmitted FIN and to absorb any other late segments so
+// that they are not mistaken for segments of a new connection on the same 4-tuple.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::inetstack::protocols::layer4::tcp::{header::TcpHeader, timestamps::TcpTimestamps, SeqNumber};
+use ::std::time::{Duration, Instant};
+
+//======================================================================================================================
+// Structures
+//======================================================================================================================
+
+/// State of a connection in the TIME_WAIT state.
+#[derive(Clone, Copy, Debug)]
+pub struct TimeWait {
+    // Time at which the connection leaves the TIME_WAIT state.
+    expiry: Instant,
+    // How long the connection stays in the TIME_WAIT state.  Normally, this is 2*MSL.
+    timeout: Duration,
+    // Our final sequence number, just past our FIN.
+    send_next: SeqNumber,
+    // The peer's final sequence number, just past its FIN.
+    receive_next: SeqNumber,
+    // Window size to advertise, as it goes in the header.
+    window_size: u16,
+    // State of the timestamps option, if the connection used it.
+    timestamps: Option<TcpTimestamps>,
+}
+
+//======================================================================================================================
+// Associated Functions
+//======================================================================================================================
+
+impl TimeWait {
+    pub fn new(
+        now: Instant,
+        timeout: Duration,
+        send_next: SeqNumber,
+        receive_next: SeqNumber,
+        window_size: u16,
+        timestamps: Option<TcpTimestamps>,
+    ) -> Self {
+        Self {
+            expiry: now + timeout,
+            timeout,
+            send_next,
+            receive_next,
+            window_size,
+            timestamps,
+        }
+    }
+
+    /// Returns true if the connection has left the TIME_WAIT state.
+    pub fn is_expired(&self, now: Instant) -> bool {
+        now >= self.expiry
+    }
+
+    /// Processes a late segment for this connection, and returns the header of the ACK to send back, if any.
+    pub fn receive(&mut self, header: &TcpHeader, data_len: usize, now: Instant) -> Option<TcpHeader> {
+        // Ignore resets, so that an old duplicate RST can't cut the TIME_WAIT state short (RFC 1337).
+        if header.rst {
+            return None;
+        }
+
+        if header.fin {
+            // The peer did not get the ACK for its FIN and retransmitted it.  Acknowledge it again and restart the
+            // 2*MSL timeout (RFC 9293 Section 3.10.7.4).
+            self.expiry = now + self.timeout;
+        } else if !header.syn && data_len == 0 {
+            // A pure ACK needs no response.
+            return None;
+        }
+
+        // We don't expect any more data, so anything that occupies sequence space is not acceptable and gets an ACK.
+        let mut ack: TcpHeader = TcpHeader::new(header.dst_port, header.src_port);
+        ack.seq_num = self.send_next;
+        ack.ack = true;
+        ack.ack_num = self.receive_next;
+        ack.window_size = self.window_size;
+        if let Some(timestamps) = self.timestamps.as_ref() {
+            ack.push_option(timestamps.option(now));
+        }
+        Some(ack)
+    }
+}
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::TimeWait;
+    use crate::inetstack::protocols::layer4::tcp::{constants::MSL, header::TcpHeader, SeqNumber};
+    use ::anyhow::Result;
+    use ::std::time::{Duration, Instant};
+
+    const LOCAL_PORT: u16 = 12345;
+    const REMOTE_PORT: u16 = 80;
+
+    fn time_wait(now: Instant) -> TimeWait {
+        TimeWait::new(now, MSL * 2, SeqNumber::from(101), SeqNumber::from(201), 1024, None)
+    }
+
+    fn segment(seq_num: u32) -> TcpHeader {
+        let mut header: TcpHeader = TcpHeader::new(REMOTE_PORT, LOCAL_PORT);
+        header.seq_num = SeqNumber::from(seq_num);
+        header.ack = true;
+        header.ack_num = SeqNumber::from(101);
+        header
+    }
+
+    // Test that a retransmitted FIN gets acknowledged again and restarts the timeout.
+    #[test]
+    fn retransmitted_fin() -> Result<()> {
+        let start: Instant = Instant::now();
+        let mut entry: TimeWait = time_wait(start);
+
+        let now: Instant = start + MSL;
+        let mut fin: TcpHeader = segment(200);
+        fin.fin = true;
+        let ack: TcpHeader = match entry.receive(&fin, 0, now) {
+            Some(ack) => ack,
+            None => anyhow::bail!("should acknowledge a retransmitted FIN"),
+        };
+        crate::ensure_eq!(ack.src_port, LOCAL_PORT);
+        crate::ensure_eq!(ack.dst_port, REMOTE_PORT);
+        crate::ensure_eq!(ack.ack, true);
+        crate::ensure_eq!(ack.seq_num, SeqNumber::from(101));
+        crate::ensure_eq!(ack.ack_num, SeqNumber::from(201));
+        crate::ensure_eq!(ack.window_size, 1024);
+
+        crate::ensure_eq!(entry.is_expired(start + MSL * 2), false);
+        crate::ensure_eq!(entry.is_expired(now + MSL * 2), true);
+
+        Ok(())
+    }
+
+    // Test that late segments are absorbed without closing the TIME_WAIT state early.
+    #[test]
+    fn late_segments() -> Result<()> {
+        let start: Instant = Instant::now();
+        let mut entry: TimeWait = time_wait(start);
+        let now: Instant = start + Duration::from_millis(10);
+
+        // A pure ACK is dropped silently.
+        crate::ensure_eq!(entry.receive(&segment(201), 0, now).is_none(), true);
+
+        // So is a RST.
+        let mut rst: TcpHeader = segment(201);
+        rst.rst = true;
+        crate::ensure_eq!(entry.receive(&rst, 0, now).is_none(), true);
+        crate::ensure_eq!(entry.is_expired(start + MSL * 2), true);
+
+        // Data is not acceptable anymore, so it gets an ACK.
+        crate::ensure_eq!(entry.receive(&segment(150), 10, now).is_some(), true);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,130 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+// TCP timestamps option.
+// See: https://datatracker.ietf.org/doc/html/rfc7323 for more details.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::inetstack::protocols::layer4::tcp::{header::TcpOptions2, SeqNumber};
+use ::std::time::{Duration, Instant};
+
+//======================================================================================================================
+// Structures
+//======================================================================================================================
+
+/// Timestamps state of a connection on which both ends agreed to use the timestamps option.
+#[derive(Clone, Copy, Debug)]
+pub struct TcpTimestamps {
+    // Instant at which our timestamp clock reads zero.  The clock ticks once per millisecond.
+    epoch: Instant,
+    // Timestamp to echo in the TSecr field of the segments that we send.  In RFC 7323 terms, this is TS.Recent.
+    recent: u32,
+    // Acknowledgement number of the last segment that we sent.  In RFC 7323 terms, this is Last.ACK.sent.
+    last_ack_sent: SeqNumber,
+}
+
+//======================================================================================================================
+// Associated Functions
+//======================================================================================================================
+
+impl TcpTimestamps {
+    pub fn new(epoch: Instant, recent: u32, last_ack_sent: SeqNumber) -> Self {
+        Self {
+            epoch,
+            recent,
+            last_ack_sent,
+        }
+    }
+
+    /// Reads the timestamp clock that starts at `epoch`.
+    pub fn clock(epoch: Instant, now: Instant) -> u32 {
+        // The clock wraps around every ~49 days, comparisons are done modulo 2^32.
+        (now - epoch).as_millis() as u32
+    }
+
+    /// Returns the option to attach to an outgoing segment.
+    pub fn option(&self, now: Instant) -> TcpOptions2 {
+        TcpOptions2::Timestamp {
+            sender_timestamp: Self::clock(self.epoch, now),
+            echo_timestamp: self.recent,
+        }
+    }
+
+    /// Records the acknowledgement number of a segment that we sent.
+    pub fn on_send(&mut self, ack_num: SeqNumber) {
+        self.last_ack_sent = ack_num;
+    }
+
+    /// Returns true if `tsval` is older than the most recent timestamp that we have accepted. Such a segment is an old
+    /// duplicate and should be rejected.  This is the PAWS test of RFC 7323 Section 5.3.
+    pub fn is_old(&self, tsval: u32) -> bool {
+        (tsval.wrapping_sub(self.recent) as i32) < 0
+    }
+
+    /// Updates TS.Recent with the timestamp of an acceptable segment that starts at or before the last acknowledgement
+    /// that we sent.  See RFC 7323 Section 4.3.
+    pub fn update_recent(&mut self, tsval: u32, seq_num: SeqNumber) {
+        if !self.is_old(tsval) && seq_num <= self.last_ack_sent {
+            self.recent = tsval;
+        }
+    }
+
+    /// Computes a round-trip time sample from the TSecr field of an acknowledgement.  See RFC 7323 Section 4.1.
+    pub fn rtt_sample(&self, tsecr: u32, now: Instant) -> Duration {
+        Duration::from_millis(Self::clock(self.epoch, now).wrapping_sub(tsecr) as u64)
+    }
+}
+
+/// Returns the TSval and TSecr fields of the timestamps option carried by `options`, if any.
+pub fn find_timestamps<'a>(mut options: impl Iterator<Item = &'a TcpOptions2>) -> Option<(u32, u32)> {
+    options.find_map(|option| match option {
+        TcpOptions2::Timestamp {
+            sender_timestamp,
+            echo_timestamp,
+        } => Some((*sender_timestamp, *echo_timestamp)),
+        _ => None,
+    })
+}
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::TcpTimestamps;
+    use crate::inetstack::protocols::layer4::tcp::SeqNumber;
+    use ::anyhow::Result;
+    use ::std::time::{Duration, Instant};
+
+    // Test that PAWS rejects older timestamps, including across wrap around.
+    #[test]
+    fn paws() -> Result<()> {
+        let mut timestamps: TcpTimestamps = TcpTimestamps::new(Instant::now(), 0xfffffff0, SeqNumber::from(100));
+        crate::ensure_eq!(timestamps.is_old(0xffffffef), true);
+        crate::ensure_eq!(timestamps.is_old(0xfffffff0), false);
+        crate::ensure_eq!(timestamps.is_old(0x00000010), false);
+
+        // Only segments starting at or before the last acknowledgement update TS.Recent.
+        timestamps.update_recent(0x00000010, SeqNumber::from(200));
+        crate::ensure_eq!(timestamps.is_old(0xfffffff1), false);
+        timestamps.update_recent(0x00000010, SeqNumber::from(100));
+        crate::ensure_eq!(timestamps.is_old(0xfffffff1), true);
+
+        Ok(())
+    }
+
+    // Test that round-trip time samples are taken from the echoed timestamp.
+    #[test]
+    fn rtt_sample() -> Result<()> {
+        let epoch: Instant = Instant::now();
+        let timestamps: TcpTimestamps = TcpTimestamps::new(epoch, 0, SeqNumber::from(0));
+        let now: Instant = epoch + Duration::from_millis(250);
+        crate::ensure_eq!(timestamps.rtt_sample(200, now), Duration::from_millis(50));
+
+        Ok(())
+    }
+}
@@ -8,7 +8,7 @@ use crate::{
     runtime::{
         fail::Fail,
         memory::{DemiBuffer, MemoryRuntime},
-        network::types::MacAddress,
+        network::{socket::option::SocketOption, types::MacAddress},
         OperationResult, QDesc, QToken, SharedDemiRuntime, SharedObject,
     },
 };
@@ -102,6 +102,16 @@ impl SharedEngine {
         self.libos.connect(socket_fd, remote_endpoint.into())
     }
 
+    pub fn tcp_connect_with_data(
+        &mut self,
+        socket_fd: QDesc,
+        remote_endpoint: SocketAddrV4,
+        buf: DemiBuffer,
+    ) -> Result<QToken, Fail> {
+        let data: demi_sgarray_t = self.libos.get_transport().into_sgarray(buf)?;
+        self.libos.connect_with_data(socket_fd, remote_endpoint.into(), &data)
+    }
+
     pub fn tcp_bind(&mut self, socket_fd: QDesc, endpoint: SocketAddrV4) -> Result<(), Fail> {
         self.libos.bind(socket_fd, endpoint.into())
     }
@@ -127,6 +137,14 @@ impl SharedEngine {
         self.libos.listen(socket_fd, backlog)
     }
 
+    pub fn tcp_set_socket_option(&mut self, socket_fd: QDesc, option: SocketOption) -> Result<(), Fail> {
+        self.libos.set_socket_option(socket_fd, option)
+    }
+
+    pub fn tcp_get_socket_option(&mut self, socket_fd: QDesc, option: SocketOption) -> Result<SocketOption, Fail> {
+        self.libos.get_socket_option(socket_fd, option)
+    }
+
     pub async fn arp_query(self, ipv4_addr: Ipv4Addr) -> Result<MacAddress, Fail> {
         self.libos.get_transport().arp_query(ipv4_addr).await
     }
@@ -35,7 +35,7 @@ mod catnap;
 pub use self::demikernel::libos::{name::LibOSName, LibOS};
 pub use crate::runtime::{
     network::{
-        socket::option::SocketOption,
+        socket::option::{SocketOption, TcpInfo},
         types::{MacAddress, Port16},
     },
     types::{demi_sgarray_t, demi_sgaseg_t},
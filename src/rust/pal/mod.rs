@@ -63,6 +63,39 @@ pub const SOL_SOCKET: i32 = WinSock::SOL_SOCKET;
 #[cfg(target_os = "windows")]
 pub const SO_LINGER: i32 = WinSock::SO_LINGER;
 
+#[cfg(target_os = "windows")]
+pub const SO_REUSEADDR: i32 = WinSock::SO_REUSEADDR;
+
+#[cfg(target_os = "windows")]
+pub const SO_SNDBUF: i32 = WinSock::SO_SNDBUF;
+
+#[cfg(target_os = "windows")]
+pub const SO_RCVBUF: i32 = WinSock::SO_RCVBUF;
+
+#[cfg(target_os = "windows")]
+pub const IPPROTO_TCP: i32 = WinSock::IPPROTO_TCP.0;
+
+// WinSock has no equivalent, so we use the Linux value.
+#[cfg(target_os = "windows")]
+pub const TCP_CONGESTION: i32 = 13;
+
+// WinSock has no equivalent, so we use the Linux value.
+#[cfg(target_os = "windows")]
+pub const TCP_INFO: i32 = 11;
+
+// WinSock has no equivalent, so we use the Linux value.
+#[cfg(target_os = "windows")]
+pub const TCP_USER_TIMEOUT: i32 = 18;
+
+#[cfg(target_os = "windows")]
+pub const SHUT_RD: i32 = WinSock::SD_RECEIVE.0;
+
+#[cfg(target_os = "windows")]
+pub const SHUT_WR: i32 = WinSock::SD_SEND.0;
+
+#[cfg(target_os = "windows")]
+pub const SHUT_RDWR: i32 = WinSock::SD_BOTH.0;
+
 //======================================================================================================================
 // Linux constants
 //======================================================================================================================
@@ -82,6 +115,36 @@ pub const SOL_SOCKET: i32 = libc::SOL_SOCKET;
 #[cfg(target_os = "linux")]
 pub const SO_LINGER: i32 = libc::SO_LINGER;
 
+#[cfg(target_os = "linux")]
+pub const SO_REUSEADDR: i32 = libc::SO_REUSEADDR;
+
+#[cfg(target_os = "linux")]
+pub const SO_SNDBUF: i32 = libc::SO_SNDBUF;
+
+#[cfg(target_os = "linux")]
+pub const SO_RCVBUF: i32 = libc::SO_RCVBUF;
+
+#[cfg(target_os = "linux")]
+pub const IPPROTO_TCP: i32 = libc::IPPROTO_TCP;
+
+#[cfg(target_os = "linux")]
+pub const TCP_CONGESTION: i32 = libc::TCP_CONGESTION;
+
+#[cfg(target_os = "linux")]
+pub const TCP_INFO: i32 = libc::TCP_INFO;
+
+#[cfg(target_os = "linux")]
+pub const TCP_USER_TIMEOUT: i32 = libc::TCP_USER_TIMEOUT;
+
+#[cfg(target_os = "linux")]
+pub const SHUT_RD: i32 = libc::SHUT_RD;
+
+#[cfg(target_os = "linux")]
+pub const SHUT_WR: i32 = libc::SHUT_WR;
+
+#[cfg(target_os = "linux")]
+pub const SHUT_RDWR: i32 = libc::SHUT_RDWR;
+
 //======================================================================================================================
 // Windows data structures
 //======================================================================================================================
@@ -9,4 +9,8 @@ mod udp;
 // Exports
 //======================================================================================================================
 
-pub use self::{arp::ArpConfig, tcp::TcpConfig, udp::UdpConfig};
+pub use self::{
+    arp::ArpConfig,
+    tcp::{Ecn, FastOpen, MtuProbing, TcpConfig},
+    udp::UdpConfig,
+};
@@ -9,10 +9,14 @@ use crate::{
     demikernel::config::Config,
     runtime::{
         fail::Fail,
-        network::consts::{DEFAULT_MSS, MAX_MSS, MIN_MSS, TCP_ACK_DELAY_TIMEOUT, TCP_HANDSHAKE_TIMEOUT},
+        network::consts::{
+            DEFAULT_MSS, MAX_MSS, MAX_RECEIVE_WINDOW_SIZE, MAX_WINDOW_SCALE, MIN_MSS, TCP_ACK_DELAY_TIMEOUT,
+            TCP_DATA_RETRIES, TCP_HANDSHAKE_RETRIES, TCP_HANDSHAKE_TIMEOUT, TCP_PERSIST_MAX, TCP_PERSIST_MIN,
+            TCP_RTO_INITIAL, TCP_RTO_MAX, TCP_RTO_MIN,
+        },
     },
 };
-use ::std::time::Duration;
+use ::std::{cmp, time::Duration};
 
 //======================================================================================================================
 // Structures
@@ -24,12 +28,62 @@ pub struct TcpConfig {
     advertised_mss: usize,
     handshake_retries: usize,
     handshake_timeout: Duration,
-    receive_window_size: u16,
-    /// Scaling Factor for Window Size
-    window_scale: u8,
+    /// Bounds and initial value of the retransmission timeout (RFC 6298).
+    rto_min: Duration,
+    rto_max: Duration,
+    rto_initial: Duration,
+    /// Number of retransmissions in a row without an acknowledgement, after which we drop the connection.
+    data_retries: usize,
+    /// Bounds of the interval between window probes into a zero window.
+    persist_min: Duration,
+    persist_max: Duration,
+    /// Receive buffer size in bytes, which bounds the receive window that we advertise.
+    receive_buffer_size: u32,
+    /// Bounds for the receive buffer size of connections, which grows with the rate at which the application reads.
+    receive_buffer_min_size: u32,
+    receive_buffer_max_size: u32,
     ack_delay_timeout: Duration,
     rx_checksum_offload: bool,
     tx_checksum_offload: bool,
+    /// Maximum number of connections per listening socket that may be in the middle of the handshake.
+    syn_backlog: usize,
+    /// Answer with SYN cookies when the SYN backlog is full, instead of dropping the connection request.
+    syn_cookies: bool,
+    /// Largest IP packet that our link can carry, which bounds the MSS that path MTU discovery searches for.
+    mtu: u16,
+    mtu_probing: MtuProbing,
+    ecn: Ecn,
+    fast_open: FastOpen,
+}
+
+/// When connections search for the largest segment that the path can carry (RFC 4821).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MtuProbing {
+    Disabled,
+    /// Only after retransmission timeouts suggest that our segments are too large for the path.
+    OnBlackhole,
+    Always,
+}
+
+/// When connections use Explicit Congestion Notification (RFC 3168).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Ecn {
+    Disabled,
+    /// Request ECN on the connections that we open, and agree to it when our peers request it.
+    Enabled,
+    /// Only agree to ECN when our peers request it.
+    OnRequest,
+}
+
+/// Which side of connections sends data in the SYN with TCP Fast Open (RFC 7413).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FastOpen {
+    Disabled,
+    /// Send data in the SYN of the connections that we open, once the server has given us a cookie.
+    Client,
+    /// Accept data in the SYN of the connections that our peers open, when they present a valid cookie.
+    Server,
+    ClientAndServer,
 }
 
 //======================================================================================================================
@@ -49,6 +103,114 @@ impl TcpConfig {
             options.rx_checksum_offload = value;
             options.tx_checksum_offload = value;
         }
+        if let Ok(value) = config.tcp_receive_buffer_size() {
+            if value == 0 {
+                let cause: &str = "invalid tcp_receive_buffer_size: the receive buffer must not be empty";
+                error!("new(): {}", cause);
+                return Err(Fail::new(libc::EINVAL, cause));
+            }
+            options.receive_buffer_size = cmp::min(value, MAX_RECEIVE_WINDOW_SIZE);
+        }
+        // Without bounds, the receive buffer keeps its initial size.
+        options.receive_buffer_min_size = match config.tcp_receive_buffer_min_size() {
+            Ok(value) => value,
+            Err(_) => options.receive_buffer_size,
+        };
+        options.receive_buffer_max_size = match config.tcp_receive_buffer_max_size() {
+            Ok(value) => cmp::min(value, MAX_RECEIVE_WINDOW_SIZE),
+            Err(_) => options.receive_buffer_size,
+        };
+        if options.receive_buffer_min_size == 0 || options.receive_buffer_min_size > options.receive_buffer_max_size {
+            let cause: &str = "invalid tcp_receive_buffer bounds: the minimum must not be zero or exceed the maximum";
+            error!("new(): {}", cause);
+            return Err(Fail::new(libc::EINVAL, cause));
+        }
+        options.receive_buffer_size = options
+            .receive_buffer_size
+            .clamp(options.receive_buffer_min_size, options.receive_buffer_max_size);
+        if let Ok(value) = config.tcp_handshake_timeout() {
+            options.handshake_timeout = value;
+        }
+        if let Ok(value) = config.tcp_handshake_retries() {
+            options.handshake_retries = value;
+        }
+        if let Ok(value) = config.tcp_rto_min() {
+            options.rto_min = value;
+        }
+        if let Ok(value) = config.tcp_rto_max() {
+            options.rto_max = value;
+        }
+        if let Ok(value) = config.tcp_rto_initial() {
+            options.rto_initial = value;
+        }
+        if let Ok(value) = config.tcp_data_retries() {
+            options.data_retries = value;
+        }
+        if let Ok(value) = config.tcp_persist_min() {
+            options.persist_min = value;
+        }
+        if let Ok(value) = config.tcp_persist_max() {
+            options.persist_max = value;
+        }
+        if options.handshake_timeout.is_zero()
+            || options.rto_min.is_zero()
+            || options.rto_min > options.rto_max
+            || options.rto_initial < options.rto_min
+            || options.rto_initial > options.rto_max
+            || options.persist_min.is_zero()
+            || options.persist_min > options.persist_max
+        {
+            let cause: &str =
+                "invalid tcp_timers: timeouts must not be zero and must lie between their minimums and maximums";
+            error!("new(): {}", cause);
+            return Err(Fail::new(libc::EINVAL, cause));
+        }
+        if let Ok(value) = config.tcp_syn_backlog() {
+            options.syn_backlog = value;
+        }
+        if let Ok(value) = config.tcp_syn_cookies() {
+            options.syn_cookies = value;
+        }
+        if let Ok(value) = config.mtu() {
+            options.mtu = value;
+        }
+        if let Ok(value) = config.tcp_mtu_probing() {
+            options.mtu_probing = match value {
+                0 => MtuProbing::Disabled,
+                1 => MtuProbing::OnBlackhole,
+                2 => MtuProbing::Always,
+                _ => {
+                    let cause: String = format!("invalid value for tcp_mtu_probing: {}", value);
+                    error!("new(): {}", cause);
+                    return Err(Fail::new(libc::EINVAL, &cause));
+                },
+            };
+        }
+        if let Ok(value) = config.tcp_ecn() {
+            options.ecn = match value {
+                0 => Ecn::Disabled,
+                1 => Ecn::Enabled,
+                2 => Ecn::OnRequest,
+                _ => {
+                    let cause: String = format!("invalid value for tcp_ecn: {}", value);
+                    error!("new(): {}", cause);
+                    return Err(Fail::new(libc::EINVAL, &cause));
+                },
+            };
+        }
+        if let Ok(value) = config.tcp_fastopen() {
+            options.fast_open = match value {
+                0 => FastOpen::Disabled,
+                1 => FastOpen::Client,
+                2 => FastOpen::Server,
+                3 => FastOpen::ClientAndServer,
+                _ => {
+                    let cause: String = format!("invalid value for tcp_fastopen: {}", value);
+                    error!("new(): {}", cause);
+                    return Err(Fail::new(libc::EINVAL, &cause));
+                },
+            };
+        }
 
         Ok(options)
     }
@@ -65,12 +227,49 @@ impl TcpConfig {
         self.handshake_timeout
     }
 
-    pub fn get_receive_window_size(&self) -> u16 {
-        self.receive_window_size
+    pub fn get_rto_min(&self) -> Duration {
+        self.rto_min
+    }
+
+    pub fn get_rto_max(&self) -> Duration {
+        self.rto_max
+    }
+
+    pub fn get_rto_initial(&self) -> Duration {
+        self.rto_initial
+    }
+
+    pub fn get_data_retries(&self) -> usize {
+        self.data_retries
+    }
+
+    pub fn get_persist_min(&self) -> Duration {
+        self.persist_min
     }
 
-    pub fn get_window_scale(&self) -> u8 {
-        self.window_scale
+    pub fn get_persist_max(&self) -> Duration {
+        self.persist_max
+    }
+
+    pub fn get_receive_buffer_size(&self) -> u32 {
+        self.receive_buffer_size
+    }
+
+    pub fn get_receive_buffer_min_size(&self) -> u32 {
+        self.receive_buffer_min_size
+    }
+
+    pub fn get_receive_buffer_max_size(&self) -> u32 {
+        self.receive_buffer_max_size
+    }
+
+    /// Returns the smallest window scale that lets us advertise a receive window as large as `receive_buffer_size`.
+    pub fn window_scale(receive_buffer_size: u32) -> u8 {
+        let mut window_scale: u8 = 0;
+        while (receive_buffer_size >> window_scale) > u16::MAX as u32 && (window_scale as usize) < MAX_WINDOW_SCALE {
+            window_scale += 1;
+        }
+        window_scale
     }
 
     pub fn get_ack_delay_timeout(&self) -> Duration {
@@ -84,6 +283,40 @@ impl TcpConfig {
     pub fn get_rx_checksum_offload(&self) -> bool {
         self.rx_checksum_offload
     }
+
+    pub fn get_syn_backlog(&self) -> usize {
+        self.syn_backlog
+    }
+
+    pub fn get_syn_cookies(&self) -> bool {
+        self.syn_cookies
+    }
+
+    pub fn get_mtu(&self) -> u16 {
+        self.mtu
+    }
+
+    pub fn get_mtu_probing(&self) -> MtuProbing {
+        self.mtu_probing
+    }
+
+    pub fn get_ecn(&self) -> Ecn {
+        self.ecn
+    }
+
+    pub fn get_fast_open(&self) -> FastOpen {
+        self.fast_open
+    }
+}
+
+impl FastOpen {
+    pub fn is_client_enabled(&self) -> bool {
+        matches!(self, FastOpen::Client | FastOpen::ClientAndServer)
+    }
+
+    pub fn is_server_enabled(&self) -> bool {
+        matches!(self, FastOpen::Server | FastOpen::ClientAndServer)
+    }
 }
 
 //======================================================================================================================
@@ -94,13 +327,26 @@ impl Default for TcpConfig {
     fn default() -> Self {
         TcpConfig {
             advertised_mss: DEFAULT_MSS,
-            handshake_retries: 5,
+            handshake_retries: TCP_HANDSHAKE_RETRIES,
             handshake_timeout: TCP_HANDSHAKE_TIMEOUT,
-            receive_window_size: 0xffff,
+            rto_min: TCP_RTO_MIN,
+            rto_max: TCP_RTO_MAX,
+            rto_initial: TCP_RTO_INITIAL,
+            data_retries: TCP_DATA_RETRIES,
+            persist_min: TCP_PERSIST_MIN,
+            persist_max: TCP_PERSIST_MAX,
+            receive_buffer_size: 0xffff,
+            receive_buffer_min_size: 0xffff,
+            receive_buffer_max_size: 0xffff,
             ack_delay_timeout: TCP_ACK_DELAY_TIMEOUT,
-            window_scale: 0,
             rx_checksum_offload: false,
             tx_checksum_offload: false,
+            syn_backlog: 128,
+            syn_cookies: true,
+            mtu: 1500,
+            mtu_probing: MtuProbing::Disabled,
+            ecn: Ecn::OnRequest,
+            fast_open: FastOpen::Client,
         }
     }
 }
@@ -111,7 +357,10 @@ impl Default for TcpConfig {
 
 #[cfg(test)]
 mod tests {
-    use crate::runtime::network::{config::TcpConfig, consts::DEFAULT_MSS};
+    use crate::runtime::network::{
+        config::{Ecn, FastOpen, MtuProbing, TcpConfig},
+        consts::{DEFAULT_MSS, MAX_RECEIVE_WINDOW_SIZE},
+    };
     use ::anyhow::Result;
     use ::std::time::Duration;
 
@@ -121,10 +370,35 @@ mod tests {
         crate::ensure_eq!(config.get_advertised_mss(), DEFAULT_MSS);
         crate::ensure_eq!(config.get_handshake_retries(), 5);
         crate::ensure_eq!(config.get_handshake_timeout(), Duration::from_secs(3));
-        crate::ensure_eq!(config.get_receive_window_size(), 0xffff);
-        crate::ensure_eq!(config.get_window_scale(), 0);
+        crate::ensure_eq!(config.get_rto_min(), Duration::from_millis(100));
+        crate::ensure_eq!(config.get_rto_max(), Duration::from_secs(60));
+        crate::ensure_eq!(config.get_rto_initial(), Duration::from_secs(1));
+        crate::ensure_eq!(config.get_data_retries(), 15);
+        crate::ensure_eq!(config.get_persist_min(), Duration::from_secs(1));
+        crate::ensure_eq!(config.get_persist_max(), Duration::from_secs(60));
+        crate::ensure_eq!(config.get_receive_buffer_size(), 0xffff);
+        crate::ensure_eq!(config.get_receive_buffer_min_size(), 0xffff);
+        crate::ensure_eq!(config.get_receive_buffer_max_size(), 0xffff);
         crate::ensure_eq!(config.get_rx_checksum_offload(), false);
         crate::ensure_eq!(config.get_tx_checksum_offload(), false);
+        crate::ensure_eq!(config.get_syn_backlog(), 128);
+        crate::ensure_eq!(config.get_syn_cookies(), true);
+        crate::ensure_eq!(config.get_mtu(), 1500);
+        crate::ensure_eq!(config.get_mtu_probing(), MtuProbing::Disabled);
+        crate::ensure_eq!(config.get_ecn(), Ecn::OnRequest);
+        crate::ensure_eq!(config.get_fast_open(), FastOpen::Client);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_tcp_config_window_scale() -> Result<()> {
+        crate::ensure_eq!(TcpConfig::window_scale(1), 0);
+        crate::ensure_eq!(TcpConfig::window_scale(0xffff), 0);
+        crate::ensure_eq!(TcpConfig::window_scale(0x10000), 1);
+        crate::ensure_eq!(TcpConfig::window_scale(4 * 1024 * 1024), 7);
+        crate::ensure_eq!(TcpConfig::window_scale(MAX_RECEIVE_WINDOW_SIZE), 14);
+        crate::ensure_eq!(TcpConfig::window_scale(u32::MAX), 14);
 
         Ok(())
     }
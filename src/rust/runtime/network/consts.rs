@@ -31,9 +31,27 @@ pub const TCP_ACK_DELAY_TIMEOUT: Duration = Duration::from_millis(500);
 /// Handshake timeout for tcp.
 pub const TCP_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(3);
 
+/// Number of times that tcp retransmits a SYN or SYN+ACK before giving up on the handshake.
+pub const TCP_HANDSHAKE_RETRIES: usize = 5;
+
+/// Bounds and initial value of the tcp retransmission timeout.
+/// See: https://www.rfc-editor.org/rfc/rfc6298#section-2
+pub const TCP_RTO_MIN: Duration = Duration::from_millis(100);
+pub const TCP_RTO_MAX: Duration = Duration::from_secs(60);
+pub const TCP_RTO_INITIAL: Duration = Duration::from_secs(1);
+
+/// Number of times that tcp retransmits data without hearing back before dropping the connection, like Linux's
+/// tcp_retries2.
+pub const TCP_DATA_RETRIES: usize = 15;
+
+/// Bounds of the tcp persist timer, which paces window probes while our peer advertises a zero window.
+/// See: https://www.rfc-editor.org/rfc/rfc9293#section-3.8.6.1
+pub const TCP_PERSIST_MIN: Duration = Duration::from_secs(1);
+pub const TCP_PERSIST_MAX: Duration = Duration::from_secs(60);
+
 /// Default MSS Parameter for TCP
 ///
-/// TODO: Auto-Discovery MTU Size
+/// Connections may move away from this with path MTU discovery (see the `tcp_mtu_probing` option).
 pub const DEFAULT_MSS: usize = 1450;
 
 /// Length of a [crate::memory::DemiBuffer] batch.
@@ -44,3 +62,6 @@ pub const RECEIVE_BATCH_SIZE: usize = 4;
 /// Maximum local and remote window scaling factor.
 /// See: RFC 1323, Section 2.3.
 pub const MAX_WINDOW_SCALE: usize = 14;
+
+/// Largest receive window that TCP can advertise, using the maximum window scaling factor.
+pub const MAX_RECEIVE_WINDOW_SIZE: u32 = (u16::MAX as u32) << MAX_WINDOW_SCALE;
@@ -8,7 +8,7 @@
 //======================================================================================================================
 
 use crate::{demikernel::config::Config, pal::KeepAlive, runtime::fail::Fail};
-use ::std::time::Duration;
+use ::std::{fmt, str::FromStr, time::Duration};
 #[cfg(target_os = "windows")]
 use ::windows::Win32::Networking::WinSock::tcp_keepalive;
 
@@ -25,7 +25,18 @@ const DEFAULT_KEEP_ALIVE: tcp_keepalive = tcp_keepalive {
     keepalivetime: 7200000,
     keepaliveinterval: 1000,
 };
+// Like Linux, wait two hours before probing an idle connection, then send up to 9 probes, 75 seconds apart.
+const DEFAULT_KEEP_ALIVE_IDLE: Duration = Duration::from_secs(7200);
+const DEFAULT_KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(75);
+const DEFAULT_KEEP_ALIVE_PROBES: u32 = 9;
 const DEFAULT_NO_DELAY: bool = true;
+const DEFAULT_QUICK_ACK: bool = false;
+const DEFAULT_REUSE_ADDRESS: bool = false;
+const DEFAULT_SEND_BUFFER_SIZE: usize = 1024 * 1024;
+// The inetstack picks the receive buffer size from its own configuration unless the application sets one.
+const DEFAULT_RECEIVE_BUFFER_SIZE: Option<usize> = None;
+const DEFAULT_CONGESTION_CONTROL: CongestionControlAlgorithm = CongestionControlAlgorithm::None;
+const DEFAULT_USER_TIMEOUT: Option<Duration> = None;
 
 //======================================================================================================================
 // Structures
@@ -36,14 +47,76 @@ const DEFAULT_NO_DELAY: bool = true;
 pub enum SocketOption {
     Linger(Option<Duration>),
     KeepAlive(KeepAlive),
+    /// Idle time before the first keepalive probe (like TCP_KEEPIDLE).
+    KeepAliveIdle(Duration),
+    /// Time between keepalive probes (like TCP_KEEPINTVL).
+    KeepAliveInterval(Duration),
+    /// Number of unanswered keepalive probes after which the connection is dropped (like TCP_KEEPCNT).
+    KeepAliveProbes(u32),
     NoDelay(bool),
+    /// Acknowledge every segment right away instead of delaying ACKs (like TCP_QUICKACK).
+    QuickAck(bool),
+    /// Congestion control algorithm (like TCP_CONGESTION).
+    CongestionControl(CongestionControlAlgorithm),
+    /// Allow reusing a local address, even while a connection from it is in the TIME_WAIT state (like SO_REUSEADDR).
+    ReuseAddress(bool),
+    /// Maximum number of bytes that a socket buffers for sending, until they are acknowledged (like SO_SNDBUF).
+    SendBufferSize(usize),
+    /// Maximum number of bytes that a socket buffers for receiving, which bounds its receive window (like SO_RCVBUF).
+    ReceiveBufferSize(usize),
+    /// Time after which a connection with unacknowledged data is dropped, if any (like TCP_USER_TIMEOUT).
+    UserTimeout(Option<Duration>),
+    /// Statistics of a connection, which cannot be set (like TCP_INFO).
+    TcpInfo(TcpInfo),
+}
+
+/// Statistics of a TCP connection, for debugging its performance at runtime.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TcpInfo {
+    /// Smoothed round-trip time, or zero if we have not measured one yet.
+    pub srtt: Duration,
+    /// Round-trip time variation.
+    pub rttvar: Duration,
+    /// Retransmission timeout.
+    pub rto: Duration,
+    /// Maximum segment size that we send.
+    pub send_mss: u32,
+    /// Congestion window in bytes.
+    pub congestion_window: u32,
+    /// Window that our peer advertised, in bytes.
+    pub send_window: u32,
+    /// Room left in our receive buffer, in bytes.
+    pub receive_window: u32,
+    /// Bytes that we sent and our peer has not acknowledged yet.
+    pub bytes_in_flight: u32,
+    /// Segments that we retransmitted since the connection was established.
+    pub total_retransmits: u32,
+}
+
+/// Congestion control algorithms that a TCP socket may use, named like their Linux counterparts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CongestionControlAlgorithm {
+    /// No congestion control, only flow control.
+    None,
+    Cubic,
+    NewReno,
+    Bbr,
 }
 
 #[derive(Debug, Clone, Copy)]
 pub struct TcpSocketOptions {
     linger: Option<Duration>,
     keep_alive: KeepAlive,
+    keep_alive_idle: Duration,
+    keep_alive_interval: Duration,
+    keep_alive_probes: u32,
     no_delay: bool,
+    quick_ack: bool,
+    congestion_control: CongestionControlAlgorithm,
+    reuse_address: bool,
+    send_buffer_size: usize,
+    receive_buffer_size: Option<usize>,
+    user_timeout: Option<Duration>,
 }
 
 impl TcpSocketOptions {
@@ -51,7 +124,16 @@ impl TcpSocketOptions {
         Ok(Self {
             linger: config.linger().unwrap_or(DEFAULT_LINGER),
             keep_alive: config.tcp_keepalive().unwrap_or(DEFAULT_KEEP_ALIVE),
+            keep_alive_idle: config.tcp_keepalive_idle().unwrap_or(DEFAULT_KEEP_ALIVE_IDLE),
+            keep_alive_interval: config.tcp_keepalive_interval().unwrap_or(DEFAULT_KEEP_ALIVE_INTERVAL),
+            keep_alive_probes: config.tcp_keepalive_probes().unwrap_or(DEFAULT_KEEP_ALIVE_PROBES),
             no_delay: config.no_delay().unwrap_or(DEFAULT_NO_DELAY),
+            quick_ack: DEFAULT_QUICK_ACK,
+            congestion_control: config.congestion_control().unwrap_or(DEFAULT_CONGESTION_CONTROL),
+            reuse_address: DEFAULT_REUSE_ADDRESS,
+            send_buffer_size: config.send_buffer_size().unwrap_or(DEFAULT_SEND_BUFFER_SIZE),
+            receive_buffer_size: DEFAULT_RECEIVE_BUFFER_SIZE,
+            user_timeout: config.user_timeout().unwrap_or(DEFAULT_USER_TIMEOUT),
         })
     }
 
@@ -71,6 +153,38 @@ impl TcpSocketOptions {
         self.keep_alive = keep_alive;
     }
 
+    /// Returns true if keepalive probes are enabled.
+    pub fn is_keepalive_enabled(&self) -> bool {
+        #[cfg(target_os = "linux")]
+        return self.keep_alive;
+        #[cfg(target_os = "windows")]
+        return self.keep_alive.onoff != 0;
+    }
+
+    pub fn get_keepalive_idle(&self) -> Duration {
+        self.keep_alive_idle
+    }
+
+    pub fn set_keepalive_idle(&mut self, keep_alive_idle: Duration) {
+        self.keep_alive_idle = keep_alive_idle;
+    }
+
+    pub fn get_keepalive_interval(&self) -> Duration {
+        self.keep_alive_interval
+    }
+
+    pub fn set_keepalive_interval(&mut self, keep_alive_interval: Duration) {
+        self.keep_alive_interval = keep_alive_interval;
+    }
+
+    pub fn get_keepalive_probes(&self) -> u32 {
+        self.keep_alive_probes
+    }
+
+    pub fn set_keepalive_probes(&mut self, keep_alive_probes: u32) {
+        self.keep_alive_probes = keep_alive_probes;
+    }
+
     pub fn get_nodelay(&self) -> bool {
         self.no_delay
     }
@@ -78,6 +192,65 @@ impl TcpSocketOptions {
     pub fn set_nodelay(&mut self, nodelay: bool) {
         self.no_delay = nodelay;
     }
+
+    pub fn get_quickack(&self) -> bool {
+        self.quick_ack
+    }
+
+    pub fn set_quickack(&mut self, quick_ack: bool) {
+        self.quick_ack = quick_ack;
+    }
+
+    pub fn get_congestion_control(&self) -> CongestionControlAlgorithm {
+        self.congestion_control
+    }
+
+    pub fn set_congestion_control(&mut self, congestion_control: CongestionControlAlgorithm) {
+        self.congestion_control = congestion_control;
+    }
+
+    pub fn get_reuse_address(&self) -> bool {
+        self.reuse_address
+    }
+
+    pub fn set_reuse_address(&mut self, reuse_address: bool) {
+        self.reuse_address = reuse_address;
+    }
+
+    pub fn get_send_buffer_size(&self) -> usize {
+        self.send_buffer_size
+    }
+
+    pub fn set_send_buffer_size(&mut self, send_buffer_size: usize) {
+        self.send_buffer_size = send_buffer_size;
+    }
+
+    pub fn get_receive_buffer_size(&self) -> Option<usize> {
+        self.receive_buffer_size
+    }
+
+    pub fn set_receive_buffer_size(&mut self, receive_buffer_size: Option<usize>) {
+        self.receive_buffer_size = receive_buffer_size;
+    }
+
+    pub fn get_user_timeout(&self) -> Option<Duration> {
+        self.user_timeout
+    }
+
+    pub fn set_user_timeout(&mut self, user_timeout: Option<Duration>) {
+        self.user_timeout = user_timeout;
+    }
+}
+
+impl CongestionControlAlgorithm {
+    pub fn name(&self) -> &'static str {
+        match self {
+            CongestionControlAlgorithm::None => "none",
+            CongestionControlAlgorithm::Cubic => "cubic",
+            CongestionControlAlgorithm::NewReno => "reno",
+            CongestionControlAlgorithm::Bbr => "bbr",
+        }
+    }
 }
 
 impl Default for TcpSocketOptions {
@@ -85,7 +258,40 @@ impl Default for TcpSocketOptions {
         Self {
             linger: DEFAULT_LINGER,
             keep_alive: DEFAULT_KEEP_ALIVE,
+            keep_alive_idle: DEFAULT_KEEP_ALIVE_IDLE,
+            keep_alive_interval: DEFAULT_KEEP_ALIVE_INTERVAL,
+            keep_alive_probes: DEFAULT_KEEP_ALIVE_PROBES,
             no_delay: DEFAULT_NO_DELAY,
+            quick_ack: DEFAULT_QUICK_ACK,
+            congestion_control: DEFAULT_CONGESTION_CONTROL,
+            reuse_address: DEFAULT_REUSE_ADDRESS,
+            send_buffer_size: DEFAULT_SEND_BUFFER_SIZE,
+            receive_buffer_size: DEFAULT_RECEIVE_BUFFER_SIZE,
+            user_timeout: DEFAULT_USER_TIMEOUT,
         }
     }
 }
+
+impl FromStr for CongestionControlAlgorithm {
+    type Err = Fail;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "none" => Ok(CongestionControlAlgorithm::None),
+            "cubic" => Ok(CongestionControlAlgorithm::Cubic),
+            "reno" | "newreno" => Ok(CongestionControlAlgorithm::NewReno),
+            "bbr" => Ok(CongestionControlAlgorithm::Bbr),
+            _ => {
+                let cause: String = format!("unknown congestion control algorithm (name={})", name);
+                error!("from_str(): {}", cause);
+                Err(Fail::new(libc::ENOENT, &cause))
+            },
+        }
+    }
+}
+
+impl fmt::Display for CongestionControlAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
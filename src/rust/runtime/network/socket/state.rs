@@ -159,6 +159,13 @@ impl SocketStateMachine {
         }
     }
 
+    pub fn may_shutdown(&self) -> Result<(), Fail> {
+        self.ensure_not_closing()?;
+        self.ensure_not_closed()?;
+        self.ensure_established()?;
+        Ok(())
+    }
+
     pub fn commit(&mut self) {
         let current: SocketState = self.current.get();
         self.current.set(self.next.unwrap_or(current));
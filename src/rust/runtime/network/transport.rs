@@ -14,7 +14,7 @@ use crate::runtime::{
 use ::socket2::{Domain, Type};
 use ::std::{
     fmt::Debug,
-    net::{SocketAddr, SocketAddrV4},
+    net::{Shutdown, SocketAddr, SocketAddrV4},
 };
 
 //======================================================================================================================
@@ -48,6 +48,9 @@ pub trait NetworkTransport: Clone + 'static + MemoryRuntime {
     /// Listen on this socket in the network transport layer.
     fn listen(&mut self, sd: &mut Self::SocketDescriptor, backlog: usize) -> Result<(), Fail>;
 
+    /// Shut down the receiving side, the sending side or both sides of a connected socket.
+    fn shutdown(&mut self, sd: &mut Self::SocketDescriptor, how: Shutdown) -> Result<(), Fail>;
+
     /// Forcibly close this socket in the network transport layer. This function should only be used in Drop and other
     /// internal functions, never exposed to the application.
     fn hard_close(&mut self, sd: &mut Self::SocketDescriptor) -> Result<(), Fail>;
@@ -65,6 +68,20 @@ pub trait NetworkTransport: Clone + 'static + MemoryRuntime {
         remote: SocketAddr,
     ) -> impl std::future::Future<Output = Result<(), Fail>>;
 
+    /// Asynchronously connect this socket to [remote] and send [buf] as soon as possible. Transports that support TCP
+    /// Fast Open send the data in the SYN, the others push it once connected.
+    fn connect_with_data(
+        &mut self,
+        sd: &mut Self::SocketDescriptor,
+        remote: SocketAddr,
+        buf: &mut DemiBuffer,
+    ) -> impl std::future::Future<Output = Result<(), Fail>> {
+        async move {
+            self.connect(sd, remote).await?;
+            self.push(sd, buf, None).await
+        }
+    }
+
     /// Push data to a connected socket.
     fn push(
         &mut self,
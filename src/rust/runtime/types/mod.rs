@@ -32,6 +32,30 @@ pub struct demi_args_t {
     pub callback: Option<demi_callback_t>,
 }
 
+/// TCP connection statistics, as returned by the TCP_INFO socket option.
+#[repr(C, packed)]
+#[derive(Copy, Clone, Default)]
+pub struct demi_tcp_info_t {
+    /// Smoothed round-trip time in microseconds.
+    pub tcpi_rtt: u32,
+    /// Round-trip time variation in microseconds.
+    pub tcpi_rttvar: u32,
+    /// Retransmission timeout in microseconds.
+    pub tcpi_rto: u32,
+    /// Maximum segment size for sending, in bytes.
+    pub tcpi_snd_mss: u32,
+    /// Congestion window in bytes.
+    pub tcpi_snd_cwnd: u32,
+    /// Send window advertised by the peer, in bytes.
+    pub tcpi_snd_wnd: u32,
+    /// Receive window, in bytes.
+    pub tcpi_rcv_wnd: u32,
+    /// Bytes sent but not yet acknowledged.
+    pub tcpi_bytes_in_flight: u32,
+    /// Number of retransmitted segments.
+    pub tcpi_total_retrans: u32,
+}
+
 impl Default for demi_args_t {
     fn default() -> Self {
         Self {
@@ -67,4 +91,19 @@ mod test {
 
         Ok(())
     }
+
+    /// Tests if the `demi_tcp_info_t` structure has the expected size.
+    #[test]
+    fn test_size_demi_tcp_info() -> Result<(), anyhow::Error> {
+        // Number of u32 fields.
+        const TCP_INFO_NUM_FIELDS: usize = 9;
+
+        // Check if the sizes match.
+        assert_eq!(
+            std::mem::size_of::<crate::runtime::types::demi_tcp_info_t>(),
+            TCP_INFO_NUM_FIELDS * std::mem::size_of::<u32>()
+        );
+
+        Ok(())
+    }
 }
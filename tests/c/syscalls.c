@@ -79,6 +79,20 @@ static bool inval_connect(void)
     return (demi_connect(qt, qd, saddr, size) != 0);
 }
 
+/**
+ * @brief Issues an invalid call to demi_connect_with_data().
+ */
+static bool inval_connect_with_data(void)
+{
+    demi_qtoken_t *qt = NULL;
+    int qd = -1;
+    demi_sgarray_t *sga = NULL;
+    struct sockaddr *saddr = NULL;
+    socklen_t size = -1;
+
+    return (demi_connect_with_data(qt, qd, sga, saddr, size) != 0);
+}
+
 /**
  * @brief Issues an invalid call to demi_close().
  */
@@ -89,6 +103,17 @@ static bool inval_close(void)
     return (demi_close(qd) != 0);
 }
 
+/**
+ * @brief Issues an invalid call to demi_shutdown().
+ */
+static bool inval_shutdown(void)
+{
+    int qd = -1;
+    int how = -1;
+
+    return (demi_shutdown(qd, how) != 0);
+}
+
 /**
  * @brief Issues an invalid call to demi_push().
  */
@@ -242,6 +267,8 @@ static struct test tests_libos[] = {{inval_socket, "invalid demi_socket()"},   {
                                     {inval_connect, "invalid demi_connect()"}, {inval_listen, "invalid demi_listen()"},
                                     {inval_pop, "invalid demi_pop()"},         {inval_push, "invalid demi_push()"},
                                     {inval_pushto, "invalid demi_pushto()"},   {inval_getpeername, "invalid demi_getpeername()"},
+                                    {inval_shutdown, "invalid demi_shutdown()"},
+                                    {inval_connect_with_data, "invalid demi_connect_with_data()"},
                                     {inval_setsockopt, "invalid demi_setsockopt()"}, {inval_getsockopt, "invalid demi_getsockopt()}"}};
 
 /**
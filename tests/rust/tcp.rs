@@ -12,6 +12,12 @@ mod test {
     use ::anyhow::Result;
     use ::demikernel::{
         demi_sgarray_t,
+        inetstack::protocols::{
+            layer2::{EtherType2, Ethernet2Header},
+            layer3::{ip::IpProtocol, ipv4::Ipv4Header},
+            layer4::tcp::header::TcpHeader,
+        },
+        runtime::network::socket::option::{SocketOption, TcpInfo},
         runtime::{
             memory::{DemiBuffer, MemoryRuntime},
             OperationResult, QDesc, QToken,
@@ -32,66 +38,694 @@ mod test {
     #[cfg(target_os = "linux")]
     pub const SOMAXCONN: i32 = libc::SOMAXCONN;
 
-    /// A default amount of time to wait on an operation to complete. This was chosen arbitrarily to be high enough to
-    /// ensure most OS operations will complete.
-    const TIMEOUT_MILLISECONDS: Duration = Duration::from_millis(100);
-    const BAD_WAIT_TIMEOUT_MILLISECONDS: Duration = Duration::from_millis(1);
+    /// A default amount of time to wait on an operation to complete. This was chosen arbitrarily to be high enough to
+    /// ensure most OS operations will complete.
+    const TIMEOUT_MILLISECONDS: Duration = Duration::from_millis(100);
+    const BAD_WAIT_TIMEOUT_MILLISECONDS: Duration = Duration::from_millis(1);
+
+    use std::{
+        net::{IpAddr, Ipv4Addr, Ipv6Addr, Shutdown, SocketAddr, SocketAddrV6},
+        sync::{Arc, Barrier},
+        thread::{self, JoinHandle},
+        time::{Duration, Instant},
+    };
+    #[cfg(target_os = "windows")]
+    use windows::Win32::Networking::WinSock;
+
+    //======================================================================================================================
+    // Open/Close Passive Socket
+    //======================================================================================================================
+
+    /// Opens and closes a passive socket using a non-ephemeral port.
+    fn do_passive_connection_setup(mut libos: &mut DummyLibOS) -> Result<()> {
+        let local: SocketAddr = SocketAddr::new(ALICE_IP, PORT_NUMBER);
+        let sockqd: QDesc = safe_socket(&mut libos)?;
+        safe_bind(&mut libos, sockqd, local)?;
+        safe_listen(&mut libos, sockqd)?;
+        safe_close_passive(&mut libos, sockqd)?;
+
+        Ok(())
+    }
+
+    /// Opens and closes a passive socket using an ephemeral port.
+    fn do_passive_connection_setup_ephemeral(mut libos: &mut DummyLibOS) -> Result<()> {
+        pub const PORT_EPHEMERAL_BASE: u16 = 49152;
+        let local: SocketAddr = SocketAddr::new(ALICE_IP, PORT_EPHEMERAL_BASE);
+        let sockqd: QDesc = safe_socket(&mut libos)?;
+        safe_bind(&mut libos, sockqd, local)?;
+        safe_listen(&mut libos, sockqd)?;
+        safe_close_passive(&mut libos, sockqd)?;
+
+        Ok(())
+    }
+
+    /// Tests if a passive socket may be successfully opened and closed.
+    #[test]
+    fn tcp_connection_setup() -> Result<()> {
+        let (tx, rx): (Sender<DemiBuffer>, Receiver<DemiBuffer>) = crossbeam_channel::unbounded();
+        let mut libos: DummyLibOS = DummyLibOS::new_test(ALICE_CONFIG_PATH, tx, rx)?;
+
+        do_passive_connection_setup(&mut libos)?;
+        do_passive_connection_setup_ephemeral(&mut libos)?;
+
+        Ok(())
+    }
+
+    //======================================================================================================================
+    // Establish Connection
+    //======================================================================================================================
+
+    /// Tests if connection may be successfully established by an unbound active socket.
+    #[test]
+    fn tcp_establish_connection_unbound() -> Result<()> {
+        let (alice_tx, alice_rx): (Sender<DemiBuffer>, Receiver<DemiBuffer>) = crossbeam_channel::unbounded();
+        let (bob_tx, bob_rx): (Sender<DemiBuffer>, Receiver<DemiBuffer>) = crossbeam_channel::unbounded();
+
+        let bob_barrier: Arc<Barrier> = Arc::new(Barrier::new(2));
+        let alice_barrier: Arc<Barrier> = bob_barrier.clone();
+
+        let alice: JoinHandle<Result<()>> = thread::spawn(move || {
+            let mut libos: DummyLibOS = match DummyLibOS::new_test(ALICE_CONFIG_PATH, alice_tx, bob_rx) {
+                Ok(libos) => libos,
+                Err(e) => anyhow::bail!("Could not create inetstack: {:?}", e),
+            };
+
+            let local: SocketAddr = SocketAddr::new(ALICE_IP, PORT_NUMBER);
+
+            // Open connection.
+            let sockqd: QDesc = safe_socket(&mut libos)?;
+            safe_bind(&mut libos, sockqd, local)?;
+            safe_listen(&mut libos, sockqd)?;
+            let qt: QToken = safe_accept(&mut libos, sockqd)?;
+            let (_, qr): (QDesc, OperationResult) = safe_wait(&mut libos, qt)?;
+
+            let qd: QDesc = match qr {
+                OperationResult::Accept((qd, addr)) if addr.ip() == &BOB_IP => qd,
+                _ => {
+                    // Close socket on error.
+                    // FIXME: https://github.com/demikernel/demikernel/issues/633
+                    anyhow::bail!("accept() has failed")
+                },
+            };
+
+            // Close connection.
+            safe_close_active(&mut libos, qd)?;
+            safe_close_passive(&mut libos, sockqd)?;
+            alice_barrier.wait();
+
+            Ok(())
+        });
+
+        let bob: JoinHandle<Result<()>> = thread::spawn(move || {
+            let mut libos: DummyLibOS = match DummyLibOS::new_test(BOB_CONFIG_PATH, bob_tx, alice_rx) {
+                Ok(libos) => libos,
+                Err(e) => anyhow::bail!("Could not create inetstack: {:?}", e),
+            };
+
+            let remote: SocketAddr = SocketAddr::new(ALICE_IP, PORT_NUMBER);
+
+            // Open connection.
+            let sockqd: QDesc = safe_socket(&mut libos)?;
+            let qt: QToken = safe_connect(&mut libos, sockqd, remote)?;
+            let (_, qr): (QDesc, OperationResult) = safe_wait(&mut libos, qt)?;
+            match qr {
+                OperationResult::Connect => (),
+                _ => {
+                    // Close socket on error.
+                    // FIXME: https://github.com/demikernel/demikernel/issues/633
+                    anyhow::bail!("connect() has failed")
+                },
+            }
+
+            // Close connection.
+            safe_close_active(&mut libos, sockqd)?;
+            // Sleep for a while to give Alice time to finish.
+            bob_barrier.wait();
+
+            Ok(())
+        });
+
+        // It is safe to use unwrap here because there should not be any reason that we can't join the thread and if there
+        // is, there is nothing to clean up here on the main thread.
+        alice.join().unwrap()?;
+        bob.join().unwrap()?;
+
+        Ok(())
+    }
+
+    /// Tests if connection may be successfully established by a bound active socket.
+    #[test]
+    fn tcp_establish_connection_bound() -> Result<()> {
+        let (alice_tx, alice_rx): (Sender<DemiBuffer>, Receiver<DemiBuffer>) = crossbeam_channel::unbounded();
+        let (bob_tx, bob_rx): (Sender<DemiBuffer>, Receiver<DemiBuffer>) = crossbeam_channel::unbounded();
+
+        let bob_barrier: Arc<Barrier> = Arc::new(Barrier::new(2));
+        let alice_barrier: Arc<Barrier> = bob_barrier.clone();
+
+        let alice: JoinHandle<Result<()>> = thread::spawn(move || {
+            let mut libos: DummyLibOS = match DummyLibOS::new_test(ALICE_CONFIG_PATH, alice_tx, bob_rx) {
+                Ok(libos) => libos,
+                Err(e) => anyhow::bail!("Could not create inetstack: {:?}", e),
+            };
+
+            let local: SocketAddr = SocketAddr::new(ALICE_IP, PORT_NUMBER);
+
+            // Open connection.
+            let sockqd: QDesc = safe_socket(&mut libos)?;
+            safe_bind(&mut libos, sockqd, local)?;
+            safe_listen(&mut libos, sockqd)?;
+            let qt: QToken = safe_accept(&mut libos, sockqd)?;
+            let (_, qr): (QDesc, OperationResult) = safe_wait(&mut libos, qt)?;
+
+            let qd: QDesc = match qr {
+                OperationResult::Accept((qd, addr)) if addr.ip() == &BOB_IP => qd,
+                _ => {
+                    // Close socket on error.
+                    // FIXME: https://github.com/demikernel/demikernel/issues/633
+                    anyhow::bail!("accept() has failed")
+                },
+            };
+
+            // Close connection.
+            safe_close_active(&mut libos, qd)?;
+            safe_close_passive(&mut libos, sockqd)?;
+            // Sleep for a while to give Bob time to finish.
+            alice_barrier.wait();
+
+            Ok(())
+        });
+
+        let bob: JoinHandle<Result<()>> = thread::spawn(move || {
+            let mut libos: DummyLibOS = match DummyLibOS::new_test(BOB_CONFIG_PATH, bob_tx, alice_rx) {
+                Ok(libos) => libos,
+                Err(e) => anyhow::bail!("Could not create inetstack: {:?}", e),
+            };
+
+            let local: SocketAddr = SocketAddr::new(BOB_IP, PORT_NUMBER);
+            let remote: SocketAddr = SocketAddr::new(ALICE_IP, PORT_NUMBER);
+
+            // Open connection.
+            let sockqd: QDesc = safe_socket(&mut libos)?;
+            safe_bind(&mut libos, sockqd, local)?;
+            let qt: QToken = safe_connect(&mut libos, sockqd, remote)?;
+            let (_, qr): (QDesc, OperationResult) = safe_wait(&mut libos, qt)?;
+            match qr {
+                OperationResult::Connect => (),
+                _ => {
+                    // Close socket on error.
+                    // FIXME: https://github.com/demikernel/demikernel/issues/633
+                    anyhow::bail!("connect() has failed")
+                },
+            }
+
+            // Close connection.
+            safe_close_active(&mut libos, sockqd)?;
+            // Sleep for a while to give Alice time to finish.
+            bob_barrier.wait();
+            Ok(())
+        });
+
+        // It is safe to use unwrap here because there should not be any reason that we can't join the thread and if there
+        // is, there is nothing to clean up here on the main thread.
+        alice.join().unwrap()?;
+        bob.join().unwrap()?;
+
+        Ok(())
+    }
+
+    //======================================================================================================================
+    // Push
+    //======================================================================================================================
+
+    /// Tests if data can be pushed.
+    #[test]
+    fn tcp_push_remote() -> Result<()> {
+        let (alice_tx, alice_rx): (Sender<DemiBuffer>, Receiver<DemiBuffer>) = crossbeam_channel::unbounded();
+        let (bob_tx, bob_rx): (Sender<DemiBuffer>, Receiver<DemiBuffer>) = crossbeam_channel::unbounded();
+
+        let bob_barrier: Arc<Barrier> = Arc::new(Barrier::new(2));
+        let alice_barrier: Arc<Barrier> = bob_barrier.clone();
+
+        let alice: JoinHandle<Result<()>> = thread::spawn(move || {
+            let mut libos: DummyLibOS = match DummyLibOS::new_test(ALICE_CONFIG_PATH, alice_tx, bob_rx) {
+                Ok(libos) => libos,
+                Err(e) => anyhow::bail!("Could not create inetstack: {:?}", e),
+            };
+
+            let local: SocketAddr = SocketAddr::new(ALICE_IP, PORT_NUMBER);
+
+            // Open connection.
+            let sockqd: QDesc = safe_socket(&mut libos)?;
+            safe_bind(&mut libos, sockqd, local)?;
+            safe_listen(&mut libos, sockqd)?;
+            let qt: QToken = safe_accept(&mut libos, sockqd)?;
+            let (_, qr): (QDesc, OperationResult) = safe_wait(&mut libos, qt)?;
+            let qd: QDesc = match qr {
+                OperationResult::Accept((qd, addr)) if addr.ip() == &BOB_IP => qd,
+                _ => {
+                    // Close socket on error.
+                    // FIXME: https://github.com/demikernel/demikernel/issues/633
+                    anyhow::bail!("accept() has failed")
+                },
+            };
+
+            // Pop data.
+            let qt: QToken = safe_pop(&mut libos, qd)?;
+            let (qd, qr): (QDesc, OperationResult) = safe_wait(&mut libos, qt)?;
+            match qr {
+                OperationResult::Pop(_, _) => (),
+                _ => {
+                    // Close socket on error.
+                    // FIXME: https://github.com/demikernel/demikernel/issues/633
+                    anyhow::bail!("pop() has has failed {:?}", qr)
+                },
+            }
+
+            // Close connection.
+            safe_close_active(&mut libos, qd)?;
+            safe_close_passive(&mut libos, sockqd)?;
+            alice_barrier.wait();
+            Ok(())
+        });
+
+        let bob: JoinHandle<Result<()>> = thread::spawn(move || {
+            let mut libos: DummyLibOS = match DummyLibOS::new_test(BOB_CONFIG_PATH, bob_tx, alice_rx) {
+                Ok(libos) => libos,
+                Err(e) => anyhow::bail!("Could not create inetstack: {:?}", e),
+            };
+
+            let remote: SocketAddr = SocketAddr::new(ALICE_IP, PORT_NUMBER);
+
+            // Open connection.
+            let sockqd: QDesc = safe_socket(&mut libos)?;
+            let qt: QToken = safe_connect(&mut libos, sockqd, remote)?;
+            let (_, qr): (QDesc, OperationResult) = safe_wait(&mut libos, qt)?;
+            match qr {
+                OperationResult::Connect => (),
+                _ => {
+                    // Close socket on error.
+                    // FIXME: https://github.com/demikernel/demikernel/issues/633
+                    anyhow::bail!("connect() has failed")
+                },
+            }
+
+            let buf = libos.prepare_dummy_buffer(32)?;
+            let qt: QToken = safe_push(&mut libos, sockqd, buf)?;
+            let (_, qr): (QDesc, OperationResult) = safe_wait(&mut libos, qt)?;
+            match qr {
+                OperationResult::Push => (),
+                _ => {
+                    // Close socket on error.
+                    // FIXME: https://github.com/demikernel/demikernel/issues/633
+                    anyhow::bail!("push() has failed")
+                },
+            }
+
+            // Close connection.
+            safe_close_active(&mut libos, sockqd)?;
+            bob_barrier.wait();
+
+            Ok(())
+        });
+        // It is safe to use unwrap here because there should not be any reason that we can't join the thread and if there
+        // is, there is nothing to clean up here on the main thread.
+        alice.join().unwrap()?;
+        bob.join().unwrap()?;
+
+        Ok(())
+    }
+
+    /// Tests if data that a client connects with reaches the server, on a first connection that asks for a TCP Fast Open
+    /// cookie and on a second one that sends the data in the SYN with that cookie.
+    #[test]
+    fn tcp_connect_with_data() -> Result<()> {
+        let (alice_tx, alice_rx): (Sender<DemiBuffer>, Receiver<DemiBuffer>) = crossbeam_channel::unbounded();
+        let (bob_tx, bob_rx): (Sender<DemiBuffer>, Receiver<DemiBuffer>) = crossbeam_channel::unbounded();
+        let (relay_tx, relay_rx): (Sender<DemiBuffer>, Receiver<DemiBuffer>) = crossbeam_channel::unbounded();
+        let (stop_tx, stop_rx): (Sender<()>, Receiver<()>) = crossbeam_channel::unbounded();
+
+        let bob_barrier: Arc<Barrier> = Arc::new(Barrier::new(2));
+        let alice_barrier: Arc<Barrier> = bob_barrier.clone();
+
+        // Forward Bob's frames to Alice until both are done, recording how much data each of Bob's SYNs carries.
+        let relay: JoinHandle<Result<Vec<usize>>> = thread::spawn(move || {
+            let mut syn_data_lens: Vec<usize> = Vec::new();
+            loop {
+                crossbeam_channel::select! {
+                    recv(relay_rx) -> frame => match frame {
+                        Ok(frame) => {
+                            if let Some(len) = syn_data_len(frame.clone())? {
+                                syn_data_lens.push(len);
+                            }
+                            // Alice may be gone already.
+                            let _ = bob_tx.send(frame);
+                        },
+                        // Bob is gone.
+                        Err(_) => return Ok(syn_data_lens),
+                    },
+                    recv(stop_rx) -> _ => return Ok(syn_data_lens),
+                }
+            }
+        });
+
+        let alice: JoinHandle<Result<()>> = thread::spawn(move || {
+            let mut libos: DummyLibOS = match DummyLibOS::new_test(ALICE_CONFIG_PATH, alice_tx, bob_rx) {
+                Ok(libos) => libos,
+                Err(e) => anyhow::bail!("Could not create inetstack: {:?}", e),
+            };
+
+            let local: SocketAddr = SocketAddr::new(ALICE_IP, PORT_NUMBER);
+
+            // Open connection.
+            let sockqd: QDesc = safe_socket(&mut libos)?;
+            safe_bind(&mut libos, sockqd, local)?;
+            safe_listen(&mut libos, sockqd)?;
+
+            for _ in 0..2 {
+                let qt: QToken = safe_accept(&mut libos, sockqd)?;
+                let (_, qr): (QDesc, OperationResult) = safe_wait(&mut libos, qt)?;
+                let qd: QDesc = match qr {
+                    OperationResult::Accept((qd, addr)) if addr.ip() == &BOB_IP => qd,
+                    _ => anyhow::bail!("accept() has failed"),
+                };
+
+                // Pop data.
+                let qt: QToken = safe_pop(&mut libos, qd)?;
+                let (qd, qr): (QDesc, OperationResult) = safe_wait(&mut libos, qt)?;
+                match qr {
+                    OperationResult::Pop(_, buf) if buf.len() == 32 => (),
+                    _ => anyhow::bail!("pop() has has failed {:?}", qr),
+                }
+
+                // Close connection.
+                safe_close_active(&mut libos, qd)?;
+            }
+
+            safe_close_passive(&mut libos, sockqd)?;
+            alice_barrier.wait();
+            Ok(())
+        });
+
+        let bob: JoinHandle<Result<()>> = thread::spawn(move || {
+            let mut libos: DummyLibOS = match DummyLibOS::new_test(BOB_CONFIG_PATH, relay_tx, alice_rx) {
+                Ok(libos) => libos,
+                Err(e) => anyhow::bail!("Could not create inetstack: {:?}", e),
+            };
+
+            let remote: SocketAddr = SocketAddr::new(ALICE_IP, PORT_NUMBER);
+
+            for _ in 0..2 {
+                // Open connection and send data.
+                let sockqd: QDesc = safe_socket(&mut libos)?;
+                let buf: demi_sgarray_t = libos.prepare_dummy_buffer(32)?;
+                let qt: QToken = match libos.connect_with_data(sockqd, remote, &buf) {
+                    Ok(qt) => qt,
+                    Err(e) => anyhow::bail!("connect_with_data() failed: {:?}", e),
+                };
+                let (_, qr): (QDesc, OperationResult) = safe_wait(&mut libos, qt)?;
+                match qr {
+                    OperationResult::Connect => (),
+                    _ => anyhow::bail!("connect_with_data() has failed {:?}", qr),
+                }
+
+                // Close connection.
+                safe_close_active(&mut libos, sockqd)?;
+            }
+
+            bob_barrier.wait();
+
+            Ok(())
+        });
+        // It is safe to use unwrap here because there should not be any reason that we can't join the thread and if there
+        // is, there is nothing to clean up here on the main thread.
+        alice.join().unwrap()?;
+        bob.join().unwrap()?;
+        stop_tx.send(())?;
+
+        // The first connection asks for a cookie, so only the second one sends its data in the SYN.
+        match relay.join().unwrap()?.as_slice() {
+            [0, 32] => (),
+            syn_data_lens => anyhow::bail!("only the second SYN should carry data: {:?}", syn_data_lens),
+        }
+
+        Ok(())
+    }
+
+    //======================================================================================================================
+    // Shutdown
+    //======================================================================================================================
+
+    /// Tests if a connection can still receive data after shutting down its sending side.
+    #[test]
+    fn tcp_shutdown_write() -> Result<()> {
+        let (alice_tx, alice_rx): (Sender<DemiBuffer>, Receiver<DemiBuffer>) = crossbeam_channel::unbounded();
+        let (bob_tx, bob_rx): (Sender<DemiBuffer>, Receiver<DemiBuffer>) = crossbeam_channel::unbounded();
+
+        let bob_barrier: Arc<Barrier> = Arc::new(Barrier::new(2));
+        let alice_barrier: Arc<Barrier> = bob_barrier.clone();
+
+        let alice: JoinHandle<Result<()>> = thread::spawn(move || {
+            let mut libos: DummyLibOS = match DummyLibOS::new_test(ALICE_CONFIG_PATH, alice_tx, bob_rx) {
+                Ok(libos) => libos,
+                Err(e) => anyhow::bail!("Could not create inetstack: {:?}", e),
+            };
+
+            let local: SocketAddr = SocketAddr::new(ALICE_IP, PORT_NUMBER);
+
+            // Open connection.
+            let sockqd: QDesc = safe_socket(&mut libos)?;
+            // Acknowledge data right away, so that Bob does not wait for the delayed ACK.
+            if let Err(e) = libos.set_socket_option(sockqd, SocketOption::QuickAck(true)) {
+                anyhow::bail!("set_socket_option() has failed: {:?}", e)
+            }
+            safe_bind(&mut libos, sockqd, local)?;
+            safe_listen(&mut libos, sockqd)?;
+            let qt: QToken = safe_accept(&mut libos, sockqd)?;
+            let (_, qr): (QDesc, OperationResult) = safe_wait(&mut libos, qt)?;
+            let qd: QDesc = match qr {
+                OperationResult::Accept((qd, addr)) if addr.ip() == &BOB_IP => qd,
+                _ => anyhow::bail!("accept() has failed"),
+            };
+
+            // Pop data.
+            let qt: QToken = safe_pop(&mut libos, qd)?;
+            let (_, qr): (QDesc, OperationResult) = safe_wait(&mut libos, qt)?;
+            match qr {
+                OperationResult::Pop(_, buf) if buf.len() > 0 => (),
+                _ => anyhow::bail!("pop() has has failed {:?}", qr),
+            }
+
+            // Pop end of file.
+            let qt: QToken = safe_pop(&mut libos, qd)?;
+            let (_, qr): (QDesc, OperationResult) = safe_wait(&mut libos, qt)?;
+            match qr {
+                OperationResult::Pop(_, buf) if buf.len() == 0 => (),
+                _ => anyhow::bail!("pop() should have returned end of file {:?}", qr),
+            }
+
+            // Push a response.
+            let buf: demi_sgarray_t = libos.prepare_dummy_buffer(32)?;
+            let qt: QToken = safe_push(&mut libos, qd, buf)?;
+            let (qd, qr): (QDesc, OperationResult) = safe_wait(&mut libos, qt)?;
+            match qr {
+                OperationResult::Push => (),
+                _ => anyhow::bail!("push() has failed"),
+            }
+
+            // Close connection.
+            safe_close_active(&mut libos, qd)?;
+            safe_close_passive(&mut libos, sockqd)?;
+            alice_barrier.wait();
+            Ok(())
+        });
+
+        let bob: JoinHandle<Result<()>> = thread::spawn(move || {
+            let mut libos: DummyLibOS = match DummyLibOS::new_test(BOB_CONFIG_PATH, bob_tx, alice_rx) {
+                Ok(libos) => libos,
+                Err(e) => anyhow::bail!("Could not create inetstack: {:?}", e),
+            };
+
+            let remote: SocketAddr = SocketAddr::new(ALICE_IP, PORT_NUMBER);
+
+            // Open connection.
+            let sockqd: QDesc = safe_socket(&mut libos)?;
+            let qt: QToken = safe_connect(&mut libos, sockqd, remote)?;
+            let (_, qr): (QDesc, OperationResult) = safe_wait(&mut libos, qt)?;
+            match qr {
+                OperationResult::Connect => (),
+                _ => anyhow::bail!("connect() has failed"),
+            }
+
+            // Acknowledge data right away, so that Alice does not wait for the delayed ACK.
+            if let Err(e) = libos.set_socket_option(sockqd, SocketOption::QuickAck(true)) {
+                anyhow::bail!("set_socket_option() has failed: {:?}", e)
+            }
+
+            // Push data.
+            let buf: demi_sgarray_t = libos.prepare_dummy_buffer(32)?;
+            let qt: QToken = safe_push(&mut libos, sockqd, buf)?;
+            let (_, qr): (QDesc, OperationResult) = safe_wait(&mut libos, qt)?;
+            match qr {
+                OperationResult::Push => (),
+                _ => anyhow::bail!("push() has failed"),
+            }
+
+            // Shut down the sending side.
+            if let Err(e) = libos.shutdown(sockqd, Shutdown::Write) {
+                anyhow::bail!("shutdown() has failed: {:?}", e)
+            }
+
+            // Push data after shutting down the sending side.
+            let buf: demi_sgarray_t = libos.prepare_dummy_buffer(32)?;
+            let qt: QToken = safe_push(&mut libos, sockqd, buf)?;
+            let (_, qr): (QDesc, OperationResult) = safe_wait(&mut libos, qt)?;
+            match qr {
+                OperationResult::Failed(e) if e.errno == libc::EPIPE => (),
+                _ => anyhow::bail!("push() after shutdown() should fail {:?}", qr),
+            }
+
+            // Pop the response.
+            let qt: QToken = safe_pop(&mut libos, sockqd)?;
+            let (_, qr): (QDesc, OperationResult) = safe_wait(&mut libos, qt)?;
+            match qr {
+                OperationResult::Pop(_, buf) if buf.len() > 0 => (),
+                _ => anyhow::bail!("pop() has has failed {:?}", qr),
+            }
+
+            // Close connection.
+            safe_close_active(&mut libos, sockqd)?;
+            bob_barrier.wait();
+
+            Ok(())
+        });
+        // It is safe to use unwrap here because there should not be any reason that we can't join the thread and if there
+        // is, there is nothing to clean up here on the main thread.
+        alice.join().unwrap()?;
+        bob.join().unwrap()?;
+
+        Ok(())
+    }
+
+    //======================================================================================================================
+    // Send Buffer
+    //======================================================================================================================
+
+    /// Tests if pushes that do not fit into the send buffer complete in order once there is room for them.
+    #[test]
+    fn tcp_push_with_small_send_buffer() -> Result<()> {
+        let (alice_tx, alice_rx): (Sender<DemiBuffer>, Receiver<DemiBuffer>) = crossbeam_channel::unbounded();
+        let (bob_tx, bob_rx): (Sender<DemiBuffer>, Receiver<DemiBuffer>) = crossbeam_channel::unbounded();
+
+        let bob_barrier: Arc<Barrier> = Arc::new(Barrier::new(2));
+        let alice_barrier: Arc<Barrier> = bob_barrier.clone();
+
+        let alice: JoinHandle<Result<()>> = thread::spawn(move || {
+            let mut libos: DummyLibOS = match DummyLibOS::new_test(ALICE_CONFIG_PATH, alice_tx, bob_rx) {
+                Ok(libos) => libos,
+                Err(e) => anyhow::bail!("Could not create inetstack: {:?}", e),
+            };
+
+            let local: SocketAddr = SocketAddr::new(ALICE_IP, PORT_NUMBER);
+
+            // Open connection.
+            let sockqd: QDesc = safe_socket(&mut libos)?;
+            // Acknowledge data right away, so that Bob does not wait for the delayed ACK.
+            if let Err(e) = libos.set_socket_option(sockqd, SocketOption::QuickAck(true)) {
+                anyhow::bail!("set_socket_option() has failed: {:?}", e)
+            }
+            safe_bind(&mut libos, sockqd, local)?;
+            safe_listen(&mut libos, sockqd)?;
+            let qt: QToken = safe_accept(&mut libos, sockqd)?;
+            let (_, qr): (QDesc, OperationResult) = safe_wait(&mut libos, qt)?;
+            let qd: QDesc = match qr {
+                OperationResult::Accept((qd, addr)) if addr.ip() == &BOB_IP => qd,
+                _ => anyhow::bail!("accept() has failed"),
+            };
+
+            // Pop all data.
+            let mut received: usize = 0;
+            while received < 128 {
+                let qt: QToken = safe_pop(&mut libos, qd)?;
+                let (_, qr): (QDesc, OperationResult) = safe_wait(&mut libos, qt)?;
+                match qr {
+                    OperationResult::Pop(_, buf) if buf.len() > 0 => received += buf.len(),
+                    _ => anyhow::bail!("pop() has has failed {:?}", qr),
+                }
+            }
 
-    use std::{
-        net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV6},
-        sync::{Arc, Barrier},
-        thread::{self, JoinHandle},
-        time::Duration,
-    };
-    #[cfg(target_os = "windows")]
-    use windows::Win32::Networking::WinSock;
+            // Close connection.
+            safe_close_active(&mut libos, qd)?;
+            safe_close_passive(&mut libos, sockqd)?;
+            alice_barrier.wait();
+            Ok(())
+        });
 
-    //======================================================================================================================
-    // Open/Close Passive Socket
-    //======================================================================================================================
+        let bob: JoinHandle<Result<()>> = thread::spawn(move || {
+            let mut libos: DummyLibOS = match DummyLibOS::new_test(BOB_CONFIG_PATH, bob_tx, alice_rx) {
+                Ok(libos) => libos,
+                Err(e) => anyhow::bail!("Could not create inetstack: {:?}", e),
+            };
 
-    /// Opens and closes a passive socket using a non-ephemeral port.
-    fn do_passive_connection_setup(mut libos: &mut DummyLibOS) -> Result<()> {
-        let local: SocketAddr = SocketAddr::new(ALICE_IP, PORT_NUMBER);
-        let sockqd: QDesc = safe_socket(&mut libos)?;
-        safe_bind(&mut libos, sockqd, local)?;
-        safe_listen(&mut libos, sockqd)?;
-        safe_close_passive(&mut libos, sockqd)?;
+            let remote: SocketAddr = SocketAddr::new(ALICE_IP, PORT_NUMBER);
 
-        Ok(())
-    }
+            // Open connection.
+            let sockqd: QDesc = safe_socket(&mut libos)?;
+            let qt: QToken = safe_connect(&mut libos, sockqd, remote)?;
+            let (_, qr): (QDesc, OperationResult) = safe_wait(&mut libos, qt)?;
+            match qr {
+                OperationResult::Connect => (),
+                _ => anyhow::bail!("connect() has failed"),
+            }
 
-    /// Opens and closes a passive socket using an ephemeral port.
-    fn do_passive_connection_setup_ephemeral(mut libos: &mut DummyLibOS) -> Result<()> {
-        pub const PORT_EPHEMERAL_BASE: u16 = 49152;
-        let local: SocketAddr = SocketAddr::new(ALICE_IP, PORT_EPHEMERAL_BASE);
-        let sockqd: QDesc = safe_socket(&mut libos)?;
-        safe_bind(&mut libos, sockqd, local)?;
-        safe_listen(&mut libos, sockqd)?;
-        safe_close_passive(&mut libos, sockqd)?;
+            // Only buffer one push at a time.
+            if let Err(e) = libos.set_socket_option(sockqd, SocketOption::SendBufferSize(32)) {
+                anyhow::bail!("set_socket_option() has failed: {:?}", e)
+            }
+            match libos.get_socket_option(sockqd, SocketOption::SendBufferSize(0)) {
+                Ok(SocketOption::SendBufferSize(32)) => (),
+                result => anyhow::bail!("get_socket_option() has failed: {:?}", result),
+            }
 
-        Ok(())
-    }
+            // Push more data than fits into the send buffer, including a buffer that is larger than the send buffer.
+            let mut qts: Vec<QToken> = Vec::new();
+            for size in [32, 32, 64] {
+                let buf: demi_sgarray_t = libos.prepare_dummy_buffer(size)?;
+                qts.push(safe_push(&mut libos, sockqd, buf)?);
+            }
+            for qt in qts {
+                let (_, qr): (QDesc, OperationResult) = safe_wait(&mut libos, qt)?;
+                match qr {
+                    OperationResult::Push => (),
+                    _ => anyhow::bail!("push() has failed {:?}", qr),
+                }
+            }
 
-    /// Tests if a passive socket may be successfully opened and closed.
-    #[test]
-    fn tcp_connection_setup() -> Result<()> {
-        let (tx, rx): (Sender<DemiBuffer>, Receiver<DemiBuffer>) = crossbeam_channel::unbounded();
-        let mut libos: DummyLibOS = DummyLibOS::new_test(ALICE_CONFIG_PATH, tx, rx)?;
+            // Close connection.
+            safe_close_active(&mut libos, sockqd)?;
+            bob_barrier.wait();
 
-        do_passive_connection_setup(&mut libos)?;
-        do_passive_connection_setup_ephemeral(&mut libos)?;
+            Ok(())
+        });
+        // It is safe to use unwrap here because there should not be any reason that we can't join the thread and if there
+        // is, there is nothing to clean up here on the main thread.
+        alice.join().unwrap()?;
+        bob.join().unwrap()?;
 
         Ok(())
     }
 
     //======================================================================================================================
-    // Establish Connection
+    // Receive Buffer
     //======================================================================================================================
 
-    /// Tests if connection may be successfully established by an unbound active socket.
+    /// Tests if a connection can receive more data than fits in an unscaled window with a large receive buffer.
     #[test]
-    fn tcp_establish_connection_unbound() -> Result<()> {
+    fn tcp_large_receive_buffer() -> Result<()> {
+        const RECEIVE_BUFFER_SIZE: usize = 1024 * 1024;
+        // Keep every push in a single segment.
+        const PUSH_SIZE: usize = 1000;
+        const NUM_PUSHES: usize = 100;
+
         let (alice_tx, alice_rx): (Sender<DemiBuffer>, Receiver<DemiBuffer>) = crossbeam_channel::unbounded();
         let (bob_tx, bob_rx): (Sender<DemiBuffer>, Receiver<DemiBuffer>) = crossbeam_channel::unbounded();
 
@@ -112,21 +746,29 @@ mod test {
             safe_listen(&mut libos, sockqd)?;
             let qt: QToken = safe_accept(&mut libos, sockqd)?;
             let (_, qr): (QDesc, OperationResult) = safe_wait(&mut libos, qt)?;
-
             let qd: QDesc = match qr {
                 OperationResult::Accept((qd, addr)) if addr.ip() == &BOB_IP => qd,
-                _ => {
-                    // Close socket on error.
-                    // FIXME: https://github.com/demikernel/demikernel/issues/633
-                    anyhow::bail!("accept() has failed")
-                },
+                _ => anyhow::bail!("accept() has failed"),
             };
 
+            // Push more data than fits in a 64 KiB window.
+            let mut qts: Vec<QToken> = Vec::new();
+            for _ in 0..NUM_PUSHES {
+                let buf: demi_sgarray_t = libos.prepare_dummy_buffer(PUSH_SIZE)?;
+                qts.push(safe_push(&mut libos, qd, buf)?);
+            }
+            for qt in qts {
+                let (_, qr): (QDesc, OperationResult) = safe_wait(&mut libos, qt)?;
+                match qr {
+                    OperationResult::Push => (),
+                    _ => anyhow::bail!("push() has failed {:?}", qr),
+                }
+            }
+
             // Close connection.
             safe_close_active(&mut libos, qd)?;
             safe_close_passive(&mut libos, sockqd)?;
             alice_barrier.wait();
-
             Ok(())
         });
 
@@ -138,27 +780,43 @@ mod test {
 
             let remote: SocketAddr = SocketAddr::new(ALICE_IP, PORT_NUMBER);
 
-            // Open connection.
+            // Open connection with a receive buffer that needs window scaling.
             let sockqd: QDesc = safe_socket(&mut libos)?;
+            if let Err(e) = libos.set_socket_option(sockqd, SocketOption::ReceiveBufferSize(RECEIVE_BUFFER_SIZE)) {
+                anyhow::bail!("set_socket_option() has failed: {:?}", e)
+            }
+            // Acknowledge data right away, so that Alice does not wait for the delayed ACK.
+            if let Err(e) = libos.set_socket_option(sockqd, SocketOption::QuickAck(true)) {
+                anyhow::bail!("set_socket_option() has failed: {:?}", e)
+            }
             let qt: QToken = safe_connect(&mut libos, sockqd, remote)?;
             let (_, qr): (QDesc, OperationResult) = safe_wait(&mut libos, qt)?;
             match qr {
                 OperationResult::Connect => (),
-                _ => {
-                    // Close socket on error.
-                    // FIXME: https://github.com/demikernel/demikernel/issues/633
-                    anyhow::bail!("connect() has failed")
-                },
+                _ => anyhow::bail!("connect() has failed"),
+            }
+            match libos.get_socket_option(sockqd, SocketOption::ReceiveBufferSize(0)) {
+                Ok(SocketOption::ReceiveBufferSize(RECEIVE_BUFFER_SIZE)) => (),
+                result => anyhow::bail!("get_socket_option() has failed: {:?}", result),
+            }
+
+            // Pop all data.
+            let mut received: usize = 0;
+            while received < PUSH_SIZE * NUM_PUSHES {
+                let qt: QToken = safe_pop(&mut libos, sockqd)?;
+                let (_, qr): (QDesc, OperationResult) = safe_wait(&mut libos, qt)?;
+                match qr {
+                    OperationResult::Pop(_, buf) if buf.len() > 0 => received += buf.len(),
+                    _ => anyhow::bail!("pop() has has failed {:?}", qr),
+                }
             }
 
             // Close connection.
             safe_close_active(&mut libos, sockqd)?;
-            // Sleep for a while to give Alice time to finish.
             bob_barrier.wait();
 
             Ok(())
         });
-
         // It is safe to use unwrap here because there should not be any reason that we can't join the thread and if there
         // is, there is nothing to clean up here on the main thread.
         alice.join().unwrap()?;
@@ -167,14 +825,24 @@ mod test {
         Ok(())
     }
 
-    /// Tests if connection may be successfully established by a bound active socket.
+    /// Tests if the receive buffer grows, and with it the window that we advertise, when the application drains it
+    /// quickly.
     #[test]
-    fn tcp_establish_connection_bound() -> Result<()> {
+    fn tcp_receive_buffer_auto_tuning() -> Result<()> {
+        // Bob starts with a 64 KiB receive buffer, and may grow it up to 1 MiB.
+        const INITIAL_RECEIVE_BUFFER_SIZE: u32 = 65535;
+        const PUSH_SIZE: usize = 1000;
+        const NUM_PUSHES: usize = 100;
+        // Read more than half of the initial receive buffer in a single round trip.
+        const FAST_READ_SIZE: usize = 40 * PUSH_SIZE;
+
         let (alice_tx, alice_rx): (Sender<DemiBuffer>, Receiver<DemiBuffer>) = crossbeam_channel::unbounded();
         let (bob_tx, bob_rx): (Sender<DemiBuffer>, Receiver<DemiBuffer>) = crossbeam_channel::unbounded();
 
         let bob_barrier: Arc<Barrier> = Arc::new(Barrier::new(2));
         let alice_barrier: Arc<Barrier> = bob_barrier.clone();
+        let bob_drained: Arc<Barrier> = Arc::new(Barrier::new(2));
+        let alice_drained: Arc<Barrier> = bob_drained.clone();
 
         let alice: JoinHandle<Result<()>> = thread::spawn(move || {
             let mut libos: DummyLibOS = match DummyLibOS::new_test(ALICE_CONFIG_PATH, alice_tx, bob_rx) {
@@ -190,22 +858,43 @@ mod test {
             safe_listen(&mut libos, sockqd)?;
             let qt: QToken = safe_accept(&mut libos, sockqd)?;
             let (_, qr): (QDesc, OperationResult) = safe_wait(&mut libos, qt)?;
-
             let qd: QDesc = match qr {
                 OperationResult::Accept((qd, addr)) if addr.ip() == &BOB_IP => qd,
-                _ => {
-                    // Close socket on error.
-                    // FIXME: https://github.com/demikernel/demikernel/issues/633
-                    anyhow::bail!("accept() has failed")
-                },
+                _ => anyhow::bail!("accept() has failed"),
             };
 
+            // Push data and wait for all of it to be acknowledged.
+            let mut qts: Vec<QToken> = Vec::new();
+            for _ in 0..NUM_PUSHES {
+                let buf: demi_sgarray_t = libos.prepare_dummy_buffer(PUSH_SIZE)?;
+                qts.push(safe_push(&mut libos, qd, buf)?);
+            }
+            for qt in qts {
+                let (_, qr): (QDesc, OperationResult) = safe_wait(&mut libos, qt)?;
+                match qr {
+                    OperationResult::Push => (),
+                    _ => anyhow::bail!("push() has failed {:?}", qr),
+                }
+            }
+
+            // Once Bob has read everything, the ACK for one more byte advertises the grown receive buffer.
+            alice_drained.wait();
+            let buf: demi_sgarray_t = libos.prepare_dummy_buffer(1)?;
+            let qt: QToken = safe_push(&mut libos, qd, buf)?;
+            let (_, qr): (QDesc, OperationResult) = safe_wait(&mut libos, qt)?;
+            match qr {
+                OperationResult::Push => (),
+                _ => anyhow::bail!("push() has failed {:?}", qr),
+            }
+            match libos.get_socket_option(qd, SocketOption::TcpInfo(TcpInfo::default())) {
+                Ok(SocketOption::TcpInfo(info)) if info.send_window > INITIAL_RECEIVE_BUFFER_SIZE => (),
+                result => anyhow::bail!("get_socket_option() has failed: {:?}", result),
+            }
+
             // Close connection.
             safe_close_active(&mut libos, qd)?;
             safe_close_passive(&mut libos, sockqd)?;
-            // Sleep for a while to give Bob time to finish.
             alice_barrier.wait();
-
             Ok(())
         });
 
@@ -215,30 +904,66 @@ mod test {
                 Err(e) => anyhow::bail!("Could not create inetstack: {:?}", e),
             };
 
-            let local: SocketAddr = SocketAddr::new(BOB_IP, PORT_NUMBER);
             let remote: SocketAddr = SocketAddr::new(ALICE_IP, PORT_NUMBER);
 
-            // Open connection.
+            // Open connection. Leave SO_RCVBUF alone, because setting it turns auto-tuning off.
             let sockqd: QDesc = safe_socket(&mut libos)?;
-            safe_bind(&mut libos, sockqd, local)?;
+            // Acknowledge data right away, so that Alice does not wait for the delayed ACK.
+            if let Err(e) = libos.set_socket_option(sockqd, SocketOption::QuickAck(true)) {
+                anyhow::bail!("set_socket_option() has failed: {:?}", e)
+            }
             let qt: QToken = safe_connect(&mut libos, sockqd, remote)?;
             let (_, qr): (QDesc, OperationResult) = safe_wait(&mut libos, qt)?;
             match qr {
                 OperationResult::Connect => (),
-                _ => {
-                    // Close socket on error.
-                    // FIXME: https://github.com/demikernel/demikernel/issues/633
-                    anyhow::bail!("connect() has failed")
-                },
+                _ => anyhow::bail!("connect() has failed"),
+            }
+            match libos.get_socket_option(sockqd, SocketOption::ReceiveBufferSize(0)) {
+                Ok(SocketOption::ReceiveBufferSize(size)) if size == INITIAL_RECEIVE_BUFFER_SIZE as usize => (),
+                result => anyhow::bail!("get_socket_option() has failed: {:?}", result),
+            }
+
+            // Waiting does not move the clock, so all of these reads happen within a single round trip.
+            let mut received: usize = 0;
+            while received < FAST_READ_SIZE {
+                let qt: QToken = safe_pop(&mut libos, sockqd)?;
+                let (_, qr): (QDesc, OperationResult) = safe_wait(&mut libos, qt)?;
+                match qr {
+                    OperationResult::Pop(_, buf) if buf.len() > 0 => received += buf.len(),
+                    _ => anyhow::bail!("pop() has has failed {:?}", qr),
+                }
+            }
+
+            // Once the round trip is over, the next read grows the receive buffer.
+            libos
+                .get_runtime()
+                .advance_clock(Instant::now() + Duration::from_millis(100));
+            while received < PUSH_SIZE * NUM_PUSHES {
+                let qt: QToken = safe_pop(&mut libos, sockqd)?;
+                let (_, qr): (QDesc, OperationResult) = safe_wait(&mut libos, qt)?;
+                match qr {
+                    OperationResult::Pop(_, buf) if buf.len() > 0 => received += buf.len(),
+                    _ => anyhow::bail!("pop() has has failed {:?}", qr),
+                }
+            }
+            match libos.get_socket_option(sockqd, SocketOption::ReceiveBufferSize(0)) {
+                Ok(SocketOption::ReceiveBufferSize(size)) if size > INITIAL_RECEIVE_BUFFER_SIZE as usize => (),
+                result => anyhow::bail!("get_socket_option() has failed: {:?}", result),
+            }
+            bob_drained.wait();
+            let qt: QToken = safe_pop(&mut libos, sockqd)?;
+            let (_, qr): (QDesc, OperationResult) = safe_wait(&mut libos, qt)?;
+            match qr {
+                OperationResult::Pop(_, buf) if buf.len() == 1 => (),
+                _ => anyhow::bail!("pop() has has failed {:?}", qr),
             }
 
             // Close connection.
             safe_close_active(&mut libos, sockqd)?;
-            // Sleep for a while to give Alice time to finish.
             bob_barrier.wait();
+
             Ok(())
         });
-
         // It is safe to use unwrap here because there should not be any reason that we can't join the thread and if there
         // is, there is nothing to clean up here on the main thread.
         alice.join().unwrap()?;
@@ -248,12 +973,12 @@ mod test {
     }
 
     //======================================================================================================================
-    // Push
+    // Connection Statistics
     //======================================================================================================================
 
-    /// Tests if data can be pushed.
+    /// Tests if statistics can be read from a connection, and only from a connection.
     #[test]
-    fn tcp_push_remote() -> Result<()> {
+    fn tcp_info() -> Result<()> {
         let (alice_tx, alice_rx): (Sender<DemiBuffer>, Receiver<DemiBuffer>) = crossbeam_channel::unbounded();
         let (bob_tx, bob_rx): (Sender<DemiBuffer>, Receiver<DemiBuffer>) = crossbeam_channel::unbounded();
 
@@ -272,27 +997,29 @@ mod test {
             let sockqd: QDesc = safe_socket(&mut libos)?;
             safe_bind(&mut libos, sockqd, local)?;
             safe_listen(&mut libos, sockqd)?;
+
+            // A listening socket has no connection statistics.
+            match libos.get_socket_option(sockqd, SocketOption::TcpInfo(TcpInfo::default())) {
+                Err(e) if e.errno == libc::ENOTCONN => (),
+                result => anyhow::bail!("get_socket_option() should have failed: {:?}", result),
+            }
+
             let qt: QToken = safe_accept(&mut libos, sockqd)?;
             let (_, qr): (QDesc, OperationResult) = safe_wait(&mut libos, qt)?;
             let qd: QDesc = match qr {
                 OperationResult::Accept((qd, addr)) if addr.ip() == &BOB_IP => qd,
-                _ => {
-                    // Close socket on error.
-                    // FIXME: https://github.com/demikernel/demikernel/issues/633
-                    anyhow::bail!("accept() has failed")
-                },
+                _ => anyhow::bail!("accept() has failed"),
             };
 
-            // Pop data.
-            let qt: QToken = safe_pop(&mut libos, qd)?;
-            let (qd, qr): (QDesc, OperationResult) = safe_wait(&mut libos, qt)?;
-            match qr {
-                OperationResult::Pop(_, _) => (),
-                _ => {
-                    // Close socket on error.
-                    // FIXME: https://github.com/demikernel/demikernel/issues/633
-                    anyhow::bail!("pop() has has failed {:?}", qr)
-                },
+            // Pop all data.
+            let mut received: usize = 0;
+            while received < 128 {
+                let qt: QToken = safe_pop(&mut libos, qd)?;
+                let (_, qr): (QDesc, OperationResult) = safe_wait(&mut libos, qt)?;
+                match qr {
+                    OperationResult::Pop(_, buf) if buf.len() > 0 => received += buf.len(),
+                    _ => anyhow::bail!("pop() has has failed {:?}", qr),
+                }
             }
 
             // Close connection.
@@ -316,23 +1043,29 @@ mod test {
             let (_, qr): (QDesc, OperationResult) = safe_wait(&mut libos, qt)?;
             match qr {
                 OperationResult::Connect => (),
-                _ => {
-                    // Close socket on error.
-                    // FIXME: https://github.com/demikernel/demikernel/issues/633
-                    anyhow::bail!("connect() has failed")
-                },
+                _ => anyhow::bail!("connect() has failed"),
             }
 
-            let buf = libos.prepare_dummy_buffer(32)?;
+            // Push data and wait for it to be acknowledged.
+            let buf: demi_sgarray_t = libos.prepare_dummy_buffer(128)?;
             let qt: QToken = safe_push(&mut libos, sockqd, buf)?;
             let (_, qr): (QDesc, OperationResult) = safe_wait(&mut libos, qt)?;
             match qr {
                 OperationResult::Push => (),
-                _ => {
-                    // Close socket on error.
-                    // FIXME: https://github.com/demikernel/demikernel/issues/633
-                    anyhow::bail!("push() has failed")
-                },
+                _ => anyhow::bail!("push() has failed {:?}", qr),
+            }
+
+            // Everything that we sent has been acknowledged, without retransmissions.
+            match libos.get_socket_option(sockqd, SocketOption::TcpInfo(TcpInfo::default())) {
+                Ok(SocketOption::TcpInfo(info))
+                    if !info.rto.is_zero()
+                        && info.send_mss > 0
+                        && info.congestion_window > 0
+                        && info.send_window > 0
+                        && info.receive_window > 0
+                        && info.bytes_in_flight == 0
+                        && info.total_retransmits == 0 => {},
+                result => anyhow::bail!("get_socket_option() has failed: {:?}", result),
             }
 
             // Close connection.
@@ -349,6 +1082,102 @@ mod test {
         Ok(())
     }
 
+    //======================================================================================================================
+    // User Timeout
+    //======================================================================================================================
+
+    /// Tests if a connection is dropped once pushed data goes unacknowledged for longer than the user timeout.
+    #[test]
+    fn tcp_user_timeout() -> Result<()> {
+        let (alice_tx, alice_rx): (Sender<DemiBuffer>, Receiver<DemiBuffer>) = crossbeam_channel::unbounded();
+        let (bob_tx, bob_rx): (Sender<DemiBuffer>, Receiver<DemiBuffer>) = crossbeam_channel::unbounded();
+
+        let bob_barrier: Arc<Barrier> = Arc::new(Barrier::new(2));
+        let alice_barrier: Arc<Barrier> = bob_barrier.clone();
+
+        let alice: JoinHandle<Result<()>> = thread::spawn(move || {
+            let mut libos: DummyLibOS = match DummyLibOS::new_test(ALICE_CONFIG_PATH, alice_tx, bob_rx) {
+                Ok(libos) => libos,
+                Err(e) => anyhow::bail!("Could not create inetstack: {:?}", e),
+            };
+
+            let local: SocketAddr = SocketAddr::new(ALICE_IP, PORT_NUMBER);
+
+            // Open connection.
+            let sockqd: QDesc = safe_socket(&mut libos)?;
+            safe_bind(&mut libos, sockqd, local)?;
+            safe_listen(&mut libos, sockqd)?;
+            let qt: QToken = safe_accept(&mut libos, sockqd)?;
+            let (_, qr): (QDesc, OperationResult) = safe_wait(&mut libos, qt)?;
+            match qr {
+                OperationResult::Accept((_, addr)) if addr.ip() == &BOB_IP => (),
+                _ => anyhow::bail!("accept() has failed"),
+            }
+
+            // Disappear without acknowledging anything until Bob gives up on us.
+            alice_barrier.wait();
+            Ok(())
+        });
+
+        let bob: JoinHandle<Result<()>> = thread::spawn(move || {
+            let mut libos: DummyLibOS = match DummyLibOS::new_test(BOB_CONFIG_PATH, bob_tx, alice_rx) {
+                Ok(libos) => libos,
+                Err(e) => anyhow::bail!("Could not create inetstack: {:?}", e),
+            };
+
+            let remote: SocketAddr = SocketAddr::new(ALICE_IP, PORT_NUMBER);
+
+            // Open connection.
+            let sockqd: QDesc = safe_socket(&mut libos)?;
+            let qt: QToken = safe_connect(&mut libos, sockqd, remote)?;
+            let (_, qr): (QDesc, OperationResult) = safe_wait(&mut libos, qt)?;
+            match qr {
+                OperationResult::Connect => (),
+                _ => anyhow::bail!("connect() has failed"),
+            }
+
+            let user_timeout: Duration = Duration::from_millis(200);
+            if let Err(e) = libos.set_socket_option(sockqd, SocketOption::UserTimeout(Some(user_timeout))) {
+                anyhow::bail!("set_socket_option() has failed: {:?}", e)
+            }
+            match libos.get_socket_option(sockqd, SocketOption::UserTimeout(None)) {
+                Ok(SocketOption::UserTimeout(Some(timeout))) if timeout == user_timeout => (),
+                result => anyhow::bail!("get_socket_option() has failed: {:?}", result),
+            }
+
+            // Both the outstanding pop and the push of data that never gets acknowledged fail.
+            let pop_qt: QToken = safe_pop(&mut libos, sockqd)?;
+            let buf: demi_sgarray_t = libos.prepare_dummy_buffer(32)?;
+            let push_qt: QToken = safe_push(&mut libos, sockqd, buf)?;
+            match libos.wait(push_qt, Some(TIMEOUT_MILLISECONDS)) {
+                Err(e) if e.errno == libc::ETIMEDOUT => (),
+                result => anyhow::bail!("push() should still be waiting for an acknowledgement: {:?}", result),
+            }
+
+            // Waiting does not move the clock, so move it past the user timeout ourselves.
+            libos.get_runtime().advance_clock(Instant::now() + user_timeout);
+            let (_, qr): (QDesc, OperationResult) = safe_wait(&mut libos, push_qt)?;
+            match qr {
+                OperationResult::Failed(e) if e.errno == libc::ETIMEDOUT => (),
+                _ => anyhow::bail!("push() should have timed out {:?}", qr),
+            }
+            let (_, qr): (QDesc, OperationResult) = safe_wait(&mut libos, pop_qt)?;
+            match qr {
+                OperationResult::Failed(e) if e.errno == libc::ETIMEDOUT => (),
+                _ => anyhow::bail!("pop() should have timed out {:?}", qr),
+            }
+
+            bob_barrier.wait();
+            Ok(())
+        });
+        // It is safe to use unwrap here because there should not be any reason that we can't join the thread and if there
+        // is, there is nothing to clean up here on the main thread.
+        alice.join().unwrap()?;
+        bob.join().unwrap()?;
+
+        Ok(())
+    }
+
     //======================================================================================================================
     // Bad Socket
     //======================================================================================================================
@@ -1165,4 +1994,22 @@ mod test {
             Err(_) => anyhow::bail!("close() on active socket has failed"),
         }
     }
+
+    /// Returns how much data `frame` carries if it holds a TCP SYN.
+    fn syn_data_len(mut frame: DemiBuffer) -> Result<Option<usize>> {
+        if Ethernet2Header::parse_and_strip(&mut frame)?.ether_type() != EtherType2::Ipv4 {
+            return Ok(None);
+        }
+        let ipv4_header: Ipv4Header = Ipv4Header::parse_and_strip(&mut frame)?;
+        if ipv4_header.get_protocol() != IpProtocol::TCP {
+            return Ok(None);
+        }
+        let tcp_header: TcpHeader = TcpHeader::parse_and_strip(
+            &ipv4_header.get_src_addr(),
+            &ipv4_header.get_dest_addr(),
+            &mut frame,
+            true,
+        )?;
+        Ok(if tcp_header.syn { Some(frame.len()) } else { None })
+    }
 }